@@ -0,0 +1,1396 @@
+// AutoSocket prefab generation: matches the sockets of an .xob against the prefab index
+// and renders (or merges into) an .et with slot components and child entities.
+//
+// Nothing in here depends on Tauri; progress goes through a `LogSink`, so the same code
+// backs the GUI commands, the CLI and any other Rust caller.
+
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Duration;
+
+use crate::log_sink::LogSink;
+use crate::socket_discovery::{self, SocketDiscovery};
+use crate::socket_rules::{self, RuleSet, SearchScope, SocketMatch};
+use crate::{blender_worker, emit_scan_log, enfusion_text, extract_cache, fbx, prefab_db, write_plan};
+use crate::{
+    extract_guid, gen_hex16, load_settings, read_xob_object_field_from_meta, rel_from_known_roots,
+    remember_extra_dirs, remember_save_dir, remember_svn_root, update_prefab_cache_with_new_meta,
+};
+
+fn build_et_meta_text(name_value: &str) -> String {
+    let mut out = String::new();
+    out.push_str("MetaFileClass {\n");
+    out.push_str(&format!(" Name \"{}\"\n", name_value));
+    out.push_str(" Configurations {\n");
+    out.push_str("  EntityTemplateResourceClass PC {\n");
+    out.push_str("  }\n");
+    out.push_str("  EntityTemplateResourceClass XBOX_ONE : PC {\n");
+    out.push_str("  }\n");
+    out.push_str("  EntityTemplateResourceClass XBOX_SERIES : PC {\n");
+    out.push_str("  }\n");
+    out.push_str("  EntityTemplateResourceClass PS4 : PC {\n");
+    out.push_str("  }\n");
+    out.push_str("  EntityTemplateResourceClass PS5 : PC {\n");
+    out.push_str("  }\n");
+    out.push_str("  EntityTemplateResourceClass HEADLESS : PC {\n");
+    out.push_str("  }\n");
+    out.push_str(" }\n");
+    out.push_str("}\n");
+    out
+}
+
+fn resolve_et_save_path(xob_abs: &Path, save_dir: Option<&str>) -> Result<PathBuf, String> {
+    let base = xob_abs
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| "Invalid xob file name".to_string())?;
+    let auto_name = format!("{}_test_prefab.et", base);
+    let dir = if let Some(sd) = save_dir {
+        let pb = PathBuf::from(sd);
+        if pb.is_dir() {
+            pb
+        } else {
+            xob_abs
+                .parent()
+                .ok_or_else(|| "Invalid xob directory".to_string())?
+                .to_path_buf()
+        }
+    } else {
+        xob_abs
+            .parent()
+            .ok_or_else(|| "Invalid xob directory".to_string())?
+            .to_path_buf()
+    };
+    Ok(dir.join(auto_name))
+}
+
+/// The prefab index, or an error telling the user to scan first when it is empty. File changes
+/// the watcher has not flushed yet are applied first, so matching sees prefabs saved a moment
+/// ago.
+fn open_prefab_index() -> Result<prefab_db::PrefabDb, String> {
+    crate::index_watcher::flush();
+    let db = prefab_db::open()?;
+    if db.count()? == 0 {
+        return Err("Prefab index is empty; scan the SVN root first".into());
+    }
+    Ok(db)
+}
+
+fn normalize_socket_key(s: &str) -> String {
+    s.trim()
+        .to_lowercase()
+        .replace('-', "_")
+        .replace(' ', "_")
+}
+
+/// Runs the configured match rules over `sockets` and ranks fuzzy candidates for the ones left
+/// unmatched; `ref_guids` are the Blender ref_guids by socket name and `scope` the prefab
+/// folders searched first. Reports progress when `progress` is set.
+fn match_sockets(
+    app: &dyn LogSink,
+    index: &prefab_db::PrefabDb,
+    sockets: &[String],
+    ref_guids: Option<&BTreeMap<String, String>>,
+    scope: &SearchScope,
+    progress: bool,
+) -> Result<Vec<SocketMatch>, String> {
+    let rules = RuleSet::configured()?;
+    if let Some(src) = rules.source.as_ref() {
+        emit_scan_log(app, "info", format!("Match rules: {}", src.to_string_lossy()), None, None);
+    }
+    let ref_guids: Option<BTreeMap<String, String>> =
+        ref_guids.map(|m| m.iter().map(|(k, v)| (normalize_socket_key(k), v.clone())).collect());
+    let total = sockets.len();
+    let mut out = Vec::with_capacity(total);
+    for (i, s) in sockets.iter().enumerate() {
+        if progress {
+            emit_scan_log(app, "info", "Matching sockets...", Some(i), Some(total));
+        }
+        let ref_guid = ref_guids.as_ref().and_then(|m| m.get(&normalize_socket_key(s)));
+        out.push(rules.match_socket(index, s, ref_guid.map(|g| g.as_str()), scope));
+    }
+    if progress && total > 0 {
+        emit_scan_log(app, "info", "Matching sockets...", Some(total), Some(total));
+    }
+    if out.iter().any(|m| m.prefab.is_none()) {
+        let prefabs = socket_rules::fuzzy_pool(index)?;
+        socket_rules::add_fuzzy_candidates(&prefabs, &mut out, scope);
+    }
+    Ok(out)
+}
+
+/// Logs every socket left unmatched with what was tried and its best candidate.
+fn log_unmatched(app: &dyn LogSink, matches: &[SocketMatch]) {
+    for m in matches.iter().filter(|m| m.prefab.is_none()) {
+        let tried: Vec<String> = m.trace.iter().filter_map(|a| a.lookup.clone()).collect();
+        let tried = if tried.is_empty() { "no rule applied".to_string() } else { format!("tried {}", tried.join(", ")) };
+        let best = match m.candidates.first() {
+            Some(c) => format!("; best candidate {} ({:?}, {:.2})", c.prefab.rel_path, c.method, c.score),
+            None => String::new(),
+        };
+        emit_scan_log(app, "warn", format!("Unmatched socket: {} ({}{})", m.socket, tried, best), None, None);
+    }
+}
+
+/// Sockets of the xob from its .txo, the .xob itself and the FBX; logs files that could not
+/// be read and sockets the files disagree on.
+pub fn discover_sockets(app: &dyn LogSink, xob_abs: &Path) -> SocketDiscovery {
+    let found = socket_discovery::discover(xob_abs);
+    for e in &found.errors {
+        emit_scan_log(app, "warn", e.clone(), None, None);
+    }
+    for d in &found.discrepancies {
+        emit_scan_log(app, "warn", format!("Socket mismatch: {}", d), None, None);
+    }
+    found
+}
+
+/// Every socket of the xob with the prefab it matches and the rules tried on it.
+pub async fn explain_socket_matches(app: &dyn LogSink, xob_path: &str) -> Result<Vec<SocketMatch>, String> {
+    let xob_abs = PathBuf::from(xob_path);
+    if !xob_abs.is_file() {
+        return Err(format!("Invalid .xob path: {}", xob_path));
+    }
+    let sockets = discover_sockets(app, &xob_abs).names();
+    let index = open_prefab_index()?;
+    let ref_guids = extract_socket_guids(app, &xob_abs).await;
+    let scope = SearchScope::new(&load_settings().extra_dirs.unwrap_or_default(), false);
+    match_sockets(app, &index, &sockets, ref_guids.as_ref(), &scope, false)
+}
+
+/// Socket name -> ref_guid from the FBX next to the xob. Reads the FBX natively and only
+/// launches Blender when the file cannot be parsed.
+pub async fn extract_socket_guids(app: &dyn LogSink, xob_abs: &Path) -> Option<BTreeMap<String, String>> {
+    let fbx_abs = xob_abs.with_extension("fbx");
+    if !fbx_abs.is_file() {
+        emit_scan_log(app, "info", format!("FBX GUID match: FBX not found: {}", fbx_abs.to_string_lossy()), None, None);
+        return None;
+    }
+    if let Some(map) = extract_cache::get(&fbx_abs).and_then(|e| e.guids) {
+        emit_scan_log(app, "info", format!("FBX GUID match: {} ref_guid (cached)", map.len()), None, None);
+        return Some(map);
+    }
+    match fbx::read_models(&fbx_abs) {
+        Ok(models) => {
+            let data = extract_cache::FbxExtract::from_models(&models);
+            let guids = data.guids.clone();
+            if let Err(e) = extract_cache::update(&fbx_abs, |d| d.merge(data)) {
+                emit_scan_log(app, "warn", format!("Failed to update extraction cache: {}", e), None, None);
+            }
+            if let Some(map) = guids {
+                emit_scan_log(app, "info", format!("FBX GUID match: extracted {} ref_guid", map.len()), None, None);
+                return Some(map);
+            }
+            emit_scan_log(app, "info", "FBX sockets carry no user properties (falling back to Blender)", None, None);
+        }
+        Err(e) => {
+            emit_scan_log(app, "warn", format!("{} (falling back to Blender)", e), None, None);
+        }
+    }
+    let map = extract_socket_guids_with_blender(app, xob_abs).await;
+    if let Some(m) = map.as_ref() {
+        let _ = extract_cache::update(&fbx_abs, |d| {
+            d.guids = Some(m.clone());
+            d.source = "blender".to_string();
+        });
+    }
+    map
+}
+
+async fn extract_socket_guids_with_blender(app: &dyn LogSink, xob_abs: &Path) -> Option<BTreeMap<String, String>> {
+    let fbx_abs = xob_abs.with_extension("fbx");
+    if !fbx_abs.is_file() {
+        emit_scan_log(app, "info", format!("Blender GUID match: FBX not found: {}", fbx_abs.to_string_lossy()), None, None);
+        return None;
+    }
+    let launch = match blender_worker::resolve_launch() {
+        Some(l) => l,
+        None => {
+            emit_scan_log(
+                app,
+                "info",
+                "Blender GUID match: blender.exe not configured (set OWLTOOLS_BLENDER_PATH) — skip",
+                None,
+                None,
+            );
+            return None;
+        }
+    };
+    emit_scan_log(
+        app,
+        "info",
+        format!("Blender GUID match: running {}", launch.exe.to_string_lossy()),
+        None,
+        None,
+    );
+
+    let res = blender_worker::request(
+        &launch,
+        "socket_guids",
+        json!({ "fbx": fbx_abs.to_string_lossy() }),
+        Duration::from_secs(60 * 5),
+        Duration::from_secs(60 * 5),
+        &|_| {},
+    )
+    .await;
+    let v = match res {
+        Ok(v) => v,
+        Err(e) => {
+            emit_scan_log(app, "warn", format!("Blender GUID match failed: {}", e), None, None);
+            return None;
+        }
+    };
+    let obj = v.as_object()?;
+    let mut map: BTreeMap<String, String> = BTreeMap::new();
+    for (k, val) in obj {
+        if let Some(g) = val.as_str() {
+            map.insert(k.to_string(), g.to_string());
+        }
+    }
+    if !map.is_empty() {
+        emit_scan_log(app, "info", format!("Blender GUID match: extracted {} ref_guid", map.len()), None, None);
+    }
+    Some(map)
+}
+
+fn build_slot_component_text_from_mappings(maps: &Vec<(String, String)>) -> Option<String> {
+    if maps.is_empty() {
+        return None;
+    }
+    let slot_guid = gen_hex16();
+    let mut lines: Vec<String> = Vec::new();
+    lines.push(format!("  WB_SlotBoneMappingsComponent \"{{{}}}\" {{", slot_guid));
+    lines.push("   SlotBoneMappings {".to_string());
+    for (bone_prefix, prefab_name) in maps {
+        let obj_guid = gen_hex16();
+        lines.push(format!("    SlotBoneMappingObject \"{{{}}}\" {{", obj_guid));
+        lines.push(format!("     BonePrefix \"{}\"", bone_prefix));
+        lines.push(format!("     Prefab \"{}\"", prefab_name));
+        lines.push("    }".to_string());
+    }
+    lines.push("   }".to_string());
+    lines.push("  }".to_string());
+    Some(lines.join("\n") + "\n")
+}
+
+fn build_child_entities_block(maps: &Vec<(String, String)>, hier_guid: &str) -> Option<String> {
+    if maps.is_empty() {
+        return None;
+    }
+    let mut by_prefab: BTreeMap<String, Vec<String>> = BTreeMap::new();
+    for (bone_prefix, prefab_name) in maps {
+        by_prefab.entry(prefab_name.clone()).or_default().push(bone_prefix.clone());
+    }
+    let mut lines: Vec<String> = Vec::new();
+    lines.push(" {".to_string());
+    for (prefab, sockets) in by_prefab {
+        if sockets.len() >= 2 {
+            lines.push(format!("  $grp GenericEntity : \"{}\" {{", prefab));
+            for s in sockets {
+                let inst_id = gen_hex16();
+                lines.push("   {".to_string());
+                lines.push(format!("    ID \"{}\"", inst_id));
+                lines.push("    components {".to_string());
+                lines.push(format!("     Hierarchy \"{{{}}}\" {{", hier_guid));
+                lines.push("      Enabled 1".to_string());
+                lines.push(format!("      PivotID \"{}\"", s));
+                lines.push("      AutoTransform 1".to_string());
+                lines.push("     }".to_string());
+                lines.push("    }".to_string());
+                lines.push("    coords 0 0 0".to_string());
+                lines.push("   }".to_string());
+            }
+            lines.push("  }".to_string());
+        } else {
+            let socket_name = sockets.get(0).cloned().unwrap_or_default();
+            lines.extend(socket_child_entity_lines("  ", &prefab, &socket_name, hier_guid));
+        }
+    }
+    lines.push(" }".to_string());
+    Some(lines.join("\n") + "\n")
+}
+
+fn socket_child_entity_lines(indent: &str, prefab: &str, socket_name: &str, hier_guid: &str) -> Vec<String> {
+    let inst_id = gen_hex16();
+    vec![
+        format!("{}GenericEntity : \"{}\" {{", indent, prefab),
+        format!("{} ID \"{}\"", indent, inst_id),
+        format!("{} components {{", indent),
+        format!("{}  Hierarchy \"{{{}}}\" {{", indent, hier_guid),
+        format!("{}   Enabled 1", indent),
+        format!("{}   PivotID \"{}\"", indent, socket_name),
+        format!("{}   AutoTransform 1", indent),
+        format!("{}  }}", indent),
+        format!("{} }}", indent),
+        format!("{} coords 0 0 0", indent),
+        format!("{}}}", indent),
+    ]
+}
+
+fn build_new_et_with_mesh(gen_id: &str, obj_field: &str) -> String {
+    let mesh_guid = gen_hex16();
+    format!(
+        "GenericEntity {{\n ID \"{}\"\n components {{\n  MeshObject \"{{{}}}\" {{\n   Object \"{}\"\n  }}\n }}\n coords 0 0 0\n}}\n",
+        gen_id, mesh_guid, obj_field
+    )
+}
+
+fn remove_blank_lines(text: &str) -> String {
+    text.lines()
+        .filter(|l| !l.trim().is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn insert_or_replace_slot_component(et_text: String, component_text: &str) -> String {
+    let (Ok(mut doc), Ok(mut nodes)) = (enfusion_text::parse(&et_text), enfusion_text::parse_nodes(component_text)) else {
+        // Fallback append
+        return format!("{}\n{}", et_text, component_text);
+    };
+    let Some(component) = nodes.pop() else { return et_text; };
+    let class = component.as_object().and_then(|o| o.class_name()).unwrap_or("").to_string();
+    let Some(components) = doc.root_mut().and_then(|r| r.object_mut("components")) else {
+        return format!("{}\n{}", et_text, component_text);
+    };
+    match components.position(|n| n.as_object().and_then(|o| o.class_name()) == Some(class.as_str())) {
+        Some(idx) => components.replace_child(idx, component),
+        None => components.push_child(component),
+    }
+    doc.to_string()
+}
+
+fn insert_or_replace_child_entities_block(et_text: String, block_text: &str) -> String {
+    let (Ok(mut doc), Ok(mut nodes)) = (enfusion_text::parse(&et_text), enfusion_text::parse_nodes(block_text)) else {
+        return format!("{}\n{}", et_text, block_text);
+    };
+    let Some(block) = nodes.pop() else { return et_text; };
+    let Some(root) = doc.root_mut() else {
+        return format!("{}\n{}", et_text, block_text);
+    };
+    // Child entities live in the entity's anonymous `{ }` block after `coords`.
+    match root.position(|n| n.as_object().map(|o| o.is_anonymous()).unwrap_or(false)) {
+        Some(idx) => root.replace_child(idx, block),
+        None => root.push_child(block),
+    }
+    doc.to_string()
+}
+
+#[derive(Serialize, Default)]
+pub struct EtMergeReport {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    pub unchanged: Vec<String>,
+    /// Sockets referenced by the prefab that are gone from the .txo.
+    pub missing: Vec<String>,
+    pub removed: Vec<String>,
+}
+
+fn same_resource(a: &str, b: &str) -> bool {
+    match (extract_guid(a), extract_guid(b)) {
+        (Some(ga), Some(gb)) => ga == gb,
+        _ => a.trim().eq_ignore_ascii_case(b.trim()),
+    }
+}
+
+fn socket_pivot_of(entity: &enfusion_text::Object) -> Option<String> {
+    let hier = entity.object("components")?.object("Hierarchy")?;
+    hier.property("PivotID")?.value_str().map(|s| s.to_string())
+}
+
+fn push_generated_node(obj: &mut enfusion_text::Object, text: &str, nl: &str) -> Result<(), String> {
+    let indent = obj.child_indent();
+    for mut node in enfusion_text::parse_nodes(text)? {
+        node.reindent(&indent, nl);
+        obj.push_child(node);
+    }
+    Ok(())
+}
+
+/// Text of the prefab a `: "{GUID}Prefabs/..."` reference names, looked up in the index.
+fn read_parent_prefab(index: &prefab_db::PrefabDb, reference: &str) -> Option<String> {
+    let rel = reference.rsplit_once('}').map(|(_, r)| r).unwrap_or(reference);
+    let res = extract_guid(reference).and_then(|g| index.prefab_by_guid(&g)).or_else(|| index.by_rel_path(rel))?;
+    fs::read_to_string(&res.abs_path).ok()
+}
+
+const MAX_INHERITANCE_DEPTH: usize = 32;
+
+/// Parsed prefabs `entity` inherits from, nearest first.
+fn ancestor_prefabs(
+    entity: &enfusion_text::Object,
+    parent_text: &dyn Fn(&str) -> Option<String>,
+) -> Vec<enfusion_text::Document> {
+    let mut out = Vec::new();
+    let mut parent = entity.parent().map(|p| p.to_string());
+    while let Some(reference) = parent.take() {
+        if out.len() >= MAX_INHERITANCE_DEPTH {
+            break;
+        }
+        let Some(doc) = parent_text(&reference).and_then(|t| enfusion_text::parse(&t).ok()) else { break; };
+        parent = doc.root().and_then(|r| r.parent()).map(|p| p.to_string());
+        out.push(doc);
+    }
+    out
+}
+
+fn entity_id(entity: &enfusion_text::Object) -> Option<&str> {
+    entity.property("ID").and_then(|p| p.value_str())
+}
+
+/// Slot component a prefab inherits: its ID and, by lowercase bone prefix, the mapping
+/// object IDs and prefabs as the nearest ancestor declares or overrides them.
+struct InheritedSlot {
+    id: String,
+    mappings: BTreeMap<String, (Option<String>, String)>,
+}
+
+fn inherited_slot_component(ancestors: &[enfusion_text::Document]) -> Option<InheritedSlot> {
+    let mut id: Option<String> = None;
+    // Overrides name a mapping by its object ID only; the bone prefix comes from further up.
+    let mut bones: HashMap<String, String> = HashMap::new();
+    let mut prefabs: HashMap<String, String> = HashMap::new();
+    let mut mappings = BTreeMap::new();
+    for doc in ancestors {
+        let Some(slot) = doc.root().and_then(|r| r.object("components")).and_then(|c| c.object("WB_SlotBoneMappingsComponent")) else {
+            continue;
+        };
+        if id.is_none() {
+            id = slot.id().map(|s| s.to_string());
+        }
+        for o in slot.object("SlotBoneMappings").into_iter().flat_map(|a| a.objects()) {
+            let bone = o.property("BonePrefix").and_then(|p| p.value_str());
+            let prefab = o.property("Prefab").and_then(|p| p.value_str());
+            match o.id() {
+                Some(oid) => {
+                    if let Some(b) = bone {
+                        bones.entry(oid.to_string()).or_insert_with(|| b.to_lowercase());
+                    }
+                    if let Some(p) = prefab {
+                        prefabs.entry(oid.to_string()).or_insert_with(|| p.to_string());
+                    }
+                }
+                None => {
+                    if let Some(b) = bone {
+                        mappings.entry(b.to_lowercase()).or_insert((None, prefab.unwrap_or("").to_string()));
+                    }
+                }
+            }
+        }
+    }
+    for (oid, bone) in bones {
+        let prefab = prefabs.get(&oid).cloned().unwrap_or_default();
+        mappings.entry(bone).or_insert((Some(oid), prefab));
+    }
+    Some(InheritedSlot { id: id?, mappings })
+}
+
+/// Socket child entity a prefab inherits: its `ID` and the prefab the nearest ancestor gives it.
+struct InheritedChild {
+    id: Option<String>,
+    prefab: String,
+}
+
+/// Inherited socket child entities by lowercase `PivotID`.
+fn inherited_socket_children(ancestors: &[enfusion_text::Document]) -> HashMap<String, InheritedChild> {
+    let mut pivots: HashMap<String, String> = HashMap::new();
+    let mut prefabs: HashMap<String, String> = HashMap::new();
+    let mut out = HashMap::new();
+    for doc in ancestors {
+        let Some(block) = doc.root().and_then(|r| r.objects().find(|o| o.is_anonymous())) else { continue; };
+        for ent in block.objects() {
+            // Group members take the group's prefab.
+            let members: Vec<&enfusion_text::Object> = if ent.is_group() { ent.objects().collect() } else { vec![ent] };
+            for member in members {
+                let pivot = socket_pivot_of(member).map(|p| p.to_lowercase());
+                let prefab = ent.parent().unwrap_or("").to_string();
+                match entity_id(member) {
+                    Some(eid) => {
+                        if let Some(p) = pivot {
+                            pivots.entry(eid.to_string()).or_insert(p);
+                        }
+                        prefabs.entry(eid.to_string()).or_insert(prefab);
+                    }
+                    None => {
+                        if let Some(p) = pivot {
+                            out.entry(p).or_insert(InheritedChild { id: None, prefab });
+                        }
+                    }
+                }
+            }
+        }
+    }
+    for (eid, pivot) in pivots {
+        let prefab = prefabs.get(&eid).cloned().unwrap_or_default();
+        out.entry(pivot).or_insert(InheritedChild { id: Some(eid), prefab });
+    }
+    out
+}
+
+/// Update socket mappings and socket child entities of an existing prefab in place.
+/// Everything else (other components, IDs, comments, formatting) is left untouched.
+/// `parent_text` returns the text of an inherited prefab, so the slot component and socket
+/// child entities that come from a parent are overridden by their IDs rather than added a
+/// second time.
+pub fn merge_sockets_into_et(
+    app: &dyn LogSink,
+    et_text: &str,
+    maps: &[(String, String)],
+    sockets: &[String],
+    remove_missing: bool,
+    parent_text: &dyn Fn(&str) -> Option<String>,
+) -> Result<(String, EtMergeReport), String> {
+    let mut doc = enfusion_text::parse(et_text)?;
+    let nl = doc.newline();
+    let wanted: BTreeMap<String, (String, String)> = maps
+        .iter()
+        .map(|(s, p)| (s.to_lowercase(), (s.clone(), p.clone())))
+        .collect();
+    let present: HashSet<String> = sockets.iter().map(|s| s.to_lowercase()).collect();
+    let is_gone = |sock: &str| {
+        let l = sock.to_lowercase();
+        l.starts_with("socket_") && !present.contains(&l)
+    };
+
+    let mut had: HashSet<String> = HashSet::new();
+    let mut updated: BTreeSet<String> = BTreeSet::new();
+    let mut missing: BTreeSet<String> = BTreeSet::new();
+    let mut removed: BTreeSet<String> = BTreeSet::new();
+
+    let ancestors = doc.root().map(|r| ancestor_prefabs(r, parent_text)).unwrap_or_default();
+    let inherited = inherited_slot_component(&ancestors);
+    let inherited_children = inherited_socket_children(&ancestors);
+    let cannot_override = |what: &str, sock: &str| {
+        emit_scan_log(
+            app,
+            "warn",
+            format!("Inherited {} for {} has no ID and cannot be overridden; change it in the parent prefab", what, sock),
+            None,
+            None,
+        );
+    };
+
+    let root = doc.root_mut().ok_or_else(|| "Prefab has no entity".to_string())?;
+
+    // 1) WB_SlotBoneMappingsComponent
+    if root.object("components").is_none() {
+        let at = root.position(|n| n.as_property().map(|p| p.key() == "ID").unwrap_or(false)).map(|i| i + 1).unwrap_or(0);
+        let mut node = enfusion_text::parse_nodes("components {\n}")?.remove(0);
+        node.reindent(&root.child_indent(), nl);
+        root.insert_child(at, node);
+    }
+    let components = root.object_mut("components").ok_or_else(|| "Prefab has no components block".to_string())?;
+    if let Some(slot) = components.object_mut("WB_SlotBoneMappingsComponent") {
+        if slot.object("SlotBoneMappings").is_none() {
+            push_generated_node(slot, "SlotBoneMappings {\n}", nl)?;
+        }
+        // The prefab already overrides the inherited component: its mappings may carry only an
+        // object ID and a prefab.
+        let overridden = inherited.as_ref().filter(|i| slot.id() == Some(i.id.as_str()));
+        let bone_of: HashMap<&str, &str> = overridden
+            .into_iter()
+            .flat_map(|i| i.mappings.iter())
+            .filter_map(|(bone, (oid, _))| Some((oid.as_deref()?, bone.as_str())))
+            .collect();
+        let arr = slot.object_mut("SlotBoneMappings").ok_or_else(|| "Invalid SlotBoneMappings".to_string())?;
+        let mut seen: HashSet<String> = HashSet::new();
+        for idx in (0..arr.children.len()).rev() {
+            let enfusion_text::Node::Object(o) = &mut arr.children[idx] else { continue; };
+            let own_bone = o.property("BonePrefix").and_then(|p| p.value_str()).map(|s| s.to_string());
+            let Some(bone) = own_bone.clone().or_else(|| o.id().and_then(|id| bone_of.get(id)).map(|b| b.to_string())) else {
+                continue;
+            };
+            let key = bone.to_lowercase();
+            if let Some((_, prefab)) = wanted.get(&key) {
+                had.insert(key.clone());
+                seen.insert(key);
+                let same = o.property("Prefab").and_then(|p| p.value_str()).map(|v| same_resource(v, prefab)).unwrap_or(false);
+                if !same {
+                    match o.property_mut("Prefab") {
+                        Some(p) => p.set_value_str(prefab),
+                        None => push_generated_node(o, &format!("Prefab \"{}\"", prefab), nl)?,
+                    }
+                    updated.insert(bone);
+                }
+            } else if own_bone.is_some() && is_gone(&bone) {
+                missing.insert(bone.clone());
+                if remove_missing {
+                    arr.remove_child(idx);
+                    removed.insert(bone);
+                }
+            }
+        }
+        for (sock, prefab) in maps {
+            let key = sock.to_lowercase();
+            if seen.contains(&key) {
+                continue;
+            }
+            if let Some((obj_id, current)) = overridden.and_then(|i| i.mappings.get(&key)) {
+                had.insert(key);
+                if same_resource(current, prefab) {
+                    continue;
+                }
+                let Some(obj_id) = obj_id else {
+                    cannot_override("slot bone mapping", sock);
+                    continue;
+                };
+                updated.insert(sock.clone());
+                let text = format!("SlotBoneMappingObject \"{}\" {{\n Prefab \"{}\"\n}}", obj_id, prefab);
+                push_generated_node(arr, &text, nl)?;
+                continue;
+            }
+            let text = format!(
+                "SlotBoneMappingObject \"{{{}}}\" {{\n BonePrefix \"{}\"\n Prefab \"{}\"\n}}",
+                gen_hex16(),
+                sock,
+                prefab
+            );
+            push_generated_node(arr, &text, nl)?;
+        }
+    } else if let Some(inherited) = inherited {
+        // Override the parent's component: changed mappings by their object ID, new ones added.
+        let mut objs: Vec<String> = Vec::new();
+        for (sock, prefab) in maps {
+            let key = sock.to_lowercase();
+            match inherited.mappings.get(&key) {
+                Some((_, current)) if same_resource(current, prefab) => {
+                    had.insert(key);
+                }
+                Some((Some(obj_id), _)) => {
+                    had.insert(key);
+                    updated.insert(sock.clone());
+                    objs.push(format!("  SlotBoneMappingObject \"{}\" {{\n   Prefab \"{}\"\n  }}", obj_id, prefab));
+                }
+                Some((None, _)) => {
+                    had.insert(key);
+                    cannot_override("slot bone mapping", sock);
+                }
+                None => objs.push(format!(
+                    "  SlotBoneMappingObject \"{{{}}}\" {{\n   BonePrefix \"{}\"\n   Prefab \"{}\"\n  }}",
+                    gen_hex16(),
+                    sock,
+                    prefab
+                )),
+            }
+        }
+        if !objs.is_empty() {
+            emit_scan_log(app, "info", format!("Overriding inherited WB_SlotBoneMappingsComponent {}", inherited.id), None, None);
+            let text = format!(
+                "WB_SlotBoneMappingsComponent \"{}\" {{\n SlotBoneMappings {{\n{}\n }}\n}}",
+                inherited.id,
+                objs.join("\n")
+            );
+            push_generated_node(components, &text, nl)?;
+        }
+    } else if let Some(comp) = build_slot_component_text_from_mappings(&maps.to_vec()) {
+        push_generated_node(components, &comp, nl)?;
+    }
+
+    // 2) Child entities attached to sockets (`PivotID "socket_..."`)
+    if root.objects().all(|o| !o.is_anonymous()) {
+        push_generated_node(root, "{\n}", nl)?;
+    }
+    let block = root.objects_mut().find(|o| o.is_anonymous()).ok_or_else(|| "Invalid child entity block".to_string())?;
+    let mut hier_guid: Option<String> = None;
+    let mut seen: HashSet<String> = HashSet::new();
+    for idx in (0..block.children.len()).rev() {
+        let enfusion_text::Node::Object(ent) = &mut block.children[idx] else { continue; };
+        if ent.is_group() {
+            let group_prefab = ent.parent().unwrap_or("").to_string();
+            let before = ent.objects().count();
+            for j in (0..ent.children.len()).rev() {
+                let Some(inst) = ent.children[j].as_object() else { continue; };
+                let Some(pivot) = socket_pivot_of(inst) else { continue; };
+                if hier_guid.is_none() {
+                    hier_guid = inst.object("components").and_then(|c| c.object("Hierarchy")).and_then(|h| h.id()).map(|s| s.to_string());
+                }
+                let key = pivot.to_lowercase();
+                if let Some((_, prefab)) = wanted.get(&key) {
+                    had.insert(key.clone());
+                    if !same_resource(&group_prefab, prefab) {
+                        // Re-created below as a standalone entity with the new prefab.
+                        ent.remove_child(j);
+                        updated.insert(pivot);
+                    } else {
+                        seen.insert(key);
+                    }
+                } else if is_gone(&pivot) {
+                    missing.insert(pivot.clone());
+                    if remove_missing {
+                        ent.remove_child(j);
+                        removed.insert(pivot);
+                    }
+                }
+            }
+            if before > 0 && ent.objects().count() == 0 {
+                block.remove_child(idx);
+            }
+        } else {
+            let Some(pivot) = socket_pivot_of(ent) else { continue; };
+            if hier_guid.is_none() {
+                hier_guid = ent.object("components").and_then(|c| c.object("Hierarchy")).and_then(|h| h.id()).map(|s| s.to_string());
+            }
+            let key = pivot.to_lowercase();
+            if let Some((_, prefab)) = wanted.get(&key) {
+                had.insert(key.clone());
+                seen.insert(key);
+                if !ent.parent().map(|p| same_resource(p, prefab)).unwrap_or(false) {
+                    ent.set_parent(prefab);
+                    updated.insert(pivot);
+                }
+            } else if is_gone(&pivot) {
+                missing.insert(pivot.clone());
+                if remove_missing {
+                    block.remove_child(idx);
+                    removed.insert(pivot);
+                }
+            }
+        }
+    }
+    let hier_guid = hier_guid
+        .map(|h| h.trim_matches(|c| c == '{' || c == '}').to_string())
+        .unwrap_or_else(gen_hex16);
+    for (sock, prefab) in maps {
+        let key = sock.to_lowercase();
+        if seen.contains(&key) {
+            continue;
+        }
+        if let Some(child) = inherited_children.get(&key) {
+            had.insert(key);
+            let Some(eid) = child.id.as_deref() else {
+                cannot_override("socket child entity", sock);
+                continue;
+            };
+            // Overridden by an earlier merge, or still as the parent has it.
+            let own = block.position(|n| n.as_object().is_some_and(|o| !o.is_group() && entity_id(o) == Some(eid)));
+            if let Some(enfusion_text::Node::Object(ent)) = own.map(|i| &mut block.children[i]) {
+                if !ent.parent().map(|p| same_resource(p, prefab)).unwrap_or(false) {
+                    ent.set_parent(prefab);
+                    updated.insert(sock.clone());
+                }
+            } else if !same_resource(&child.prefab, prefab) {
+                push_generated_node(block, &format!("GenericEntity : \"{}\" {{\n ID \"{}\"\n}}", prefab, eid), nl)?;
+                updated.insert(sock.clone());
+            }
+            continue;
+        }
+        let text = socket_child_entity_lines("", prefab, sock, &hier_guid).join("\n");
+        push_generated_node(block, &text, nl)?;
+    }
+
+    let mut report = EtMergeReport::default();
+    for (key, (sock, _)) in &wanted {
+        if !had.contains(key) {
+            report.added.push(sock.clone());
+        } else if updated.iter().any(|u| u.eq_ignore_ascii_case(sock)) {
+            report.updated.push(sock.clone());
+        } else {
+            report.unchanged.push(sock.clone());
+        }
+    }
+    report.missing = missing.into_iter().collect();
+    report.removed = removed.into_iter().collect();
+    emit_scan_log(
+        app,
+        "info",
+        format!(
+            "Merge: added={}, updated={}, unchanged={}, removed={}",
+            report.added.len(),
+            report.updated.len(),
+            report.unchanged.len(),
+            report.removed.len()
+        ),
+        None,
+        None,
+    );
+    for m in &report.missing {
+        let action = if report.removed.contains(m) { "removed" } else { "kept" };
+        emit_scan_log(app, "warn", format!("Socket no longer in .txo: {} ({})", m, action), None, None);
+    }
+    Ok((doc.to_string(), report))
+}
+
+#[derive(Serialize)]
+pub struct CreateEtResult {
+    pub et_path: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub meta_path: Option<String>,
+    pub sockets: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub suggested_extra_dirs: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub merge: Option<EtMergeReport>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<write_plan::WritePlan>,
+    /// Per socket: the matched prefab and the rules tried.
+    pub matches: Vec<SocketMatch>,
+    /// Where each socket was found, its local transform, and what the files disagree on.
+    pub discovery: SocketDiscovery,
+}
+
+#[derive(Serialize)]
+pub struct SuggestFoldersResult {
+    pub sockets: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub suggested_extra_dirs: Vec<String>,
+}
+
+pub async fn suggest_prefab_folders(
+    app: &dyn LogSink,
+    xob_path: String,
+    svn_root: Option<String>,
+    extra_dirs: Option<Vec<String>>,
+) -> Result<SuggestFoldersResult, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() {
+        return Err(format!("Invalid .xob path: {}", xob_path));
+    }
+    emit_scan_log(app, "info", format!("Detect folders for xob: {}", xob_abs.to_string_lossy()), None, None);
+
+    let sockets = discover_sockets(app, &xob_abs).names();
+
+    let index = open_prefab_index()?;
+    let _svn_root = svn_root
+        .or_else(|| load_settings().svn_root)
+        .filter(|s| !s.is_empty());
+    let extra_dirs = extra_dirs
+        .or_else(|| load_settings().extra_dirs)
+        .unwrap_or_default();
+
+    let total_s = sockets.len();
+    let blender_sock_guids = extract_socket_guids(app, &xob_abs).await;
+    let scope = SearchScope::new(&extra_dirs, false);
+    let matches = match_sockets(app, &index, &sockets, blender_sock_guids.as_ref(), &scope, false)?;
+    log_unmatched(app, &matches);
+
+    let matched = matches.iter().filter(|m| m.prefab.is_some()).count();
+    let unmatched = total_s.saturating_sub(matched);
+
+    let mut suggested: BTreeSet<String> = BTreeSet::new();
+    for r in matches.iter().filter_map(|m| m.prefab.as_ref()) {
+        if let Some(dir) = Path::new(&r.abs_path).parent() {
+            let dir_s = dir.to_string_lossy().to_string();
+            if extra_dirs.contains(&dir_s) {
+                continue;
+            }
+            // IMPORTANT: show detected folders even if they are under SVN root.
+            // This matches the EnfAutoSocket UX where users can see/choose prefab folders explicitly.
+            suggested.insert(dir_s);
+        }
+    }
+    let suggested_extra_dirs: Vec<String> = suggested.into_iter().collect();
+    emit_scan_log(
+        app,
+        "info",
+        format!("Detected prefab folders: {}", suggested_extra_dirs.len()),
+        None,
+        None,
+    );
+
+    Ok(SuggestFoldersResult {
+        sockets: total_s,
+        matched,
+        unmatched,
+        suggested_extra_dirs,
+    })
+}
+
+/// Matches sockets and renders the prefab text; returns the result and the .et contents
+/// without touching the output file.
+pub async fn build_et_from_xob(app: &dyn LogSink, args: &CreateEtArgs) -> Result<(CreateEtResult, String), String> {
+    let CreateEtArgs {
+        xob_path,
+        save_dir,
+        svn_root,
+        extra_dirs,
+        extra_dirs_only,
+        merge_into,
+        remove_missing,
+        choices,
+        accept_top,
+        out_path,
+        persist_settings,
+        ..
+    } = args.clone();
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() {
+        let msg = format!("Invalid .xob path: {}", xob_path);
+        emit_scan_log(app, "error", msg.clone(), None, None);
+        return Err(msg);
+    }
+
+    emit_scan_log(app, "info", format!("Processing xob: {}", xob_abs.to_string_lossy()), None, None);
+
+    let (obj_guid, obj_path) = read_xob_object_field_from_meta(&xob_abs)?;
+    let obj_field = format!("{{{}}}{}", obj_guid, obj_path);
+    emit_scan_log(app, "info", "Loaded .xob.meta Name/GUID", None, None);
+
+    let discovery = discover_sockets(app, &xob_abs);
+    let sockets = discovery.names();
+    if sockets.is_empty() {
+        emit_scan_log(app, "warn", "No sockets found in the .txo, .xob or FBX", None, None);
+    } else {
+        emit_scan_log(app, "info", format!("Found {} sockets", sockets.len()), None, None);
+    }
+
+    let index = open_prefab_index()?;
+    emit_scan_log(
+        app,
+        "info",
+        format!("Loaded prefab index (entries={})", index.count()?),
+        None,
+        None,
+    );
+
+    let svn_root = svn_root
+        .or_else(|| load_settings().svn_root)
+        .filter(|s| !s.is_empty());
+    let extra_dirs = extra_dirs
+        .or_else(|| load_settings().extra_dirs)
+        .unwrap_or_default();
+    if !extra_dirs.is_empty() {
+        let mode = if extra_dirs_only.unwrap_or(false) { "only these" } else { "searched first" };
+        emit_scan_log(app, "info", format!("Extra dirs: {} ({})", extra_dirs.len(), mode), None, None);
+    }
+
+    let total_s = sockets.len();
+
+    // Blender GUID extraction (optional)
+    let blender_sock_guids = extract_socket_guids(app, &xob_abs).await;
+    let blender_sock_guids_lc: Option<BTreeMap<String, String>> = blender_sock_guids.as_ref().map(|m| {
+        m.iter()
+            .map(|(k, v)| (normalize_socket_key(k), v.clone()))
+            .collect::<BTreeMap<String, String>>()
+    });
+    if let Some(m) = blender_sock_guids.as_ref() {
+        for (k, v) in m.iter().take(6) {
+            emit_scan_log(app, "info", format!("Blender GUID: {} -> {}", k, v), None, None);
+        }
+    }
+
+    if let Some(bg) = blender_sock_guids_lc.as_ref() {
+        emit_scan_log(
+            app,
+            "info",
+            format!("Blender GUID keys: {} (normalized)", bg.len()),
+            None,
+            None,
+        );
+        for s in sockets.iter().take(5) {
+            let nk = normalize_socket_key(s);
+            let hit = bg.contains_key(&nk);
+            emit_scan_log(
+                app,
+                "info",
+                format!("Socket key check: '{}' -> '{}' | in_blender={}", s, nk, hit),
+                None,
+                None,
+            );
+        }
+    }
+
+    let scope = SearchScope::new(&extra_dirs, extra_dirs_only.unwrap_or(false));
+    let mut matches = match_sockets(app, &index, &sockets, blender_sock_guids.as_ref(), &scope, true)?;
+    socket_rules::apply_choices(&index, &mut matches, &choices.unwrap_or_default(), accept_top.unwrap_or(false))?;
+    log_unmatched(app, &matches);
+    let maps: Vec<(String, String)> = matches
+        .iter()
+        .filter_map(|m| m.prefab.as_ref().map(|r| (m.socket.clone(), r.name_value.clone())))
+        .collect();
+
+    let matched = maps.len();
+    let unmatched = total_s.saturating_sub(matched);
+    emit_scan_log(app, "info", format!("Matched sockets: {}/{}", matched, total_s), None, None);
+
+    // Auto-suggest extra dirs based on matched prefab absolute paths in cache
+    let mut suggested: BTreeSet<String> = BTreeSet::new();
+    let svn_norm = svn_root.as_ref().map(|s| PathBuf::from(s).to_string_lossy().to_string().to_lowercase());
+    for r in matches.iter().filter_map(|m| m.prefab.as_ref()) {
+        if let Some(dir) = Path::new(&r.abs_path).parent() {
+            let dir_s = dir.to_string_lossy().to_string();
+            let dir_l = dir_s.to_lowercase();
+            let under_svn = svn_norm.as_ref().map(|svn| dir_l.starts_with(svn)).unwrap_or(false);
+            if !under_svn {
+                suggested.insert(dir_s);
+            }
+        }
+    }
+    let suggested_extra_dirs: Vec<String> = suggested.into_iter().collect();
+    if !suggested_extra_dirs.is_empty() {
+        emit_scan_log(
+            app,
+            "info",
+            format!("Auto-assign prefab folders: {}", suggested_extra_dirs.len()),
+            None,
+            None,
+        );
+    }
+
+    let merge_into = merge_into.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+    let out_path = match (merge_into.as_ref(), out_path.filter(|p| !p.trim().is_empty())) {
+        (Some(p), _) => p.clone(),
+        (None, Some(p)) => PathBuf::from(p),
+        (None, None) => resolve_et_save_path(&xob_abs, save_dir.as_deref())?,
+    };
+    if persist_settings.unwrap_or(true) {
+        if let Some(sd) = save_dir.as_deref() {
+            remember_save_dir(Some(sd.to_string())).ok();
+        }
+        // Persist extra dirs union (existing + suggested)
+        let mut merged_extra = extra_dirs.clone();
+        for p in &suggested_extra_dirs {
+            if !merged_extra.contains(p) {
+                merged_extra.push(p.clone());
+            }
+        }
+        if !merged_extra.is_empty() {
+            remember_extra_dirs(merged_extra.clone()).ok();
+        }
+        if let Some(sr) = svn_root.as_deref() {
+            remember_svn_root(Some(sr.to_string())).ok();
+        }
+    }
+
+    let mut merge_report: Option<EtMergeReport> = None;
+    let et_text = if let Some(target) = merge_into.as_ref() {
+        if sockets.is_empty() {
+            let msg = "No sockets found; refusing to merge into existing prefab".to_string();
+            emit_scan_log(app, "error", msg.clone(), None, None);
+            return Err(msg);
+        }
+        emit_scan_log(app, "info", format!("Merging into existing prefab: {}", target.to_string_lossy()), None, None);
+        let existing = fs::read_to_string(target).map_err(|e| format!("Failed to read prefab: {}", e))?;
+        let parent_text = |r: &str| read_parent_prefab(&index, r);
+        let (merged, report) =
+            merge_sockets_into_et(app, &existing, &maps, &sockets, remove_missing.unwrap_or(false), &parent_text)
+                .map_err(|e| format!("Failed to parse prefab {}: {}", target.to_string_lossy(), e))?;
+        merge_report = Some(report);
+        merged
+    } else {
+        let gen_id = gen_hex16();
+        let mut et_text = build_new_et_with_mesh(&gen_id, &obj_field);
+        if let Some(comp) = build_slot_component_text_from_mappings(&maps) {
+            et_text = insert_or_replace_slot_component(et_text, &comp);
+        }
+        let hier_guid = gen_hex16();
+        if let Some(child) = build_child_entities_block(&maps, &hier_guid) {
+            et_text = insert_or_replace_child_entities_block(et_text, &child);
+        }
+        remove_blank_lines(&et_text)
+    };
+
+    let res = CreateEtResult {
+        et_path: out_path.to_string_lossy().to_string(),
+        meta_path: None,
+        sockets: total_s,
+        matched,
+        unmatched,
+        suggested_extra_dirs,
+        merge: merge_report,
+        plan: None,
+        matches,
+        discovery,
+    };
+    Ok((res, et_text))
+}
+
+fn write_et_file(app: &dyn LogSink, out_path: &Path, et_text: &str) -> Result<(), String> {
+    if let Some(parent) = out_path.parent() {
+        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+    }
+    emit_scan_log(app, "info", format!("Writing .et: {}", out_path.to_string_lossy()), None, None);
+    fs::write(out_path, et_text).map_err(|e| e.to_string())
+}
+
+fn emit_plan_summary(app: &dyn LogSink, plan: &write_plan::WritePlan) {
+    for f in &plan.files {
+        emit_scan_log(app, "info", format!("Dry run: {} ({})", f.path, f.status()), None, None);
+    }
+    emit_scan_log(app, "info", format!("Dry run finished; nothing written (plan {})", plan.id), None, None);
+}
+
+/// Arguments of the AutoSocket prefab generators (`create_new_et_from_xob` and friends).
+#[derive(Deserialize, Default, Clone)]
+pub struct CreateEtArgs {
+    pub xob_path: String,
+    pub save_dir: Option<String>,
+    pub svn_root: Option<String>,
+    /// Prefab folders searched first, in priority order.
+    pub extra_dirs: Option<Vec<String>>,
+    /// Match only prefabs inside `extra_dirs` (GUID matches excepted).
+    pub extra_dirs_only: Option<bool>,
+    pub merge_into: Option<String>,
+    pub remove_missing: Option<bool>,
+    pub dry_run: Option<bool>,
+    /// Socket name -> GUID, project path or file name of the prefab to use instead of the
+    /// rule match; an empty value leaves the socket unmatched.
+    pub choices: Option<BTreeMap<String, String>>,
+    /// Map sockets no rule matched to their best candidate.
+    pub accept_top: Option<bool>,
+    /// Output .et; `save_dir` and the default name decide when unset.
+    pub out_path: Option<String>,
+    /// Remember the save dir, SVN root and detected prefab folders in the settings; on
+    /// unless false.
+    pub persist_settings: Option<bool>,
+}
+
+pub async fn create_et(app: &dyn LogSink, args: CreateEtArgs) -> Result<CreateEtResult, String> {
+    let (mut res, et_text) = build_et_from_xob(app, &args).await?;
+    let out_path = PathBuf::from(&res.et_path);
+    if args.dry_run.unwrap_or(false) {
+        let plan = write_plan::store(vec![write_plan::plan_file(&out_path, et_text)]);
+        emit_plan_summary(app, &plan);
+        res.plan = Some(plan);
+        return Ok(res);
+    }
+    write_et_file(app, &out_path, &et_text)?;
+    emit_scan_log(app, "info", "Process finished", None, None);
+    Ok(res)
+}
+
+pub async fn create_et_with_meta(app: &dyn LogSink, args: CreateEtArgs) -> Result<CreateEtResult, String> {
+    let (mut res, et_text) = build_et_from_xob(app, &args).await?;
+    let dry_run = args.dry_run.unwrap_or(false);
+    let et_abs = PathBuf::from(&res.et_path);
+    let meta_path = PathBuf::from(format!("{}.meta", et_abs.to_string_lossy()));
+    res.meta_path = Some(meta_path.to_string_lossy().to_string());
+
+    // Merged prefabs keep their GUID; never regenerate an existing .meta.
+    let meta = if res.merge.is_some() && meta_path.is_file() {
+        None
+    } else {
+        let rel = rel_from_known_roots(&et_abs);
+        let name_value = format!("{{{}}}{}", gen_hex16(), rel);
+        Some((build_et_meta_text(&name_value), name_value))
+    };
+
+    if dry_run {
+        let mut files = vec![write_plan::plan_file(&et_abs, et_text)];
+        if let Some((meta_text, _)) = meta {
+            files.push(write_plan::plan_file(&meta_path, meta_text));
+        }
+        let plan = write_plan::store(files);
+        emit_plan_summary(app, &plan);
+        res.plan = Some(plan);
+        return Ok(res);
+    }
+
+    write_et_file(app, &et_abs, &et_text)?;
+    if let Some((meta_text, name_value)) = meta {
+        emit_scan_log(app, "info", format!("Writing .et.meta: {}", meta_path.to_string_lossy()), None, None);
+        fs::write(&meta_path, meta_text).map_err(|e| e.to_string())?;
+
+        // Incrementally update prefab cache so the new .et is available immediately for matching/suggestions.
+        if let Err(err) = update_prefab_cache_with_new_meta(&et_abs, &meta_path, &name_value) {
+            emit_scan_log(app, "warn", format!("Failed to update prefab cache: {}", err), None, None);
+        } else {
+            emit_scan_log(app, "info", "Updated prefab cache", None, None);
+        }
+    }
+    emit_scan_log(app, "info", "Process finished", None, None);
+    Ok(res)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_sink::{LogChannel, MemorySink};
+
+    const PARENT: &str = "GenericEntity {\n ID \"5A3C00000000AAAA\"\n components {\n  WB_SlotBoneMappingsComponent \"{5A3C0000000000A1}\" {\n   SlotBoneMappings {\n    SlotBoneMappingObject \"{5A3C0000000000B1}\" {\n     BonePrefix \"socket_lamp\"\n     Prefab \"{1111111111111111}Prefabs/lamp.et\"\n    }\n   }\n  }\n }\n coords 0 0 0\n {\n  GenericEntity : \"{1111111111111111}Prefabs/lamp.et\" {\n   ID \"5A3C0000000000D1\"\n   components {\n    Hierarchy \"{5A3C0000000000F0}\" {\n     Enabled 1\n     PivotID \"socket_lamp\"\n     AutoTransform 1\n    }\n   }\n   coords 0 0 0\n  }\n }\n}\n";
+    const CHILD: &str = "GenericEntity : \"{2222222222222222}Prefabs/parent.et\" {\n ID \"5A3C00000000CCCC\"\n components {\n  MeshObject \"{5A3C0000000000C1}\" {\n   Object \"{3333333333333333}Assets/m.xob\"\n  }\n }\n coords 0 0 0\n}\n";
+
+    fn parent_of(reference: &str) -> Option<String> {
+        (reference == "{2222222222222222}Prefabs/parent.et").then(|| PARENT.to_string())
+    }
+
+    fn maps(pairs: &[(&str, &str)]) -> Vec<(String, String)> {
+        pairs.iter().map(|(s, p)| (s.to_string(), p.to_string())).collect()
+    }
+
+    fn socket_entity(prefab: &str, id: &str, socket: &str) -> String {
+        format!(
+            "  GenericEntity : \"{}\" {{\n   ID \"{}\"\n   components {{\n    Hierarchy \"{{5A3C0000000000F0}}\" {{\n     Enabled 1\n     PivotID \"{}\"\n     AutoTransform 1\n    }}\n   }}\n   coords 0 0 0\n  }}\n",
+            prefab, id, socket
+        )
+    }
+
+    fn mapping(id: &str, socket: &str, prefab: &str) -> String {
+        format!(
+            "    SlotBoneMappingObject \"{{{}}}\" {{\n     BonePrefix \"{}\"\n     Prefab \"{}\"\n    }}\n",
+            id, socket, prefab
+        )
+    }
+
+    const LAMP: &str = "{1111111111111111}Prefabs/lamp.et";
+    const BARREL: &str = "{4444444444444444}Prefabs/barrel.et";
+
+    fn existing_prefab() -> String {
+        let mut text = String::from("// hand edited\nGenericEntity {\n ID \"5A3C00000000EEEE\"\n components {\n");
+        text.push_str("  MeshObject \"{5A3C0000000000E1}\" {\n   Object \"{3333333333333333}Assets/m.xob\"\n  }\n");
+        text.push_str("  WB_SlotBoneMappingsComponent \"{5A3C0000000000E2}\" {\n   SlotBoneMappings {\n");
+        text.push_str(&mapping("5A3C0000000000E3", "socket_keep", LAMP));
+        text.push_str(&mapping("5A3C0000000000E4", "socket_swap", LAMP));
+        text.push_str(&mapping("5A3C0000000000E5", "socket_old", LAMP));
+        text.push_str("   }\n  }\n }\n coords 0 0 0\n {\n");
+        text.push_str(&socket_entity(LAMP, "5A3C0000000000F1", "socket_keep"));
+        text.push_str(&socket_entity(LAMP, "5A3C0000000000F2", "socket_swap"));
+        text.push_str(&socket_entity(LAMP, "5A3C0000000000F3", "socket_old"));
+        text.push_str(" }\n}\n");
+        text
+    }
+
+    #[test]
+    fn merge_updates_sockets_and_logs_the_report() {
+        let existing = existing_prefab();
+        let maps = maps(&[("socket_keep", LAMP), ("socket_swap", BARREL), ("socket_new", BARREL)]);
+        let sockets: Vec<String> = ["socket_keep", "socket_swap", "socket_new"].iter().map(|s| s.to_string()).collect();
+        let sink = MemorySink::new();
+        let (text, report) = merge_sockets_into_et(&sink, &existing, &maps, &sockets, true, &|_| None).unwrap();
+
+        assert_eq!(report.added, ["socket_new"]);
+        assert_eq!(report.updated, ["socket_swap"]);
+        assert_eq!(report.unchanged, ["socket_keep"]);
+        assert_eq!(report.missing, ["socket_old"]);
+        assert_eq!(report.removed, ["socket_old"]);
+
+        // Untouched parts stay byte-identical.
+        assert!(text.starts_with("// hand edited\nGenericEntity {\n ID \"5A3C00000000EEEE\"\n"));
+        assert!(text.contains(&mapping("5A3C0000000000E3", "socket_keep", LAMP)));
+        assert!(text.contains(&socket_entity(LAMP, "5A3C0000000000F1", "socket_keep")));
+        // Changed socket keeps its IDs, only the prefab changes.
+        assert!(text.contains(&mapping("5A3C0000000000E4", "socket_swap", BARREL)));
+        assert!(text.contains(&socket_entity(BARREL, "5A3C0000000000F2", "socket_swap")));
+        // Gone socket removed, new socket added with the existing Hierarchy ID.
+        assert!(!text.contains("socket_old"));
+        assert!(text.contains("     BonePrefix \"socket_new\"\n"));
+        assert!(text.contains("    Hierarchy \"{5A3C0000000000F0}\" {\n     Enabled 1\n     PivotID \"socket_new\"\n"));
+        enfusion_text::parse(&text).unwrap();
+
+        let records = sink.records();
+        assert_eq!(records.len(), 2);
+        assert!(records.iter().all(|r| r.channel == LogChannel::Scan));
+        assert_eq!((records[0].level.as_str(), records[0].message.as_str()), ("info", "Merge: added=1, updated=1, unchanged=1, removed=1"));
+        assert_eq!((records[1].level.as_str(), records[1].message.as_str()), ("warn", "Socket no longer in .txo: socket_old (removed)"));
+    }
+
+    #[test]
+    fn merge_keeps_missing_sockets_unless_asked() {
+        let existing = existing_prefab();
+        let maps = maps(&[("socket_keep", LAMP), ("socket_swap", LAMP)]);
+        let sockets: Vec<String> = ["socket_keep", "socket_swap"].iter().map(|s| s.to_string()).collect();
+        let sink = MemorySink::new();
+        let (text, report) = merge_sockets_into_et(&sink, &existing, &maps, &sockets, false, &|_| None).unwrap();
+
+        assert_eq!(text, existing);
+        assert!(report.removed.is_empty());
+        assert_eq!(
+            sink.messages(LogChannel::Scan),
+            ["Merge: added=0, updated=0, unchanged=2, removed=0", "Socket no longer in .txo: socket_old (kept)"]
+        );
+    }
+
+    #[test]
+    fn new_prefab_text_groups_sockets_by_prefab() {
+        let maps = maps(&[("socket_a", LAMP), ("socket_b", LAMP), ("socket_c", BARREL)]);
+        let mut text = build_new_et_with_mesh("5A3C00000000AAAA", "{3333333333333333}Assets/m.xob");
+        text = insert_or_replace_slot_component(text, &build_slot_component_text_from_mappings(&maps).unwrap());
+        text = insert_or_replace_child_entities_block(text, &build_child_entities_block(&maps, "5A3C0000000000F0").unwrap());
+        let text = remove_blank_lines(&text);
+
+        let doc = enfusion_text::parse(&text).unwrap();
+        let root = doc.root().unwrap();
+        let slot = root.object("components").unwrap().object("WB_SlotBoneMappingsComponent").unwrap();
+        assert_eq!(slot.object("SlotBoneMappings").unwrap().objects().count(), 3);
+        let block = root.objects().find(|o| o.is_anonymous()).unwrap();
+        let grp = block.objects().find(|o| o.is_group()).unwrap();
+        assert_eq!(grp.parent(), Some(LAMP));
+        assert_eq!(grp.objects().count(), 2);
+        let single = block.objects().find(|o| !o.is_group()).unwrap();
+        assert_eq!(single.parent(), Some(BARREL));
+        assert_eq!(socket_pivot_of(single).as_deref(), Some("socket_c"));
+    }
+
+    #[test]
+    fn inherited_slot_component_is_overridden_by_id() {
+        let maps = maps(&[
+            ("socket_lamp", "{1111111111111111}Prefabs/lamp.et"),
+            ("socket_barrel", "{4444444444444444}Prefabs/barrel.et"),
+        ]);
+        let sockets = vec!["socket_lamp".to_string(), "socket_barrel".to_string()];
+        let sink = MemorySink::new();
+        let (text, report) = merge_sockets_into_et(&sink, CHILD, &maps, &sockets, false, &parent_of).unwrap();
+
+        assert_eq!(text.matches("WB_SlotBoneMappingsComponent").count(), 1);
+        assert!(text.contains("  WB_SlotBoneMappingsComponent \"{5A3C0000000000A1}\" {\n   SlotBoneMappings {\n"));
+        assert_eq!(sink.messages(LogChannel::Scan)[0], "Overriding inherited WB_SlotBoneMappingsComponent {5A3C0000000000A1}");
+        // Unchanged inherited mapping is not repeated; the new socket is added to the override.
+        assert!(!text.contains("BonePrefix \"socket_lamp\""));
+        assert!(text.contains("BonePrefix \"socket_barrel\""));
+        assert_eq!(report.unchanged, ["socket_lamp"]);
+        assert_eq!(report.added, ["socket_barrel"]);
+        // The parent already attaches socket_lamp; only socket_barrel gets a child entity.
+        assert!(!text.contains("PivotID \"socket_lamp\""));
+        assert!(!text.contains("5A3C0000000000D1"));
+        assert_eq!(text.matches("PivotID \"socket_barrel\"").count(), 1);
+        assert_eq!(text.matches("GenericEntity :").count(), 2);
+
+        // Merging again changes nothing.
+        let (again, report) = merge_sockets_into_et(&MemorySink::new(), &text, &maps, &sockets, false, &parent_of).unwrap();
+        assert_eq!(again, text);
+        assert_eq!(report.unchanged, ["socket_barrel", "socket_lamp"]);
+    }
+
+    #[test]
+    fn changed_inherited_mapping_overrides_parent_object() {
+        let maps = maps(&[("socket_lamp", "{5555555555555555}Prefabs/lamp_red.et")]);
+        let sockets = vec!["socket_lamp".to_string()];
+        let (text, report) = merge_sockets_into_et(&MemorySink::new(), CHILD, &maps, &sockets, false, &parent_of).unwrap();
+
+        assert!(text.contains(
+            "    SlotBoneMappingObject \"{5A3C0000000000B1}\" {\n     Prefab \"{5555555555555555}Prefabs/lamp_red.et\"\n    }\n"
+        ));
+        assert!(!text.contains("BonePrefix"));
+        // The inherited child entity is overridden by its ID, not attached a second time.
+        assert!(text.contains("  GenericEntity : \"{5555555555555555}Prefabs/lamp_red.et\" {\n   ID \"5A3C0000000000D1\"\n  }\n"));
+        assert!(!text.contains("PivotID"));
+        assert_eq!(report.updated, ["socket_lamp"]);
+
+        let (again, report) = merge_sockets_into_et(&MemorySink::new(), &text, &maps, &sockets, false, &parent_of).unwrap();
+        assert_eq!(again, text);
+        assert_eq!(report.unchanged, ["socket_lamp"]);
+    }
+
+    #[test]
+    fn inherited_mapping_without_id_is_not_duplicated() {
+        let parent = PARENT.replace("SlotBoneMappingObject \"{5A3C0000000000B1}\" {", "SlotBoneMappingObject {");
+        let parent_of = |r: &str| (r == "{2222222222222222}Prefabs/parent.et").then(|| parent.clone());
+        let maps = maps(&[("socket_lamp", "{5555555555555555}Prefabs/lamp_red.et")]);
+        let sockets = vec!["socket_lamp".to_string()];
+        let sink = MemorySink::new();
+        let (text, report) = merge_sockets_into_et(&sink, CHILD, &maps, &sockets, false, &parent_of).unwrap();
+
+        assert!(!text.contains("WB_SlotBoneMappingsComponent"));
+        assert!(report.added.is_empty());
+        assert_eq!(
+            sink.messages(LogChannel::Scan)[0],
+            "Inherited slot bone mapping for socket_lamp has no ID and cannot be overridden; change it in the parent prefab"
+        );
+    }
+
+    #[test]
+    fn unresolved_parent_gets_a_new_component() {
+        let maps = maps(&[("socket_lamp", "{1111111111111111}Prefabs/lamp.et")]);
+        let sockets = vec!["socket_lamp".to_string()];
+        let (text, report) = merge_sockets_into_et(&MemorySink::new(), CHILD, &maps, &sockets, false, &|_| None).unwrap();
+
+        assert_eq!(text.matches("WB_SlotBoneMappingsComponent").count(), 1);
+        assert!(!text.contains("{5A3C0000000000A1}"));
+        assert!(text.contains("BonePrefix \"socket_lamp\""));
+        assert_eq!(report.added, ["socket_lamp"]);
+    }
+}
@@ -0,0 +1,462 @@
+// AutoSocket over many models: one prefab per .xob found in the given folders or listed.
+//
+// Models run a few at a time (`parallel`), each through the same code as a single
+// `create_new_et_from_xob` call. Output paths come from a naming pattern; an output that
+// already exists is merged into, so edits made to it survive a re-run.
+//
+// Every model written is recorded in AutoSocket_BatchState.json with a fingerprint of its
+// inputs (.xob, .xob.meta, .txo, .fbx, the match rules and the batch options). A later run
+// skips models whose fingerprint and output are unchanged unless `force` is set. Changes to
+// the prefab index alone do not count as changed inputs.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashMap};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use tokio::sync::Semaphore;
+use tokio::task::JoinSet;
+use walkdir::WalkDir;
+
+use crate::autosocket::{self, CreateEtArgs};
+use crate::log_sink::{LogChannel, LogSink};
+use crate::socket_rules::RuleSet;
+use crate::{batch_state_path, emit_scan_log, extract_cache, load_json_or_backup, load_settings, write_atomic};
+
+pub const DEFAULT_NAME_PATTERN: &str = "{stem}_test_prefab.et";
+const DEFAULT_PARALLEL: usize = 4;
+const MAX_PARALLEL: usize = 16;
+
+#[derive(Deserialize, Default, Clone)]
+pub struct BatchArgs {
+    /// Folders searched recursively for .xob files, and single .xob files.
+    pub inputs: Vec<String>,
+    /// Base folder for relative output paths; each model's own folder when unset.
+    pub save_dir: Option<String>,
+    /// Output path per model, relative to the base folder or absolute. `{stem}` is the .xob
+    /// name without extension, `{parent}` the name of its folder and `{rel_dir}` its folder
+    /// below the input folder it was found in. Defaults to `DEFAULT_NAME_PATTERN`.
+    pub name_pattern: Option<String>,
+    pub svn_root: Option<String>,
+    pub extra_dirs: Option<Vec<String>>,
+    pub extra_dirs_only: Option<bool>,
+    pub accept_top: Option<bool>,
+    /// Write a .meta for each new prefab and add it to the index.
+    pub with_meta: Option<bool>,
+    pub dry_run: Option<bool>,
+    /// Models processed at the same time; 4 when unset.
+    pub parallel: Option<usize>,
+    /// Process models even when their inputs are unchanged.
+    pub force: Option<bool>,
+}
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum BatchStatus {
+    Created,
+    Merged,
+    /// Dry run: the write plan is in the row.
+    Planned,
+    Unchanged,
+    NoSockets,
+    Failed,
+    Cancelled,
+}
+
+/// One line of the summary table.
+#[derive(Serialize, Clone, Debug)]
+pub struct BatchRow {
+    pub model: String,
+    pub status: BatchStatus,
+    pub sockets: usize,
+    pub matched: usize,
+    pub unmatched: usize,
+    pub output: Option<String>,
+    pub error: Option<String>,
+    /// Sockets the .txo, .xob and FBX disagree on.
+    pub discrepancies: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan_id: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct BatchResult {
+    pub rows: Vec<BatchRow>,
+    pub written: usize,
+    pub unchanged: usize,
+    pub failed: usize,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+struct StateEntry {
+    fingerprint: String,
+    output: String,
+    updated: String,
+}
+
+/// Inputs fingerprint of the last successful run, by lowercase .xob path.
+#[derive(Serialize, Deserialize, Default)]
+struct BatchState {
+    #[serde(default)]
+    models: BTreeMap<String, StateEntry>,
+}
+
+fn state_key(xob: &Path) -> String {
+    xob.to_string_lossy().replace('\\', "/").to_lowercase()
+}
+
+/// Tags every message with the model it belongs to. Per-model progress is dropped; the
+/// batch reports its own.
+struct ModelSink {
+    inner: Arc<dyn LogSink>,
+    model: String,
+}
+
+impl LogSink for ModelSink {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, _current: Option<usize>, _total: Option<usize>) {
+        self.inner.log(channel, level, &format!("[{}] {}", self.model, message), None, None);
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.inner.is_cancelled()
+    }
+}
+
+/// .xob files under the inputs, with the folder each was found below relative to its input.
+fn collect_models(inputs: &[String]) -> Result<Vec<(PathBuf, String)>, String> {
+    let is_xob = |p: &Path| p.extension().is_some_and(|e| e.eq_ignore_ascii_case("xob"));
+    let mut out: Vec<(PathBuf, String)> = Vec::new();
+    for input in inputs.iter().map(|i| i.trim()).filter(|i| !i.is_empty()) {
+        let path = PathBuf::from(input);
+        if path.is_file() && is_xob(&path) {
+            out.push((path, String::new()));
+        } else if path.is_dir() {
+            let mut found: Vec<(PathBuf, String)> = WalkDir::new(&path)
+                .into_iter()
+                .filter_map(Result::ok)
+                .filter(|e| e.file_type().is_file() && is_xob(e.path()))
+                .map(|e| {
+                    let rel = e
+                        .path()
+                        .parent()
+                        .and_then(|d| d.strip_prefix(&path).ok())
+                        .map(|d| d.to_string_lossy().replace('\\', "/"))
+                        .unwrap_or_default();
+                    (e.into_path(), rel)
+                })
+                .collect();
+            found.sort();
+            out.extend(found);
+        } else {
+            return Err(format!("Not a folder or .xob file: {}", input));
+        }
+    }
+    let mut seen = std::collections::HashSet::new();
+    out.retain(|(p, _)| seen.insert(state_key(p)));
+    Ok(out)
+}
+
+fn output_path(xob: &Path, rel_dir: &str, args: &BatchArgs) -> PathBuf {
+    let stem = xob.file_stem().map(|s| s.to_string_lossy().to_string()).unwrap_or_default();
+    let parent = xob
+        .parent()
+        .and_then(|p| p.file_name())
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let pattern = args.name_pattern.as_deref().map(str::trim).filter(|p| !p.is_empty()).unwrap_or(DEFAULT_NAME_PATTERN);
+    // Models found directly in an input folder have no `{rel_dir}`; drop its separator too.
+    let pattern = if rel_dir.is_empty() { pattern.replace("{rel_dir}/", "") } else { pattern.to_string() };
+    let mut name = pattern.replace("{stem}", &stem).replace("{parent}", &parent).replace("{rel_dir}", rel_dir);
+    if !name.to_lowercase().ends_with(".et") {
+        name.push_str(".et");
+    }
+    let name = PathBuf::from(name);
+    if name.is_absolute() {
+        return name;
+    }
+    let base = match args.save_dir.as_deref().map(str::trim).filter(|d| !d.is_empty()) {
+        Some(d) => PathBuf::from(d),
+        None => xob.parent().map(Path::to_path_buf).unwrap_or_default(),
+    };
+    base.join(name)
+}
+
+/// Errors for the models whose output path another model of the batch also resolves to,
+/// by position in `models`. Those models are not run, so no two tasks write one file.
+fn output_collisions(models: &[(PathBuf, String)], args: &BatchArgs) -> HashMap<usize, String> {
+    let mut by_output: BTreeMap<String, Vec<usize>> = BTreeMap::new();
+    for (i, (xob, rel_dir)) in models.iter().enumerate() {
+        by_output.entry(state_key(&output_path(xob, rel_dir, args))).or_default().push(i);
+    }
+    let mut errors = HashMap::new();
+    for shared in by_output.values().filter(|v| v.len() > 1) {
+        for &i in shared {
+            let others: Vec<String> =
+                shared.iter().filter(|&&j| j != i).map(|&j| models[j].0.to_string_lossy().to_string()).collect();
+            errors.insert(
+                i,
+                format!(
+                    "Same output as {}; add {{rel_dir}} or {{parent}} to the name pattern",
+                    others.join(", ")
+                ),
+            );
+        }
+    }
+    errors
+}
+
+/// Hash over the model's input files, the match rules and the options that shape the output.
+fn fingerprint(xob: &Path, out: &Path, rules_key: &str, args: &BatchArgs) -> Result<String, String> {
+    let mut h = Sha256::new();
+    let meta = PathBuf::from(format!("{}.meta", xob.to_string_lossy()));
+    for p in [xob.to_path_buf(), meta, xob.with_extension("txo"), xob.with_extension("fbx")] {
+        let sha = if p.is_file() { extract_cache::sha256_file(&p)? } else { "-".to_string() };
+        h.update(format!("{}\n", sha));
+    }
+    let options = serde_json::json!({
+        "out": out.to_string_lossy(),
+        "rules": rules_key,
+        "extra_dirs": args.extra_dirs,
+        "extra_dirs_only": args.extra_dirs_only,
+        "accept_top": args.accept_top,
+        "with_meta": args.with_meta,
+    });
+    h.update(options.to_string());
+    Ok(hex::encode(h.finalize()))
+}
+
+fn row(model: &Path, status: BatchStatus) -> BatchRow {
+    BatchRow {
+        model: model.to_string_lossy().to_string(),
+        status,
+        sockets: 0,
+        matched: 0,
+        unmatched: 0,
+        output: None,
+        error: None,
+        discrepancies: Vec::new(),
+        plan_id: None,
+    }
+}
+
+async fn process_model(
+    sink: Arc<dyn LogSink>,
+    xob: PathBuf,
+    rel_dir: String,
+    args: Arc<BatchArgs>,
+    rules_key: Arc<String>,
+    state: Arc<Mutex<BatchState>>,
+) -> BatchRow {
+    if sink.is_cancelled() {
+        return row(&xob, BatchStatus::Cancelled);
+    }
+    let model = xob.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let msink = ModelSink { inner: sink.clone(), model };
+    let out = output_path(&xob, &rel_dir, &args);
+    let mut r = row(&xob, BatchStatus::Failed);
+    r.output = Some(out.to_string_lossy().to_string());
+
+    let dry_run = args.dry_run.unwrap_or(false);
+    let fp = match fingerprint(&xob, &out, &rules_key, &args) {
+        Ok(fp) => fp,
+        Err(e) => {
+            r.error = Some(e);
+            return r;
+        }
+    };
+    if !dry_run && !args.force.unwrap_or(false) && out.is_file() {
+        let unchanged = state.lock().unwrap().models.get(&state_key(&xob)).is_some_and(|e| e.fingerprint == fp);
+        if unchanged {
+            emit_scan_log(&msink, "info", "Inputs unchanged; skipped", None, None);
+            r.status = BatchStatus::Unchanged;
+            return r;
+        }
+    }
+
+    let discovery = autosocket::discover_sockets(&msink, &xob);
+    r.discrepancies = discovery.discrepancies.clone();
+    r.sockets = discovery.sockets.len();
+    if discovery.sockets.is_empty() {
+        r.status = BatchStatus::NoSockets;
+        return r;
+    }
+    if sink.is_cancelled() {
+        r.status = BatchStatus::Cancelled;
+        return r;
+    }
+
+    let merge = out.is_file();
+    let create = CreateEtArgs {
+        xob_path: xob.to_string_lossy().to_string(),
+        svn_root: args.svn_root.clone(),
+        extra_dirs: args.extra_dirs.clone(),
+        extra_dirs_only: args.extra_dirs_only,
+        merge_into: merge.then(|| out.to_string_lossy().to_string()),
+        dry_run: Some(dry_run),
+        accept_top: args.accept_top,
+        out_path: Some(out.to_string_lossy().to_string()),
+        persist_settings: Some(false),
+        ..Default::default()
+    };
+    let res = if args.with_meta.unwrap_or(false) {
+        autosocket::create_et_with_meta(&msink, create).await
+    } else {
+        autosocket::create_et(&msink, create).await
+    };
+    match res {
+        Ok(res) => {
+            r.sockets = res.sockets;
+            r.matched = res.matched;
+            r.unmatched = res.unmatched;
+            r.plan_id = res.plan.as_ref().map(|p| p.id.clone());
+            r.status = match (dry_run, merge) {
+                (true, _) => BatchStatus::Planned,
+                (false, true) => BatchStatus::Merged,
+                (false, false) => BatchStatus::Created,
+            };
+            if !dry_run {
+                let entry = StateEntry { fingerprint: fp, output: res.et_path, updated: chrono::Utc::now().to_rfc3339() };
+                state.lock().unwrap().models.insert(state_key(&xob), entry);
+            }
+        }
+        Err(e) => r.error = Some(e),
+    }
+    r
+}
+
+/// Runs AutoSocket over every model of `args.inputs` and returns one row per model.
+pub async fn run_batch(sink: Arc<dyn LogSink>, args: BatchArgs) -> Result<BatchResult, String> {
+    let models = collect_models(&args.inputs)?;
+    if models.is_empty() {
+        return Err("No .xob files found".into());
+    }
+    let rules = RuleSet::configured()?;
+    let rules_key = match rules.source.as_ref() {
+        Some(p) => extract_cache::sha256_file(p)?,
+        None => "builtin".to_string(),
+    };
+    let settings = load_settings();
+    let args = BatchArgs {
+        svn_root: args.svn_root.clone().or(settings.svn_root),
+        extra_dirs: args.extra_dirs.clone().or(settings.extra_dirs),
+        ..args
+    };
+    let parallel = args.parallel.unwrap_or(DEFAULT_PARALLEL).clamp(1, MAX_PARALLEL);
+    let total = models.len();
+    emit_scan_log(sink.as_ref(), "info", format!("AutoSocket batch: {} model(s), {} at a time", total, parallel), None, None);
+
+    let state = Arc::new(Mutex::new(load_json_or_backup::<BatchState>(&batch_state_path())));
+    let args = Arc::new(args);
+    let rules_key = Arc::new(rules_key);
+    let slots = Arc::new(Semaphore::new(parallel));
+    let done = Arc::new(Mutex::new(0usize));
+    // Owned here so that dropping the batch (job cancelled or replaced) aborts every model task.
+    let mut tasks = JoinSet::new();
+    let mut pending = HashMap::with_capacity(total);
+    let mut rows = Vec::with_capacity(total);
+    let collisions = output_collisions(&models, &args);
+    for (i, (xob, rel_dir)) in models.into_iter().enumerate() {
+        if let Some(err) = collisions.get(&i) {
+            let mut r = row(&xob, BatchStatus::Failed);
+            r.output = Some(output_path(&xob, &rel_dir, &args).to_string_lossy().to_string());
+            r.error = Some(err.clone());
+            emit_scan_log(sink.as_ref(), "warn", format!("{}: {}", r.model, err), None, None);
+            rows.push((i, r));
+            continue;
+        }
+        let (sink, args, rules_key, state, slots, done) =
+            (sink.clone(), args.clone(), rules_key.clone(), state.clone(), slots.clone(), done.clone());
+        let model = xob.clone();
+        let task = tasks.spawn(async move {
+            let Ok(_permit) = slots.acquire_owned().await else { return row(&xob, BatchStatus::Cancelled) };
+            let r = process_model(sink.clone(), xob, rel_dir, args, rules_key, state).await;
+            let n = {
+                let mut d = done.lock().unwrap();
+                *d += 1;
+                *d
+            };
+            let name = Path::new(&r.model).file_name().map(|f| f.to_string_lossy().to_string()).unwrap_or_default();
+            let level = if r.status == BatchStatus::Failed { "warn" } else { "info" };
+            let detail = r.error.as_deref().map(|e| format!(": {}", e)).unwrap_or_default();
+            emit_scan_log(sink.as_ref(), level, format!("{} ({:?}){}", name, r.status, detail), Some(n), Some(total));
+            r
+        });
+        pending.insert(task.id(), (i, model));
+    }
+
+    while let Some(joined) = tasks.join_next_with_id().await {
+        match joined {
+            Ok((id, r)) => rows.push((pending[&id].0, r)),
+            Err(e) => {
+                // A panicking model still gets its row, and the state of the others is saved.
+                let (i, xob) = &pending[&e.id()];
+                let mut r = row(xob, BatchStatus::Failed);
+                r.error = Some(format!("Model task failed: {}", e));
+                emit_scan_log(sink.as_ref(), "warn", format!("{}: {}", r.model, e), None, None);
+                rows.push((*i, r));
+            }
+        }
+    }
+    rows.sort_by_key(|(i, _)| *i);
+
+    let mut result = BatchResult::default();
+    for (_, r) in rows {
+        match r.status {
+            BatchStatus::Created | BatchStatus::Merged => result.written += 1,
+            BatchStatus::Unchanged => result.unchanged += 1,
+            BatchStatus::Failed => result.failed += 1,
+            _ => {}
+        }
+        result.rows.push(r);
+    }
+
+    if !args.dry_run.unwrap_or(false) {
+        let json = serde_json::to_string_pretty(&*state.lock().unwrap()).map_err(|e| e.to_string())?;
+        write_atomic(&batch_state_path(), json)?;
+    }
+    emit_scan_log(
+        sink.as_ref(),
+        "info",
+        format!(
+            "AutoSocket batch finished: {} written, {} unchanged, {} failed",
+            result.written, result.unchanged, result.failed
+        ),
+        None,
+        None,
+    );
+    Ok(result)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn models(paths: &[(&str, &str)]) -> Vec<(PathBuf, String)> {
+        paths.iter().map(|(p, rel)| (PathBuf::from(p), rel.to_string())).collect()
+    }
+
+    #[test]
+    fn output_path_fills_the_pattern() {
+        let args = BatchArgs { save_dir: Some("/out".into()), name_pattern: Some("{rel_dir}/{parent}_{stem}".into()), ..Default::default() };
+        assert_eq!(output_path(Path::new("/in/a/b/crate.xob"), "a/b", &args), PathBuf::from("/out/a/b/b_crate.et"));
+        assert_eq!(output_path(Path::new("/in/crate.xob"), "", &args), PathBuf::from("/out/in_crate.et"));
+        let args = BatchArgs::default();
+        assert_eq!(output_path(Path::new("/in/a/crate.xob"), "a", &args), PathBuf::from("/in/a/crate_test_prefab.et"));
+    }
+
+    #[test]
+    fn shared_outputs_are_reported_for_every_model() {
+        let models = models(&[("/in/a/crate.xob", "a"), ("/in/b/crate.xob", "b"), ("/in/b/barrel.xob", "b")]);
+        let args = BatchArgs { save_dir: Some("/out".into()), ..Default::default() };
+        let errors = output_collisions(&models, &args);
+        assert_eq!(errors.len(), 2);
+        assert!(errors[&0].starts_with("Same output as /in/b/crate.xob;"));
+        assert!(errors[&1].starts_with("Same output as /in/a/crate.xob;"));
+
+        // Without a save folder each model writes next to itself.
+        assert!(output_collisions(&models, &BatchArgs::default()).is_empty());
+        let args = BatchArgs { save_dir: Some("/out".into()), name_pattern: Some("{rel_dir}/{stem}.et".into()), ..Default::default() };
+        assert!(output_collisions(&models, &args).is_empty());
+    }
+}
@@ -0,0 +1,4 @@
+// Headless command-line front end; see owltools_lib::cli.
+fn main() {
+    std::process::exit(owltools_lib::cli::main_with_args(std::env::args().skip(1).collect()))
+}
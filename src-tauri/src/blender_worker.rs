@@ -0,0 +1,677 @@
+// Long-lived Blender (or bpy Python) process shared by every Blender-backed feature.
+//
+// Launching `blender --background --factory-startup` costs 5-20 seconds, so instead of
+// one launch per query the worker script below stays resident and serves JSON requests
+// read line by line from stdin. Replies are stdout lines prefixed with
+// `OWLTOOLS_WORKER=`; `OWLTOOLS_STAGE=` lines report progress. Anything else Blender
+// prints is ignored. The EBT addon is imported and registered once per addons dir.
+//
+// Requests are serialized through one mutex. A worker that exits mid-request is
+// restarted and the request retried once; a worker that stalls or times out is killed
+// and replaced by the next request.
+
+use once_cell::sync::OnceCell;
+use serde_json::{json, Value as JsonValue};
+use std::collections::{HashMap, VecDeque};
+use std::path::PathBuf;
+use std::process::Stdio;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex as StdMutex;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWriteExt, BufReader};
+use tokio::process::{Child, ChildStdin, Command as TokioCommand};
+use tokio::sync::{mpsc, Mutex};
+use tokio::time::{Duration, Instant};
+
+const WORKER_PY: &str = r#"import sys, os, json, re, ast, pathlib, socket, traceback
+import bpy
+
+def send(obj):
+    sys.stdout.write('OWLTOOLS_WORKER=' + json.dumps(obj, ensure_ascii=False) + '\n')
+    sys.stdout.flush()
+
+def stage(msg):
+    try:
+        print('OWLTOOLS_STAGE=' + str(msg), flush=True)
+    except Exception:
+        pass
+
+def _remove_orphans_from(collection):
+    # Remove datablocks with no users; repeat until stable.
+    changed = True
+    while changed:
+        changed = False
+        for datablock in list(collection):
+            try:
+                if getattr(datablock, 'users', 0) == 0:
+                    collection.remove(datablock)
+                    changed = True
+            except Exception:
+                pass
+
+def reset_scene(deep=False):
+    # Avoid bpy.ops.wm.read_factory_settings: it can crash headless and would drop the EBT registration.
+    try:
+        for obj in list(bpy.data.objects):
+            try:
+                bpy.data.objects.remove(obj, do_unlink=True)
+            except Exception:
+                pass
+        for col in list(bpy.data.collections):
+            try:
+                bpy.data.collections.remove(col)
+            except Exception:
+                pass
+        if deep:
+            for coll in (bpy.data.meshes, bpy.data.materials, bpy.data.images, bpy.data.node_groups,
+                         bpy.data.textures, bpy.data.actions, bpy.data.armatures, bpy.data.curves,
+                         bpy.data.lights, bpy.data.cameras):
+                try:
+                    _remove_orphans_from(coll)
+                except Exception:
+                    pass
+            try:
+                if hasattr(bpy.data, 'orphans_purge'):
+                    bpy.data.orphans_purge(do_recursive=True)
+            except Exception:
+                pass
+            try:
+                import gc
+                gc.collect()
+            except Exception:
+                pass
+    except Exception:
+        pass
+
+_imports = 0
+
+def import_plain_fbx(path):
+    global _imports
+    reset_scene(deep=((_imports % 10) == 0))
+    _imports += 1
+    bpy.ops.import_scene.fbx(filepath=path, automatic_bone_orientation=True)
+
+def op_ping(args):
+    return dict(version=bpy.app.version_string)
+
+def op_socket_guids(args):
+    import_plain_fbx(args['fbx'])
+    data = {}
+    for ob in bpy.data.objects:
+        n = (ob.name or '')
+        if not n.lower().startswith('socket'):
+            continue
+        guid = None
+        try:
+            guid = ob.get('ref_guid') or ob.get('ref guid')
+        except Exception:
+            guid = None
+        if not guid:
+            try:
+                for k in ob.keys():
+                    kk = str(k).lower().replace(' ', '').replace('_', '')
+                    if kk == 'refguid':
+                        v = ob.get(k)
+                        guid = v if isinstance(v, str) else None
+                        break
+            except Exception:
+                pass
+        if isinstance(guid, str):
+            m = re.search(r'([0-9A-Fa-f]{16})', guid)
+            guid = m.group(1).upper() if m else None
+        if guid:
+            data[n] = guid
+    return data
+
+def find_ucx_offset():
+    for ob in bpy.data.objects:
+        n = (ob.name or '').lower()
+        if not n.startswith('ucx_d_'):
+            continue
+        v = None
+        try:
+            v = ob.get('ebt_original_transform_matrix')
+        except Exception:
+            v = None
+        if v is None:
+            continue
+        try:
+            if isinstance(v, str):
+                v = ast.literal_eval(v)
+            if isinstance(v, (list, tuple)) and len(v) >= 12:
+                x = float(v[3]); y = float(v[7]); z = float(v[11])
+                return (x, z, y)
+        except Exception:
+            pass
+    return None
+
+def op_ucx_offsets(args):
+    results = {}
+    for key, path in args.get('files', []):
+        try:
+            import_plain_fbx(path)
+        except Exception:
+            continue
+        loc = find_ucx_offset()
+        results[key] = {'x': loc[0], 'y': loc[1], 'z': loc[2]} if loc else None
+    return results
+
+_ebt = {}
+
+def ensure_ebt(addons_dir, debug, errors):
+    if addons_dir in _ebt:
+        debug.update(_ebt[addons_dir])
+        debug['ebt_reused'] = True
+        return
+    from bpy.props import StringProperty, EnumProperty
+    try:
+        if not hasattr(bpy.types.Material, 'ebt_enfusion_shader_type'):
+            bpy.types.Material.ebt_enfusion_shader_type = StringProperty()
+        if not hasattr(bpy.types.Material, 'ebt_resource_name'):
+            bpy.types.Material.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the material, used for exporting to .xob')
+        if not hasattr(bpy.types.Image, 'ebt_resource_name'):
+            bpy.types.Image.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the texture, used for exporting to .xob')
+        if not hasattr(bpy.types.Material, 'Cull'):
+            bpy.types.Material.Cull = EnumProperty(items=[('ccw','ccw','',0), ('none','none','',1)], default='ccw')
+    except Exception:
+        pass
+
+    p = pathlib.Path(addons_dir)
+    if not p.exists():
+        raise RuntimeError('EBT addons dir does not exist: ' + str(p))
+    if str(p) not in sys.path:
+        sys.path.insert(0, str(p))
+
+    state = {}
+    stage('Load EBT modules')
+    try:
+        import EnfusionBlenderTools
+        state['ebt_imported'] = True
+    except Exception as e:
+        state['ebt_imported'] = False
+        errors.append('import EnfusionBlenderTools failed: ' + str(e))
+
+    stage('Register ModelQA')
+    try:
+        import EnfusionBlenderTools.modelqa as modelqa
+        try:
+            modelqa.register()
+            state['modelqa_registered'] = True
+        except Exception as e:
+            msg = str(e)
+            if 'already registered' in msg or 'already registered as a subclass' in msg:
+                state['modelqa_registered'] = True
+                state['modelqa_register_already'] = True
+            else:
+                state['modelqa_registered'] = False
+                errors.append('modelqa.register failed: ' + msg)
+    except Exception as e:
+        state['modelqa_registered'] = False
+        errors.append('import modelqa failed: ' + str(e))
+
+    try:
+        from EnfusionBlenderTools.workbench import Workbench
+        from EnfusionBlenderTools.core.fbx import fbx_io
+        state['ebt_modules_ok'] = True
+    except Exception as e:
+        state['ebt_modules_ok'] = False
+        errors.append('import Workbench/fbx_io failed: ' + str(e))
+        debug.update(state)
+        raise
+
+    stage('Register material schemas')
+    try:
+        from EnfusionBlenderTools.core.materials.accessors import schemas as mat_schemas
+        mat_schemas.register()
+        state['material_schemas_registered'] = True
+    except Exception as e:
+        state['material_schemas_registered'] = False
+        errors.append('material schemas register failed: ' + str(e))
+
+    _ebt[addons_dir] = state
+    debug.update(state)
+
+def op_mqa(args):
+    debug = {}
+    errors = []
+    socket.setdefaulttimeout(3.0)
+    ensure_ebt(args['addons_dir'], debug, errors)
+    from EnfusionBlenderTools.workbench import Workbench
+    from EnfusionBlenderTools.core.fbx import fbx_io
+
+    stage('Check Workbench status')
+    try:
+        Workbench.init(client_id='OwlTools', port=int(args['port']))
+        st = Workbench.get_status()
+        if not st:
+            errors.append('Workbench not ready: ' + str(st))
+            return dict(reports=[], debug=debug, errors=errors)
+    except Exception as e:
+        errors.append('Workbench.init/status failed: ' + str(e))
+        return dict(reports=[], debug=debug, errors=errors)
+
+    global _imports
+    xobs = args.get('xobs', [])
+    reports = []
+    for idx, xobp in enumerate(xobs):
+        xob = pathlib.Path(xobp)
+        fbx = xob.with_suffix('.fbx')
+        name = xob.name
+        stage('File {}/{}: {} | Import FBX'.format(idx+1, len(xobs), name))
+        file_errors = []
+        try:
+            reset_scene(deep=((_imports % 10) == 0))
+            _imports += 1
+            fbx_io.import_fbx(fbx)
+        except Exception as e:
+            file_errors.append('import_fbx failed: ' + str(e))
+
+        stage('File {}/{}: {} | Run MQA'.format(idx+1, len(xobs), name))
+        if not file_errors:
+            try:
+                if hasattr(bpy.ops, 'ebt') and ('mqa_report_conventions' in dir(bpy.ops.ebt)):
+                    bpy.ops.ebt.mqa_report_conventions(asset_type=str(args.get('asset_type', 'GENERIC')))
+                else:
+                    file_errors.append('mqa_report_conventions operator not found')
+            except Exception as e:
+                file_errors.append('mqa_report_conventions failed: ' + str(e))
+
+        items = []
+        try:
+            scene = bpy.context.scene
+            if hasattr(scene, 'ebt_report_messages'):
+                for m in scene.ebt_report_messages:
+                    objs = []
+                    try:
+                        for o in m.objs:
+                            objs.append(getattr(o, 'name', ''))
+                    except Exception:
+                        pass
+                    items.append(dict(
+                        category=getattr(m, 'category', ''),
+                        message=getattr(m, 'message', ''),
+                        count=len(objs),
+                        objects=objs,
+                    ))
+            else:
+                file_errors.append('scene.ebt_report_messages not found')
+        except Exception as e:
+            file_errors.append('collect messages failed: ' + str(e))
+
+        reports.append(dict(xob=str(xob), fbx=str(fbx), count=len(items), items=items, errors=file_errors))
+
+    return dict(reports=reports, debug=debug, errors=errors)
+
+OPS = dict(ping=op_ping, socket_guids=op_socket_guids, ucx_offsets=op_ucx_offsets, mqa=op_mqa)
+
+send(dict(id=0, ok=True, result=dict(ready=True)))
+for line in sys.stdin:
+    line = line.strip()
+    if not line:
+        continue
+    try:
+        req = json.loads(line)
+    except Exception:
+        continue
+    rid = req.get('id')
+    fn = OPS.get(req.get('op'))
+    if fn is None:
+        send(dict(id=rid, ok=False, error='Unknown worker op: ' + str(req.get('op'))))
+        continue
+    try:
+        send(dict(id=rid, ok=True, result=fn(req.get('args') or {})))
+    except Exception as e:
+        send(dict(id=rid, ok=False, error=str(e), trace=traceback.format_exc()))
+
+try:
+    sys.stdout.flush()
+except Exception:
+    pass
+os._exit(0)
+"#;
+
+/// Printed by Blender when an operator needs a GPU context in `--background` mode.
+pub const GPU_BACKGROUND_MSG: &str = "GPU functions for drawing are not available in background mode";
+
+/// Set once a request failed with `GPU_BACKGROUND_MSG`; later launches skip `--background`.
+static GPU_REQUIRED: AtomicBool = AtomicBool::new(false);
+
+/// Whether a worker process exists; readable while a request holds the worker lock.
+static WORKER_RUNNING: AtomicBool = AtomicBool::new(false);
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct WorkerLaunch {
+    pub exe: PathBuf,
+    /// `exe` is a Python interpreter with the `bpy` module rather than Blender itself.
+    pub python: bool,
+    pub background: bool,
+}
+
+impl WorkerLaunch {
+    fn args(&self, script: &str) -> Vec<String> {
+        if self.python {
+            vec!["-u".into(), script.into()]
+        } else if self.background {
+            vec!["--background".into(), "--factory-startup".into(), "--python".into(), script.into()]
+        } else {
+            vec!["--factory-startup".into(), "--python".into(), script.into()]
+        }
+    }
+}
+
+fn python_bpy_cached(python: &std::path::Path) -> bool {
+    static PROBES: OnceCell<StdMutex<HashMap<PathBuf, bool>>> = OnceCell::new();
+    let probes = PROBES.get_or_init(|| StdMutex::new(HashMap::new()));
+    if let Some(ok) = probes.lock().unwrap().get(python) {
+        return *ok;
+    }
+    let ok = crate::python_has_bpy(python);
+    probes.lock().unwrap().insert(python.to_path_buf(), ok);
+    ok
+}
+
+/// Picks the worker executable: a Python with `bpy` if one is on PATH, else the
+/// configured or auto-detected Blender.
+pub fn resolve_launch() -> Option<WorkerLaunch> {
+    if let Some(python) = crate::resolve_python_path().filter(|p| python_bpy_cached(p)) {
+        return Some(WorkerLaunch { exe: python, python: true, background: true });
+    }
+    let blender = crate::load_settings()
+        .blender_path
+        .as_deref()
+        .map(PathBuf::from)
+        .filter(|p| p.is_file())
+        .or_else(crate::resolve_blender_path)?;
+    Some(WorkerLaunch {
+        exe: blender,
+        python: false,
+        background: !GPU_REQUIRED.load(Ordering::Relaxed),
+    })
+}
+
+enum WorkerLine {
+    Out(String),
+    Err(String),
+}
+
+enum CallError {
+    /// The op raised inside the worker; the process is still usable.
+    Failed(String),
+    /// The process exited or its pipes closed before replying.
+    Exited(String),
+    Stalled(String),
+}
+
+struct Worker {
+    launch: WorkerLaunch,
+    child: Child,
+    stdin: ChildStdin,
+    lines: mpsc::UnboundedReceiver<WorkerLine>,
+    stderr_tail: VecDeque<String>,
+    next_id: u64,
+    started: Instant,
+}
+
+const STDERR_TAIL_LINES: usize = 80;
+
+fn forward_lines<R: AsyncRead + Unpin + Send + 'static>(
+    reader: R,
+    tx: mpsc::UnboundedSender<WorkerLine>,
+    wrap: fn(String) -> WorkerLine,
+) {
+    tokio::spawn(async move {
+        let mut reader = BufReader::new(reader);
+        let mut buf: Vec<u8> = Vec::new();
+        loop {
+            buf.clear();
+            match reader.read_until(b'\n', &mut buf).await {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+                    if tx.send(wrap(line)).is_err() {
+                        break;
+                    }
+                }
+            }
+        }
+    });
+}
+
+impl Worker {
+    fn spawn(launch: &WorkerLaunch) -> Result<Worker, String> {
+        let mut script_path = std::env::temp_dir();
+        script_path.push(format!("owltools_blender_worker_{}.py", std::process::id()));
+        std::fs::write(&script_path, WORKER_PY).map_err(|e| format!("Failed to write temp script: {}", e))?;
+
+        let mut cmd = TokioCommand::new(&launch.exe);
+        cmd.args(launch.args(&script_path.to_string_lossy()));
+        cmd.env("EBT_TEST", "1");
+        cmd.kill_on_drop(true);
+        cmd.stdin(Stdio::piped());
+        cmd.stdout(Stdio::piped());
+        cmd.stderr(Stdio::piped());
+        let mut child = cmd.spawn().map_err(|e| format!("Failed to run Blender: {}", e))?;
+        let stdin = child.stdin.take().ok_or_else(|| "Failed to open Blender stdin".to_string())?;
+        let stdout = child.stdout.take().ok_or_else(|| "Failed to capture Blender stdout".to_string())?;
+        let stderr = child.stderr.take().ok_or_else(|| "Failed to capture Blender stderr".to_string())?;
+
+        let (tx, rx) = mpsc::unbounded_channel();
+        forward_lines(stdout, tx.clone(), WorkerLine::Out);
+        forward_lines(stderr, tx, WorkerLine::Err);
+
+        Ok(Worker {
+            launch: launch.clone(),
+            child,
+            stdin,
+            lines: rx,
+            stderr_tail: VecDeque::new(),
+            next_id: 1,
+            started: Instant::now(),
+        })
+    }
+
+    fn is_alive(&mut self) -> bool {
+        matches!(self.child.try_wait(), Ok(None))
+    }
+
+    async fn kill(mut self) {
+        WORKER_RUNNING.store(false, Ordering::Relaxed);
+        let _ = self.child.kill().await;
+        let _ = self.child.wait().await;
+    }
+
+    fn stderr_text(&self) -> String {
+        self.stderr_tail.iter().cloned().collect::<Vec<String>>().join("\n")
+    }
+
+    async fn call(
+        &mut self,
+        op: &str,
+        args: &JsonValue,
+        idle_timeout: Duration,
+        on_stage: &(dyn Fn(&str) + Send + Sync),
+    ) -> Result<JsonValue, CallError> {
+        let id = self.next_id;
+        self.next_id += 1;
+        let mut line = json!({ "id": id, "op": op, "args": args }).to_string();
+        line.push('\n');
+        let sent = async {
+            self.stdin.write_all(line.as_bytes()).await?;
+            self.stdin.flush().await
+        }
+        .await;
+        if let Err(e) = sent {
+            return Err(CallError::Exited(format!("Blender worker is not accepting requests: {}", e)));
+        }
+
+        let mut last_stage = String::new();
+        let mut idle_deadline = Instant::now() + idle_timeout;
+        loop {
+            tokio::select! {
+                msg = self.lines.recv() => {
+                    idle_deadline = Instant::now() + idle_timeout;
+                    match msg {
+                        Some(WorkerLine::Out(l)) => {
+                            let t = l.trim();
+                            if let Some(stage) = t.strip_prefix("OWLTOOLS_STAGE=") {
+                                last_stage = stage.trim().to_string();
+                                on_stage(&last_stage);
+                            } else if let Some(rest) = t.strip_prefix("OWLTOOLS_WORKER=") {
+                                let Ok(v) = serde_json::from_str::<JsonValue>(rest) else { continue };
+                                if v.get("id").and_then(|x| x.as_u64()) != Some(id) {
+                                    continue;
+                                }
+                                if v.get("ok").and_then(|x| x.as_bool()) == Some(true) {
+                                    return Ok(v.get("result").cloned().unwrap_or(JsonValue::Null));
+                                }
+                                let err = v.get("error").and_then(|x| x.as_str()).unwrap_or("unknown error");
+                                return Err(CallError::Failed(format!("Blender worker {} failed: {}", op, err)));
+                            }
+                        }
+                        Some(WorkerLine::Err(l)) => {
+                            if self.stderr_tail.len() >= STDERR_TAIL_LINES {
+                                self.stderr_tail.pop_front();
+                            }
+                            self.stderr_tail.push_back(l);
+                        }
+                        None => {
+                            let status = match self.child.wait().await {
+                                Ok(s) => s.code().map(|c| format!("exit code {}", c)).unwrap_or_else(|| "terminated".to_string()),
+                                Err(e) => e.to_string(),
+                            };
+                            let stderr = self.stderr_text();
+                            let mut msg = format!("Blender worker exited ({})", status);
+                            if !stderr.trim().is_empty() {
+                                msg.push_str(&format!("\n--- stderr (tail) ---\n{}", stderr.trim()));
+                            }
+                            return Err(CallError::Exited(msg));
+                        }
+                    }
+                }
+                _ = tokio::time::sleep_until(idle_deadline) => {
+                    let stage_suffix = if last_stage.is_empty() {
+                        "".to_string()
+                    } else {
+                        format!(" (last stage: {})", last_stage)
+                    };
+                    return Err(CallError::Stalled(format!(
+                        "Blender stalled (no output for {}s){}",
+                        idle_timeout.as_secs(),
+                        stage_suffix
+                    )));
+                }
+            }
+        }
+    }
+}
+
+static WORKER: OnceCell<Mutex<Option<Worker>>> = OnceCell::new();
+
+fn worker_slot() -> &'static Mutex<Option<Worker>> {
+    WORKER.get_or_init(|| Mutex::new(None))
+}
+
+/// Runs `op` on the shared worker, starting (or replacing) it as needed.
+///
+/// `timeout` bounds the whole call including a cold start; `idle_timeout` bounds the gap
+/// between two lines of worker output.
+pub async fn request(
+    launch: &WorkerLaunch,
+    op: &str,
+    args: JsonValue,
+    timeout: Duration,
+    idle_timeout: Duration,
+    on_stage: &(dyn Fn(&str) + Send + Sync),
+) -> Result<JsonValue, String> {
+    let mut slot = worker_slot().lock().await;
+    let mut retried = false;
+    loop {
+        let reusable = match slot.as_mut() {
+            Some(w) => w.launch == *launch && w.is_alive(),
+            None => false,
+        };
+        if !reusable {
+            if let Some(old) = slot.take() {
+                old.kill().await;
+            }
+            on_stage("Launching Blender");
+            *slot = Some(Worker::spawn(launch)?);
+            WORKER_RUNNING.store(true, Ordering::Relaxed);
+            on_stage("Blender spawned");
+        }
+        let Some(worker) = slot.as_mut() else {
+            return Err("Blender worker unavailable".into());
+        };
+
+        let err = match tokio::time::timeout(timeout, worker.call(op, &args, idle_timeout, on_stage)).await {
+            Ok(Ok(v)) => return Ok(v),
+            Ok(Err(CallError::Failed(e))) => {
+                if e.contains(GPU_BACKGROUND_MSG) || worker.stderr_text().contains(GPU_BACKGROUND_MSG) {
+                    GPU_REQUIRED.store(true, Ordering::Relaxed);
+                }
+                return Err(e);
+            }
+            Ok(Err(CallError::Exited(e))) => {
+                if let Some(w) = slot.take() {
+                    w.kill().await;
+                }
+                if !retried {
+                    retried = true;
+                    on_stage("Blender worker exited; restarting");
+                    continue;
+                }
+                e
+            }
+            Ok(Err(CallError::Stalled(e))) => e,
+            Err(_) => format!("Blender timed out after {}s", timeout.as_secs()),
+        };
+        if let Some(w) = slot.take() {
+            if w.stderr_text().contains(GPU_BACKGROUND_MSG) {
+                GPU_REQUIRED.store(true, Ordering::Relaxed);
+            }
+            w.kill().await;
+        }
+        return Err(err);
+    }
+}
+
+/// True if `launch` failed because an op needs a GPU context and should be retried
+/// with the launch returned by `without_background`.
+pub fn needs_gpu_retry(launch: &WorkerLaunch) -> bool {
+    !launch.python && launch.background && GPU_REQUIRED.load(Ordering::Relaxed)
+}
+
+pub fn without_background(launch: &WorkerLaunch) -> WorkerLaunch {
+    WorkerLaunch { background: false, ..launch.clone() }
+}
+
+pub async fn stop() -> bool {
+    match worker_slot().lock().await.take() {
+        Some(w) => {
+            w.kill().await;
+            true
+        }
+        None => false,
+    }
+}
+
+pub fn status() -> JsonValue {
+    let Ok(mut slot) = worker_slot().try_lock() else {
+        return json!({ "running": WORKER_RUNNING.load(Ordering::Relaxed), "busy": true });
+    };
+    let Some(w) = slot.as_mut() else {
+        return json!({ "running": false, "busy": false });
+    };
+    if !w.is_alive() {
+        WORKER_RUNNING.store(false, Ordering::Relaxed);
+        return json!({ "running": false, "busy": false });
+    }
+    json!({
+        "running": true,
+        "busy": false,
+        "pid": w.child.id(),
+        "exe": w.launch.exe.to_string_lossy(),
+        "python": w.launch.python,
+        "background": w.launch.background,
+        "uptime_secs": w.started.elapsed().as_secs(),
+    })
+}
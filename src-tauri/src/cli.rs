@@ -0,0 +1,428 @@
+// Headless entry point used by the `owltools-cli` binary (nightly batches, pre-commit hooks).
+//
+// Subcommands call the same backend functions as the GUI commands and read the same
+// AutoSocket_Settings.json. Results go to stdout as JSON, log lines to stderr.
+// Exit codes: 0 ok, 1 command failed, 2 bad usage, 3 MQA, reference validation or the
+// conflict check found issues (--fail-on-issues).
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use serde::Serialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::log_sink::{LogSink, StderrSink};
+
+const USAGE: &str = "\
+Usage: owltools-cli [--quiet] <command> [options]
+
+Commands:
+  scan-prefab-index [--svn-root DIR] [--verbose]
+  roots
+  search <query> [--limit N] [--type EXT|CLASS]...
+  resolve-guid <guid>
+  browse [folder] [--name TEXT] [--guid PREFIX] [--path-prefix PATH] [--root NAME]
+         [--type EXT|CLASS]... [--direct] [--offset N] [--limit N]
+  entry <guid|path>
+  refs <guid> [--depth N]
+  deps <guid> [--depth N]
+  validate-refs [--fail-on-issues]
+  conflicts [--fail-on-issues]
+  regen-guid <resource> [--subtree DIR] [--dry-run]
+  move <resource> <destination> [--dry-run]
+  create-et <xob> [--save-dir DIR] [--svn-root DIR] [--extra-dir DIR]... [--extra-dirs-only]
+            [--merge-into ET] [--remove-missing] [--with-meta] [--dry-run]
+            [--choose SOCKET=PREFAB]... [--accept-top]
+  sockets <xob>
+  match-sockets <xob>
+  batch-et <folder|xob>... [--save-dir DIR] [--pattern PATTERN] [--parallel N] [--force]
+           [--svn-root DIR] [--extra-dir DIR]... [--extra-dirs-only] [--accept-top]
+           [--with-meta] [--dry-run]
+  prefabdst build --preset FILE --out DIR [--zones N] [--hp N] [--debris-mass KG] [--dry-run] <xob>...
+  prefabdst scan <xob> [--scr]
+  mqa <xob>... [--port N] [--asset-type GENERIC|BUILDINGS|VEHICLES|WEAPONS] [--fail-on-issues]
+  wb-call <func> [--params JSON] [--ip IP] [--port N] [--client-id ID]
+
+Results are printed to stdout as JSON; logs go to stderr.";
+
+/// Flags that never take a value.
+const SWITCHES: &[&str] = &[
+    "quiet",
+    "verbose",
+    "dry-run",
+    "with-meta",
+    "remove-missing",
+    "fail-on-issues",
+    "scr",
+    "direct",
+    "accept-top",
+    "extra-dirs-only",
+    "force",
+    "help",
+];
+
+const EXIT_FAILED: i32 = 1;
+const EXIT_USAGE: i32 = 2;
+const EXIT_ISSUES: i32 = 3;
+
+#[derive(Default)]
+struct Args {
+    positional: Vec<String>,
+    options: HashMap<String, Vec<String>>,
+}
+
+impl Args {
+    fn parse(raw: &[String]) -> Result<Args, String> {
+        let mut out = Args::default();
+        let mut it = raw.iter();
+        while let Some(a) = it.next() {
+            let Some(name) = a.strip_prefix("--") else {
+                out.positional.push(a.clone());
+                continue;
+            };
+            let (name, value) = match name.split_once('=') {
+                Some((n, v)) => (n.to_string(), v.to_string()),
+                None if SWITCHES.contains(&name) => (name.to_string(), String::new()),
+                None => {
+                    let v = it.next().ok_or_else(|| format!("--{} needs a value", name))?;
+                    (name.to_string(), v.clone())
+                }
+            };
+            out.options.entry(name).or_default().push(value);
+        }
+        Ok(out)
+    }
+
+    fn flag(&self, name: &str) -> bool {
+        self.options.contains_key(name)
+    }
+
+    fn value(&self, name: &str) -> Option<String> {
+        self.options.get(name).and_then(|v| v.last()).cloned()
+    }
+
+    fn values(&self, name: &str) -> Vec<String> {
+        self.options.get(name).cloned().unwrap_or_default()
+    }
+
+    fn parsed<T: std::str::FromStr>(&self, name: &str) -> Result<Option<T>, String> {
+        match self.value(name) {
+            Some(v) => v.parse::<T>().map(Some).map_err(|_| format!("Invalid value for --{}: {}", name, v)),
+            None => Ok(None),
+        }
+    }
+}
+
+enum CliError {
+    Usage(String),
+    Failed(String),
+}
+
+impl From<String> for CliError {
+    fn from(e: String) -> Self {
+        CliError::Failed(e)
+    }
+}
+
+fn usage(msg: impl Into<String>) -> CliError {
+    CliError::Usage(msg.into())
+}
+
+fn to_json<T: Serialize>(v: &T) -> Result<JsonValue, CliError> {
+    serde_json::to_value(v).map_err(|e| CliError::Failed(e.to_string()))
+}
+
+/// Runs the CLI with `args` (without the program name) and returns the process exit code.
+pub fn main_with_args(args: Vec<String>) -> i32 {
+    let args = match Args::parse(&args) {
+        Ok(a) => a,
+        Err(e) => {
+            eprintln!("{}\n\n{}", e, USAGE);
+            return EXIT_USAGE;
+        }
+    };
+    if args.flag("help") || args.positional.is_empty() {
+        println!("{}", USAGE);
+        return if args.flag("help") { 0 } else { EXIT_USAGE };
+    }
+
+    let sink: Arc<dyn LogSink> = Arc::new(StderrSink { quiet: args.flag("quiet") });
+    let result = tauri::async_runtime::block_on(dispatch(sink, &args));
+    match result {
+        Ok((value, code)) => {
+            println!("{}", serde_json::to_string_pretty(&value).unwrap_or_else(|_| value.to_string()));
+            code
+        }
+        Err(CliError::Usage(msg)) => {
+            eprintln!("{}\n\n{}", msg, USAGE);
+            EXIT_USAGE
+        }
+        Err(CliError::Failed(msg)) => {
+            eprintln!("error: {}", msg);
+            println!("{}", json!({ "error": msg }));
+            EXIT_FAILED
+        }
+    }
+}
+
+async fn dispatch(sink: Arc<dyn LogSink>, args: &Args) -> Result<(JsonValue, i32), CliError> {
+    let cmd = args.positional[0].as_str();
+    let rest = &args.positional[1..];
+    match cmd {
+        "scan-prefab-index" => {
+            // Without --svn-root, scan every configured root when more than the base is set.
+            if args.value("svn-root").is_none() && crate::load_settings().prefab_roots.is_some_and(|r| !r.is_empty()) {
+                let res = crate::run_prefab_roots_scan(sink, Some(args.flag("verbose"))).await?;
+                return Ok((to_json(&res)?, 0));
+            }
+            let svn_root = args
+                .value("svn-root")
+                .or_else(|| crate::load_settings().svn_root)
+                .filter(|s| !s.is_empty())
+                .ok_or_else(|| usage("No --svn-root given and none stored in settings"))?;
+            let res = crate::run_prefab_scan(sink, svn_root, Some(args.flag("verbose"))).await?;
+            Ok((to_json(&res)?, 0))
+        }
+        "roots" => Ok((to_json(&crate::get_prefab_roots()?)?, 0)),
+        "search" => {
+            if rest.is_empty() {
+                return Err(usage("search needs a query"));
+            }
+            let limit = args.parsed::<usize>("limit").map_err(usage)?.unwrap_or(20);
+            let hits = crate::prefab_db::open()?.search(&rest.join(" "), limit, &args.values("type"))?;
+            Ok((to_json(&hits)?, 0))
+        }
+        "browse" => {
+            if rest.len() > 1 {
+                return Err(usage("browse takes at most one folder"));
+            }
+            let types = args.values("type");
+            let query = crate::prefab_db::IndexQuery {
+                folder: rest.first().cloned(),
+                direct_only: Some(args.flag("direct")),
+                name: args.value("name"),
+                guid: args.value("guid"),
+                path_prefix: args.value("path-prefix"),
+                root: args.value("root"),
+                types: if types.is_empty() { None } else { Some(types) },
+                offset: args.parsed::<usize>("offset").map_err(usage)?,
+                limit: args.parsed::<usize>("limit").map_err(usage)?,
+            };
+            let db = crate::prefab_db::open()?;
+            Ok((json!({ "folders": db.folders(&query)?, "page": db.query(&query)? }), 0))
+        }
+        "entry" => {
+            let [key] = rest else {
+                return Err(usage("entry takes exactly one GUID or path"));
+            };
+            let details = crate::prefab_entry_details(&crate::prefab_db::open()?, key)?;
+            Ok((to_json(&details)?, 0))
+        }
+        "resolve-guid" => {
+            let [guid] = rest else {
+                return Err(usage("resolve-guid takes exactly one GUID"));
+            };
+            let guid = guid.trim_start_matches('{').trim_end_matches('}').to_uppercase();
+            let hit = crate::prefab_db::open()?.by_guid(&guid);
+            Ok((to_json(&hit)?, 0))
+        }
+        "refs" | "deps" => {
+            let [guid] = rest else {
+                return Err(usage(format!("{} takes exactly one GUID", cmd)));
+            };
+            let guid = guid.trim_start_matches('{').trim_end_matches('}');
+            let depth = args.parsed::<usize>("depth").map_err(usage)?.unwrap_or(1);
+            let db = crate::prefab_db::open()?;
+            let nodes = if cmd == "refs" {
+                crate::ref_graph::references(&db, guid, depth)?
+            } else {
+                crate::ref_graph::dependencies(&db, guid, depth)?
+            };
+            Ok((to_json(&nodes)?, 0))
+        }
+        "validate-refs" => {
+            let report = crate::ref_validate::validate(&crate::prefab_db::open()?, sink.as_ref())?;
+            let code = if args.flag("fail-on-issues") && !report.issues.is_empty() { EXIT_ISSUES } else { 0 };
+            Ok((to_json(&report)?, code))
+        }
+        "conflicts" => {
+            let conflicts = crate::guid_conflicts::find(&crate::prefab_db::open()?)?;
+            let found = !conflicts.guid_collisions.is_empty() || !conflicts.duplicate_names.is_empty();
+            let code = if args.flag("fail-on-issues") && found { EXIT_ISSUES } else { 0 };
+            Ok((to_json(&conflicts)?, code))
+        }
+        "regen-guid" => {
+            let [path] = rest else {
+                return Err(usage("regen-guid takes exactly one resource path"));
+            };
+            let regen_args = crate::guid_conflicts::RegenerateGuidArgs {
+                path: path.clone(),
+                subtree: args.value("subtree"),
+                dry_run: Some(args.flag("dry-run")),
+            };
+            let res = crate::guid_conflicts::regenerate_guid(sink.as_ref(), regen_args)?;
+            Ok((to_json(&res)?, 0))
+        }
+        "move" => {
+            let [path, destination] = rest else {
+                return Err(usage("move takes a resource path and a destination"));
+            };
+            let move_args = crate::resource_move::MoveResourceArgs {
+                path: path.clone(),
+                destination: destination.clone(),
+                dry_run: Some(args.flag("dry-run")),
+            };
+            let res = crate::resource_move::move_resource(sink.as_ref(), move_args)?;
+            Ok((to_json(&res)?, 0))
+        }
+        "create-et" => {
+            let [xob] = rest else {
+                return Err(usage("create-et takes exactly one .xob path"));
+            };
+            let extra_dirs = args.values("extra-dir");
+            let mut choices = BTreeMap::new();
+            for c in args.values("choose") {
+                let (socket, prefab) = c.split_once('=').ok_or_else(|| usage("--choose takes SOCKET=PREFAB"))?;
+                choices.insert(socket.to_string(), prefab.to_string());
+            }
+            let create_args = crate::autosocket::CreateEtArgs {
+                xob_path: xob.clone(),
+                save_dir: args.value("save-dir"),
+                svn_root: args.value("svn-root"),
+                extra_dirs: if extra_dirs.is_empty() { None } else { Some(extra_dirs) },
+                extra_dirs_only: Some(args.flag("extra-dirs-only")),
+                merge_into: args.value("merge-into"),
+                remove_missing: Some(args.flag("remove-missing")),
+                dry_run: Some(args.flag("dry-run")),
+                choices: Some(choices),
+                accept_top: Some(args.flag("accept-top")),
+                ..Default::default()
+            };
+            let res = if args.flag("with-meta") {
+                crate::autosocket::create_et_with_meta(sink.as_ref(), create_args).await?
+            } else {
+                crate::autosocket::create_et(sink.as_ref(), create_args).await?
+            };
+            Ok((to_json(&res)?, 0))
+        }
+        "sockets" => {
+            let [xob] = rest else {
+                return Err(usage("sockets takes exactly one .xob path"));
+            };
+            if !Path::new(xob).is_file() {
+                return Err(format!("Invalid .xob path: {}", xob).into());
+            }
+            Ok((to_json(&crate::autosocket::discover_sockets(sink.as_ref(), Path::new(xob)))?, 0))
+        }
+        "match-sockets" => {
+            let [xob] = rest else {
+                return Err(usage("match-sockets takes exactly one .xob path"));
+            };
+            let matches = crate::autosocket::explain_socket_matches(sink.as_ref(), xob).await?;
+            Ok((to_json(&matches)?, 0))
+        }
+        "batch-et" => {
+            if rest.is_empty() {
+                return Err(usage("batch-et needs at least one folder or .xob path"));
+            }
+            let extra_dirs = args.values("extra-dir");
+            let batch_args = crate::autosocket_batch::BatchArgs {
+                inputs: rest.to_vec(),
+                save_dir: args.value("save-dir"),
+                name_pattern: args.value("pattern"),
+                svn_root: args.value("svn-root"),
+                extra_dirs: if extra_dirs.is_empty() { None } else { Some(extra_dirs) },
+                extra_dirs_only: Some(args.flag("extra-dirs-only")),
+                accept_top: Some(args.flag("accept-top")),
+                with_meta: Some(args.flag("with-meta")),
+                dry_run: Some(args.flag("dry-run")),
+                parallel: args.parsed::<usize>("parallel").map_err(usage)?,
+                force: Some(args.flag("force")),
+            };
+            let res = crate::autosocket_batch::run_batch(sink.clone(), batch_args).await?;
+            let code = if res.failed > 0 { EXIT_FAILED } else { 0 };
+            Ok((to_json(&res)?, code))
+        }
+        "prefabdst" => match rest.first().map(|s| s.as_str()) {
+            Some("build") => prefabdst_build(sink.as_ref(), args, &rest[1..]).await,
+            Some("scan") => {
+                let [xob] = &rest[1..] else {
+                    return Err(usage("prefabdst scan takes exactly one .xob path"));
+                };
+                let res = if args.flag("scr") {
+                    to_json(&crate::prefabdst::scan_scr_dst(xob.clone()).await?)?
+                } else {
+                    to_json(&crate::prefabdst::scan_full_dst(xob.clone()).await?)?
+                };
+                Ok((res, 0))
+            }
+            _ => Err(usage("prefabdst needs a subcommand: build | scan")),
+        },
+        "mqa" => {
+            if rest.is_empty() {
+                return Err(usage("mqa needs at least one .xob path"));
+            }
+            let port = args.parsed::<u16>("port").map_err(usage)?;
+            let asset_type = args.value("asset-type");
+            let res = if rest.len() == 1 {
+                crate::mqa_report(sink.as_ref(), rest[0].clone(), port, asset_type).await?
+            } else {
+                crate::mqa_report_batch(sink.as_ref(), rest.to_vec(), port, asset_type).await?
+            };
+            let code = if args.flag("fail-on-issues") && mqa_issue_count(&res) > 0 { EXIT_ISSUES } else { 0 };
+            Ok((res, code))
+        }
+        "wb-call" => {
+            let [func] = rest else {
+                return Err(usage("wb-call takes exactly one function name"));
+            };
+            let params = match args.value("params") {
+                Some(p) => serde_json::from_str::<JsonValue>(&p).map_err(|e| usage(format!("Invalid --params JSON: {}", e)))?,
+                None => json!({}),
+            };
+            let port = args.parsed::<u16>("port").map_err(usage)?;
+            let client_id = args.value("client-id").or_else(|| Some("OwlTools-CLI".to_string()));
+            let res = crate::wb_call(func.clone(), params, args.value("ip"), port, client_id)?;
+            Ok((res, 0))
+        }
+        other => Err(usage(format!("Unknown command: {}", other))),
+    }
+}
+
+async fn prefabdst_build(sink: &dyn LogSink, args: &Args, models: &[String]) -> Result<(JsonValue, i32), CliError> {
+    if models.is_empty() {
+        return Err(usage("prefabdst build needs at least one .xob path"));
+    }
+    let preset = args.value("preset").ok_or_else(|| usage("prefabdst build needs --preset FILE"))?;
+    let save_folder = args.value("out").ok_or_else(|| usage("prefabdst build needs --out DIR"))?;
+    let preset_text = fs::read_to_string(&preset).map_err(|e| format!("Failed to read preset {}: {}", preset, e))?;
+    let preset_file = Path::new(&preset)
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_else(|| preset.clone());
+    let build_args = crate::prefabdst::PrefabDstBuildArgs {
+        preset_file,
+        preset_text,
+        zones: args.parsed::<usize>("zones").map_err(usage)?.unwrap_or(3),
+        hp_zone: args.parsed::<i32>("hp").map_err(usage)?.unwrap_or(50),
+        debris_mass: args.parsed::<f32>("debris-mass").map_err(usage)?.unwrap_or(500.0),
+        model_files: models.to_vec(),
+        save_folder,
+        scr_override: None,
+        full_override: None,
+        dry_run: Some(args.flag("dry-run")),
+    };
+    let res = crate::prefabdst::build_dst_prefabs(sink, build_args).await?;
+    Ok((to_json(&res)?, 0))
+}
+
+/// Total number of MQA findings in a single or batch report.
+fn mqa_issue_count(report: &JsonValue) -> u64 {
+    let count_of = |r: &JsonValue| r.get("count").and_then(|c| c.as_u64()).unwrap_or(0);
+    match report.get("reports").and_then(|r| r.as_array()) {
+        Some(reports) => reports.iter().map(count_of).sum(),
+        None => count_of(report),
+    }
+}
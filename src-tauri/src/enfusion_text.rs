@@ -0,0 +1,888 @@
+// Lossless parser/serializer for Enfusion text resources (.et, .conf, .meta, .layer).
+//
+// Every token keeps the whitespace and comments in front of it, so a parsed document
+// serializes back byte-identical as long as nothing was edited. Edits only touch the
+// tokens they replace; tabs, CRLF and inline braces elsewhere in the file survive.
+//
+// Statement shapes understood by the parser:
+//   Name "{GUID}Prefabs/x.et"                 property (key + values on one line)
+//   coords 0 0 0                              property with several values
+//   GenericEntity : "{GUID}parent.et" {       object with inheritance
+//   MeshObject "{GUID}" {                     object with quoted ID
+//   LocalTransform AttachPoint "{GUID}" {     property holding an object (key + class)
+//   $grp GenericEntity : "..." {              group of instances sharing a parent
+//   components { ... }                        named block / array
+//   { ... }                                   anonymous block (child entities)
+//   "a" "b"                                   bare array values
+
+use std::fmt;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    Str,
+    Colon,
+    Open,
+    Close,
+}
+
+#[derive(Clone, Debug)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Whitespace and comments in front of the token, verbatim.
+    pub leading: String,
+    /// Token text as written; strings keep their quotes.
+    pub text: String,
+    /// Byte offset of `text` in the parsed source (0 for tokens created in code).
+    pub offset: usize,
+}
+
+impl Token {
+    pub fn word(leading: &str, text: &str) -> Token {
+        Token { kind: TokenKind::Word, leading: leading.to_string(), text: text.to_string(), offset: 0 }
+    }
+
+    pub fn string(leading: &str, value: &str) -> Token {
+        Token { kind: TokenKind::Str, leading: leading.to_string(), text: quote(value), offset: 0 }
+    }
+
+    /// String contents without the surrounding quotes; words are returned as-is.
+    pub fn unquoted(&self) -> &str {
+        if self.kind == TokenKind::Str && self.text.len() >= 2 {
+            &self.text[1..self.text.len() - 1]
+        } else {
+            &self.text
+        }
+    }
+
+    /// Replace the value, keeping the token kind and leading trivia.
+    pub fn set_value(&mut self, value: &str) {
+        self.text = if self.kind == TokenKind::Str { quote(value) } else { value.to_string() };
+    }
+
+    fn starts_line(&self) -> bool {
+        self.leading.contains('\n')
+    }
+
+    fn write_to(&self, out: &mut String) {
+        out.push_str(&self.leading);
+        out.push_str(&self.text);
+    }
+}
+
+fn quote(value: &str) -> String {
+    format!("\"{}\"", value.replace('"', "\\\""))
+}
+
+#[derive(Clone, Debug)]
+pub struct Property {
+    pub key: Token,
+    pub values: Vec<Token>,
+}
+
+impl Property {
+    pub fn key(&self) -> &str {
+        &self.key.text
+    }
+
+    /// First value, unquoted (`Name "{GUID}x.et"` -> `{GUID}x.et`).
+    pub fn value_str(&self) -> Option<&str> {
+        self.values.first().map(|t| t.unquoted())
+    }
+
+    pub fn set_value_str(&mut self, value: &str) {
+        match self.values.first_mut() {
+            Some(t) => t.set_value(value),
+            None => self.values.push(Token::string(" ", value)),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Object {
+    /// Header tokens before `{` (`$grp`, key, class, ID, `:`, parent).
+    pub head: Vec<Token>,
+    pub open: Token,
+    pub children: Vec<Node>,
+    pub close: Token,
+}
+
+/// Typed view of an object header: `[$grp] [key] Class ["id"] [: parent]`.
+/// A header with two bare words reads as `key Class`.
+struct Header<'a> {
+    grp: bool,
+    words: Vec<&'a Token>,
+    id: Option<usize>,
+    parent: Option<usize>,
+}
+
+impl Object {
+    fn header(&self) -> Header<'_> {
+        let mut h = Header { grp: false, words: Vec::new(), id: None, parent: None };
+        let mut i = 0usize;
+        if self.head.first().map(|t| t.kind == TokenKind::Word && t.text == "$grp").unwrap_or(false) {
+            h.grp = true;
+            i = 1;
+        }
+        while i < self.head.len() {
+            let t = &self.head[i];
+            match t.kind {
+                TokenKind::Word if h.id.is_none() => h.words.push(t),
+                TokenKind::Str if h.id.is_none() => h.id = Some(i),
+                TokenKind::Colon => {
+                    if i + 1 < self.head.len() {
+                        h.parent = Some(i + 1);
+                    }
+                    break;
+                }
+                _ => {}
+            }
+            i += 1;
+        }
+        h
+    }
+
+    /// `{ ... }` without any header.
+    pub fn is_anonymous(&self) -> bool {
+        self.head.is_empty()
+    }
+
+    pub fn is_group(&self) -> bool {
+        self.header().grp
+    }
+
+    /// Property name when the object is the value of a property (`LocalTransform AttachPoint {`).
+    pub fn key(&self) -> Option<&str> {
+        let h = self.header();
+        if h.words.len() >= 2 { Some(h.words[0].text.as_str()) } else { None }
+    }
+
+    pub fn class_name(&self) -> Option<&str> {
+        self.header().words.last().map(|t| t.text.as_str())
+    }
+
+    /// Quoted ID, unquoted (`MeshObject "{5A3C...}"` -> `{5A3C...}`).
+    pub fn id(&self) -> Option<&str> {
+        let idx = self.header().id?;
+        Some(self.head[idx].unquoted())
+    }
+
+    /// Inherited resource or class after `:`, unquoted.
+    pub fn parent(&self) -> Option<&str> {
+        let idx = self.header().parent?;
+        Some(self.head[idx].unquoted())
+    }
+
+    pub fn set_id(&mut self, id: &str) {
+        match self.header().id {
+            Some(idx) => self.head[idx].set_value(id),
+            None => {
+                let at = self.header().parent.map(|p| p - 1).unwrap_or(self.head.len());
+                self.head.insert(at, Token::string(" ", id));
+            }
+        }
+    }
+
+    pub fn set_parent(&mut self, parent: &str) {
+        match self.header().parent {
+            Some(idx) => self.head[idx].set_value(parent),
+            None => {
+                self.head.push(Token { kind: TokenKind::Colon, leading: " ".into(), text: ":".into(), offset: 0 });
+                self.head.push(Token::string(" ", parent));
+            }
+        }
+    }
+
+    pub fn properties(&self) -> impl Iterator<Item = &Property> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Property(p) => Some(p),
+            _ => None,
+        })
+    }
+
+    pub fn objects(&self) -> impl Iterator<Item = &Object> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Object(o) => Some(o),
+            _ => None,
+        })
+    }
+
+    pub fn objects_mut(&mut self) -> impl Iterator<Item = &mut Object> {
+        self.children.iter_mut().filter_map(|n| match n {
+            Node::Object(o) => Some(o),
+            _ => None,
+        })
+    }
+
+    /// Bare values of an array block (`Tags { "a" "b" }`).
+    pub fn values(&self) -> impl Iterator<Item = &Token> {
+        self.children.iter().filter_map(|n| match n {
+            Node::Value(t) => Some(t),
+            _ => None,
+        })
+    }
+
+    pub fn property(&self, key: &str) -> Option<&Property> {
+        self.properties().find(|p| p.key() == key)
+    }
+
+    pub fn property_mut(&mut self, key: &str) -> Option<&mut Property> {
+        self.children.iter_mut().find_map(|n| match n {
+            Node::Property(p) if p.key() == key => Some(p),
+            _ => None,
+        })
+    }
+
+    /// First child object whose class (or block name) is `class`.
+    pub fn object(&self, class: &str) -> Option<&Object> {
+        self.objects().find(|o| o.class_name() == Some(class))
+    }
+
+    pub fn object_mut(&mut self, class: &str) -> Option<&mut Object> {
+        self.objects_mut().find(|o| o.class_name() == Some(class))
+    }
+
+    pub fn position(&self, pred: impl Fn(&Node) -> bool) -> Option<usize> {
+        self.children.iter().position(pred)
+    }
+
+    /// Indentation of the line the header starts on.
+    fn indent(&self) -> &str {
+        let first = self.head.first().unwrap_or(&self.open);
+        line_indent(&first.leading).unwrap_or("")
+    }
+
+    /// Indentation of the first child line, or the closing brace's plus one space.
+    pub fn child_indent(&self) -> String {
+        if let Some(first) = self.children.first() {
+            if let Some(ind) = line_indent(&first.first_token().leading) {
+                return ind.to_string();
+            }
+        }
+        format!("{} ", line_indent(&self.close.leading).unwrap_or(self.indent()))
+    }
+
+    /// Insert a node before the closing brace, on its own line.
+    pub fn push_child(&mut self, node: Node) {
+        let at = self.children.len();
+        self.insert_child(at, node);
+    }
+
+    /// Insert a node at `index`, forcing it onto its own line.
+    pub fn insert_child(&mut self, index: usize, mut node: Node) {
+        let nl = newline_of(&self.close.leading).or_else(|| newline_of(&self.open.leading)).unwrap_or("\n");
+        let lead = &mut node.first_token_mut().leading;
+        if !lead.contains('\n') {
+            lead.insert_str(0, nl);
+        }
+        if index == self.children.len() && !self.close.starts_line() {
+            self.close.leading = format!("{}{}", nl, self.indent());
+        }
+        self.children.insert(index.min(self.children.len()), node);
+    }
+
+    /// Replace a child, keeping the trivia (and comments) of the node it replaces.
+    pub fn replace_child(&mut self, index: usize, mut node: Node) {
+        let old_lead = self.children[index].first_token().leading.clone();
+        node.first_token_mut().leading = old_lead;
+        self.children[index] = node;
+    }
+
+    pub fn remove_child(&mut self, index: usize) -> Node {
+        self.children.remove(index)
+    }
+
+    pub fn write_to(&self, out: &mut String) {
+        for t in &self.head {
+            t.write_to(out);
+        }
+        self.open.write_to(out);
+        for c in &self.children {
+            c.write_to(out);
+        }
+        self.close.write_to(out);
+    }
+}
+
+#[derive(Clone, Debug)]
+pub enum Node {
+    Property(Property),
+    Object(Object),
+    Value(Token),
+}
+
+impl Node {
+    pub fn first_token(&self) -> &Token {
+        match self {
+            Node::Property(p) => &p.key,
+            Node::Object(o) => o.head.first().unwrap_or(&o.open),
+            Node::Value(t) => t,
+        }
+    }
+
+    pub fn first_token_mut(&mut self) -> &mut Token {
+        match self {
+            Node::Property(p) => &mut p.key,
+            Node::Object(o) => match o.head.first_mut() {
+                Some(t) => t,
+                None => &mut o.open,
+            },
+            Node::Value(t) => t,
+        }
+    }
+
+    pub fn as_object(&self) -> Option<&Object> {
+        match self {
+            Node::Object(o) => Some(o),
+            _ => None,
+        }
+    }
+
+    pub fn as_property(&self) -> Option<&Property> {
+        match self {
+            Node::Property(p) => Some(p),
+            _ => None,
+        }
+    }
+
+    pub fn write_to(&self, out: &mut String) {
+        match self {
+            Node::Property(p) => {
+                p.key.write_to(out);
+                for v in &p.values {
+                    v.write_to(out);
+                }
+            }
+            Node::Object(o) => o.write_to(out),
+            Node::Value(t) => t.write_to(out),
+        }
+    }
+
+    /// Move a generated node to `indent`, keeping its inner relative indentation and
+    /// switching line breaks to `nl`. Tab-indented targets get one tab per extra space.
+    pub fn reindent(&mut self, indent: &str, nl: &str) {
+        let lead = &self.first_token().leading;
+        let base = match line_indent(lead) {
+            Some(ind) => ind.len(),
+            None if lead.chars().all(|c| c == ' ' || c == '\t') => lead.len(),
+            None => 0,
+        };
+        let tabs = indent.contains('\t');
+        let mut first = true;
+        self.for_each_token_mut(&mut |t| {
+            if first {
+                t.leading = match t.leading.starts_with('\n') || t.leading.starts_with("\r\n") {
+                    true => format!("{}{}", nl, indent),
+                    false => indent.to_string(),
+                };
+                first = false;
+                return;
+            }
+            let Some(idx) = t.leading.rfind('\n') else { return; };
+            let old = &t.leading[idx + 1..];
+            if !old.chars().all(|c| c == ' ' || c == '\t') {
+                return;
+            }
+            let extra = old.len().saturating_sub(base);
+            let pad = if tabs { "\t".repeat(extra) } else { " ".repeat(extra) };
+            let head = t.leading[..idx].trim_end_matches('\r').replace("\r\n", "\n").replace('\n', nl);
+            t.leading = format!("{}{}{}{}", head, nl, indent, pad);
+        });
+    }
+
+    /// Visit every token of this node in source order.
+    pub fn for_each_token(&self, f: &mut dyn FnMut(&Token)) {
+        match self {
+            Node::Property(p) => {
+                f(&p.key);
+                for t in &p.values {
+                    f(t);
+                }
+            }
+            Node::Object(o) => {
+                for t in &o.head {
+                    f(t);
+                }
+                f(&o.open);
+                for c in &o.children {
+                    c.for_each_token(f);
+                }
+                f(&o.close);
+            }
+            Node::Value(t) => f(t),
+        }
+    }
+
+    pub fn for_each_token_mut(&mut self, f: &mut dyn FnMut(&mut Token)) {
+        match self {
+            Node::Property(p) => {
+                f(&mut p.key);
+                for t in p.values.iter_mut() {
+                    f(t);
+                }
+            }
+            Node::Object(o) => {
+                for t in o.head.iter_mut() {
+                    f(t);
+                }
+                f(&mut o.open);
+                for c in o.children.iter_mut() {
+                    c.for_each_token_mut(f);
+                }
+                f(&mut o.close);
+            }
+            Node::Value(t) => f(t),
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Document {
+    pub bom: bool,
+    pub nodes: Vec<Node>,
+    /// Whitespace and comments after the last node.
+    pub trailing: String,
+}
+
+impl Document {
+    /// First top-level object (the entity of an .et, `MetaFileClass` of a .meta).
+    pub fn root(&self) -> Option<&Object> {
+        self.nodes.iter().find_map(|n| n.as_object())
+    }
+
+    pub fn root_mut(&mut self) -> Option<&mut Object> {
+        self.nodes.iter_mut().find_map(|n| match n {
+            Node::Object(o) => Some(o),
+            _ => None,
+        })
+    }
+
+    /// `Name` of a .meta file (`{GUID}path/to/resource`).
+    pub fn meta_name(&self) -> Option<&str> {
+        self.root()?.property("Name")?.value_str()
+    }
+
+    /// Resource class of a .meta file, from its first `Configurations` entry
+    /// (`EntityTemplateResourceClass PC {` -> `EntityTemplateResourceClass`).
+    pub fn meta_resource_class(&self) -> Option<&str> {
+        let entry = self.root()?.object("Configurations")?.objects().next()?;
+        entry.key().or_else(|| entry.class_name())
+    }
+
+    pub fn newline(&self) -> &'static str {
+        let mut nl = None;
+        self.for_each_token(&mut |t| {
+            if nl.is_none() {
+                nl = newline_of(&t.leading);
+            }
+        });
+        nl.or_else(|| newline_of(&self.trailing)).unwrap_or("\n")
+    }
+
+    pub fn for_each_token(&self, f: &mut dyn FnMut(&Token)) {
+        for n in &self.nodes {
+            n.for_each_token(f);
+        }
+    }
+
+    pub fn for_each_token_mut(&mut self, f: &mut dyn FnMut(&mut Token)) {
+        for n in self.nodes.iter_mut() {
+            n.for_each_token_mut(f);
+        }
+    }
+}
+
+impl fmt::Display for Document {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut out = String::new();
+        if self.bom {
+            out.push('\u{feff}');
+        }
+        for n in &self.nodes {
+            n.write_to(&mut out);
+        }
+        out.push_str(&self.trailing);
+        f.write_str(&out)
+    }
+}
+
+fn newline_of(trivia: &str) -> Option<&'static str> {
+    let idx = trivia.find('\n')?;
+    if idx > 0 && trivia.as_bytes()[idx - 1] == b'\r' { Some("\r\n") } else { Some("\n") }
+}
+
+/// Whitespace after the last line break of `trivia`, if it contains one.
+fn line_indent(trivia: &str) -> Option<&str> {
+    let idx = trivia.rfind('\n')?;
+    let tail = &trivia[idx + 1..];
+    if tail.chars().all(|c| c == ' ' || c == '\t') { Some(tail) } else { None }
+}
+
+fn line_col(text: &str, offset: usize) -> (usize, usize) {
+    let before = &text[..offset.min(text.len())];
+    let line = before.matches('\n').count() + 1;
+    let col = before.len() - before.rfind('\n').map(|i| i + 1).unwrap_or(0) + 1;
+    (line, col)
+}
+
+/// Split text into tokens; the second value is the trailing trivia.
+pub fn tokenize(text: &str) -> Result<(Vec<Token>, String), String> {
+    let bytes = text.as_bytes();
+    let mut tokens: Vec<Token> = Vec::new();
+    let mut i = if text.starts_with('\u{feff}') { '\u{feff}'.len_utf8() } else { 0 };
+    loop {
+        let trivia_start = i;
+        loop {
+            if i >= bytes.len() {
+                break;
+            }
+            let c = bytes[i];
+            if c.is_ascii_whitespace() {
+                i += 1;
+            } else if bytes[i..].starts_with(b"//") {
+                while i < bytes.len() && bytes[i] != b'\n' {
+                    i += 1;
+                }
+            } else if bytes[i..].starts_with(b"/*") {
+                match text[i + 2..].find("*/") {
+                    Some(end) => i = i + 2 + end + 2,
+                    None => {
+                        let (l, c) = line_col(text, i);
+                        return Err(format!("line {}:{}: unterminated comment", l, c));
+                    }
+                }
+            } else {
+                break;
+            }
+        }
+        let leading = text[trivia_start..i].to_string();
+        if i >= bytes.len() {
+            return Ok((tokens, leading));
+        }
+        let start = i;
+        let kind = match bytes[i] {
+            b'{' => {
+                i += 1;
+                TokenKind::Open
+            }
+            b'}' => {
+                i += 1;
+                TokenKind::Close
+            }
+            b':' => {
+                i += 1;
+                TokenKind::Colon
+            }
+            b'"' => {
+                i += 1;
+                loop {
+                    if i >= bytes.len() {
+                        let (l, c) = line_col(text, start);
+                        return Err(format!("line {}:{}: unterminated string", l, c));
+                    }
+                    match bytes[i] {
+                        b'\\' => i += 2,
+                        b'"' => {
+                            i += 1;
+                            break;
+                        }
+                        _ => i += 1,
+                    }
+                }
+                i = i.min(bytes.len());
+                TokenKind::Str
+            }
+            _ => {
+                while i < bytes.len() && !bytes[i].is_ascii_whitespace() && !matches!(bytes[i], b'{' | b'}' | b'"' | b':') {
+                    i += 1;
+                }
+                TokenKind::Word
+            }
+        };
+        tokens.push(Token { kind, leading, text: text[start..i].to_string(), offset: start });
+    }
+}
+
+pub fn parse(text: &str) -> Result<Document, String> {
+    let (tokens, trailing) = tokenize(text)?;
+    let mut p = Parser { text, tokens, pos: 0 };
+    let nodes = p.block(None)?;
+    Ok(Document { bom: text.starts_with('\u{feff}'), nodes, trailing })
+}
+
+/// Parse a snippet of statements (e.g. a generated component) for insertion into a document.
+pub fn parse_nodes(text: &str) -> Result<Vec<Node>, String> {
+    Ok(parse(text)?.nodes)
+}
+
+struct Parser<'a> {
+    text: &'a str,
+    tokens: Vec<Token>,
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn err(&self, offset: usize, msg: &str) -> String {
+        let (l, c) = line_col(self.text, offset);
+        format!("line {}:{}: {}", l, c, msg)
+    }
+
+    fn take(&mut self) -> Token {
+        let t = std::mem::replace(
+            &mut self.tokens[self.pos],
+            Token { kind: TokenKind::Word, leading: String::new(), text: String::new(), offset: 0 },
+        );
+        self.pos += 1;
+        t
+    }
+
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    /// Parse statements until the matching `}` (returned through `close`) or end of input.
+    fn block(&mut self, open_offset: Option<usize>) -> Result<Vec<Node>, String> {
+        let mut nodes: Vec<Node> = Vec::new();
+        loop {
+            let Some(t) = self.peek() else {
+                return match open_offset {
+                    Some(off) => Err(self.err(off, "missing closing '}'")),
+                    None => Ok(nodes),
+                };
+            };
+            match t.kind {
+                TokenKind::Close => {
+                    if open_offset.is_some() {
+                        return Ok(nodes);
+                    }
+                    return Err(self.err(t.offset, "unexpected '}'"));
+                }
+                TokenKind::Open => {
+                    let obj = self.object(Vec::new())?;
+                    nodes.push(Node::Object(obj));
+                }
+                TokenKind::Str | TokenKind::Colon => {
+                    let v = self.take();
+                    nodes.push(Node::Value(v));
+                }
+                TokenKind::Word => {
+                    let mut head = vec![self.take()];
+                    while let Some(n) = self.peek() {
+                        if matches!(n.kind, TokenKind::Open | TokenKind::Close) || n.starts_line() {
+                            break;
+                        }
+                        head.push(self.take());
+                    }
+                    let opens = match self.peek() {
+                        Some(n) if n.kind == TokenKind::Open => !n.starts_line() || header_takes_next_line_brace(&head),
+                        _ => false,
+                    };
+                    if opens {
+                        nodes.push(Node::Object(self.object(head)?));
+                    } else {
+                        let key = head.remove(0);
+                        nodes.push(Node::Property(Property { key, values: head }));
+                    }
+                }
+            }
+        }
+    }
+
+    fn object(&mut self, head: Vec<Token>) -> Result<Object, String> {
+        let open = self.take();
+        let children = self.block(Some(open.offset))?;
+        let close = self.take();
+        Ok(Object { head, open, children, close })
+    }
+}
+
+/// A `{` on the line after a statement belongs to it only when the statement cannot be a
+/// property: a lone class name, a `$grp`/inheritance header or `Class "{GUID}"`. Otherwise
+/// it is an anonymous block, like the child entity list after `coords 0 0 0`.
+fn header_takes_next_line_brace(head: &[Token]) -> bool {
+    if head.len() == 1 || head.iter().any(|t| t.kind == TokenKind::Colon) || head[0].text == "$grp" {
+        return true;
+    }
+    if let [w, s] = head {
+        if w.kind == TokenKind::Word && s.kind == TokenKind::Str {
+            let v = s.unquoted();
+            return v.len() == 18
+                && v.starts_with('{')
+                && v.ends_with('}')
+                && v[1..17].chars().all(|c| c.is_ascii_hexdigit());
+        }
+    }
+    false
+}
+
+/// Byte offsets `(keyword, open_brace, close_brace)` of the first block introduced by
+/// `keyword`; braces inside strings and comments are ignored.
+pub fn find_block(text: &str, keyword: &str) -> Option<(usize, usize, usize)> {
+    let (tokens, _) = tokenize(text).ok()?;
+    let kw = tokens.iter().position(|t| t.kind == TokenKind::Word && t.text == keyword)?;
+    let open = kw + tokens[kw..].iter().position(|t| t.kind == TokenKind::Open)?;
+    let mut depth = 0i32;
+    for t in &tokens[open..] {
+        match t.kind {
+            TokenKind::Open => depth += 1,
+            TokenKind::Close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some((tokens[kw].offset, tokens[open].offset, t.offset));
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn round_trip(text: &str) -> Document {
+        let doc = parse(text).unwrap();
+        assert_eq!(doc.to_string(), text);
+        doc
+    }
+
+    /// Lines of `after` that differ from `before`, for edits that keep the line count.
+    fn changed_lines(before: &str, after: &str) -> Vec<usize> {
+        let (b, a): (Vec<&str>, Vec<&str>) = (before.split('\n').collect(), after.split('\n').collect());
+        assert_eq!(b.len(), a.len(), "line count changed:\n{}", after);
+        (0..b.len()).filter(|&i| b[i] != a[i]).collect()
+    }
+
+    const PREFAB: &str = "GenericEntity : \"{AAAAAAAAAAAAAAAA}Prefabs/base.et\" {\n ID \"5A3C0000DEADBEEF\"\n components {\n  MeshObject \"{5A3C000011112222}\" {\n   Object \"{BBBBBBBBBBBBBBBB}Assets/m.xob\"\n  }\n }\n coords 0 0 0\n}\n";
+
+    #[test]
+    fn round_trips_spaces() {
+        let doc = round_trip(PREFAB);
+        let root = doc.root().unwrap();
+        assert_eq!(root.class_name(), Some("GenericEntity"));
+        assert_eq!(root.parent(), Some("{AAAAAAAAAAAAAAAA}Prefabs/base.et"));
+        let mesh = root.object("components").unwrap().object("MeshObject").unwrap();
+        assert_eq!(mesh.id(), Some("{5A3C000011112222}"));
+        assert_eq!(mesh.property("Object").unwrap().value_str(), Some("{BBBBBBBBBBBBBBBB}Assets/m.xob"));
+    }
+
+    #[test]
+    fn round_trips_tabs_crlf_and_bom() {
+        let text = "\u{feff}GenericEntity {\r\n\tcomponents {\r\n\t\tHierarchy \"{5A3C000011112222}\" {\r\n\t\t}\r\n\t}\r\n\tcoords 1 2 3\r\n}\r\n";
+        let doc = round_trip(text);
+        assert!(doc.bom);
+        assert_eq!(doc.newline(), "\r\n");
+        assert_eq!(doc.root().unwrap().property("coords").unwrap().values.len(), 3);
+    }
+
+    #[test]
+    fn round_trips_comments() {
+        let text = "// header comment\nGenericEntity { // trailing\n /* block\n    comment { not a brace } */\n Flags 3 // why\n}\n// end\n";
+        let doc = round_trip(text);
+        let root = doc.root().unwrap();
+        assert_eq!(root.children.len(), 1);
+        assert_eq!(root.property("Flags").unwrap().value_str(), Some("3"));
+        assert_eq!(doc.trailing, "\n// end\n");
+    }
+
+    #[test]
+    fn round_trips_inline_braces_and_arrays() {
+        let text = "GenericEntity { components { } Tags { \"a\" \"b\" } coords 0 0 0 }\n";
+        let doc = round_trip(text);
+        let root = doc.root().unwrap();
+        assert!(root.object("components").unwrap().children.is_empty());
+        let tags: Vec<&str> = root.object("Tags").unwrap().values().map(|t| t.unquoted()).collect();
+        assert_eq!(tags, ["a", "b"]);
+    }
+
+    #[test]
+    fn round_trips_grp_and_inheritance() {
+        let text = "Layer {\n $grp GenericEntity : \"{AAAAAAAAAAAAAAAA}Prefabs/tree.et\" {\n  tree_1 {\n   coords 1 0 1\n  }\n  {\n   coords 2 0 2\n  }\n }\n}\n";
+        let doc = round_trip(text);
+        let grp = doc.root().unwrap().objects().next().unwrap();
+        assert!(grp.is_group());
+        assert_eq!(grp.class_name(), Some("GenericEntity"));
+        assert_eq!(grp.parent(), Some("{AAAAAAAAAAAAAAAA}Prefabs/tree.et"));
+        assert_eq!(grp.objects().count(), 2);
+        assert!(grp.objects().nth(1).unwrap().is_anonymous());
+    }
+
+    #[test]
+    fn next_line_brace_binds_to_headers_only() {
+        let text = "GenericEntity\n{\n MeshObject \"{5A3C000011112222}\"\n {\n }\n Child : \"{AAAAAAAAAAAAAAAA}x.et\"\n {\n }\n coords 0 0 0\n {\n  GenericEntity {\n  }\n }\n}\n";
+        let doc = round_trip(text);
+        let root = doc.root().unwrap();
+        assert_eq!(root.class_name(), Some("GenericEntity"));
+        assert_eq!(root.object("MeshObject").unwrap().id(), Some("{5A3C000011112222}"));
+        assert_eq!(root.object("Child").unwrap().parent(), Some("{AAAAAAAAAAAAAAAA}x.et"));
+        // `coords 0 0 0` stays a property; the block after it is the anonymous child list.
+        assert_eq!(root.property("coords").unwrap().values.len(), 3);
+        let anon = root.objects().find(|o| o.is_anonymous()).unwrap();
+        assert!(anon.object("GenericEntity").is_some());
+    }
+
+    #[test]
+    fn rejects_unbalanced_braces() {
+        assert!(parse("GenericEntity {\n").unwrap_err().contains("missing closing"));
+        assert!(parse("}\n").unwrap_err().contains("unexpected"));
+    }
+
+    #[test]
+    fn set_parent_touches_only_the_header_line() {
+        let mut doc = parse(PREFAB).unwrap();
+        doc.root_mut().unwrap().set_parent("{CCCCCCCCCCCCCCCC}Prefabs/other.et");
+        let after = doc.to_string();
+        assert_eq!(changed_lines(PREFAB, &after), [0]);
+        assert!(after.starts_with("GenericEntity : \"{CCCCCCCCCCCCCCCC}Prefabs/other.et\" {\n"));
+
+        let text = "GenericEntity {\n\tcoords 0 0 0\n}\n";
+        let mut doc = parse(text).unwrap();
+        doc.root_mut().unwrap().set_parent("{AAAAAAAAAAAAAAAA}x.et");
+        let after = doc.to_string();
+        assert_eq!(changed_lines(text, &after), [0]);
+        assert_eq!(after, "GenericEntity : \"{AAAAAAAAAAAAAAAA}x.et\" {\n\tcoords 0 0 0\n}\n");
+    }
+
+    #[test]
+    fn insert_child_adds_lines_without_touching_others() {
+        let text = "GenericEntity {\r\n\tcomponents {\r\n\t\tHierarchy {\r\n\t\t}\r\n\t}\r\n\tcoords 0 0 0\r\n}\r\n";
+        let mut doc = parse(text).unwrap();
+        let comps = doc.root_mut().unwrap().object_mut("components").unwrap();
+        let indent = comps.child_indent();
+        let mut node = parse_nodes("MeshObject {\n Object \"x.xob\"\n}").unwrap().remove(0);
+        node.reindent(&indent, "\r\n");
+        comps.push_child(node);
+        assert_eq!(
+            doc.to_string(),
+            "GenericEntity {\r\n\tcomponents {\r\n\t\tHierarchy {\r\n\t\t}\r\n\t\tMeshObject {\r\n\t\t\tObject \"x.xob\"\r\n\t\t}\r\n\t}\r\n\tcoords 0 0 0\r\n}\r\n"
+        );
+    }
+
+    #[test]
+    fn insert_child_into_inline_block_moves_only_its_close() {
+        let text = "GenericEntity {\n components { }\n coords 0 0 0\n}\n";
+        let mut doc = parse(text).unwrap();
+        let comps = doc.root_mut().unwrap().object_mut("components").unwrap();
+        let indent = comps.child_indent();
+        let mut node = parse_nodes("Hierarchy {\n}").unwrap().remove(0);
+        node.reindent(&indent, "\n");
+        comps.insert_child(0, node);
+        assert_eq!(doc.to_string(), "GenericEntity {\n components {\n  Hierarchy {\n  }\n }\n coords 0 0 0\n}\n");
+    }
+
+    #[test]
+    fn reindent_keeps_relative_indentation() {
+        let mut node = parse_nodes("\n    A {\n      B 1\n      C {\n        D 2\n      }\n    }").unwrap().remove(0);
+        node.reindent("\t", "\r\n");
+        let mut out = String::new();
+        node.write_to(&mut out);
+        assert_eq!(out, "\r\n\tA {\r\n\t\t\tB 1\r\n\t\t\tC {\r\n\t\t\t\t\tD 2\r\n\t\t\t}\r\n\t}");
+
+        let mut node = parse_nodes("A {\n B 1\n}").unwrap().remove(0);
+        node.reindent("  ", "\n");
+        let mut out = String::new();
+        node.write_to(&mut out);
+        assert_eq!(out, "  A {\n   B 1\n  }");
+    }
+}
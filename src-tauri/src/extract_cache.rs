@@ -0,0 +1,274 @@
+// On-disk cache of FBX extraction results (socket names, socket ref_guids, UCX offsets).
+//
+// Entries are keyed by the FBX path and carry the SHA-256 of the file they were
+// extracted from; an entry whose hash no longer matches is ignored and replaced. Size
+// and mtime are stored too so unchanged files skip rehashing.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::Read;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::UNIX_EPOCH;
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct FbxExtract {
+    /// Names of `socket*` models; `None` until the FBX was read natively.
+    #[serde(default)]
+    pub sockets: Option<Vec<String>>,
+    /// Socket name -> ref_guid; `None` until extracted.
+    #[serde(default)]
+    pub guids: Option<BTreeMap<String, String>>,
+    /// True once UCX offsets were looked for, whether or not one was found.
+    #[serde(default)]
+    pub ucx_extracted: bool,
+    #[serde(default)]
+    pub ucx_offset: Option<[f32; 3]>,
+    /// Socket name -> local transform; `None` until the FBX was read natively.
+    #[serde(default)]
+    pub socket_transforms: Option<BTreeMap<String, crate::fbx::LocalTransform>>,
+    /// "fbx" or "blender", whichever produced the last update.
+    #[serde(default)]
+    pub source: String,
+}
+
+impl FbxExtract {
+    /// Everything the cache holds, from a natively parsed FBX. GUIDs and UCX offsets stay
+    /// unextracted when the models they live on carry no user properties at all, so callers
+    /// fall back to Blender for them.
+    pub fn from_models(models: &[crate::fbx::FbxModel]) -> FbxExtract {
+        let guids_native = !crate::fbx::user_props_missing(models, "socket");
+        FbxExtract {
+            sockets: Some(
+                models
+                    .iter()
+                    .filter(|m| m.name.to_lowercase().starts_with("socket"))
+                    .map(|m| m.name.clone())
+                    .collect(),
+            ),
+            guids: guids_native.then(|| crate::fbx::socket_ref_guids(models)),
+            ucx_extracted: !crate::fbx::user_props_missing(models, "ucx_d_"),
+            ucx_offset: crate::fbx::ucx_offset(models).map(|(x, y, z)| [x, y, z]),
+            socket_transforms: Some(crate::fbx::socket_transforms(models)),
+            source: "fbx".to_string(),
+        }
+    }
+
+    /// Takes what a native read (`from_models`) produced and keeps the rest, so GUIDs and UCX
+    /// offsets Blender stored for the same content survive.
+    pub fn merge(&mut self, native: FbxExtract) {
+        if native.sockets.is_some() {
+            self.sockets = native.sockets;
+        }
+        if native.socket_transforms.is_some() {
+            self.socket_transforms = native.socket_transforms;
+        }
+        if native.guids.is_some() {
+            self.guids = native.guids;
+        }
+        if native.ucx_extracted {
+            self.ucx_extracted = true;
+            self.ucx_offset = native.ucx_offset;
+        }
+        self.source = native.source;
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct CacheEntry {
+    pub sha256: String,
+    pub size: u64,
+    pub mtime: f64,
+    pub updated: String,
+    #[serde(flatten)]
+    pub data: FbxExtract,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct CacheFile {
+    version: u32,
+    entries: BTreeMap<String, CacheEntry>,
+}
+
+static CACHE: OnceCell<Mutex<CacheFile>> = OnceCell::new();
+
+fn cache() -> &'static Mutex<CacheFile> {
+    CACHE.get_or_init(|| {
+        Mutex::new(crate::load_json_or_backup(&crate::extract_cache_path()))
+    })
+}
+
+fn save(file: &CacheFile) -> Result<(), String> {
+    let text = serde_json::to_string_pretty(file).map_err(|e| e.to_string())?;
+    crate::write_atomic(&crate::extract_cache_path(), text)
+}
+
+fn key_of(path: &Path) -> String {
+    path.to_string_lossy().replace('\\', "/").to_lowercase()
+}
+
+fn size_and_mtime(path: &Path) -> Option<(u64, f64)> {
+    let md = fs::metadata(path).ok()?;
+    let mtime = md.modified().ok()?.duration_since(UNIX_EPOCH).ok()?.as_secs_f64();
+    Some((md.len(), mtime))
+}
+
+pub fn sha256_file(path: &Path) -> Result<String, String> {
+    let mut f = fs::File::open(path).map_err(|e| e.to_string())?;
+    let mut hasher = Sha256::new();
+    let mut buf = vec![0u8; 1 << 16];
+    loop {
+        let n = f.read(&mut buf).map_err(|e| e.to_string())?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex::encode(hasher.finalize()))
+}
+
+/// Current content hash of `path`, reusing the cached one when size and mtime match.
+fn fingerprint(path: &Path, cached: Option<&CacheEntry>) -> Option<(String, u64, f64)> {
+    let (size, mtime) = size_and_mtime(path)?;
+    if let Some(e) = cached {
+        if e.size == size && e.mtime == mtime {
+            return Some((e.sha256.clone(), size, mtime));
+        }
+    }
+    let sha = sha256_file(path).ok()?;
+    Some((sha, size, mtime))
+}
+
+/// Cached extraction for `path` if the file content is unchanged.
+pub fn get(path: &Path) -> Option<FbxExtract> {
+    let key = key_of(path);
+    let cached = cache().lock().unwrap().entries.get(&key).cloned()?;
+    let (sha, size, mtime) = fingerprint(path, Some(&cached))?;
+    if sha != cached.sha256 {
+        return None;
+    }
+    if cached.size != size || cached.mtime != mtime {
+        // Touched but identical content: refresh the stat fields so the next lookup skips hashing.
+        let mut c = cache().lock().unwrap();
+        if let Some(e) = c.entries.get_mut(&key) {
+            e.size = size;
+            e.mtime = mtime;
+        }
+        let _ = save(&c);
+    }
+    Some(cached.data)
+}
+
+/// Applies `edit` to the entry for `path`, starting from scratch if the content changed.
+pub fn update(path: &Path, edit: impl FnOnce(&mut FbxExtract)) -> Result<(), String> {
+    let key = key_of(path);
+    let mut c = cache().lock().unwrap();
+    let (sha, size, mtime) =
+        fingerprint(path, c.entries.get(&key)).ok_or_else(|| format!("Cannot hash {}", path.to_string_lossy()))?;
+    let mut data = match c.entries.get(&key) {
+        Some(e) if e.sha256 == sha => e.data.clone(),
+        _ => FbxExtract::default(),
+    };
+    edit(&mut data);
+    c.version = 1;
+    c.entries.insert(
+        key,
+        CacheEntry {
+            sha256: sha,
+            size,
+            mtime,
+            updated: chrono::Utc::now().to_rfc3339(),
+            data,
+        },
+    );
+    save(&c)
+}
+
+#[derive(Serialize)]
+pub struct ExtractCacheStatus {
+    pub path: String,
+    pub entries: usize,
+    pub size_bytes: u64,
+    pub items: BTreeMap<String, CacheEntry>,
+}
+
+pub fn status() -> ExtractCacheStatus {
+    let path = crate::extract_cache_path();
+    let size_bytes = fs::metadata(&path).map(|m| m.len()).unwrap_or(0);
+    let c = cache().lock().unwrap();
+    ExtractCacheStatus {
+        path: path.to_string_lossy().to_string(),
+        entries: c.entries.len(),
+        size_bytes,
+        items: c.entries.clone(),
+    }
+}
+
+/// Drops the entry for `path`, or every entry when `path` is `None`. Returns how many were removed.
+pub fn clear(path: Option<&Path>) -> Result<usize, String> {
+    let mut c = cache().lock().unwrap();
+    let removed = match path {
+        Some(p) => usize::from(c.entries.remove(&key_of(p)).is_some()),
+        None => {
+            let n = c.entries.len();
+            c.entries.clear();
+            n
+        }
+    };
+    save(&c)?;
+    Ok(removed)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn native(guids: Option<&[(&str, &str)]>, ucx: Option<Option<[f32; 3]>>) -> FbxExtract {
+        FbxExtract {
+            sockets: Some(vec!["socket_a".into()]),
+            guids: guids.map(|g| g.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect()),
+            ucx_extracted: ucx.is_some(),
+            ucx_offset: ucx.flatten(),
+            socket_transforms: Some(BTreeMap::new()),
+            source: "fbx".into(),
+        }
+    }
+
+    #[test]
+    fn native_update_keeps_blender_results() {
+        let mut entry = FbxExtract {
+            guids: Some(BTreeMap::from([("socket_a".to_string(), "1111111111111111".to_string())])),
+            ucx_extracted: true,
+            ucx_offset: Some([1.0, 2.0, 3.0]),
+            source: "blender".into(),
+            ..Default::default()
+        };
+        entry.merge(native(None, None));
+
+        assert_eq!(entry.sockets.as_deref(), Some(&["socket_a".to_string()][..]));
+        assert!(entry.socket_transforms.is_some());
+        assert_eq!(entry.guids.as_ref().and_then(|g| g.get("socket_a")).map(String::as_str), Some("1111111111111111"));
+        assert!(entry.ucx_extracted);
+        assert_eq!(entry.ucx_offset, Some([1.0, 2.0, 3.0]));
+        assert_eq!(entry.source, "fbx");
+    }
+
+    #[test]
+    fn native_results_replace_what_they_cover() {
+        let mut entry = FbxExtract {
+            guids: Some(BTreeMap::from([("socket_a".to_string(), "1111111111111111".to_string())])),
+            ucx_extracted: true,
+            ucx_offset: Some([1.0, 2.0, 3.0]),
+            ..Default::default()
+        };
+        entry.merge(native(Some(&[("socket_a", "2222222222222222")]), Some(None)));
+
+        assert_eq!(entry.guids.as_ref().and_then(|g| g.get("socket_a")).map(String::as_str), Some("2222222222222222"));
+        assert!(entry.ucx_extracted);
+        assert_eq!(entry.ucx_offset, None);
+    }
+}
@@ -0,0 +1,794 @@
+// Minimal FBX reader (binary 7.x and ASCII) for the bits OwlTools needs without Blender:
+// model nodes, their user properties and local transforms.
+//
+// Both formats are parsed into the same generic node tree (name, properties, children).
+// Array properties (vertices, normals, ...) are skipped without decompressing, so only
+// the `Objects/Model/Properties70` part of the file is materialized.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use once_cell::sync::Lazy;
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+const BINARY_MAGIC: &[u8] = b"Kaydara FBX Binary  \x00";
+
+static GUID_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"([0-9A-Fa-f]{16})").unwrap());
+static NUMBER_RE: Lazy<Regex> = Lazy::new(|| Regex::new(r"[-+]?(?:\d+\.?\d*|\.\d+)(?:[eE][-+]?\d+)?").unwrap());
+
+#[derive(Clone, Debug, PartialEq, Serialize)]
+#[serde(untagged)]
+pub enum FbxValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    /// Array property; only its element count is kept.
+    Array(usize),
+}
+
+impl FbxValue {
+    pub fn as_f64(&self) -> Option<f64> {
+        match self {
+            FbxValue::Int(v) => Some(*v as f64),
+            FbxValue::Float(v) => Some(*v),
+            FbxValue::Bool(v) => Some(if *v { 1.0 } else { 0.0 }),
+            _ => None,
+        }
+    }
+
+    pub fn as_str(&self) -> Option<&str> {
+        match self {
+            FbxValue::Str(s) => Some(s),
+            _ => None,
+        }
+    }
+
+    fn as_i64(&self) -> Option<i64> {
+        match self {
+            FbxValue::Int(v) => Some(*v),
+            FbxValue::Float(v) => Some(*v as i64),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct FbxNode {
+    pub name: String,
+    pub props: Vec<FbxValue>,
+    pub children: Vec<FbxNode>,
+}
+
+impl FbxNode {
+    pub fn child(&self, name: &str) -> Option<&FbxNode> {
+        self.children.iter().find(|c| c.name == name)
+    }
+
+    pub fn children_named<'a>(&'a self, name: &'a str) -> impl Iterator<Item = &'a FbxNode> + 'a {
+        self.children.iter().filter(move |c| c.name == name)
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct FbxModel {
+    pub id: i64,
+    pub name: String,
+    /// Model subtype: "Mesh", "Null", "LimbNode", ...
+    pub kind: String,
+    pub translation: [f64; 3],
+    /// Euler degrees, as stored in `Lcl Rotation`.
+    pub rotation: [f64; 3],
+    pub scaling: [f64; 3],
+    /// Properties flagged `U` (custom/user properties), values as written.
+    pub user_props: BTreeMap<String, Vec<FbxValue>>,
+    /// Id of the parent model, from the `Connections` section.
+    pub parent: Option<i64>,
+}
+
+impl FbxModel {
+    pub fn user_prop(&self, key: &str) -> Option<&[FbxValue]> {
+        self.user_props.get(key).map(|v| v.as_slice())
+    }
+
+    /// `ref_guid` user property (any spelling Blender would accept), as 16 uppercase hex digits.
+    pub fn ref_guid(&self) -> Option<String> {
+        let value = self
+            .user_props
+            .iter()
+            .find(|(k, _)| k.to_lowercase().replace([' ', '_'], "") == "refguid")
+            .and_then(|(_, v)| v.first())
+            .and_then(|v| v.as_str())?;
+        GUID_RE.captures(value).map(|c| c[1].to_uppercase())
+    }
+
+    /// Row-major 4x4 matrix from `ebt_original_transform_matrix`, stored either as a
+    /// Python-style list string or as plain numbers.
+    pub fn ebt_original_transform(&self) -> Option<Vec<f64>> {
+        let values = self.user_prop("ebt_original_transform_matrix")?;
+        let nums: Vec<f64> = match values {
+            [FbxValue::Str(s)] => NUMBER_RE.find_iter(s).filter_map(|m| m.as_str().parse::<f64>().ok()).collect(),
+            _ => values.iter().filter_map(|v| v.as_f64()).collect(),
+        };
+        if nums.len() >= 12 {
+            Some(nums)
+        } else {
+            None
+        }
+    }
+}
+
+pub fn read_file(path: &Path) -> Result<Vec<FbxNode>, String> {
+    let data = fs::read(path).map_err(|e| format!("Failed to read FBX {}: {}", path.to_string_lossy(), e))?;
+    parse(&data).map_err(|e| format!("Failed to parse FBX {}: {}", path.to_string_lossy(), e))
+}
+
+pub fn parse(data: &[u8]) -> Result<Vec<FbxNode>, String> {
+    if data.starts_with(BINARY_MAGIC) {
+        BinaryReader::new(data)?.read_top_level()
+    } else {
+        let text = String::from_utf8_lossy(data);
+        AsciiParser::new(&text).parse_top_level()
+    }
+}
+
+pub fn read_models(path: &Path) -> Result<Vec<FbxModel>, String> {
+    let nodes = read_file(path)?;
+    Ok(models_from_nodes(&nodes))
+}
+
+pub fn models_from_nodes(nodes: &[FbxNode]) -> Vec<FbxModel> {
+    let mut models: Vec<FbxModel> = Vec::new();
+    let Some(objects) = nodes.iter().find(|n| n.name == "Objects") else {
+        return models;
+    };
+    for m in objects.children_named("Model") {
+        // 7.x writes `Model: id, "name", "kind"`; 6.x has no id.
+        let id = m.props.first().and_then(|v| v.as_i64());
+        let at = usize::from(id.is_some());
+        let name = m.props.get(at).and_then(|v| v.as_str()).map(object_name).unwrap_or_default();
+        let kind = m.props.get(at + 1).and_then(|v| v.as_str()).unwrap_or("").to_string();
+        let id = id.unwrap_or(0);
+        let mut model = FbxModel {
+            id,
+            name,
+            kind,
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scaling: [1.0; 3],
+            user_props: BTreeMap::new(),
+            parent: None,
+        };
+        // 7.x writes `Properties70 { P: name, type, label, flags, values... }`;
+        // 6.x wrote `Properties60 { Property: name, type, flags, values... }`.
+        let props = m
+            .child("Properties70")
+            .map(|p| (p, "P", 4))
+            .or_else(|| m.child("Properties60").map(|p| (p, "Property", 3)));
+        if let Some((block, entry, value_at)) = props {
+            for p in block.children_named(entry) {
+                let Some(key) = p.props.first().and_then(|v| v.as_str()) else { continue };
+                let flags = p.props.get(value_at - 1).and_then(|v| v.as_str()).unwrap_or("");
+                let values: Vec<FbxValue> = p.props.iter().skip(value_at).cloned().collect();
+                match key {
+                    "Lcl Translation" => model.translation = vec3(&values, model.translation),
+                    "Lcl Rotation" => model.rotation = vec3(&values, model.rotation),
+                    "Lcl Scaling" => model.scaling = vec3(&values, model.scaling),
+                    _ => {}
+                }
+                if flags.contains('U') {
+                    model.user_props.insert(key.to_string(), values);
+                }
+            }
+        }
+        models.push(model);
+    }
+
+    // Object-object connections: `C: "OO", child, parent`.
+    if let Some(conns) = nodes.iter().find(|n| n.name == "Connections") {
+        let mut parents: BTreeMap<i64, i64> = BTreeMap::new();
+        for c in conns.children_named("C") {
+            if c.props.first().and_then(|v| v.as_str()) != Some("OO") {
+                continue;
+            }
+            if let (Some(child), Some(parent)) = (
+                c.props.get(1).and_then(|v| v.as_i64()),
+                c.props.get(2).and_then(|v| v.as_i64()),
+            ) {
+                parents.insert(child, parent);
+            }
+        }
+        let ids: std::collections::HashSet<i64> = models.iter().map(|m| m.id).collect();
+        for m in models.iter_mut() {
+            m.parent = parents.get(&m.id).copied().filter(|p| ids.contains(p));
+        }
+    }
+    models
+}
+
+/// True when there are models named `prefix*` but none of them carries a user property:
+/// the native reader cannot tell what Blender would see, so the caller should ask Blender.
+pub fn user_props_missing(models: &[FbxModel], prefix: &str) -> bool {
+    let mut named = models.iter().filter(|m| m.name.to_lowercase().starts_with(prefix)).peekable();
+    named.peek().is_some() && named.all(|m| m.user_props.is_empty())
+}
+
+/// Socket name -> ref_guid, for every model whose name starts with `socket`.
+pub fn socket_ref_guids(models: &[FbxModel]) -> BTreeMap<String, String> {
+    let mut out = BTreeMap::new();
+    for m in models {
+        if !m.name.to_lowercase().starts_with("socket") {
+            continue;
+        }
+        if let Some(g) = m.ref_guid() {
+            out.insert(m.name.clone(), g);
+        }
+    }
+    out
+}
+
+/// Local transform of a socket model, relative to the model it hangs under.
+#[derive(Clone, Debug, Serialize, Deserialize, PartialEq)]
+pub struct LocalTransform {
+    /// Name of the parent model; None at the scene root.
+    pub parent: Option<String>,
+    pub translation: [f64; 3],
+    /// Euler degrees, as stored in `Lcl Rotation`.
+    pub rotation: [f64; 3],
+    pub scaling: [f64; 3],
+}
+
+/// Socket name -> local transform, for every model whose name starts with `socket`.
+pub fn socket_transforms(models: &[FbxModel]) -> BTreeMap<String, LocalTransform> {
+    let names: BTreeMap<i64, &str> = models.iter().map(|m| (m.id, m.name.as_str())).collect();
+    models
+        .iter()
+        .filter(|m| m.name.to_lowercase().starts_with("socket"))
+        .map(|m| {
+            let t = LocalTransform {
+                parent: m.parent.and_then(|p| names.get(&p)).map(|n| n.to_string()),
+                translation: m.translation,
+                rotation: m.rotation,
+                scaling: m.scaling,
+            };
+            (m.name.clone(), t)
+        })
+        .collect()
+}
+
+/// Debris offset from the first `UCX_D_*` model carrying `ebt_original_transform_matrix`,
+/// returned as (x, z, y) like the Blender script does.
+pub fn ucx_offset(models: &[FbxModel]) -> Option<(f32, f32, f32)> {
+    for m in models {
+        if !m.name.to_lowercase().starts_with("ucx_d_") {
+            continue;
+        }
+        if let Some(v) = m.ebt_original_transform() {
+            return Some((v[3] as f32, v[11] as f32, v[7] as f32));
+        }
+    }
+    None
+}
+
+fn vec3(values: &[FbxValue], default: [f64; 3]) -> [f64; 3] {
+    let nums: Vec<f64> = values.iter().filter_map(|v| v.as_f64()).collect();
+    if nums.len() >= 3 {
+        [nums[0], nums[1], nums[2]]
+    } else {
+        default
+    }
+}
+
+/// Object names are stored as `Name\0\x01Class` in binary files and `Class::Name` in ASCII.
+fn object_name(raw: &str) -> String {
+    if let Some((name, _)) = raw.split_once("\u{0}\u{1}") {
+        return name.to_string();
+    }
+    match raw.split_once("::") {
+        Some((_, name)) => name.to_string(),
+        None => raw.to_string(),
+    }
+}
+
+struct BinaryReader<'a> {
+    data: &'a [u8],
+    pos: usize,
+    wide: bool,
+}
+
+impl<'a> BinaryReader<'a> {
+    fn new(data: &'a [u8]) -> Result<Self, String> {
+        if data.len() < 27 {
+            return Err("truncated header".into());
+        }
+        let version = u32::from_le_bytes([data[23], data[24], data[25], data[26]]);
+        if version < 7000 {
+            return Err(format!("unsupported binary FBX version {}", version));
+        }
+        Ok(BinaryReader { data, pos: 27, wide: version >= 7500 })
+    }
+
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        let end = self.pos.checked_add(n).filter(|e| *e <= self.data.len());
+        let Some(end) = end else {
+            return Err(format!("unexpected end of file at byte {}", self.pos));
+        };
+        let out = &self.data[self.pos..end];
+        self.pos = end;
+        Ok(out)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.take(8)?;
+        let mut a = [0u8; 8];
+        a.copy_from_slice(b);
+        Ok(u64::from_le_bytes(a))
+    }
+
+    fn offset(&mut self) -> Result<usize, String> {
+        if self.wide {
+            Ok(self.u64()? as usize)
+        } else {
+            Ok(self.u32()? as usize)
+        }
+    }
+
+    fn read_top_level(&mut self) -> Result<Vec<FbxNode>, String> {
+        let mut nodes = Vec::new();
+        while self.pos < self.data.len() {
+            match self.read_node()? {
+                Some(n) => nodes.push(n),
+                None => break,
+            }
+        }
+        Ok(nodes)
+    }
+
+    /// Reads one node record; `None` for the null record that terminates a node list.
+    fn read_node(&mut self) -> Result<Option<FbxNode>, String> {
+        let start = self.pos;
+        let end = self.offset()?;
+        let num_props = self.offset()?;
+        let _prop_len = self.offset()?;
+        let name_len = self.u8()? as usize;
+        if end == 0 {
+            return Ok(None);
+        }
+        if end <= start || end > self.data.len() {
+            return Err(format!("bad node end offset {} at byte {}", end, start));
+        }
+        let name = String::from_utf8_lossy(self.take(name_len)?).to_string();
+        let mut props = Vec::with_capacity(num_props.min(64));
+        for _ in 0..num_props {
+            props.push(self.read_prop()?);
+        }
+        let mut children = Vec::new();
+        while self.pos < end {
+            match self.read_node()? {
+                Some(c) => children.push(c),
+                None => break,
+            }
+        }
+        self.pos = end;
+        Ok(Some(FbxNode { name, props, children }))
+    }
+
+    fn read_prop(&mut self) -> Result<FbxValue, String> {
+        let code = self.u8()?;
+        let v = match code {
+            b'C' => FbxValue::Bool(self.u8()? != 0),
+            b'Y' => {
+                let b = self.take(2)?;
+                FbxValue::Int(i16::from_le_bytes([b[0], b[1]]) as i64)
+            }
+            b'I' => FbxValue::Int(self.u32()? as i32 as i64),
+            b'L' => FbxValue::Int(self.u64()? as i64),
+            b'F' => FbxValue::Float(f32::from_bits(self.u32()?) as f64),
+            b'D' => FbxValue::Float(f64::from_bits(self.u64()?)),
+            b'S' | b'R' => {
+                let len = self.u32()? as usize;
+                let raw = self.take(len)?;
+                if code == b'S' {
+                    FbxValue::Str(String::from_utf8_lossy(raw).to_string())
+                } else {
+                    FbxValue::Array(len)
+                }
+            }
+            b'f' | b'd' | b'l' | b'i' | b'b' => {
+                let count = self.u32()? as usize;
+                let _encoding = self.u32()?;
+                let byte_len = self.u32()? as usize;
+                self.take(byte_len)?;
+                FbxValue::Array(count)
+            }
+            other => {
+                return Err(format!("unknown property type '{}' at byte {}", other as char, self.pos - 1));
+            }
+        };
+        Ok(v)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+enum AsciiToken {
+    /// `Name:`
+    Key(String),
+    Str(String),
+    Num(String),
+    Word(String),
+    /// `*123` array length marker.
+    Count(usize),
+    Open,
+    Close,
+    Comma,
+}
+
+struct AsciiParser {
+    tokens: Vec<AsciiToken>,
+    pos: usize,
+}
+
+impl AsciiParser {
+    fn new(text: &str) -> Self {
+        AsciiParser { tokens: ascii_tokens(text), pos: 0 }
+    }
+
+    fn parse_top_level(&mut self) -> Result<Vec<FbxNode>, String> {
+        let nodes = self.parse_nodes()?;
+        if self.pos < self.tokens.len() {
+            return Err(format!("unexpected token {:?}", self.tokens[self.pos]));
+        }
+        Ok(nodes)
+    }
+
+    fn parse_nodes(&mut self) -> Result<Vec<FbxNode>, String> {
+        let mut nodes = Vec::new();
+        while let Some(tok) = self.tokens.get(self.pos).cloned() {
+            match tok {
+                AsciiToken::Key(name) => {
+                    self.pos += 1;
+                    nodes.push(self.parse_node_body(name)?);
+                }
+                AsciiToken::Close => break,
+                other => return Err(format!("expected node name, found {:?}", other)),
+            }
+        }
+        Ok(nodes)
+    }
+
+    fn parse_node_body(&mut self, name: String) -> Result<FbxNode, String> {
+        let mut node = FbxNode { name, ..Default::default() };
+        while let Some(tok) = self.tokens.get(self.pos).cloned() {
+            match tok {
+                AsciiToken::Str(s) => node.props.push(FbxValue::Str(s)),
+                AsciiToken::Num(n) => node.props.push(parse_number(&n)),
+                AsciiToken::Word(w) => node.props.push(match w.as_str() {
+                    "T" | "Y" => FbxValue::Bool(true),
+                    "F" | "N" => FbxValue::Bool(false),
+                    _ => FbxValue::Str(w),
+                }),
+                AsciiToken::Count(n) => node.props.push(FbxValue::Array(n)),
+                AsciiToken::Comma => {}
+                AsciiToken::Open => {
+                    self.pos += 1;
+                    node.children = self.parse_nodes()?;
+                    if self.tokens.get(self.pos) != Some(&AsciiToken::Close) {
+                        return Err(format!("unclosed block in node {}", node.name));
+                    }
+                    self.pos += 1;
+                    break;
+                }
+                AsciiToken::Key(_) | AsciiToken::Close => break,
+            }
+            self.pos += 1;
+        }
+        // `Vertices: *9 { a: ... }` keeps its values in the child `a`; collapse to a count.
+        if let Some(FbxValue::Array(_)) = node.props.first() {
+            node.children.clear();
+        }
+        Ok(node)
+    }
+}
+
+fn parse_number(n: &str) -> FbxValue {
+    if let Ok(i) = n.parse::<i64>() {
+        return FbxValue::Int(i);
+    }
+    n.parse::<f64>().map(FbxValue::Float).unwrap_or_else(|_| FbxValue::Str(n.to_string()))
+}
+
+fn ascii_tokens(text: &str) -> Vec<AsciiToken> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut out = Vec::new();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match c {
+            ';' => {
+                while i < chars.len() && chars[i] != '\n' {
+                    i += 1;
+                }
+            }
+            '{' => {
+                out.push(AsciiToken::Open);
+                i += 1;
+            }
+            '}' => {
+                out.push(AsciiToken::Close);
+                i += 1;
+            }
+            ',' => {
+                out.push(AsciiToken::Comma);
+                i += 1;
+            }
+            '"' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i] != '"' {
+                    i += 1;
+                }
+                let s: String = chars[start..i.min(chars.len())].iter().collect();
+                out.push(AsciiToken::Str(s.replace("&quot;", "\"")));
+                i += 1;
+            }
+            '*' => {
+                let start = i + 1;
+                i = start;
+                while i < chars.len() && chars[i].is_ascii_digit() {
+                    i += 1;
+                }
+                let n: String = chars[start..i].iter().collect();
+                out.push(AsciiToken::Count(n.parse().unwrap_or(0)));
+            }
+            _ if c.is_whitespace() => i += 1,
+            _ => {
+                let start = i;
+                while i < chars.len()
+                    && !chars[i].is_whitespace()
+                    && !matches!(chars[i], ',' | '{' | '}' | '"' | ':' | ';')
+                {
+                    i += 1;
+                }
+                let word: String = chars[start..i].iter().collect();
+                if i < chars.len() && chars[i] == ':' {
+                    out.push(AsciiToken::Key(word));
+                    i += 1;
+                } else if word.is_empty() {
+                    // Stray ':' with no name in front; skip it.
+                    i += 1;
+                } else if word.starts_with(|ch: char| ch.is_ascii_digit() || ch == '-' || ch == '+' || ch == '.') {
+                    out.push(AsciiToken::Num(word));
+                } else {
+                    out.push(AsciiToken::Word(word));
+                }
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ASCII: &str = r#"; FBX 7.4.0 project file
+FBXHeaderExtension:  {
+	FBXVersion: 7400
+}
+Objects:  {
+	Model: 1001, "Model::socket_lamp_01", "Null" {
+		Version: 232
+		Properties70:  {
+			P: "Lcl Translation", "Lcl Translation", "", "A",0.5,-1,2e1
+			P: "Lcl Rotation", "Lcl Rotation", "", "A",0,90,0
+			P: "Ref GUID", "KString", "", "U", "guid 5a3c00000000ba02"
+			P: "DefaultAttributeIndex", "int", "Integer", "",0
+		}
+	}
+	Model: 1002, "Model::Body", "Mesh" {
+		Properties70:  {
+		}
+	}
+	Geometry: 2001, "Geometry::", "Mesh" {
+		Vertices: *6 {
+			a: 0,0,0,1,1,1
+		}
+	}
+}
+Connections:  {
+	;Model::socket_lamp_01, Model::Body
+	C: "OO",1001,1002
+	C: "OO",1002,0
+}
+"#;
+
+    /// Node of a binary FBX under construction; properties are already encoded.
+    struct Node {
+        name: &'static str,
+        props: Vec<Vec<u8>>,
+        children: Vec<Node>,
+    }
+
+    fn node(name: &'static str, props: Vec<Vec<u8>>, children: Vec<Node>) -> Node {
+        Node { name, props, children }
+    }
+
+    fn s(v: &str) -> Vec<u8> {
+        let mut out = vec![b'S'];
+        out.extend((v.len() as u32).to_le_bytes());
+        out.extend(v.as_bytes());
+        out
+    }
+
+    fn l(v: i64) -> Vec<u8> {
+        let mut out = vec![b'L'];
+        out.extend(v.to_le_bytes());
+        out
+    }
+
+    fn d(v: f64) -> Vec<u8> {
+        let mut out = vec![b'D'];
+        out.extend(v.to_le_bytes());
+        out
+    }
+
+    fn doubles(values: &[f64]) -> Vec<u8> {
+        let mut out = vec![b'd'];
+        out.extend((values.len() as u32).to_le_bytes());
+        out.extend(0u32.to_le_bytes());
+        out.extend(((values.len() * 8) as u32).to_le_bytes());
+        values.iter().for_each(|v| out.extend(v.to_le_bytes()));
+        out
+    }
+
+    fn write_node(out: &mut Vec<u8>, n: &Node, wide: bool) {
+        let width = if wide { 8 } else { 4 };
+        let start = out.len();
+        out.resize(start + width * 3, 0);
+        out.push(n.name.len() as u8);
+        out.extend(n.name.as_bytes());
+        let props_start = out.len();
+        n.props.iter().for_each(|p| out.extend(p));
+        let props_len = out.len() - props_start;
+        for c in &n.children {
+            write_node(out, c, wide);
+        }
+        if !n.children.is_empty() {
+            out.resize(out.len() + width * 3 + 1, 0);
+        }
+        let end = out.len();
+        for (i, v) in [end, n.props.len(), props_len].into_iter().enumerate() {
+            let at = start + i * width;
+            if wide {
+                out[at..at + 8].copy_from_slice(&(v as u64).to_le_bytes());
+            } else {
+                out[at..at + 4].copy_from_slice(&(v as u32).to_le_bytes());
+            }
+        }
+    }
+
+    fn binary(version: u32) -> Vec<u8> {
+        let p = |name: &str, flags: &str, values: Vec<Vec<u8>>| {
+            let mut props = vec![s(name), s(""), s(""), s(flags)];
+            props.extend(values);
+            node("P", props, vec![])
+        };
+        let matrix = "[1.0, 0.0, 0.0, 10.5, 0.0, 1.0, 0.0, 20.0, 0.0, 0.0, 1.0, 30.0, 0.0, 0.0, 0.0, 1.0]";
+        let nodes = [
+            node("Objects", vec![], vec![
+                node("Geometry", vec![l(3), s("g\0\x01Geometry"), s("Mesh")], vec![node("Vertices", vec![doubles(&[0.0, 1.0, 2.0])], vec![])]),
+                node("Model", vec![l(1), s("socket_a\0\x01Model"), s("Null")], vec![node("Properties70", vec![], vec![
+                    p("Lcl Translation", "A", vec![d(1.0), d(2.0), d(3.0)]),
+                    p("ref_guid", "U", vec![s("{5A3C00000000BA01}")]),
+                ])]),
+                node("Model", vec![l(2), s("UCX_D_a\0\x01Model"), s("Mesh")], vec![node("Properties70", vec![], vec![
+                    p("ebt_original_transform_matrix", "U", vec![s(matrix)]),
+                ])]),
+            ]),
+            node("Connections", vec![], vec![node("C", vec![s("OO"), l(1), l(2)], vec![]), node("C", vec![s("OO"), l(2), l(0)], vec![])]),
+        ];
+        let mut out = BINARY_MAGIC.to_vec();
+        out.extend([0x1A, 0x00]);
+        out.extend(version.to_le_bytes());
+        let wide = version >= 7500;
+        nodes.iter().for_each(|n| write_node(&mut out, n, wide));
+        out.resize(out.len() + if wide { 25 } else { 13 }, 0);
+        out
+    }
+
+    #[test]
+    fn ascii_models_props_and_parents() {
+        let models = models_from_nodes(&parse(ASCII.as_bytes()).unwrap());
+        assert_eq!(models.len(), 2);
+        let socket = &models[0];
+        assert_eq!((socket.id, socket.name.as_str(), socket.kind.as_str()), (1001, "socket_lamp_01", "Null"));
+        assert_eq!(socket.translation, [0.5, -1.0, 20.0]);
+        assert_eq!(socket.rotation, [0.0, 90.0, 0.0]);
+        assert_eq!(socket.scaling, [1.0; 3]);
+        // Only `U`-flagged properties are user properties.
+        assert_eq!(socket.user_props.keys().collect::<Vec<_>>(), ["Ref GUID"]);
+        assert_eq!(socket.ref_guid().as_deref(), Some("5A3C00000000BA02"));
+        assert_eq!(socket.parent, Some(1002));
+        assert_eq!(models[1].parent, None);
+
+        let transforms = socket_transforms(&models);
+        assert_eq!(transforms["socket_lamp_01"].parent.as_deref(), Some("Body"));
+        assert_eq!(socket_ref_guids(&models)["socket_lamp_01"], "5A3C00000000BA02");
+        assert!(!user_props_missing(&models, "socket"));
+        assert!(!user_props_missing(&models, "ucx_d_"));
+    }
+
+    #[test]
+    fn properties60_values_follow_the_flags() {
+        let text = "Objects: {\n Model: \"Model::socket_old\", \"Null\" {\n  Properties60: {\n   Property: \"Lcl Translation\", \"Lcl Translation\", \"A\",4,5,6\n   Property: \"ref_guid\", \"KString\", \"U\", \"5A3C00000000BA03\"\n  }\n }\n}\n";
+        let models = models_from_nodes(&parse(text.as_bytes()).unwrap());
+        // 6.x models have no numeric ID; the name is the first property.
+        assert_eq!((models[0].id, models[0].name.as_str(), models[0].kind.as_str()), (0, "socket_old", "Null"));
+        assert_eq!(models[0].translation, [4.0, 5.0, 6.0]);
+        assert_eq!(models[0].user_prop("ref_guid"), Some(&[FbxValue::Str("5A3C00000000BA03".into())][..]));
+    }
+
+    #[test]
+    fn binary_74_and_75_read_the_same() {
+        for version in [7400, 7500] {
+            let models = models_from_nodes(&parse(&binary(version)).unwrap());
+            assert_eq!(models.iter().map(|m| m.name.as_str()).collect::<Vec<_>>(), ["socket_a", "UCX_D_a"], "{}", version);
+            assert_eq!(models[0].translation, [1.0, 2.0, 3.0]);
+            assert_eq!(models[0].ref_guid().as_deref(), Some("5A3C00000000BA01"));
+            assert_eq!(models[0].parent, Some(2));
+            assert_eq!(models[1].parent, None);
+            assert_eq!(ucx_offset(&models), Some((10.5, 30.0, 20.0)));
+        }
+        let nodes = parse(&binary(7500)).unwrap();
+        let geometry = nodes[0].child("Geometry").unwrap();
+        assert_eq!(geometry.child("Vertices").unwrap().props, [FbxValue::Array(3)]);
+        // A 7.4 file read with 64-bit offsets (or the reverse) does not parse.
+        let mut wrong = binary(7400);
+        wrong[23..27].copy_from_slice(&7500u32.to_le_bytes());
+        assert!(parse(&wrong).is_err());
+    }
+
+    #[test]
+    fn object_names_split_both_formats() {
+        assert_eq!(object_name("socket_a\0\x01Model"), "socket_a");
+        assert_eq!(object_name("Model::socket_a"), "socket_a");
+        assert_eq!(object_name("Model::ns::socket_a"), "ns::socket_a");
+        assert_eq!(object_name("plain"), "plain");
+    }
+
+    #[test]
+    fn ucx_offset_is_x_z_y_like_blender() {
+        let model = |name: &str, values: Vec<FbxValue>| FbxModel {
+            id: 0,
+            name: name.into(),
+            kind: "Mesh".into(),
+            translation: [0.0; 3],
+            rotation: [0.0; 3],
+            scaling: [1.0; 3],
+            user_props: BTreeMap::from([("ebt_original_transform_matrix".to_string(), values)]),
+            parent: None,
+        };
+        let numbers: Vec<FbxValue> = (0..16).map(|i| FbxValue::Float(i as f64)).collect();
+        let models = vec![
+            model("Body", numbers.clone()),
+            model("UCX_D_short", vec![FbxValue::Str("[1, 2, 3]".into())]),
+            model("ucx_d_01", numbers),
+        ];
+        // Translation column is (v[3], v[7], v[11]); Blender's find_offset returns (x, z, y).
+        assert_eq!(ucx_offset(&models), Some((3.0, 11.0, 7.0)));
+        let text = vec![FbxValue::Str("[[1, 0, 0, -2.5], [0, 1, 0, 1e1], [0, 0, 1, .5], [0, 0, 0, 1]]".into())];
+        assert_eq!(ucx_offset(&[model("UCX_D_x", text)]), Some((-2.5, 0.5, 10.0)));
+        assert!(user_props_missing(&[FbxModel { user_props: BTreeMap::new(), ..model("UCX_D_y", vec![]) }], "ucx_d_"));
+    }
+}
@@ -0,0 +1,177 @@
+// GUID collisions and ambiguous prefab names in the prefab index.
+//
+// Copying a folder in Explorer duplicates its .meta files, so two resources end up with the
+// same GUID and lookups silently resolve to one of them. `find` reports such groups, and
+// prefab file names used more than once (socket matching by name cannot tell them apart).
+// The same GUID at the same project path in different roots is an addon override, not a
+// collision. `regenerate_guid` gives a copy a fresh GUID and points the references inside
+// the copied folder at it.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+use crate::log_sink::LogSink;
+use crate::prefab_db::{PrefabDb, Resource};
+use crate::ref_graph::{rewrite_references, scans_references};
+use crate::{emit_scan_log, enfusion_text, extract_guid, gen_guid16, reindex_written_files, write_all_atomic, write_plan};
+
+#[derive(Serialize, Clone, Debug)]
+pub struct ConflictGroup {
+    /// The shared GUID or lowercase file name.
+    pub key: String,
+    pub resources: Vec<Resource>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct IndexConflicts {
+    pub guid_collisions: Vec<ConflictGroup>,
+    pub duplicate_names: Vec<ConflictGroup>,
+}
+
+/// Splits rows (ordered by `key`) into groups, dropping groups that are only overrides of
+/// one project path across roots.
+fn group(rows: Vec<Resource>, key: impl Fn(&Resource) -> String) -> Vec<ConflictGroup> {
+    let mut groups: Vec<ConflictGroup> = Vec::new();
+    for r in rows {
+        let k = key(&r);
+        match groups.last_mut() {
+            Some(g) if g.key == k => g.resources.push(r),
+            _ => groups.push(ConflictGroup { key: k, resources: vec![r] }),
+        }
+    }
+    groups.retain(|g| {
+        let first = &g.resources[0];
+        let override_only = g.resources.iter().enumerate().all(|(i, r)| {
+            r.rel_path.eq_ignore_ascii_case(&first.rel_path) && g.resources[..i].iter().all(|o| o.root != r.root)
+        });
+        g.resources.len() > 1 && !override_only
+    });
+    groups
+}
+
+pub fn find(db: &PrefabDb) -> Result<IndexConflicts, String> {
+    Ok(IndexConflicts {
+        guid_collisions: group(db.shared_guids()?, |r| r.guid.clone().unwrap_or_default()),
+        duplicate_names: group(db.shared_prefab_names()?, |r| r.file_name.clone()),
+    })
+}
+
+/// Logs a summary of `conflicts` and the first few groups of each kind.
+pub fn log_conflicts(conflicts: &IndexConflicts, mut on_log: impl FnMut(&str, String)) {
+    const SHOWN: usize = 20;
+    for (label, groups) in [("GUID collision", &conflicts.guid_collisions), ("Ambiguous prefab name", &conflicts.duplicate_names)] {
+        if groups.is_empty() {
+            continue;
+        }
+        on_log("warn", format!("{} {}(s) in the index", groups.len(), label));
+        for g in groups.iter().take(SHOWN) {
+            let paths: Vec<&str> = g.resources.iter().map(|r| r.abs_path.as_str()).collect();
+            on_log("warn", format!("{} {}: {}", label, g.key, paths.join(" | ")));
+        }
+        if groups.len() > SHOWN {
+            on_log("warn", format!("... and {} more", groups.len() - SHOWN));
+        }
+    }
+}
+
+#[derive(Deserialize, Default, Clone)]
+pub struct RegenerateGuidArgs {
+    /// The copied resource or its .meta file.
+    pub path: String,
+    /// Folder whose text resources get their references updated; defaults to the folder of
+    /// the resource.
+    pub subtree: Option<String>,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize)]
+pub struct RegenerateGuidResult {
+    pub meta_path: String,
+    pub old_name: String,
+    pub new_name: String,
+    /// Files rewritten besides the .meta, with their number of updated references.
+    pub updated_files: Vec<(String, usize)>,
+    pub plan: Option<write_plan::WritePlan>,
+}
+
+/// Gives the resource at `args.path` a new GUID (and the project path of its current
+/// location), then rewrites references to the old GUID in the text resources under the
+/// subtree. Dry runs return a write plan instead of writing.
+pub fn regenerate_guid(app: &dyn LogSink, args: RegenerateGuidArgs) -> Result<RegenerateGuidResult, String> {
+    let (res_path, meta_path) = match args.path.strip_suffix(".meta") {
+        Some(res) => (PathBuf::from(res), PathBuf::from(&args.path)),
+        None => (PathBuf::from(&args.path), PathBuf::from(format!("{}.meta", args.path))),
+    };
+    let meta_text = fs::read_to_string(&meta_path)
+        .map_err(|e| format!("Failed to read {}: {}", meta_path.to_string_lossy(), e))?;
+    let doc = enfusion_text::parse(&meta_text).map_err(|e| format!("Failed to parse .meta: {}", e))?;
+    let old_name = doc.meta_name().ok_or("Name field not found in .meta")?.trim().to_string();
+    let old_guid = extract_guid(&old_name).ok_or("GUID not found in .meta Name")?;
+
+    let db = crate::prefab_db::open()?;
+    let mut new_guid = gen_guid16();
+    while db.by_guid(&new_guid).is_some() {
+        new_guid = gen_guid16();
+    }
+    let old_rel = old_name.find('}').map(|i| old_name[i + 1..].to_string()).unwrap_or_default();
+    let new_rel = db.project_path(&res_path.to_string_lossy())?.unwrap_or(old_rel);
+    let new_name = format!("{{{}}}{}", new_guid, new_rel);
+    drop(db);
+
+    let quoted = format!("\"{}\"", old_name);
+    if !meta_text.contains(&quoted) {
+        return Err("Name value not found verbatim in .meta".into());
+    }
+    let new_meta = meta_text.replacen(&quoted, &format!("\"{}\"", new_name), 1);
+    emit_scan_log(app, "info", format!("Regenerating GUID: {} -> {}", old_name, new_name), None, None);
+
+    let subtree = args
+        .subtree
+        .map(PathBuf::from)
+        .or_else(|| res_path.parent().map(Path::to_path_buf))
+        .ok_or("No folder to update references in")?;
+    let mut files: Vec<(PathBuf, String)> = vec![(meta_path.clone(), new_meta)];
+    let mut updated_files: Vec<(String, usize)> = Vec::new();
+    for entry in WalkDir::new(&subtree).into_iter().filter_map(Result::ok) {
+        let p = entry.path();
+        let is_text = p
+            .extension()
+            .is_some_and(|e| scans_references(&e.to_string_lossy().to_lowercase()));
+        if !entry.file_type().is_file() || !is_text {
+            continue;
+        }
+        let Ok(text) = fs::read_to_string(p) else { continue };
+        let (new_text, n) = rewrite_references(&text, &old_guid, &new_name);
+        if n > 0 {
+            updated_files.push((p.to_string_lossy().to_string(), n));
+            files.push((p.to_path_buf(), new_text));
+        }
+    }
+    emit_scan_log(
+        app,
+        "info",
+        format!("References to {} updated in {} file(s) under {}", old_guid, updated_files.len(), subtree.to_string_lossy()),
+        None,
+        None,
+    );
+
+    let mut result = RegenerateGuidResult {
+        meta_path: meta_path.to_string_lossy().to_string(),
+        old_name,
+        new_name,
+        updated_files,
+        plan: None,
+    };
+    if args.dry_run.unwrap_or(false) {
+        let plan = write_plan::store(files.into_iter().map(|(p, c)| write_plan::plan_file(&p, c)).collect());
+        emit_scan_log(app, "info", format!("Dry run finished; nothing written (plan {})", plan.id), None, None);
+        result.plan = Some(plan);
+        return Ok(result);
+    }
+    // All or nothing: a failed write restores the files already rewritten.
+    let written = write_all_atomic(files)?;
+    reindex_written_files(app, &written);
+    Ok(result)
+}
@@ -0,0 +1,282 @@
+// Background watcher that keeps the prefab index current.
+//
+// Every configured root is watched recursively. Changed paths are collected and applied in
+// one batch once the tree has been quiet for `DEBOUNCE` (or after `MAX_DELAY` of constant
+// activity): new or modified .meta files are upserted, deleted ones removed, and edited text
+// resources get their references re-read. Folder moves and deletions, and a watcher that
+// dropped events, cannot be applied path by path; they fall back to a full rescan of the
+// roots. `flush` applies whatever is pending right away for callers that need fresh data.
+
+use chrono::Utc;
+use notify::event::{CreateKind, ModifyKind, RemoveKind};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use once_cell::sync::OnceCell;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::log_sink::LogSink;
+use crate::prefab_db::ProjectRoot;
+use crate::{
+    canonical_dir, emit_scan_log, prefab_db, read_meta_name_field, ref_graph, update_prefab_cache_with_new_meta,
+    INDEX_EXCLUDE_DIRS,
+};
+
+const DEBOUNCE: Duration = Duration::from_millis(750);
+const MAX_DELAY: Duration = Duration::from_secs(5);
+const POLL: Duration = Duration::from_millis(200);
+
+pub type Rescan = Arc<dyn Fn() + Send + Sync>;
+
+struct Handlers {
+    sink: Arc<dyn LogSink>,
+    rescan: Rescan,
+}
+
+struct Running {
+    _watcher: RecommendedWatcher,
+    stop: Arc<AtomicBool>,
+}
+
+#[derive(Default)]
+struct Pending {
+    paths: HashSet<PathBuf>,
+    rescan: bool,
+    first: Option<Instant>,
+    last: Option<Instant>,
+}
+
+#[derive(Serialize, Clone, Default)]
+pub struct WatcherStatus {
+    pub running: bool,
+    pub roots: Vec<String>,
+    /// Changed paths waiting for the next batch.
+    pub pending: usize,
+    pub last_update: Option<String>,
+    /// Totals since the app started.
+    pub updated: usize,
+    pub removed: usize,
+    pub rescans: usize,
+    pub last_error: Option<String>,
+}
+
+static HANDLERS: OnceCell<Handlers> = OnceCell::new();
+static RUNNING: OnceCell<Mutex<Option<Running>>> = OnceCell::new();
+static PENDING: OnceCell<Mutex<Pending>> = OnceCell::new();
+static STATUS: OnceCell<Mutex<WatcherStatus>> = OnceCell::new();
+/// Held while a batch is applied, so the debounce thread and `flush` never race.
+static APPLY: OnceCell<Mutex<()>> = OnceCell::new();
+
+fn running() -> &'static Mutex<Option<Running>> {
+    RUNNING.get_or_init(|| Mutex::new(None))
+}
+
+fn pending() -> &'static Mutex<Pending> {
+    PENDING.get_or_init(|| Mutex::new(Pending::default()))
+}
+
+fn status_cell() -> &'static Mutex<WatcherStatus> {
+    STATUS.get_or_init(|| Mutex::new(WatcherStatus::default()))
+}
+
+/// Sets where the watcher logs and how it requests a full rescan. Until this is called (the
+/// CLI never does) `restart` is a no-op.
+pub fn init(sink: Arc<dyn LogSink>, rescan: Rescan) {
+    let _ = HANDLERS.set(Handlers { sink, rescan });
+}
+
+pub fn status() -> WatcherStatus {
+    let queued = pending().lock().unwrap().paths.len();
+    WatcherStatus { pending: queued, ..status_cell().lock().unwrap().clone() }
+}
+
+/// Stops watching and drops pending changes.
+pub fn stop() {
+    if let Some(r) = running().lock().unwrap().take() {
+        r.stop.store(true, Ordering::SeqCst);
+    }
+    *pending().lock().unwrap() = Pending::default();
+    let mut s = status_cell().lock().unwrap();
+    s.running = false;
+    s.roots.clear();
+}
+
+/// Watches `roots` from now on, replacing any previous watch. Roots that are not
+/// directories are skipped; with none left the watcher stays stopped.
+pub fn restart(roots: &[ProjectRoot]) -> Result<(), String> {
+    let Some(h) = HANDLERS.get() else { return Ok(()) };
+    stop();
+    let dirs: Vec<PathBuf> = roots.iter().map(|r| canonical_dir(&r.path)).filter(|p| p.is_dir()).collect();
+    if dirs.is_empty() {
+        return Ok(());
+    }
+    let event_dirs = dirs.clone();
+    let mut watcher = notify::recommended_watcher(move |res| on_event(&event_dirs, res))
+        .map_err(|e| format!("Failed to start index watcher: {}", e))?;
+    for d in &dirs {
+        watcher
+            .watch(d, RecursiveMode::Recursive)
+            .map_err(|e| format!("Failed to watch {}: {}", d.to_string_lossy(), e))?;
+    }
+    let stop = Arc::new(AtomicBool::new(false));
+    let thread_stop = stop.clone();
+    std::thread::spawn(move || debounce_loop(thread_stop));
+    *running().lock().unwrap() = Some(Running { _watcher: watcher, stop });
+    {
+        let mut s = status_cell().lock().unwrap();
+        s.running = true;
+        s.roots = dirs.iter().map(|d| d.to_string_lossy().to_string()).collect();
+        s.last_error = None;
+    }
+    emit_scan_log(h.sink.as_ref(), "info", format!("Watching {} root(s) for index changes", dirs.len()), None, None);
+    Ok(())
+}
+
+/// Applies pending changes now instead of after the debounce delay. A pending full rescan is
+/// only requested; it runs as a job.
+pub fn flush() {
+    if let Some(h) = HANDLERS.get() {
+        apply_pending(h);
+    }
+}
+
+/// True for paths inside a folder the scan skips (.svn, .git, ...) below the watched root.
+fn excluded(dirs: &[PathBuf], path: &Path) -> bool {
+    let rel = dirs.iter().find_map(|d| path.strip_prefix(d).ok()).unwrap_or(path);
+    rel.components().any(|c| {
+        let name = c.as_os_str().to_string_lossy();
+        INDEX_EXCLUDE_DIRS.iter().any(|d| name.eq_ignore_ascii_case(d))
+    })
+}
+
+fn is_folder(path: &Path, kind: &EventKind) -> bool {
+    matches!(kind, EventKind::Create(CreateKind::Folder) | EventKind::Remove(RemoveKind::Folder))
+        || path.is_dir()
+        || (!path.exists() && path.extension().is_none())
+}
+
+fn on_event(dirs: &[PathBuf], res: notify::Result<Event>) {
+    let mut p = pending().lock().unwrap();
+    match res {
+        Ok(event) if event.need_rescan() => p.rescan = true,
+        Ok(event) => {
+            if matches!(event.kind, EventKind::Access(_)) {
+                return;
+            }
+            // Folders report content changes as modifications; only a folder appearing,
+            // disappearing or being renamed needs the rescan.
+            let structural = matches!(
+                event.kind,
+                EventKind::Create(_) | EventKind::Remove(_) | EventKind::Modify(ModifyKind::Name(_))
+            );
+            for path in event.paths {
+                if excluded(dirs, &path) {
+                    continue;
+                }
+                if is_folder(&path, &event.kind) {
+                    p.rescan |= structural;
+                } else {
+                    p.paths.insert(path);
+                }
+            }
+            if p.paths.is_empty() && !p.rescan {
+                return;
+            }
+        }
+        Err(e) => {
+            p.rescan = true;
+            status_cell().lock().unwrap().last_error = Some(e.to_string());
+        }
+    }
+    let now = Instant::now();
+    p.first.get_or_insert(now);
+    p.last = Some(now);
+}
+
+fn debounce_loop(stop: Arc<AtomicBool>) {
+    while !stop.load(Ordering::SeqCst) {
+        std::thread::sleep(POLL);
+        let due = {
+            let p = pending().lock().unwrap();
+            match (p.first, p.last) {
+                (Some(first), Some(last)) => last.elapsed() >= DEBOUNCE || first.elapsed() >= MAX_DELAY,
+                _ => false,
+            }
+        };
+        if due {
+            flush();
+        }
+    }
+}
+
+fn apply_pending(h: &Handlers) {
+    let _guard = APPLY.get_or_init(|| Mutex::new(())).lock().unwrap();
+    let batch = std::mem::take(&mut *pending().lock().unwrap());
+    if batch.first.is_none() {
+        return;
+    }
+    let app = h.sink.as_ref();
+    if batch.rescan {
+        emit_scan_log(app, "info", "Index watcher: folder change or dropped events; rescanning roots", None, None);
+        (h.rescan)();
+        let mut s = status_cell().lock().unwrap();
+        s.rescans += 1;
+        s.last_update = Some(Utc::now().to_rfc3339());
+        return;
+    }
+
+    let mut updated = 0usize;
+    let mut gone: Vec<String> = Vec::new();
+    let mut errors: Vec<String> = Vec::new();
+    for path in &batch.paths {
+        let path_s = path.to_string_lossy().to_string();
+        let res = if let Some(res_s) = path_s.strip_suffix(".meta") {
+            if !path.is_file() {
+                gone.push(res_s.to_string());
+                continue;
+            }
+            // A .meta caught mid-write has no Name yet; its next event brings it in.
+            let Some(name_value) = read_meta_name_field(path) else { continue };
+            update_prefab_cache_with_new_meta(Path::new(res_s), path, &name_value)
+        } else if path.is_file()
+            && path.extension().is_some_and(|e| ref_graph::scans_references(&e.to_string_lossy().to_lowercase()))
+            && Path::new(&format!("{}.meta", path_s)).is_file()
+        {
+            prefab_db::open().and_then(|mut db| db.set_references(&[(path_s.clone(), ref_graph::read_references(path))]))
+        } else {
+            continue;
+        };
+        match res {
+            Ok(()) => updated += 1,
+            Err(err) => errors.push(format!("{}: {}", path_s, err)),
+        }
+    }
+    let removed = if gone.is_empty() {
+        0
+    } else {
+        match prefab_db::open().and_then(|mut db| db.remove_paths(&gone)) {
+            Ok(n) => n,
+            Err(err) => {
+                errors.push(err);
+                0
+            }
+        }
+    };
+
+    if updated > 0 || removed > 0 {
+        emit_scan_log(app, "info", format!("Index watcher: {} updated, {} removed", updated, removed), None, None);
+    }
+    for err in &errors {
+        emit_scan_log(app, "warn", format!("Index watcher: failed to update prefab cache: {}", err), None, None);
+    }
+    let mut s = status_cell().lock().unwrap();
+    s.updated += updated;
+    s.removed += removed;
+    s.last_update = Some(Utc::now().to_rfc3339());
+    if let Some(err) = errors.pop() {
+        s.last_error = Some(err);
+    }
+}
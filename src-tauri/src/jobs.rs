@@ -0,0 +1,338 @@
+// Background jobs for the long operations (prefab scan, batch MQA, PrefabDST build).
+//
+// Jobs wait behind a concurrency limit so two of them never write the same cache file at
+// once. Each job logs through its own `JobSink`, which records the lines and progress on the
+// job before forwarding them to the usual sink, and can be cancelled: the task is aborted,
+// blocking loops see `is_cancelled()`, and the Blender worker is killed for jobs that use it.
+// The last MAX_HISTORY finished jobs are kept, with their logs, in OwlTools_JobHistory.json
+// so they can still be queried after the window is reloaded.
+
+use once_cell::sync::OnceCell;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::{oneshot, Semaphore};
+
+use crate::log_sink::{LogChannel, LogSink};
+
+/// Jobs allowed to run at the same time; the rest stay queued.
+pub const MAX_RUNNING: usize = 1;
+const MAX_HISTORY: usize = 50;
+const MAX_LOG_LINES: usize = 2000;
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Done,
+    Failed,
+    Cancelled,
+}
+
+impl JobStatus {
+    pub fn is_finished(self) -> bool {
+        !matches!(self, JobStatus::Queued | JobStatus::Running)
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct JobLogLine {
+    pub level: String,
+    pub message: String,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Job {
+    pub id: String,
+    pub kind: String,
+    pub label: String,
+    pub status: JobStatus,
+    pub created: String,
+    pub started: Option<String>,
+    pub finished: Option<String>,
+    pub current: Option<usize>,
+    pub total: Option<usize>,
+    /// Lines dropped from the front of `logs` once MAX_LOG_LINES was reached.
+    #[serde(default)]
+    pub logs_truncated: usize,
+    #[serde(default)]
+    pub logs: Vec<JobLogLine>,
+    pub result: Option<JsonValue>,
+    pub error: Option<String>,
+}
+
+impl Job {
+    /// The job without its log lines, as sent in `job_update` events and by `list`.
+    pub fn summary(&self) -> Job {
+        Job { logs: Vec::new(), ..self.clone() }
+    }
+}
+
+/// Called with the job summary whenever its status or progress changes.
+pub type Notify = Arc<dyn Fn(&Job) + Send + Sync>;
+
+struct Control {
+    cancelled: Arc<AtomicBool>,
+    uses_blender: bool,
+    task: Option<tauri::async_runtime::JoinHandle<()>>,
+}
+
+#[derive(Default)]
+struct JobTable {
+    /// Oldest first.
+    order: Vec<String>,
+    jobs: HashMap<String, Job>,
+    controls: HashMap<String, Control>,
+    notify: Option<Notify>,
+}
+
+#[derive(Serialize, Deserialize, Default)]
+struct HistoryFile {
+    version: u32,
+    jobs: Vec<Job>,
+}
+
+static TABLE: OnceCell<Mutex<JobTable>> = OnceCell::new();
+static SLOTS: OnceCell<Arc<Semaphore>> = OnceCell::new();
+
+fn table() -> &'static Mutex<JobTable> {
+    TABLE.get_or_init(|| {
+        let history: HistoryFile = crate::load_json_or_backup(&crate::job_history_path());
+        let mut t = JobTable::default();
+        for job in history.jobs.into_iter().filter(|j| j.status.is_finished()) {
+            t.order.push(job.id.clone());
+            t.jobs.insert(job.id.clone(), job);
+        }
+        Mutex::new(t)
+    })
+}
+
+fn slots() -> Arc<Semaphore> {
+    SLOTS.get_or_init(|| Arc::new(Semaphore::new(MAX_RUNNING))).clone()
+}
+
+fn now() -> String {
+    chrono::Utc::now().to_rfc3339()
+}
+
+fn new_id() -> String {
+    static CTR: AtomicU64 = AtomicU64::new(1);
+    format!("job-{}-{}", chrono::Utc::now().format("%Y%m%d%H%M%S"), CTR.fetch_add(1, Ordering::Relaxed))
+}
+
+fn save(t: &JobTable) {
+    let jobs: Vec<Job> = t
+        .order
+        .iter()
+        .filter_map(|id| t.jobs.get(id))
+        .filter(|j| j.status.is_finished())
+        .cloned()
+        .collect();
+    if let Ok(text) = serde_json::to_string(&HistoryFile { version: 1, jobs }) {
+        let _ = crate::write_atomic(&crate::job_history_path(), text);
+    }
+}
+
+/// Drops the oldest finished jobs beyond MAX_HISTORY.
+fn trim(t: &mut JobTable) {
+    let finished = t.order.iter().filter(|id| t.jobs.get(*id).is_some_and(|j| j.status.is_finished())).count();
+    let mut excess = finished.saturating_sub(MAX_HISTORY);
+    let jobs = &mut t.jobs;
+    t.order.retain(|id| {
+        if excess > 0 && jobs.get(id).is_some_and(|j| j.status.is_finished()) {
+            jobs.remove(id);
+            excess -= 1;
+            return false;
+        }
+        true
+    });
+}
+
+fn notify(t: &JobTable, id: &str) {
+    if let (Some(n), Some(job)) = (t.notify.as_ref(), t.jobs.get(id)) {
+        n(&job.summary());
+    }
+}
+
+/// Sets the callback used for `job_update` notifications.
+pub fn set_notify(n: Notify) {
+    table().lock().unwrap().notify = Some(n);
+}
+
+/// Moves a queued/running job to a final state. No-op if it already finished.
+fn finish(id: &str, status: JobStatus, result: Option<JsonValue>, error: Option<String>) {
+    let mut t = table().lock().unwrap();
+    let Some(job) = t.jobs.get_mut(id) else { return };
+    if job.status.is_finished() {
+        return;
+    }
+    job.status = status;
+    job.finished = Some(now());
+    job.result = result;
+    job.error = error;
+    t.controls.remove(id);
+    trim(&mut t);
+    save(&t);
+    notify(&t, id);
+}
+
+/// LogSink handed to a running job.
+pub struct JobSink {
+    id: String,
+    cancelled: Arc<AtomicBool>,
+    forward: Arc<dyn LogSink>,
+}
+
+impl LogSink for JobSink {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, current: Option<usize>, total: Option<usize>) {
+        self.forward.log(channel, level, message, current, total);
+        let mut t = table().lock().unwrap();
+        let Some(job) = t.jobs.get_mut(&self.id) else { return };
+        if channel != LogChannel::MqaStage {
+            job.logs.push(JobLogLine { level: level.to_string(), message: message.to_string() });
+            if job.logs.len() > MAX_LOG_LINES {
+                let drop = job.logs.len() - MAX_LOG_LINES;
+                job.logs.drain(..drop);
+                job.logs_truncated += drop;
+            }
+        }
+        let progress = (current.or(job.current), total.or(job.total));
+        if progress != (job.current, job.total) {
+            (job.current, job.total) = progress;
+            notify(&t, &self.id);
+        }
+    }
+
+    fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+/// Handle returned by `spawn`; `wait` resolves with the job result.
+pub struct JobTicket {
+    pub id: String,
+    done: oneshot::Receiver<Result<JsonValue, String>>,
+}
+
+impl JobTicket {
+    pub async fn wait(self) -> Result<JsonValue, String> {
+        self.done.await.unwrap_or_else(|_| Err("Cancelled".to_string()))
+    }
+}
+
+/// Queues `run` as a job. It starts once a slot is free and logs through a `JobSink` that
+/// forwards to `forward`.
+pub fn spawn<F, Fut>(kind: &str, label: String, uses_blender: bool, forward: Arc<dyn LogSink>, run: F) -> JobTicket
+where
+    F: FnOnce(Arc<JobSink>) -> Fut + Send + 'static,
+    Fut: Future<Output = Result<JsonValue, String>> + Send + 'static,
+{
+    let id = new_id();
+    let cancelled = Arc::new(AtomicBool::new(false));
+    {
+        let mut t = table().lock().unwrap();
+        t.order.push(id.clone());
+        t.jobs.insert(
+            id.clone(),
+            Job {
+                id: id.clone(),
+                kind: kind.to_string(),
+                label,
+                status: JobStatus::Queued,
+                created: now(),
+                started: None,
+                finished: None,
+                current: None,
+                total: None,
+                logs_truncated: 0,
+                logs: Vec::new(),
+                result: None,
+                error: None,
+            },
+        );
+        t.controls.insert(id.clone(), Control { cancelled: cancelled.clone(), uses_blender, task: None });
+        notify(&t, &id);
+    }
+
+    let (tx, rx) = oneshot::channel();
+    let sink = Arc::new(JobSink { id: id.clone(), cancelled, forward });
+    let job_id = id.clone();
+    let task = tauri::async_runtime::spawn(async move {
+        let Ok(_permit) = slots().acquire_owned().await else { return };
+        {
+            let mut t = table().lock().unwrap();
+            let Some(job) = t.jobs.get_mut(&job_id) else { return };
+            if job.status != JobStatus::Queued {
+                return;
+            }
+            job.status = JobStatus::Running;
+            job.started = Some(now());
+            notify(&t, &job_id);
+        }
+        let res = run(sink.clone()).await;
+        match &res {
+            _ if sink.is_cancelled() => finish(&job_id, JobStatus::Cancelled, None, Some("Cancelled".to_string())),
+            Ok(v) => finish(&job_id, JobStatus::Done, Some(v.clone()), None),
+            Err(e) => finish(&job_id, JobStatus::Failed, None, Some(e.clone())),
+        }
+        let _ = tx.send(res);
+    });
+    if let Some(c) = table().lock().unwrap().controls.get_mut(&id) {
+        c.task = Some(task);
+    }
+    JobTicket { id, done: rx }
+}
+
+/// Cancels a queued or running job. Returns false if it is unknown or already finished.
+pub async fn cancel(id: &str) -> bool {
+    let control = {
+        let mut t = table().lock().unwrap();
+        match t.jobs.get(id) {
+            Some(j) if !j.status.is_finished() => t.controls.remove(id),
+            _ => None,
+        }
+    };
+    let Some(control) = control else { return false };
+    let was_running = table().lock().unwrap().jobs.get(id).is_some_and(|j| j.status == JobStatus::Running);
+    control.cancelled.store(true, Ordering::Relaxed);
+    if let Some(task) = control.task {
+        task.abort();
+    }
+    finish(id, JobStatus::Cancelled, None, Some("Cancelled".to_string()));
+    if was_running && control.uses_blender {
+        crate::blender_worker::stop().await;
+    }
+    true
+}
+
+/// All known jobs, oldest first, without their log lines.
+pub fn list() -> Vec<Job> {
+    let t = table().lock().unwrap();
+    t.order.iter().filter_map(|id| t.jobs.get(id)).map(Job::summary).collect()
+}
+
+pub fn get(id: &str) -> Option<Job> {
+    table().lock().unwrap().jobs.get(id).cloned()
+}
+
+/// Forgets finished jobs. Returns how many were removed.
+pub fn clear_history() -> usize {
+    let mut guard = table().lock().unwrap();
+    let t = &mut *guard;
+    let before = t.order.len();
+    let jobs = &mut t.jobs;
+    t.order.retain(|id| {
+        let keep = jobs.get(id).is_some_and(|j| !j.status.is_finished());
+        if !keep {
+            jobs.remove(id);
+        }
+        keep
+    });
+    let removed = before - t.order.len();
+    save(t);
+    removed
+}
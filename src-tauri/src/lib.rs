@@ -1,8 +1,28 @@
 // Learn more about Tauri commands at https://tauri.app/develop/calling-rust/
+pub mod enfusion_text;
+pub mod write_plan;
+pub mod blender_worker;
+pub mod fbx;
+pub mod extract_cache;
+pub mod log_sink;
+pub mod cli;
+pub mod autosocket;
+pub mod prefabdst;
+pub mod jobs;
+pub mod prefab_db;
+pub mod ref_graph;
+pub mod ref_validate;
+pub mod guid_conflicts;
+pub mod resource_move;
+pub mod index_watcher;
+pub mod socket_rules;
+pub mod socket_discovery;
+pub mod autosocket_batch;
+
 use tauri::tray::{MouseButton, MouseButtonState};
 use std::fs;
 use std::net::TcpStream;
-use std::collections::{HashMap, HashSet, BTreeMap};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::io::{Read, Write};
 use std::path::{Path, PathBuf};
 use serde_json::Value as JsonValue;
@@ -23,160 +43,16 @@ use tokio_stream::wrappers::BroadcastStream;
 use futures::StreamExt;
 use std::convert::Infallible;
 use walkdir::WalkDir;
+use rayon::prelude::*;
 use dirs_next::{home_dir, document_dir};
 use chrono::Utc;
 use std::time::UNIX_EPOCH;
 use std::sync::atomic::{AtomicU64, Ordering};
-use std::collections::BTreeSet;
 use sha2::{Digest, Sha256};
 use rand::Rng;
-use regex::Regex;
-
-#[derive(Serialize, Clone)]
-struct ScanLogPayload {
-    level: String,
-    message: String,
-    current: Option<usize>,
-    total: Option<usize>,
-}
-
-fn extract_ucx_offsets_bulk_with_blender(
-    app: &tauri::AppHandle,
-    pairs: &Vec<(String, PathBuf)>,
-) -> std::collections::HashMap<String, (f32, f32, f32)> {
-    let mut out: std::collections::HashMap<String, (f32, f32, f32)> = std::collections::HashMap::new();
-    if pairs.is_empty() { return out; }
-
-    let settings = load_settings();
-    let blender = settings
-        .blender_path
-        .as_deref()
-        .map(PathBuf::from)
-        .filter(|p| p.is_file())
-        .or_else(resolve_blender_path);
-    let blender = match blender {
-        Some(p) => p,
-        None => {
-            emit_prefabdst_log(app, "info", "Blender debris offset: blender.exe not configured — skip".to_string(), None, None);
-            return out;
-        }
-    };
-    emit_prefabdst_log(app, "info", format!("Debris offset: bulk running {}", blender.to_string_lossy()), None, None);
-
-    let mut list: Vec<(String, String)> = Vec::new();
-    for (key, pb) in pairs {
-        list.push((key.clone(), pb.to_string_lossy().to_string()));
-    }
-    let list_json = serde_json::to_string(&list).unwrap_or("[]".to_string());
-    let py_template = r#"import bpy, json, ast
-files = json.loads(r'''FBX_LIST_JSON''')
-results = {}
-
-def clear_scene():
-    try:
-        bpy.ops.object.select_all(action='SELECT')
-        bpy.ops.object.delete(use_global=False)
-    except Exception:
-        pass
-    for datablock in (bpy.data.meshes, bpy.data.armatures, bpy.data.materials, bpy.data.objects):
-        try:
-            for b in list(datablock):
-                if hasattr(b, 'users') and getattr(b, 'users', 0) == 0:
-                    datablock.remove(b)
-        except Exception:
-            pass
-
-def find_offset():
-    for ob in bpy.data.objects:
-        n=(ob.name or '').lower()
-        if not n.startswith('ucx_d_'):
-            continue
-        v=None
-        try:
-            v=ob.get('ebt_original_transform_matrix')
-        except Exception:
-            v=None
-        if v is None:
-            continue
-        try:
-            if isinstance(v, str):
-                v=ast.literal_eval(v)
-            if isinstance(v, (list, tuple)) and len(v)>=12:
-                x=float(v[3]); y=float(v[7]); z=float(v[11])
-                return (x,z,y)
-        except Exception:
-            pass
-    return None
-
-try:
-    bpy.ops.wm.read_factory_settings(use_empty=True)
-except Exception:
-    pass
-
-for key, path in files:
-    clear_scene()
-    try:
-        bpy.ops.import_scene.fbx(filepath=path, automatic_bone_orientation=True)
-    except Exception:
-        continue
-    loc = find_offset()
-    if loc:
-        results[key] = {'x':loc[0],'y':loc[1],'z':loc[2]}
-
-print(json.dumps(results))
-"#;
-    let py = py_template.replace("FBX_LIST_JSON", &list_json);
-
-    let mut script_path = std::env::temp_dir();
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .ok()
-        .map(|d| d.as_millis())
-        .unwrap_or(0);
-    script_path.push(format!("owltools_ucx_offsets_bulk_{}_{}.py", std::process::id(), ts));
-    let _ = fs::write(&script_path, &py);
-    let script_path_str = script_path.to_string_lossy().to_string();
-
-    let outp = std::process::Command::new(&blender)
-        .args(["--background", "--factory-startup", "--python", &script_path_str])
-        .output();
-    match outp {
-        Ok(cmd_out) => {
-            let stdout = String::from_utf8_lossy(&cmd_out.stdout).to_string();
-            let stderr = String::from_utf8_lossy(&cmd_out.stderr).to_string();
-            if !stderr.trim().is_empty() {
-                emit_prefabdst_log(app, "warn", format!("Blender debris offset bulk stderr: {}", stderr.trim()), None, None);
-            }
-            let mut json_line: Option<String> = None;
-            for line in stdout.lines().rev() {
-                let t = line.trim();
-                if t.starts_with('{') && t.ends_with('}') {
-                    json_line = Some(t.to_string());
-                    break;
-                }
-            }
-            if let Some(jl) = json_line {
-                if let Ok(v) = serde_json::from_str::<serde_json::Value>(&jl) {
-                    if let Some(obj) = v.as_object() {
-                        for (k, val) in obj.iter() {
-                            if let (Some(x), Some(y), Some(z)) = (
-                                val.get("x").and_then(|n| n.as_f64()),
-                                val.get("y").and_then(|n| n.as_f64()),
-                                val.get("z").and_then(|n| n.as_f64()),
-                            ) {
-                                out.insert(k.to_lowercase(), (x as f32, y as f32, z as f32));
-                            }
-                        }
-                    }
-                }
-            }
-        }
-        Err(e) => {
-            emit_prefabdst_log(app, "warn", format!("Blender debris offset bulk failed: {}", e), None, None);
-        }
-    }
-    out
-}
+use log_sink::{LogChannel, LogSink};
+use autosocket::{CreateEtArgs, CreateEtResult, SuggestFoldersResult};
+use prefabdst::{FullDstScanResult, MetaEntry, PrefabDstBuildArgs, ScrDstScanResult};
 
 #[tauri::command]
 fn get_backend_status() -> Result<JsonValue, String> {
@@ -217,9 +93,16 @@ fn get_backend_status() -> Result<JsonValue, String> {
         "python_bpy_ok": bpy_ok,
         "blender_path": resolved_blender,
         "prefer_python": prefer_python,
+        "blender_worker": blender_worker::status(),
     }))
 }
 
+/// Kills the resident Blender worker; the next Blender-backed request starts a fresh one.
+#[tauri::command]
+async fn stop_blender_worker() -> Result<bool, String> {
+    Ok(blender_worker::stop().await)
+}
+
 fn resolve_python_path() -> Option<PathBuf> {
     if let Ok(v) = std::env::var("OWLTOOLS_PYTHON_PATH") {
         let p = PathBuf::from(v);
@@ -249,504 +132,6 @@ fn python_has_bpy(python: &Path) -> bool {
     }
 }
 
-#[derive(Serialize, Deserialize, Clone)]
-struct MetaEntry { guid: String, path: String }
-
-#[derive(Serialize, Deserialize, Clone)]
-struct FullDstZoneInfo {
-    part_id: String,
-    debris: Vec<MetaEntry>,
-    colliders: Vec<String>,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-struct FullDstScanResult {
-    base_guid: String,
-    base_path: String,
-    v2_guid: String,
-    v2_path: String,
-    zones: Vec<FullDstZoneInfo>,
-}
-
-#[tauri::command]
-async fn prefabdst_read_meta(xob_path: String) -> Result<MetaEntry, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    match read_xob_object_field_from_meta(&xob_abs) {
-        Ok((g, p)) => Ok(MetaEntry { guid: g, path: p }),
-        Err(_) => Ok(MetaEntry { guid: String::new(), path: xob_path }),
-    }
-}
-
-#[tauri::command]
-async fn prefabdst_find_ruin_xob(xob_path: String) -> Result<Option<String>, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    if !xob_abs.is_file() {
-        return Err("Invalid xob path".into());
-    }
-    let parent = xob_abs
-        .parent()
-        .ok_or_else(|| "Invalid xob directory".to_string())?
-        .to_path_buf();
-    let parent_parent = parent.parent().map(|p| p.to_path_buf());
-    let stem0 = xob_abs
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| "Invalid xob file name".to_string())?
-        .to_string();
-    let stem = Regex::new(r"(?i)_(ruin|ruined)$")
-        .unwrap()
-        .replace(&stem0, "")
-        .to_string();
-
-    let rx = Regex::new(&format!(r"(?i)^{}_(ruin|ruined)\.xob$", regex::escape(&stem)))
-        .map_err(|e| format!("Regex error: {}", e))?;
-
-    fn scan_root(root: &Path, rx: &Regex) -> Option<String> {
-        let mut best_ruin: Option<String> = None;
-        let mut best_ruined: Option<String> = None;
-        for ent in WalkDir::new(root)
-            .follow_links(false)
-            .max_depth(6)
-            .into_iter()
-            .filter_map(|e| e.ok())
-        {
-            let p = ent.path();
-            if !p.is_file() {
-                continue;
-            }
-            let name = match p.file_name().and_then(|s| s.to_str()) {
-                Some(s) => s,
-                None => continue,
-            };
-            if !rx.is_match(name) {
-                continue;
-            }
-            let lower = name.to_lowercase();
-            let abs = p.to_string_lossy().to_string();
-            if lower.ends_with("_ruin.xob") {
-                best_ruin = Some(abs);
-                break;
-            }
-            if lower.ends_with("_ruined.xob") {
-                best_ruined = Some(abs);
-            }
-        }
-        best_ruin.or(best_ruined)
-    }
-
-    if let Some(found) = scan_root(&parent, &rx) {
-        return Ok(Some(found));
-    }
-    if let Some(pp) = parent_parent {
-        if let Some(found) = scan_root(&pp, &rx) {
-            return Ok(Some(found));
-        }
-    }
-    Ok(None)
-}
-
-fn parse_geometry_param_names(v2_meta_text: &str) -> Vec<String> {
-    // Extract names from lines like:
-    //  GeometryParam UTM_Base_Ruin_base {
-    //  GeometryParam "UTM_House_..." {
-    let re = Regex::new(r#"(?m)^\s*GeometryParam\s+(?:\"([^\"]+)\"|([^\"\s\{]+))\s*\{"#).unwrap();
-    let mut out: Vec<String> = Vec::new();
-    for cap in re.captures_iter(v2_meta_text) {
-        let name = cap.get(1).or_else(|| cap.get(2)).map(|m| m.as_str()).unwrap_or("");
-        let n = name.trim();
-        if !n.is_empty() {
-            out.push(n.to_string());
-        }
-    }
-    // Preserve order but remove duplicates
-    let mut seen: HashSet<String> = HashSet::new();
-    let mut dedup: Vec<String> = Vec::new();
-    for n in out {
-        let key = n.to_lowercase();
-        if seen.insert(key) {
-            dedup.push(n);
-        }
-    }
-    dedup
-}
-
-fn geometry_param_belongs_to_part(name: &str, part_id: &str) -> bool {
-    let n = name.to_lowercase();
-    let p = part_id.to_lowercase();
-    if p.is_empty() { return false; }
-
-    // Include exact part tags commonly used by Arma prefabs
-    if n.contains(&format!("id-{}", p)) { return true; }
-    if n.contains(&format!("fdst_id-{}", p)) { return true; }
-    // VIS tags like: ..._VIS-A^B, ..._VIS-!A, ..._VIS-!A^!B
-    if n.contains(&format!("vis-{}", p)) { return true; }
-    if n.contains(&format!("vis-!{}", p)) { return true; }
-    false
-}
-
-#[tauri::command]
-async fn prefabdst_scan_full_dst(xob_path: String) -> Result<FullDstScanResult, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    if !xob_abs.is_file() { return Err("Invalid xob path".into()); }
-
-    let (base_guid, base_path) = read_xob_object_field_from_meta(&xob_abs)?;
-
-    let stem = xob_abs
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| "Invalid xob file name".to_string())?;
-    let parent = xob_abs.parent().ok_or_else(|| "Invalid xob directory".to_string())?;
-
-    // sibling v2 model
-    let v2_xob = parent.join(format!("{}_V2_dst.xob", stem));
-    if !v2_xob.is_file() {
-        return Err("V2 dst file not found next to base xob".into());
-    }
-    let (v2_guid, v2_path) = read_xob_object_field_from_meta(&v2_xob)?;
-
-    // Parse GeometryParams from v2 meta
-    let v2_meta_path = PathBuf::from(format!("{}.meta", v2_xob.to_string_lossy()));
-    let v2_meta_text = fs::read_to_string(&v2_meta_path)
-        .map_err(|e| format!("Failed to read v2 .xob.meta: {}", e))?;
-    let mut geom_params = parse_geometry_param_names(&v2_meta_text);
-
-    let base_meta_path = PathBuf::from(format!("{}.meta", xob_abs.to_string_lossy()));
-    if let Ok(base_meta_text) = fs::read_to_string(&base_meta_path) {
-        let base_params = parse_geometry_param_names(&base_meta_text)
-            .into_iter()
-            .filter(|n| n.to_lowercase().contains("fdst_"));
-        let mut seen: HashSet<String> = geom_params.iter().map(|s| s.to_lowercase()).collect();
-        for p in base_params {
-            let key = p.to_lowercase();
-            if seen.insert(key) {
-                geom_params.push(p);
-            }
-        }
-    }
-
-    // Scan debris from Dst folder
-    let mut debris_by_part: BTreeMap<String, Vec<(i32, MetaEntry)>> = BTreeMap::new();
-    if let Some(dst_dir) = find_dst_directory(&xob_abs) {
-        let rx = Regex::new(&format!(
-            r"(?i)^(?:{}_V2_dst|{}_V2_dst_FDST|{}_v2_FDST_dst|{}_FDST)_ID-(?P<p>[A-Z])(?:_VIS-[^_]+)?_dbr_(?P<d>\d+)\.xob$",
-            regex::escape(stem),
-            regex::escape(stem),
-            regex::escape(stem),
-            regex::escape(stem)
-        )).unwrap();
-        if let Ok(rd) = fs::read_dir(&dst_dir) {
-            for ent in rd.flatten() {
-                let p = ent.path();
-                if !p.is_file() { continue; }
-                let name = match p.file_name().and_then(|s| s.to_str()) { Some(s) => s, None => continue };
-                if let Some(c) = rx.captures(name) {
-                    let pid = c.name("p").map(|m| m.as_str()).unwrap_or("").to_string();
-                    let dn = c.name("d").map(|m| m.as_str()).unwrap_or("0").parse::<i32>().unwrap_or(0);
-                    let entry = match read_xob_object_field_from_meta(&p) {
-                        Ok((g, r)) => MetaEntry { guid: g, path: r },
-                        Err(_) => MetaEntry { guid: String::new(), path: rel_from_known_roots(&p) },
-                    };
-                    debris_by_part.entry(pid).or_default().push((dn, entry));
-                }
-            }
-        }
-    }
-
-    // Determine which part IDs exist.
-    // Previously we built zones only from debris. That breaks collider replacement when a model has
-    // no dbr files. Include parts inferred from GeometryParam names as well.
-    let mut part_ids: BTreeMap<String, ()> = BTreeMap::new();
-    for pid in debris_by_part.keys() {
-        part_ids.insert(pid.clone(), ());
-    }
-    {
-        let re_part = Regex::new(r"(?i)(?:id-|fdst_id-|vis-|vis-!)(?P<p>[a-z])").unwrap();
-        for n in &geom_params {
-            if let Some(c) = re_part.captures(n) {
-                let pid = c.name("p").map(|m| m.as_str()).unwrap_or("").to_uppercase();
-                if pid.len() == 1 {
-                    part_ids.insert(pid, ());
-                }
-            }
-        }
-    }
-
-    // Sort debris by dbr number and build zones list.
-    let mut zones: Vec<FullDstZoneInfo> = Vec::new();
-    for (pid, _) in part_ids {
-        let mut items = debris_by_part.remove(&pid).unwrap_or_default();
-        items.sort_by_key(|(n, _)| *n);
-        let debris: Vec<MetaEntry> = items.into_iter().map(|(_, e)| e).collect();
-        let colliders: Vec<String> = geom_params
-            .iter()
-            .filter(|n| geometry_param_belongs_to_part(n, &pid))
-            .cloned()
-            .collect();
-        zones.push(FullDstZoneInfo { part_id: pid, debris, colliders });
-    }
-
-    Ok(FullDstScanResult { base_guid, base_path, v2_guid, v2_path, zones })
-}
-
-fn extract_template_body(all_text: &str) -> String {
-    // Prefer content between '--- TEMPLATE ---' and '--- END TEMPLATE ---' (case-insensitive)
-    let nl = detect_newline(all_text);
-    let mut start: Option<usize> = None;
-    let mut end: Option<usize> = None;
-    let mut offset = 0usize;
-    for ln in all_text.lines() {
-        let low = ln.trim().to_lowercase();
-        if start.is_none() && low.contains("--- template ---") {
-            start = Some(offset + ln.len() + nl.len());
-        } else if start.is_some() && low.contains("--- end template ---") {
-            end = Some(offset);
-            break;
-        }
-        offset += ln.len() + nl.len();
-    }
-    if let (Some(s), Some(e)) = (start, end) {
-        return all_text[s..e].to_string();
-    }
-    // Fallback: strip header only
-    strip_preset_header(all_text)
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-struct ScrDebrisItem { guid: String, path: String }
-#[derive(Serialize, Deserialize, Clone)]
-struct ScrPhaseItem { pid: String, model_guid: String, model_path: String, debris: Vec<ScrDebrisItem> }
-#[derive(Serialize, Deserialize, Clone)]
-struct ScrDstScanResult { base_guid: String, base_path: String, phases: Vec<ScrPhaseItem> }
-
-#[tauri::command]
-async fn prefabdst_scan_dst(xob_path: String) -> Result<ScrDstScanResult, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    if !xob_abs.is_file() { return Err("Invalid xob path".into()); }
-    let (base_guid, base_path) = read_xob_object_field_from_meta(&xob_abs)?;
-    let (phases_vec, debris_map) = scan_dst_for_phases_debris(&xob_abs);
-    let mut phases: Vec<ScrPhaseItem> = Vec::new();
-    for (pid, (g, p)) in phases_vec {
-        let debris_items: Vec<ScrDebrisItem> = debris_map
-            .get(&pid)
-            .cloned()
-            .unwrap_or_default()
-            .into_iter()
-            .map(|(dg, dp)| ScrDebrisItem { guid: dg, path: dp })
-            .collect();
-        phases.push(ScrPhaseItem { pid, model_guid: g, model_path: p, debris: debris_items });
-    }
-    Ok(ScrDstScanResult { base_guid, base_path, phases })
-}
-
-#[derive(Default)]
-struct PresetHeader {
-    id: String,
-    title: String,
-    project: String,
-    generator: String,
-    description: String,
-}
-
-fn parse_preset_header(all_text: &str) -> PresetHeader {
-    let mut h = PresetHeader::default();
-    for ln in all_text.lines() {
-        let line = ln.trim();
-        if line.is_empty() { break; }
-        if let Some(col) = line.find(':') {
-            let k = line[..col].trim().to_lowercase();
-            let v = line[col+1..].trim();
-            match k.as_str() {
-                "id" => h.id = v.to_string(),
-                "title" => h.title = v.to_string(),
-                "project" => h.project = v.to_string(),
-                "generator" => h.generator = v.to_string(),
-                "description" => h.description = v.to_string(),
-                _ => {}
-            }
-        }
-    }
-    h
-}
-
-fn find_dst_directory(xob_abs: &Path) -> Option<PathBuf> {
-    let parent = xob_abs.parent()?;
-    let exact = parent.join("dst");
-    if exact.is_dir() { return Some(exact); }
-    // case-insensitive search among siblings
-    if let Ok(rd) = fs::read_dir(parent) {
-        for ent in rd.flatten() {
-            let p = ent.path();
-            if p.is_dir() {
-                if let Some(name) = p.file_name().and_then(|s| s.to_str()) {
-                    if name.eq_ignore_ascii_case("dst") {
-                        return Some(p);
-                    }
-                }
-            }
-        }
-    }
-    None
-}
-
-fn scan_dst_for_phases_debris(base_xob_abs: &Path) -> (Vec<(String, (String, String))>, std::collections::HashMap<String, Vec<(String, String)>>) {
-    let mut phases: Vec<(String, (String, String))> = Vec::new();
-    let mut debris: std::collections::HashMap<String, Vec<(String, String)>> = std::collections::HashMap::new();
-    let Some(dst_dir) = find_dst_directory(base_xob_abs) else { return (phases, debris); };
-
-    let stem = base_xob_abs.file_stem().and_then(|s| s.to_str()).unwrap_or("");
-    let base_cf = stem;
-    let rx_phase = Regex::new(&format!(r"(?i){}{}_dst_(?P<p>\d+)\.xob$", regex::escape(""), regex::escape(&base_cf))).unwrap();
-    let rx_debris = Regex::new(&format!(r"(?i){}{}_dst_(?P<p>\d+)_dbr_(?P<d>\d+)\.xob$", regex::escape(""), regex::escape(&base_cf))).unwrap();
-
-    if let Ok(rd) = fs::read_dir(&dst_dir) {
-        for ent in rd.flatten() {
-            let p = ent.path();
-            if !p.is_file() { continue; }
-            let name = match p.file_name().and_then(|s| s.to_str()) { Some(s)=>s, None=>continue };
-            if let Some(c) = rx_phase.captures(name) {
-                let pid_raw = c.name("p").map(|m| m.as_str()).unwrap_or("");
-                let pid = format!("{:0>2}", pid_raw);
-                match read_xob_object_field_from_meta(&p) {
-                    Ok((g, r)) => phases.push((pid, (g, r))),
-                    Err(_) => phases.push((pid, (String::new(), String::new()))),
-                }
-                continue;
-            }
-            if let Some(c) = rx_debris.captures(name) {
-                let pid_raw = c.name("p").map(|m| m.as_str()).unwrap_or("");
-                let pid = format!("{:0>2}", pid_raw);
-                match read_xob_object_field_from_meta(&p) {
-                    Ok((g, r)) => debris.entry(pid).or_default().push((g, r)),
-                    Err(_) => debris.entry(pid).or_default().push((String::new(), String::new())),
-                }
-            }
-        }
-    }
-    // Ensure every PID that has debris also has a phase entry (even if empty)
-    {
-        use std::collections::HashSet;
-        let have: HashSet<String> = phases.iter().map(|(p, _)| p.clone()).collect();
-        for pid in debris.keys() {
-            if !have.contains(pid) {
-                phases.push((pid.clone(), (String::new(), String::new())));
-            }
-        }
-    }
-    // sort phases by pid numeric
-    phases.sort_by_key(|(pid, _)| pid.parse::<i32>().unwrap_or(9999));
-    // sort debris by trailing _dbr_YY number inferred from path, fallback by name
-    for (_pid, arr) in debris.iter_mut() {
-        arr.sort_by_key(|(_g, path)| {
-            let name = Path::new(path).file_name().and_then(|s| s.to_str()).unwrap_or("");
-            let re = Regex::new(r"(?i)_dbr_(\d+)\.xob$").unwrap();
-            if let Some(c) = re.captures(name) { c[1].parse::<i32>().unwrap_or(9999) } else { 9999 }
-        });
-    }
-    (phases, debris)
-}
-
-fn render_scr_destructible_template(
-    preset_text: &str,
-    base_guid: &str,
-    base_res: &str,
-    base_xob_abs: &Path,
-    override_data: Option<&ScrDstScanResult>,
-) -> String {
-    let nl = detect_newline(preset_text);
-    let mut body = extract_template_body(preset_text);
-
-    // Generate IDs
-    let entity_id = gen_guid16();
-    let mesh_id = gen_guid16();
-    let id1 = gen_guid16();
-    let id2 = gen_guid16();
-    let id3 = gen_guid16();
-    let id4 = gen_guid16();
-    let id5 = gen_guid16();
-    let id6 = gen_guid16();
-
-    // Base object line (prefer override base if provided)
-    let (use_guid, use_res) = if let Some(ov) = override_data {
-        if !ov.base_guid.is_empty() || !ov.base_path.is_empty() {
-            (ov.base_guid.clone(), ov.base_path.clone())
-        } else { (base_guid.to_string(), base_res.to_string()) }
-    } else {
-        (base_guid.to_string(), base_res.to_string())
-    };
-    let base_object_line = if !use_guid.is_empty() && !use_res.is_empty() {
-        format!("   Object \"{{{}}}{}\"", use_guid, use_res)
-    } else if !base_guid.is_empty() && !base_res.is_empty() {
-        format!("   Object \"{{{}}}{}\"", base_guid, base_res)
-    } else {
-        "   Object \"\"".to_string()
-    };
-
-    // Scan dst for phases and debris, unless override provided from UI
-    let (phases, debris_map) = if let Some(ov) = override_data {
-        let mut phases: Vec<(String, (String, String))> = Vec::new();
-        let mut debris: std::collections::HashMap<String, Vec<(String, String)>> = std::collections::HashMap::new();
-        for ph in &ov.phases {
-            phases.push((ph.pid.clone(), (ph.model_guid.clone(), ph.model_path.clone())));
-            let mut arr: Vec<(String, String)> = Vec::new();
-            for d in &ph.debris { arr.push((d.guid.clone(), d.path.clone())); }
-            debris.insert(ph.pid.clone(), arr);
-        }
-        (phases, debris)
-    } else {
-        scan_dst_for_phases_debris(base_xob_abs)
-    };
-    let first_phase = phases.first().map(|(_pid,(g,r))| (g.clone(), r.clone()));
-    let last_phase = phases.last().map(|(_pid,(g,r))| (g.clone(), r.clone()));
-    let first_pid = phases.first().map(|(pid, _)| pid.clone());
-    let last_pid = phases.last().map(|(pid, _)| pid.clone());
-
-    let first_phase_model = first_phase.map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).unwrap_or_else(|| "\"\"".to_string());
-    let last_phase_model = last_phase.map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).unwrap_or_else(|| "\"\"".to_string());
-
-    let join_debris = |pid_opt: Option<String>| -> String {
-        if let Some(pid) = pid_opt {
-            if let Some(arr) = debris_map.get(&pid) {
-                let parts: Vec<String> = arr.iter().map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).collect();
-                return parts.join(" ");
-            }
-        }
-        String::new()
-    };
-    let first_phase_debris = join_debris(first_pid);
-    let last_phase_debris = join_debris(last_pid);
-
-    // Replace placeholders
-    let replaces = [
-        ("{{ENTITY_ID}}", &entity_id),
-        ("{{MESH_ID_BRACED}}", &format!("{{{}}}", mesh_id)),
-        ("{{ID1}}", &id1),
-        ("{{ID2}}", &id2),
-        ("{{ID3}}", &id3),
-        ("{{ID4}}", &id4),
-        ("{{ID5}}", &id5),
-        ("{{ID6}}", &id6),
-        ("{{BASE_OBJECT_LINE}}", &base_object_line),
-        ("{{FIRST_PHASE_MODEL}}", &first_phase_model),
-        ("{{LAST_PHASE_MODEL}}", &last_phase_model),
-        ("{{FIRST_PHASE_DEBRIS}}", &first_phase_debris),
-        ("{{LAST_PHASE_DEBRIS}}", &last_phase_debris),
-    ];
-    for (k, v) in replaces {
-        body = body.replace(k, v);
-    }
-    // Ensure newline style remains consistent
-    if nl == "\r\n" { body = body.replace("\n", "\r\n"); }
-    body
-}
-
-#[derive(Serialize, Clone)]
-struct PrefabDstLogPayload {
-    level: String,
-    message: String,
-    current: Option<usize>,
-    total: Option<usize>,
-}
-
 #[tauri::command]
 fn get_display_version(app: tauri::AppHandle) -> String {
     let version = app.package_info().version.to_string();
@@ -770,28 +155,6 @@ fn gen_guid16() -> String {
     format!("{:016X}", v)
 }
 
-fn build_et_meta_text(name_value: &str) -> String {
-    let mut out = String::new();
-    out.push_str("MetaFileClass {\n");
-    out.push_str(&format!(" Name \"{}\"\n", name_value));
-    out.push_str(" Configurations {\n");
-    out.push_str("  EntityTemplateResourceClass PC {\n");
-    out.push_str("  }\n");
-    out.push_str("  EntityTemplateResourceClass XBOX_ONE : PC {\n");
-    out.push_str("  }\n");
-    out.push_str("  EntityTemplateResourceClass XBOX_SERIES : PC {\n");
-    out.push_str("  }\n");
-    out.push_str("  EntityTemplateResourceClass PS4 : PC {\n");
-    out.push_str("  }\n");
-    out.push_str("  EntityTemplateResourceClass PS5 : PC {\n");
-    out.push_str("  }\n");
-    out.push_str("  EntityTemplateResourceClass HEADLESS : PC {\n");
-    out.push_str("  }\n");
-    out.push_str(" }\n");
-    out.push_str("}\n");
-    out
-}
-
 fn rel_from_known_roots(abs_path: &Path) -> String {
     let p = abs_path.to_string_lossy().replace('\\', "/");
     for anchor in ["/Prefabs/", "/prefabs/", "/Assets/", "/assets/"] {
@@ -805,44 +168,16 @@ fn rel_from_known_roots(abs_path: &Path) -> String {
         .unwrap_or_else(|| p)
 }
 
-fn resolve_et_save_path(xob_abs: &Path, save_dir: Option<&str>) -> Result<PathBuf, String> {
-    let base = xob_abs
-        .file_stem()
-        .and_then(|s| s.to_str())
-        .ok_or_else(|| "Invalid xob file name".to_string())?;
-    let auto_name = format!("{}_test_prefab.et", base);
-    let dir = if let Some(sd) = save_dir {
-        let pb = PathBuf::from(sd);
-        if pb.is_dir() {
-            pb
-        } else {
-            xob_abs
-                .parent()
-                .ok_or_else(|| "Invalid xob directory".to_string())?
-                .to_path_buf()
-        }
-    } else {
-        xob_abs
-            .parent()
-            .ok_or_else(|| "Invalid xob directory".to_string())?
-            .to_path_buf()
-    };
-    Ok(dir.join(auto_name))
-}
-
 fn read_xob_object_field_from_meta(xob_abs: &Path) -> Result<(String, String), String> {
     let meta_path = PathBuf::from(format!("{}.meta", xob_abs.to_string_lossy()));
     let text = fs::read_to_string(&meta_path)
         .map_err(|e| format!("Failed to read .xob.meta: {}", e))?;
-    let needle = "Name \"";
-    let idx = text
-        .find(needle)
-        .ok_or_else(|| "Name field not found in .xob.meta".to_string())?;
-    let rest = &text[idx + needle.len()..];
-    let end_idx = rest
-        .find('"')
-        .ok_or_else(|| "Invalid Name field in .xob.meta".to_string())?;
-    let name_value = rest[..end_idx].trim().to_string();
+    let doc = enfusion_text::parse(&text).map_err(|e| format!("Failed to parse .xob.meta: {}", e))?;
+    let name_value = doc
+        .meta_name()
+        .ok_or_else(|| "Name field not found in .xob.meta".to_string())?
+        .trim()
+        .to_string();
     // Expect "{GUID}Assets/...xob"
     let guid = extract_guid(&name_value).ok_or_else(|| "GUID not found in .xob.meta Name".to_string())?;
     let rel_assets = if let Some(close) = name_value.find('}') {
@@ -853,122 +188,6 @@ fn read_xob_object_field_from_meta(xob_abs: &Path) -> Result<(String, String), S
     Ok((guid, rel_assets))
 }
 
-fn parse_txo_socket_names(txo_text: &str) -> Vec<String> {
-    let mut out = Vec::new();
-    let mut i = 0usize;
-    let s = txo_text.as_bytes();
-    while i < s.len() {
-        // naive scan for $node "socket_
-        if s[i..].starts_with(b"$node\"") || s[i..].starts_with(b"$node \"") {
-            // find first quote
-            let q1 = txo_text[i..].find('"').map(|v| i + v);
-            if let Some(q1) = q1 {
-                let q2 = txo_text[(q1 + 1)..].find('"').map(|v| q1 + 1 + v);
-                if let Some(q2) = q2 {
-                    let name = txo_text[(q1 + 1)..q2].trim();
-                    if name.to_lowercase().starts_with("socket_") {
-                        out.push(name.to_string());
-                    }
-                    i = q2 + 1;
-                    continue;
-                }
-            }
-        }
-        i += 1;
-    }
-    out
-}
-
-fn prefab_candidates_from_socket(socket_name: &str) -> Vec<String> {
-    let s = socket_name.trim().to_lowercase();
-    if !s.starts_with("socket_") {
-        return vec![];
-    }
-    let mut tail = s["socket_".len()..].to_string();
-    if let Some(pos) = tail.find(".et") {
-        tail = tail[..pos].to_string();
-    }
-    let mut tail = tail.replace('.', "_");
-    while tail.contains("__") {
-        tail = tail.replace("__", "_");
-    }
-    tail = tail.trim_matches('_').to_string();
-    let mut cands: Vec<String> = Vec::new();
-    let mut push = |v: String| {
-        if !v.is_empty() && !cands.contains(&v) {
-            cands.push(v);
-        }
-    };
-    push(tail.clone());
-    // strip trailing _<digits>
-    if let Some(pos) = tail.rfind('_') {
-        if tail[(pos + 1)..].chars().all(|c| c.is_ascii_digit()) {
-            push(tail[..pos].to_string());
-        }
-    }
-    cands
-}
-
-fn extract_guid_from_socket_name(name: &str) -> Option<String> {
-    let s = name.trim();
-    let lower = s.to_lowercase();
-    if !lower.starts_with("socket_") {
-        return None;
-    }
-    let tail = &lower["socket_".len()..];
-    let guid = tail.get(0..16)?;
-    if guid.chars().all(|c| c.is_ascii_hexdigit()) {
-        return Some(guid.to_uppercase());
-    }
-    None
-}
-
-fn load_prefab_index_maps(
-) -> Result<(
-    BTreeMap<String, String>,
-    BTreeMap<String, String>,
-    BTreeMap<String, String>,
-    BTreeMap<String, String>,
-), String> {
-    let path = prefab_index_path();
-    let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read prefab cache: {}", e))?;
-    let value = serde_json::from_str::<JsonValue>(&text).map_err(|e| e.to_string())?;
-    let mut name_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut guid_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut et_path_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut guid_path_index: BTreeMap<String, String> = BTreeMap::new();
-    if let Some(obj) = value.get("name_index").and_then(|v| v.as_object()) {
-        for (k, v) in obj {
-            if let Some(s) = v.as_str() {
-                name_index.insert(k.to_string(), s.to_string());
-            }
-        }
-    }
-    if let Some(obj) = value.get("guid_index").and_then(|v| v.as_object()) {
-        for (k, v) in obj {
-            if let Some(s) = v.as_str() {
-                guid_index.insert(k.to_string(), s.to_string());
-            }
-        }
-    }
-
-    if let Some(obj) = value.get("et_path_index").and_then(|v| v.as_object()) {
-        for (k, v) in obj {
-            if let Some(s) = v.as_str() {
-                et_path_index.insert(k.to_string(), s.to_string());
-            }
-        }
-    }
-    if let Some(obj) = value.get("guid_path_index").and_then(|v| v.as_object()) {
-        for (k, v) in obj {
-            if let Some(s) = v.as_str() {
-                guid_path_index.insert(k.to_string(), s.to_string());
-            }
-        }
-    }
-    Ok((name_index, guid_index, et_path_index, guid_path_index))
-}
-
 fn find_in_path(exe_name: &str) -> Option<PathBuf> {
     let path = std::env::var_os("PATH")?;
     for p in std::env::split_paths(&path) {
@@ -1012,262 +231,6 @@ fn resolve_blender_path() -> Option<PathBuf> {
     None
 }
 
-fn normalize_socket_key(s: &str) -> String {
-    s.trim()
-        .to_lowercase()
-        .replace('-', "_")
-        .replace(' ', "_")
-}
-
-fn extract_socket_guids_with_blender(app: &tauri::AppHandle, xob_abs: &Path) -> Option<BTreeMap<String, String>> {
-    let fbx_abs = xob_abs.with_extension("fbx");
-    if !fbx_abs.is_file() {
-        emit_scan_log(app, "info", format!("Blender GUID match: FBX not found: {}", fbx_abs.to_string_lossy()), None, None);
-        return None;
-    }
-    let settings = load_settings();
-    let blender = settings
-        .blender_path
-        .as_deref()
-        .map(PathBuf::from)
-        .filter(|p| p.is_file())
-        .or_else(resolve_blender_path);
-    let blender = match blender {
-        Some(p) => p,
-        None => {
-            emit_scan_log(
-                app,
-                "info",
-                "Blender GUID match: blender.exe not configured (set OWLTOOLS_BLENDER_PATH) — skip",
-                None,
-                None,
-            );
-            return None;
-        }
-    };
-    emit_scan_log(
-        app,
-        "info",
-        format!("Blender GUID match: running {}", blender.to_string_lossy()),
-        None,
-        None,
-    );
-
-    let py = format!(
-        r#"import bpy, json, re
-fbx=r'''{}'''
-try:
-    bpy.ops.wm.read_factory_settings(use_empty=True)
-except Exception:
-    pass
-try:
-    bpy.ops.import_scene.fbx(filepath=fbx, automatic_bone_orientation=True)
-except Exception:
-    print("{{}}")
-    raise
-data={{}}
-for ob in bpy.data.objects:
-    n=(ob.name or '')
-    if not n.lower().startswith('socket'):
-        continue
-    guid=None
-    try:
-        guid = ob.get('ref_guid') or ob.get('ref guid')
-    except Exception:
-        guid=None
-    if not guid:
-        try:
-            for k in ob.keys():
-                kk=str(k).lower().replace(' ','').replace('_','')
-                if kk=='refguid':
-                    v=ob.get(k)
-                    guid=v if isinstance(v,str) else None
-                    break
-        except Exception:
-            pass
-    if isinstance(guid,str):
-        m=re.search(r'([0-9A-Fa-f]{{16}})', guid)
-        guid=m.group(1).upper() if m else None
-    if guid:
-        data[n]=guid
-print(json.dumps(data))
-"#,
-        fbx_abs.to_string_lossy()
-    );
-
-    let mut script_path = std::env::temp_dir();
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .ok()?
-        .as_millis();
-    script_path.push(format!("owltools_guid_match_{}_{}.py", std::process::id(), ts));
-    let _ = fs::write(&script_path, &py);
-    let script_path_str = script_path.to_string_lossy().to_string();
-
-    let out = std::process::Command::new(blender)
-        .args(["--background", "--factory-startup", "--python", &script_path_str])
-        .output()
-        .ok()?;
-    let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&out.stderr).to_string();
-    if !stderr.trim().is_empty() {
-        emit_scan_log(app, "warn", format!("Blender GUID match stderr: {}", stderr.trim()), None, None);
-    }
-
-    // Blender prints multiple lines; find the last JSON-like line
-    let mut json_line: Option<String> = None;
-    for line in stdout.lines().rev() {
-        let t = line.trim();
-        if t.starts_with('{') && t.ends_with('}') {
-            json_line = Some(t.to_string());
-            break;
-        }
-    }
-    let json_line = json_line?;
-    let v: serde_json::Value = serde_json::from_str(&json_line).ok()?;
-    let obj = v.as_object()?;
-    let mut map: BTreeMap<String, String> = BTreeMap::new();
-    for (k, val) in obj {
-        if let Some(g) = val.as_str() {
-            map.insert(k.to_string(), g.to_string());
-        }
-    }
-    if !map.is_empty() {
-        emit_scan_log(app, "info", format!("Blender GUID match: extracted {} ref_guid", map.len()), None, None);
-    }
-    Some(map)
-}
-
-fn build_slot_component_text_from_mappings(maps: &Vec<(String, String)>) -> Option<String> {
-    if maps.is_empty() {
-        return None;
-    }
-    let slot_guid = gen_hex16();
-    let mut lines: Vec<String> = Vec::new();
-    lines.push(format!("  WB_SlotBoneMappingsComponent \"{{{}}}\" {{", slot_guid));
-    lines.push("   SlotBoneMappings {".to_string());
-    for (bone_prefix, prefab_name) in maps {
-        let obj_guid = gen_hex16();
-        lines.push(format!("    SlotBoneMappingObject \"{{{}}}\" {{", obj_guid));
-        lines.push(format!("     BonePrefix \"{}\"", bone_prefix));
-        lines.push(format!("     Prefab \"{}\"", prefab_name));
-        lines.push("    }".to_string());
-    }
-    lines.push("   }".to_string());
-    lines.push("  }".to_string());
-    Some(lines.join("\n") + "\n")
-}
-
-fn build_child_entities_block(maps: &Vec<(String, String)>, hier_guid: &str) -> Option<String> {
-    if maps.is_empty() {
-        return None;
-    }
-    let mut by_prefab: BTreeMap<String, Vec<String>> = BTreeMap::new();
-    for (bone_prefix, prefab_name) in maps {
-        by_prefab.entry(prefab_name.clone()).or_default().push(bone_prefix.clone());
-    }
-    let mut lines: Vec<String> = Vec::new();
-    lines.push(" {".to_string());
-    for (prefab, sockets) in by_prefab {
-        if sockets.len() >= 2 {
-            lines.push(format!("  $grp GenericEntity : \"{}\" {{", prefab));
-            for s in sockets {
-                let inst_id = gen_hex16();
-                lines.push("   {".to_string());
-                lines.push(format!("    ID \"{}\"", inst_id));
-                lines.push("    components {".to_string());
-                lines.push(format!("     Hierarchy \"{{{}}}\" {{", hier_guid));
-                lines.push("      Enabled 1".to_string());
-                lines.push(format!("      PivotID \"{}\"", s));
-                lines.push("      AutoTransform 1".to_string());
-                lines.push("     }".to_string());
-                lines.push("    }".to_string());
-                lines.push("    coords 0 0 0".to_string());
-                lines.push("   }".to_string());
-            }
-            lines.push("  }".to_string());
-        } else {
-            let socket_name = sockets.get(0).cloned().unwrap_or_default();
-            let inst_id = gen_hex16();
-            lines.push(format!("  GenericEntity : \"{}\" {{", prefab));
-            lines.push(format!("   ID \"{}\"", inst_id));
-            lines.push("   components {".to_string());
-            lines.push(format!("    Hierarchy \"{{{}}}\" {{", hier_guid));
-            lines.push("     Enabled 1".to_string());
-            lines.push(format!("     PivotID \"{}\"", socket_name));
-            lines.push("     AutoTransform 1".to_string());
-            lines.push("    }".to_string());
-            lines.push("   }".to_string());
-            lines.push("   coords 0 0 0".to_string());
-            lines.push("  }".to_string());
-        }
-    }
-    lines.push(" }".to_string());
-    Some(lines.join("\n") + "\n")
-}
-
-fn build_new_et_with_mesh(gen_id: &str, obj_field: &str) -> String {
-    let mesh_guid = gen_hex16();
-    format!(
-        "GenericEntity {{\n ID \"{}\"\n components {{\n  MeshObject \"{{{}}}\" {{\n   Object \"{}\"\n  }}\n }}\n coords 0 0 0\n}}\n",
-        gen_id, mesh_guid, obj_field
-    )
-}
-
-fn remove_blank_lines(text: &str) -> String {
-    text.lines()
-        .filter(|l| !l.trim().is_empty())
-        .collect::<Vec<_>>()
-        .join("\n")
-}
-
-fn insert_or_replace_slot_component(mut et_text: String, component_text: &str) -> String {
-    if let Some(pos) = et_text.find(" components {") {
-        // Insert before closing of components block " }" by finding next "}\n coords"
-        if let Some(cpos) = et_text[pos..].find("}\n coords") {
-            let abs = pos + cpos;
-            et_text.insert_str(abs, &format!("\n{}", component_text));
-            return et_text;
-        }
-    }
-    // Fallback append
-    et_text.push_str("\n");
-    et_text.push_str(component_text);
-    et_text
-}
-
-fn insert_or_replace_child_entities_block(mut et_text: String, block_text: &str) -> String {
-    if let Some(pos) = et_text.find("coords 0 0 0") {
-        if let Some(endline) = et_text[pos..].find('\n') {
-            let insert_at = pos + endline;
-            et_text.insert_str(insert_at, &format!("\n{}", block_text));
-            return et_text;
-        }
-    }
-    et_text.push_str("\n");
-    et_text.push_str(block_text);
-    et_text
-}
-
-#[derive(Serialize)]
-struct CreateEtResult {
-    et_path: String,
-    #[serde(skip_serializing_if = "Option::is_none")]
-    meta_path: Option<String>,
-    sockets: usize,
-    matched: usize,
-    unmatched: usize,
-    suggested_extra_dirs: Vec<String>,
-}
-
-#[derive(Serialize)]
-struct SuggestFoldersResult {
-    sockets: usize,
-    matched: usize,
-    unmatched: usize,
-    suggested_extra_dirs: Vec<String>,
-}
-
 #[tauri::command]
 async fn suggest_prefab_folders_from_xob(
     app: tauri::AppHandle,
@@ -1275,929 +238,161 @@ async fn suggest_prefab_folders_from_xob(
     svn_root: Option<String>,
     extra_dirs: Option<Vec<String>>,
 ) -> Result<SuggestFoldersResult, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    if !xob_abs.is_file() {
-        return Err(format!("Invalid .xob path: {}", xob_path));
-    }
-    emit_scan_log(&app, "info", format!("Detect folders for xob: {}", xob_abs.to_string_lossy()), None, None);
-
-    let txo_abs = xob_abs.with_extension("txo");
-    let sockets: Vec<String> = if txo_abs.is_file() {
-        let txo_text = fs::read_to_string(&txo_abs)
-            .map_err(|e| format!("Failed to read .txo: {}", e))?;
-        parse_txo_socket_names(&txo_text)
-    } else {
-        Vec::new()
-    };
-
-    let (name_index, guid_index, et_path_index, guid_path_index) = load_prefab_index_maps()?;
-    let _svn_root = svn_root
-        .or_else(|| load_settings().svn_root)
-        .filter(|s| !s.is_empty());
-    let extra_dirs = extra_dirs
-        .or_else(|| load_settings().extra_dirs)
-        .unwrap_or_default();
-
-    let total_s = sockets.len();
-    let blender_sock_guids = extract_socket_guids_with_blender(&app, &xob_abs);
-    let blender_sock_guids_lc: Option<BTreeMap<String, String>> = blender_sock_guids.as_ref().map(|m| {
-        m.iter()
-            .map(|(k, v)| (normalize_socket_key(k), v.clone()))
-            .collect::<BTreeMap<String, String>>()
-    });
-
-    // Match sockets similarly to create_new_et_from_xob
-    let mut matched_sock: Vec<String> = Vec::new();
-    for s in &sockets {
-        let mut found = false;
-        if let Some(bg) = blender_sock_guids_lc.as_ref() {
-            if let Some(g) = bg.get(&normalize_socket_key(s)) {
-                if guid_index.contains_key(g) {
-                    found = true;
-                }
-            }
-        }
-        if !found {
-            if let Some(g) = extract_guid_from_socket_name(s) {
-                if guid_index.contains_key(&g) {
-                    found = true;
-                }
-            }
-        }
-        if !found {
-            for cand in prefab_candidates_from_socket(s) {
-                let key = format!("{}.et", cand);
-                if name_index.contains_key(&key) {
-                    found = true;
-                    break;
-                }
-            }
-        }
-        if found {
-            matched_sock.push(s.clone());
-        }
-    }
-
-    let matched = matched_sock.len();
-    let unmatched = total_s.saturating_sub(matched);
-
-    let mut suggested: BTreeSet<String> = BTreeSet::new();
-    for sock in &matched_sock {
-        let mut hit_path: Option<String> = None;
-        if let Some(bg) = blender_sock_guids_lc.as_ref() {
-            if let Some(g) = bg.get(&normalize_socket_key(sock)) {
-                if let Some(p) = guid_path_index.get(g) {
-                    hit_path = Some(p.clone());
-                }
-            }
-        }
-        if hit_path.is_none() {
-            if let Some(g) = extract_guid_from_socket_name(sock) {
-                if let Some(p) = guid_path_index.get(&g) {
-                    hit_path = Some(p.clone());
-                }
-            }
-        }
-        if hit_path.is_none() {
-            for cand in prefab_candidates_from_socket(sock) {
-                let key = format!("{}.et", cand);
-                if let Some(p) = et_path_index.get(&key) {
-                    hit_path = Some(p.clone());
-                    break;
-                }
-            }
-        }
-        if let Some(p) = hit_path {
-            let pb = PathBuf::from(p);
-            if let Some(dir) = pb.parent() {
-                let dir_s = dir.to_string_lossy().to_string();
-                if extra_dirs.contains(&dir_s) {
-                    continue;
-                }
-                // IMPORTANT: show detected folders even if they are under SVN root.
-                // This matches the EnfAutoSocket UX where users can see/choose prefab folders explicitly.
-                suggested.insert(dir_s);
-            }
-        }
-    }
-    let suggested_extra_dirs: Vec<String> = suggested.into_iter().collect();
-    emit_scan_log(
-        &app,
-        "info",
-        format!("Detected prefab folders: {}", suggested_extra_dirs.len()),
-        None,
-        None,
-    );
+    autosocket::suggest_prefab_folders(&app, xob_path, svn_root, extra_dirs).await
+}
 
-    Ok(SuggestFoldersResult {
-        sockets: total_s,
-        matched,
-        unmatched,
-        suggested_extra_dirs,
-    })
+#[derive(Serialize)]
+struct MatchRulesInfo {
+    /// File the rules are read from; None when the built-in rules are in use.
+    path: Option<String>,
+    default_path: String,
+    rules: socket_rules::RuleFile,
 }
 
 #[tauri::command]
-async fn create_new_et_from_xob(
-    app: tauri::AppHandle,
-    xob_path: String,
-    save_dir: Option<String>,
-    svn_root: Option<String>,
-    extra_dirs: Option<Vec<String>>,
-) -> Result<CreateEtResult, String> {
-    let xob_abs = PathBuf::from(&xob_path);
-    if !xob_abs.is_file() {
-        let msg = format!("Invalid .xob path: {}", xob_path);
-        emit_scan_log(&app, "error", msg.clone(), None, None);
-        return Err(msg);
-    }
-
-    emit_scan_log(&app, "info", format!("Processing xob: {}", xob_abs.to_string_lossy()), None, None);
-
-    let (obj_guid, obj_path) = read_xob_object_field_from_meta(&xob_abs)?;
-    let obj_field = format!("{{{}}}{}", obj_guid, obj_path);
-    emit_scan_log(&app, "info", "Loaded .xob.meta Name/GUID", None, None);
-
-    let txo_abs = xob_abs.with_extension("txo");
-    let sockets: Vec<String> = if txo_abs.is_file() {
-        let txo_text = fs::read_to_string(&txo_abs)
-            .map_err(|e| format!("Failed to read .txo: {}", e))?;
-        let out = parse_txo_socket_names(&txo_text);
-        emit_scan_log(&app, "info", format!("Found {} sockets", out.len()), None, None);
-        out
-    } else {
-        emit_scan_log(&app, "warn", "No .txo found (no sockets)", None, None);
-        Vec::new()
+fn get_match_rules() -> Result<MatchRulesInfo, String> {
+    let set = socket_rules::RuleSet::configured()?;
+    let rules = match set.source.as_ref() {
+        Some(p) => serde_json::from_str(&fs::read_to_string(p).map_err(|e| e.to_string())?).map_err(|e| e.to_string())?,
+        None => socket_rules::builtin(),
     };
-
-    let (name_index, guid_index, et_path_index, guid_path_index) = load_prefab_index_maps()?;
-    emit_scan_log(
-        &app,
-        "info",
-        format!(
-            "Loaded prefab cache (names={}, guids={}, et_paths={}, guid_paths={})",
-            name_index.len(),
-            guid_index.len(),
-            et_path_index.len(),
-            guid_path_index.len()
-        ),
-        None,
-        None,
-    );
-
-    let svn_root = svn_root
-        .or_else(|| load_settings().svn_root)
-        .filter(|s| !s.is_empty());
-    let extra_dirs = extra_dirs
-        .or_else(|| load_settings().extra_dirs)
-        .unwrap_or_default();
-    if !extra_dirs.is_empty() {
-        emit_scan_log(&app, "info", format!("Extra dirs: {}", extra_dirs.len()), None, None);
-    }
-
-    let total_s = sockets.len();
-
-    // Blender GUID extraction (optional)
-    let blender_sock_guids = extract_socket_guids_with_blender(&app, &xob_abs);
-    let blender_sock_guids_lc: Option<BTreeMap<String, String>> = blender_sock_guids.as_ref().map(|m| {
-        m.iter()
-            .map(|(k, v)| (normalize_socket_key(k), v.clone()))
-            .collect::<BTreeMap<String, String>>()
-    });
-    if let Some(m) = blender_sock_guids.as_ref() {
-        for (k, v) in m.iter().take(6) {
-            emit_scan_log(&app, "info", format!("Blender GUID: {} -> {}", k, v), None, None);
-        }
-    }
-
-    if let Some(bg) = blender_sock_guids_lc.as_ref() {
-        emit_scan_log(
-            &app,
-            "info",
-            format!("Blender GUID keys: {} (normalized)", bg.len()),
-            None,
-            None,
-        );
-        for s in sockets.iter().take(5) {
-            let nk = normalize_socket_key(s);
-            let hit = bg.contains_key(&nk);
-            emit_scan_log(
-                &app,
-                "info",
-                format!("Socket key check: '{}' -> '{}' | in_blender={}", s, nk, hit),
-                None,
-                None,
-            );
-        }
-    }
-
-    let mut maps: Vec<(String, String)> = Vec::new();
-    for (i, s) in sockets.iter().enumerate() {
-        if total_s > 0 {
-            emit_scan_log(&app, "info", "Matching sockets...", Some(i), Some(total_s));
-        }
-        let mut prefab_meta_name: Option<String> = None;
-        // 1) GUID from Blender ref_guid (best)
-        if let Some(bg) = blender_sock_guids_lc.as_ref() {
-            if let Some(guid) = bg.get(&normalize_socket_key(s)) {
-                if let Some(v) = guid_index.get(guid) {
-                    prefab_meta_name = Some(v.clone());
-                }
-            }
-        }
-        // 2) GUID embedded in socket name
-        if prefab_meta_name.is_none() {
-            if let Some(guid) = extract_guid_from_socket_name(s) {
-                if let Some(v) = guid_index.get(&guid) {
-                    prefab_meta_name = Some(v.clone());
-                }
-            }
-        }
-        if prefab_meta_name.is_none() {
-            for cand in prefab_candidates_from_socket(s) {
-                let key = format!("{}.et", cand);
-                if let Some(v) = name_index.get(&key) {
-                    prefab_meta_name = Some(v.clone());
-                    break;
-                }
-            }
-        }
-        if let Some(prefab) = prefab_meta_name {
-            maps.push((s.clone(), prefab));
-        }
-    }
-    if total_s > 0 {
-        emit_scan_log(&app, "info", "Matching sockets...", Some(total_s), Some(total_s));
-    }
-
-    let matched = maps.len();
-    let unmatched = total_s.saturating_sub(matched);
-    emit_scan_log(&app, "info", format!("Matched sockets: {}/{}", matched, total_s), None, None);
-
-    // Auto-suggest extra dirs based on matched prefab absolute paths in cache
-    let mut suggested: BTreeSet<String> = BTreeSet::new();
-    let svn_norm = svn_root.as_ref().map(|s| PathBuf::from(s).to_string_lossy().to_string().to_lowercase());
-    for (sock, _) in &maps {
-        // Prefer Blender ref_guid path, else embedded guid, else name-index path
-        let mut hit_path: Option<String> = None;
-        if let Some(bg) = blender_sock_guids_lc.as_ref() {
-            if let Some(g) = bg.get(&normalize_socket_key(sock)) {
-                if let Some(p) = guid_path_index.get(g) {
-                    hit_path = Some(p.clone());
-                }
-            }
-        }
-        if hit_path.is_none() {
-            if let Some(g) = extract_guid_from_socket_name(sock) {
-                if let Some(p) = guid_path_index.get(&g) {
-                    hit_path = Some(p.clone());
-                }
-            }
-        }
-        if hit_path.is_none() {
-            for cand in prefab_candidates_from_socket(sock) {
-                let key = format!("{}.et", cand);
-                if let Some(p) = et_path_index.get(&key) {
-                    hit_path = Some(p.clone());
-                    break;
-                }
-            }
-        }
-        if let Some(p) = hit_path {
-            let pb = PathBuf::from(p);
-            if let Some(dir) = pb.parent() {
-                let dir_s = dir.to_string_lossy().to_string();
-                let dir_l = dir_s.to_lowercase();
-                let under_svn = svn_norm.as_ref().map(|svn| dir_l.starts_with(svn)).unwrap_or(false);
-                if !under_svn {
-                    suggested.insert(dir_s);
-                }
-            }
-        }
-    }
-    let suggested_extra_dirs: Vec<String> = suggested.into_iter().collect();
-    if !suggested_extra_dirs.is_empty() {
-        emit_scan_log(
-            &app,
-            "info",
-            format!("Auto-assign prefab folders: {}", suggested_extra_dirs.len()),
-            None,
-            None,
-        );
-    }
-
-    let out_path = resolve_et_save_path(&xob_abs, save_dir.as_deref())?;
-    if let Some(sd) = save_dir.as_deref() {
-        remember_save_dir(Some(sd.to_string())).ok();
-    }
-    // Persist extra dirs union (existing + suggested)
-    let mut merged_extra = extra_dirs.clone();
-    for p in &suggested_extra_dirs {
-        if !merged_extra.contains(p) {
-            merged_extra.push(p.clone());
-        }
-    }
-    if !merged_extra.is_empty() {
-        remember_extra_dirs(merged_extra.clone()).ok();
-    }
-    if let Some(sr) = svn_root.as_deref() {
-        remember_svn_root(Some(sr.to_string())).ok();
-    }
-
-    let gen_id = gen_hex16();
-    let mut et_text = build_new_et_with_mesh(&gen_id, &obj_field);
-    if let Some(comp) = build_slot_component_text_from_mappings(&maps) {
-        et_text = insert_or_replace_slot_component(et_text, &comp);
-    }
-    let hier_guid = gen_hex16();
-    if let Some(child) = build_child_entities_block(&maps, &hier_guid) {
-        et_text = insert_or_replace_child_entities_block(et_text, &child);
-    }
-    et_text = remove_blank_lines(&et_text);
-
-    if let Some(parent) = out_path.parent() {
-        fs::create_dir_all(parent).map_err(|e| e.to_string())?;
-    }
-    emit_scan_log(&app, "info", format!("Writing .et: {}", out_path.to_string_lossy()), None, None);
-    fs::write(&out_path, et_text).map_err(|e| e.to_string())?;
-    emit_scan_log(&app, "info", "Process finished", None, None);
-
-    Ok(CreateEtResult {
-        et_path: out_path.to_string_lossy().to_string(),
-        meta_path: None,
-        sockets: total_s,
-        matched,
-        unmatched,
-        suggested_extra_dirs,
+    Ok(MatchRulesInfo {
+        path: set.source.map(|p| p.to_string_lossy().to_string()),
+        default_path: match_rules_path().to_string_lossy().to_string(),
+        rules,
     })
 }
 
+/// Checks and saves a rule file to `path` (the default rule file when unset) and selects it.
 #[tauri::command]
-async fn create_new_et_with_meta_from_xob(
-    app: tauri::AppHandle,
-    xob_path: String,
-    save_dir: Option<String>,
-    svn_root: Option<String>,
-    extra_dirs: Option<Vec<String>>,
-) -> Result<CreateEtResult, String> {
-    let mut res = create_new_et_from_xob(app.clone(), xob_path, save_dir, svn_root, extra_dirs).await?;
-    let et_abs = PathBuf::from(&res.et_path);
-    let rel = rel_from_known_roots(&et_abs);
-    let name_value = format!("{{{}}}{}", gen_hex16(), rel);
-    let meta_text = build_et_meta_text(&name_value);
-    let meta_path = PathBuf::from(format!("{}.meta", et_abs.to_string_lossy()));
-    emit_scan_log(&app, "info", format!("Writing .et.meta: {}", meta_path.to_string_lossy()), None, None);
-    fs::write(&meta_path, meta_text).map_err(|e| e.to_string())?;
-
-    // Incrementally update prefab cache so the new .et is available immediately for matching/suggestions.
-    if let Err(err) = update_prefab_cache_with_new_meta(&et_abs, &meta_path, &name_value) {
-        emit_scan_log(&app, "warn", format!("Failed to update prefab cache: {}", err), None, None);
-    } else {
-        emit_scan_log(&app, "info", "Updated prefab cache", None, None);
-    }
-
-    res.meta_path = Some(meta_path.to_string_lossy().to_string());
-    Ok(res)
-}
-
-fn emit_scan_log(app: &tauri::AppHandle, level: &str, message: impl Into<String>, current: Option<usize>, total: Option<usize>) {
-    let payload = ScanLogPayload {
-        level: level.to_string(),
-        message: message.into(),
-        current,
-        total,
-    };
-    let _ = app.emit("prefab_scan_log", payload);
-}
-
-fn emit_prefabdst_log(app: &tauri::AppHandle, level: &str, message: impl Into<String>, current: Option<usize>, total: Option<usize>) {
-    let payload = PrefabDstLogPayload {
-        level: level.to_string(),
-        message: message.into(),
-        current,
-        total,
-    };
-    let _ = app.emit("prefabdst_log", payload);
-}
-
-fn detect_newline(s: &str) -> &'static str {
-    if s.contains("\r\n") { "\r\n" } else { "\n" }
-}
-
-fn strip_preset_header(body: &str) -> String {
-    // Remove leading "KEY: value" lines until first blank line.
-    let nl = detect_newline(body);
-    let mut out: Vec<&str> = Vec::new();
-    let mut skipping = true;
-    let re_kv = Regex::new(r"^\s*[A-Za-z_][A-Za-z0-9_\-]*\s*:\s*.*$").unwrap();
-    for ln in body.lines() {
-        if skipping {
-            if ln.trim().is_empty() {
-                skipping = false;
-                continue;
-            }
-            if re_kv.is_match(ln) {
-                continue;
-            }
-            // Unexpected line: treat as start of body
-            skipping = false;
-        }
-        out.push(ln);
-    }
-    out.join(nl)
-}
-
-fn replace_object_marker(text: &str, guid: &str, res: &str) -> String {
-    // Replace annotated marker line like: Object "{guid from xob input}path from xob input"
-    let rep = format!("Object \"{{{}}}{}\"", guid, res);
-    let re = Regex::new(r#"Object\s+"[^"\n]*guid\s+from\s+xob\s+input[^"\n]*""#).unwrap();
-    re.replace_all(text, rep).to_string()
-}
-
-fn replace_v2_model_marker(text: &str, guid: &str, res: &str) -> String {
-    let rep = format!("Model \"{{{}}}{}\"", guid, res);
-    let re = Regex::new(r#"Model\s+"[^"\n]*same\s+name_v2_dst[^"\n]*""#).unwrap();
-    re.replace_all(text, rep).to_string()
-}
-
-fn replace_gen_guid_markers(text: &str) -> String {
-    let re_braced = Regex::new(r"\{\s*gen\s+guid\s*\}").unwrap();
-    let re_quoted = Regex::new(r#""\s*gen\s+guid\s*""#).unwrap();
-    let s = re_braced
-        .replace_all(text, |_caps: &regex::Captures| format!("{{{}}}", gen_guid16()))
-        .to_string();
-    re_quoted
-        .replace_all(&s, |_caps: &regex::Captures| format!("\"{}\"", gen_guid16()))
-        .to_string()
-}
-
-fn replace_gen_vec3_markers(text: &str) -> String {
-    // The UI/build pipeline should not generate random vec3 values.
-    // Default to 0 0 0 for any {gen vec3} marker.
-    let re = Regex::new(r"\{\s*gen\s+vec3\s*\}").unwrap();
-    re.replace_all(text, "0 0 0").to_string()
-}
-
-fn find_fractalparts_bounds(text: &str) -> Option<(usize, usize, usize)> {
-    // Returns (open_brace_idx, close_brace_idx, keyword_idx)
-    let kw = text.find("FractalParts")?;
-    let open = text[kw..].find('{').map(|i| kw + i)?;
-    let mut depth: i32 = 0;
-    let mut i = open;
-    let bytes = text.as_bytes();
-    while i < bytes.len() {
-        match bytes[i] as char {
-            '{' => depth += 1,
-            '}' => {
-                depth -= 1;
-                if depth == 0 {
-                    return Some((open, i, kw));
-                }
-            }
-            _ => {}
-        }
-        i += 1;
-    }
-    None
-}
-
-fn extract_fractalpart_blocks(inner: &str) -> (Vec<(char, String)>, Vec<String>) {
-    // Parse by lines with brace counting, matching the Python approach.
-    let lines: Vec<&str> = inner.lines().collect();
-    let mut blocks: Vec<(char, String)> = Vec::new();
-    let mut others: Vec<String> = Vec::new();
-    let mut i = 0usize;
-    while i < lines.len() {
-        let ln = lines[i];
-        if ln.trim_start().starts_with("FractalPartData") {
-            let mut depth: i32 = 0;
-            let mut j = i;
-            let mut started = false;
-            while j < lines.len() {
-                let l2 = lines[j];
-                if l2.contains('{') {
-                    started = true;
-                    depth += l2.matches('{').count() as i32;
-                }
-                if l2.contains('}') {
-                    depth -= l2.matches('}').count() as i32;
-                }
-                j += 1;
-                if started && depth <= 0 {
-                    break;
-                }
-            }
-            let nl = detect_newline(inner);
-            let blk = lines[i..j].join(nl);
-            let letter = Regex::new(r##"\bPartId\s+\"([A-Z])\""##)
-                .unwrap()
-                .captures(&blk)
-                .and_then(|c| c.get(1))
-                .and_then(|m| m.as_str().chars().next())
-                .unwrap_or('A');
-            blocks.push((letter, blk));
-            i = j;
-        } else {
-            others.push(ln.to_string());
-            i += 1;
-        }
-    }
-    blocks.sort_by_key(|(c, _)| *c as u32);
-    (blocks, others)
-}
-
-fn clone_fractalpart_block(block: &str, src_letter: char, new_letter: char) -> String {
-    let mut out = block.to_string();
-    let re_partid = Regex::new(&format!(r##"\bPartId\s+\"{}\""##, regex::escape(&src_letter.to_string()))).unwrap();
-    out = re_partid.replace_all(&out, format!("PartId \"{}\"", new_letter)).to_string();
-
-    let re_q_letter = Regex::new(&format!(r##"\"{}\""##, regex::escape(&src_letter.to_string()))).unwrap();
-    out = re_q_letter.replace_all(&out, format!("\"{}\"", new_letter)).to_string();
-
-    let re_q_letter_num = Regex::new(&format!(r##"\"{}(\d+)\""##, regex::escape(&src_letter.to_string()))).unwrap();
-    out = re_q_letter_num
-        .replace_all(&out, |caps: &regex::Captures| format!("\"{}{}\"", new_letter, &caps[1]))
-        .to_string();
-
-    let re_guid = Regex::new(r##"\"\{[0-9A-Fa-f]{16}\}\""##).unwrap();
-    out = re_guid
-        .replace_all(&out, |_caps: &regex::Captures| format!("\"{{{}}}\"", gen_guid16()))
-        .to_string();
-
-    // Also update template markers that encode the part letter.
-    // Example: {{DEBRIS_ID-A}} / {{COLLIDERS_ID-A}}
-    out = out.replace(
-        &format!("{{{{DEBRIS_ID-{}}}}}", src_letter),
-        &format!("{{{{DEBRIS_ID-{}}}}}", new_letter)
-    );
-    out = out.replace(
-        &format!("{{{{COLLIDERS_ID-{}}}}}", src_letter),
-        &format!("{{{{COLLIDERS_ID-{}}}}}", new_letter)
-    );
-    out
-}
-
-fn format_mass(mass: f32) -> String {
-    if !mass.is_finite() {
-        return "0".to_string();
-    }
-    let m = mass.max(0.0);
-    if (m.fract()).abs() < 0.00001 {
-        format!("{:.0}", m)
-    } else {
-        let s = format!("{}", m);
-        s
-    }
-}
-
-fn build_debris_infos_block(items: &[MetaEntry], indent: &str, debris_mass: f32) -> String {
-    // Build a list of SCR_DebrisInfo blocks.
-    // We don't currently have offsets/mass; use neutral defaults.
-    if items.is_empty() {
-        return format!("{}", "");
-    }
-    let mass_s = format_mass(debris_mass);
-    let mut lines: Vec<String> = Vec::new();
-    for e in items {
-        let info_id = gen_guid16();
-        let ap_id = gen_guid16();
-        lines.push(format!("{}SCR_DebrisInfo \"{{{}}}\" {{", indent, info_id));
-        lines.push(format!("{} ModelPrefab \"{{{}}}{}\"", indent, e.guid, e.path));
-        lines.push(format!("{} LocalTransform AttachPoint \"{{{}}}\" {{", indent, ap_id));
-        lines.push(format!("{}  Offset 0 0 0", indent));
-        lines.push(format!("{}  Angles 0.00001 0 0", indent));
-        lines.push(format!("{} }}", indent));
-        lines.push(format!("{} m_fMass {}", indent, mass_s));
-        lines.push(format!("{}}}", indent));
-    }
-    lines.join("\n")
-}
-
-fn build_debris_infos_block_with_offsets(
-    items: &[MetaEntry],
-    indent: &str,
-    debris_mass: f32,
-    offsets_by_file: Option<&std::collections::HashMap<String, (f32, f32, f32)>>,
-) -> String {
-    if items.is_empty() {
-        return String::new();
-    }
-    let mass_s = format_mass(debris_mass);
-    let mut lines: Vec<String> = Vec::new();
-    for e in items {
-        let info_id = gen_guid16();
-        let ap_id = gen_guid16();
-        lines.push(format!("{}SCR_DebrisInfo \"{{{}}}\" {{", indent, info_id));
-        lines.push(format!("{} ModelPrefab \"{{{}}}{}\"", indent, e.guid, e.path));
-
-        // Default offset
-        let mut off_line = format!("{}  Offset 0 0 0", indent);
-        if let Some(map) = offsets_by_file {
-            let key = std::path::Path::new(&e.path)
-                .file_name()
-                .and_then(|s| s.to_str())
-                .unwrap_or("")
-                .to_lowercase();
-            if let Some((ox, oy, oz)) = map.get(&key) {
-                off_line = format!("{}  Offset {} {} {}", indent, ox, oy, oz);
-            }
-        }
-
-        lines.push(format!("{} LocalTransform AttachPoint \"{{{}}}\" {{", indent, ap_id));
-        lines.push(off_line);
-        lines.push(format!("{}  Angles 0.00001 0 0", indent));
-        lines.push(format!("{} }}", indent));
-        lines.push(format!("{} m_fMass {}", indent, mass_s));
-        lines.push(format!("{}}}", indent));
-    }
-    lines.join("\n")
-}
-
-fn build_colliders_line(tags: &[String]) -> String {
-    if tags.is_empty() {
-        return "\"\"".to_string();
-    }
-    tags.iter()
-        .map(|t| format!("\"{}\"", t))
-        .collect::<Vec<String>>()
-        .join(" ")
+fn save_match_rules(rules: socket_rules::RuleFile, path: Option<String>) -> Result<MatchRulesInfo, String> {
+    socket_rules::RuleSet::compile(rules.clone(), None)?;
+    let path = path.filter(|p| !p.trim().is_empty());
+    let target = path.as_ref().map(PathBuf::from).unwrap_or_else(match_rules_path);
+    write_atomic(&target, serde_json::to_string_pretty(&rules).map_err(|e| e.to_string())?)?;
+    let mut settings = load_settings();
+    settings.match_rules_file = path;
+    save_settings(&settings)?;
+    get_match_rules()
 }
 
-fn replace_full_dst_markers(text: &str, scan: &FullDstScanResult, debris_mass: f32) -> String {
-    // Replace {{DEBRIS_ID-X}} and {{COLLIDERS_ID-X}} markers.
-    let mut by_part: HashMap<String, (Vec<MetaEntry>, Vec<String>)> = HashMap::new();
-    for z in &scan.zones {
-        by_part.insert(z.part_id.clone(), (z.debris.clone(), z.colliders.clone()));
-    }
-
-    let re_debris = Regex::new(r"(?m)^(?P<indent>\s*)\{\{DEBRIS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
-    let re_cols = Regex::new(r"(?m)^(?P<indent>\s*)\{\{COLLIDERS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
-
-    let s = re_debris
-        .replace_all(text, |caps: &regex::Captures| {
-            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
-            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
-            let (debris, _cols) = by_part.get(pid).cloned().unwrap_or_default();
-            // Indent children one extra space relative to marker line.
-            let child_indent = format!("{} ", indent);
-            build_debris_infos_block(&debris, &child_indent, debris_mass)
-        })
-        .to_string();
-
-    re_cols
-        .replace_all(&s, |caps: &regex::Captures| {
-            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
-            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
-            let (_debris, cols) = by_part.get(pid).cloned().unwrap_or_default();
-            format!("{}{}", indent, build_colliders_line(&cols))
-        })
-        .to_string()
+/// Runs AutoSocket over folders or lists of models as a job and returns the summary table.
+#[tauri::command]
+async fn create_ets_batch(app: tauri::AppHandle, args: autosocket_batch::BatchArgs) -> Result<JsonValue, String> {
+    spawn_job(app, JobRequest::AutosocketBatch(args)).wait().await
 }
 
-fn ensure_fractalparts_zone_count(text: &str, desired_count: usize) -> String {
-    let desired = desired_count.clamp(1, 26);
-    let nl = detect_newline(text);
-    let Some((open, close, _kw)) = find_fractalparts_bounds(text) else {
-        return text.to_string();
-    };
-    let inner = &text[(open + 1)..close];
-    let (blocks, other_lines) = extract_fractalpart_blocks(inner);
-
-    let mut existing: HashMap<char, String> = HashMap::new();
-    for (c, b) in blocks.iter() {
-        existing.insert(*c, b.clone());
-    }
-
-    let mut template_letter: Option<char> = None;
-    let mut template_block: Option<String> = None;
-    for (c, b) in blocks.iter().rev() {
-        if Regex::new(r##"\bPartId\s+\"([A-Z])\""##).unwrap().is_match(b) {
-            template_letter = Some(*c);
-            template_block = Some(b.clone());
-            break;
-        }
-    }
-    if template_block.is_none() {
-        if let Some((c, b)) = blocks.last() {
-            template_letter = Some(*c);
-            template_block = Some(b.clone());
-        }
+#[tauri::command]
+async fn discover_xob_sockets(app: tauri::AppHandle, xob_path: String) -> Result<socket_discovery::SocketDiscovery, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() {
+        return Err(format!("Invalid .xob path: {}", xob_path));
     }
+    tauri::async_runtime::spawn_blocking(move || autosocket::discover_sockets(&app, &xob_abs))
+        .await
+        .map_err(|e| e.to_string())
+}
 
-    let mut kept: Vec<String> = Vec::new();
-    for i in 0..desired {
-        let lt = (b'A' + (i as u8)) as char;
-        if let Some(b) = existing.get(&lt) {
-            kept.push(b.clone());
-        } else if let (Some(tb), Some(tl)) = (template_block.as_ref(), template_letter) {
-            kept.push(clone_fractalpart_block(tb, tl, lt));
-        }
-    }
+#[tauri::command]
+async fn explain_socket_matches(app: tauri::AppHandle, xob_path: String) -> Result<Vec<socket_rules::SocketMatch>, String> {
+    autosocket::explain_socket_matches(&app, &xob_path).await
+}
 
-    let mut inner_new_lines: Vec<String> = Vec::new();
-    for blk in kept {
-        inner_new_lines.extend(blk.lines().map(|s| s.to_string()));
-    }
-    if other_lines.iter().any(|s| !s.trim().is_empty()) {
-        inner_new_lines.extend(other_lines);
-    }
-    let new_inner = inner_new_lines.join(nl);
-    format!("{}{}{}{}{}", &text[..(open + 1)], nl, new_inner, nl, &text[close..])
+#[tauri::command]
+async fn create_new_et_from_xob(
+    app: tauri::AppHandle,
+    xob_path: String,
+    save_dir: Option<String>,
+    svn_root: Option<String>,
+    extra_dirs: Option<Vec<String>>,
+    extra_dirs_only: Option<bool>,
+    merge_into: Option<String>,
+    remove_missing: Option<bool>,
+    dry_run: Option<bool>,
+    choices: Option<BTreeMap<String, String>>,
+    accept_top: Option<bool>,
+) -> Result<CreateEtResult, String> {
+    let args = CreateEtArgs {
+        xob_path,
+        save_dir,
+        svn_root,
+        extra_dirs,
+        extra_dirs_only,
+        merge_into,
+        remove_missing,
+        dry_run,
+        choices,
+        accept_top,
+        ..Default::default()
+    };
+    autosocket::create_et(&app, args).await
 }
 
-fn apply_zone_hp(text: &str, hp: i32) -> String {
-    let nl = detect_newline(text);
-    let Some((open, close, _kw)) = find_fractalparts_bounds(text) else {
-        return text.to_string();
+#[tauri::command]
+async fn create_new_et_with_meta_from_xob(
+    app: tauri::AppHandle,
+    xob_path: String,
+    save_dir: Option<String>,
+    svn_root: Option<String>,
+    extra_dirs: Option<Vec<String>>,
+    extra_dirs_only: Option<bool>,
+    merge_into: Option<String>,
+    remove_missing: Option<bool>,
+    dry_run: Option<bool>,
+    choices: Option<BTreeMap<String, String>>,
+    accept_top: Option<bool>,
+) -> Result<CreateEtResult, String> {
+    let args = CreateEtArgs {
+        xob_path,
+        save_dir,
+        svn_root,
+        extra_dirs,
+        extra_dirs_only,
+        merge_into,
+        remove_missing,
+        dry_run,
+        choices,
+        accept_top,
+        ..Default::default()
     };
-    let inner = &text[(open + 1)..close];
-    let (blocks, other_lines) = extract_fractalpart_blocks(inner);
-    let re_hp = Regex::new(r"(?m)^(\s*MaxHealth)\s+[0-9]+(?:\.[0-9]+)?").unwrap();
-    let mut out_blocks: Vec<String> = Vec::new();
-    for (_c, b) in blocks {
-        out_blocks.push(re_hp.replace_all(&b, |caps: &regex::Captures| format!("{} {}", &caps[1], hp)).to_string());
-    }
-    let mut inner_new_lines: Vec<String> = Vec::new();
-    for blk in out_blocks {
-        inner_new_lines.extend(blk.lines().map(|s| s.to_string()));
-    }
-    if other_lines.iter().any(|s| !s.trim().is_empty()) {
-        inner_new_lines.extend(other_lines);
-    }
-    let new_inner = inner_new_lines.join(nl);
-    format!("{}{}{}{}{}", &text[..(open + 1)], nl, new_inner, nl, &text[close..])
+    autosocket::create_et_with_meta(&app, args).await
 }
 
-fn build_zone_fractal_from_preset(
-    preset_text: &str,
-    base_guid: &str,
-    base_res: &str,
-    v2_guid: Option<&str>,
-    v2_res: Option<&str>,
-    zone_count: usize,
-    hp_zone: i32,
-) -> String {
-    let mut s = strip_preset_header(preset_text);
-    s = replace_object_marker(&s, base_guid, base_res);
-    if let (Some(g), Some(r)) = (v2_guid, v2_res) {
-        s = replace_v2_model_marker(&s, g, r);
-    }
-    s = replace_gen_guid_markers(&s);
-    s = ensure_fractalparts_zone_count(&s, zone_count);
-    s = apply_zone_hp(&s, hp_zone);
-    s
+#[derive(Serialize)]
+struct ApplyWritePlanResult {
+    written: Vec<String>,
 }
 
-fn replace_full_dst_markers_with_offsets(
-    text: &str,
-    scan: &FullDstScanResult,
-    debris_mass: f32,
-    offsets_by_file: &std::collections::HashMap<String, (f32, f32, f32)>,
-) -> String {
-    let mut by_part: HashMap<String, (Vec<MetaEntry>, Vec<String>)> = HashMap::new();
-    for z in &scan.zones {
-        by_part.insert(z.part_id.clone(), (z.debris.clone(), z.colliders.clone()));
-    }
+/// Commits a plan returned by a dry run. Written `.meta` files and text resources are
+/// reindexed the same way a direct run would.
+#[tauri::command]
+async fn apply_write_plan(app: tauri::AppHandle, plan_id: String) -> Result<ApplyWritePlanResult, String> {
+    let written = write_plan::apply(&plan_id)?;
+    reindex_written_files(&app, &written);
+    Ok(ApplyWritePlanResult {
+        written: written.iter().map(|p| p.to_string_lossy().to_string()).collect(),
+    })
+}
 
-    let re_debris = Regex::new(r"(?m)^(?P<indent>\s*)\{\{DEBRIS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
-    let re_cols = Regex::new(r"(?m)^(?P<indent>\s*)\{\{COLLIDERS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
+#[tauri::command]
+fn discard_write_plan(plan_id: String) -> Result<bool, String> {
+    Ok(write_plan::discard(&plan_id))
+}
 
-    let s = re_debris
-        .replace_all(text, |caps: &regex::Captures| {
-            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
-            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
-            let (debris, _cols) = by_part.get(pid).cloned().unwrap_or_default();
-            let child_indent = format!("{} ", indent);
-            build_debris_infos_block_with_offsets(&debris, &child_indent, debris_mass, Some(offsets_by_file))
-        })
-        .to_string();
+fn emit_scan_log(app: &dyn LogSink, level: &str, message: impl Into<String>, current: Option<usize>, total: Option<usize>) {
+    app.log(LogChannel::Scan, level, &message.into(), current, total);
+}
 
-    re_cols
-        .replace_all(&s, |caps: &regex::Captures| {
-            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
-            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
-            let (_debris, cols) = by_part.get(pid).cloned().unwrap_or_default();
-            format!("{}{}", indent, build_colliders_line(&cols))
-        })
-        .to_string()
+fn emit_prefabdst_log(app: &dyn LogSink, level: &str, message: impl Into<String>, current: Option<usize>, total: Option<usize>) {
+    app.log(LogChannel::PrefabDst, level, &message.into(), current, total);
 }
 
-fn extract_ucx_offset_with_blender(app: &tauri::AppHandle, fbx_abs: &Path) -> Option<(f32, f32, f32)> {
-    if !fbx_abs.is_file() {
-        return None;
-    }
-    let settings = load_settings();
-    let blender = settings
-        .blender_path
-        .as_deref()
-        .map(PathBuf::from)
-        .filter(|p| p.is_file())
-        .or_else(resolve_blender_path)?;
-
-    emit_prefabdst_log(app, "info", format!("Debris offset: running {}", blender.to_string_lossy()), None, None);
-
-    let py_template = r#"import bpy, json, ast
-fbx=r'''FBX_PATH'''
-try:
-    bpy.ops.wm.read_factory_settings(use_empty=True)
-except Exception:
-    pass
-try:
-    bpy.ops.import_scene.fbx(filepath=fbx, automatic_bone_orientation=True)
-except Exception:
-    print("{}")
-    raise
-loc=None
-for ob in bpy.data.objects:
-    n=(ob.name or '').lower()
-    if not n.startswith('ucx_d_'):
-        continue
-    v=None
-    try:
-        v=ob.get('ebt_original_transform_matrix')
-    except Exception:
-        v=None
-    if v is None:
-        continue
-    try:
-        if isinstance(v, str):
-            v=ast.literal_eval(v)
-        if isinstance(v, (list, tuple)) and len(v)>=12:
-            x=float(v[3]); y=float(v[7]); z=float(v[11])
-            loc=(x,z,y)
-            break
-    except Exception:
-        pass
-print(json.dumps({'x':loc[0],'y':loc[1],'z':loc[2]}) if loc else "{}")
-"#;
-    let py = py_template.replace("FBX_PATH", &fbx_abs.to_string_lossy());
-
-    let mut script_path = std::env::temp_dir();
-    let ts = std::time::SystemTime::now()
-        .duration_since(std::time::UNIX_EPOCH)
-        .ok()?
-        .as_millis();
-    script_path.push(format!("owltools_ucx_offset_{}_{}.py", std::process::id(), ts));
-    let _ = fs::write(&script_path, &py);
-    let script_path_str = script_path.to_string_lossy().to_string();
-
-    let out = std::process::Command::new(blender)
-        .args(["--background", "--factory-startup", "--python", &script_path_str])
-        .output()
-        .ok()?;
-    let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-    let stderr = String::from_utf8_lossy(&out.stderr).to_string();
-    if !stderr.trim().is_empty() {
-        emit_prefabdst_log(app, "warn", format!("Blender debris offset stderr: {}", stderr.trim()), None, None);
-    }
-    let mut json_line: Option<String> = None;
-    for line in stdout.lines().rev() {
-        let t = line.trim();
-        if t.starts_with('{') && t.ends_with('}') {
-            json_line = Some(t.to_string());
-            break;
-        }
-    }
-    let Some(jl) = json_line else { return None; };
-    let v: serde_json::Value = serde_json::from_str(&jl).ok()?;
-    let x = v.get("x")?.as_f64()? as f32;
-    let y = v.get("y")?.as_f64()? as f32;
-    let z = v.get("z")?.as_f64()? as f32;
-    Some((x, y, z))
-}
-
-fn collect_debris_offsets(
-    app: &tauri::AppHandle,
-    base_xob_abs: &Path,
-    scan: &FullDstScanResult,
-) -> std::collections::HashMap<String, (f32, f32, f32)> {
-    let Some(dst_dir) = find_dst_directory(base_xob_abs) else { return std::collections::HashMap::new(); };
-    let mut pairs: Vec<(String, PathBuf)> = Vec::new();
-    for z in &scan.zones {
-        for e in &z.debris {
-            if let Some(fname) = Path::new(&e.path).file_name().and_then(|s| s.to_str()) {
-                let fbx_abs = dst_dir.join(fname).with_extension("fbx");
-                if fbx_abs.is_file() {
-                    pairs.push((fname.to_lowercase(), fbx_abs));
-                }
-            }
-        }
-    }
-    if pairs.is_empty() { return std::collections::HashMap::new(); }
-    let map = extract_ucx_offsets_bulk_with_blender(app, &pairs);
-    emit_prefabdst_log(
-        app,
-        "info",
-        format!("Debris offsets: {} found out of {} FBX", map.len(), pairs.len()),
-        None,
-        None,
-    );
-    map
+fn emit_mqa_stage(app: &dyn LogSink, stage: impl Into<String>) {
+    app.log(LogChannel::MqaStage, "info", &stage.into(), None, None);
 }
 
-#[derive(Serialize)]
-struct PrefabDstBuildResult {
-    out_paths: Vec<String>,
+fn detect_newline(s: &str) -> &'static str {
+    if s.contains("\r\n") { "\r\n" } else { "\n" }
 }
 
 #[tauri::command]
@@ -2212,149 +407,43 @@ async fn prefabdst_build(
     save_folder: String,
     scr_override: Option<ScrDstScanResult>,
     full_override: Option<HashMap<String, FullDstScanResult>>,
-) -> Result<PrefabDstBuildResult, String> {
-    let zones = zones.clamp(1, 26);
-    let hp_zone = hp_zone.clamp(1, 9999);
-    if model_files.is_empty() {
-        return Err("No model files".into());
-    }
-    if save_folder.trim().is_empty() {
-        return Err("No save folder".into());
-    }
-    let out_dir = PathBuf::from(save_folder.trim());
-    fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
-
-    let total = model_files.len();
-    emit_prefabdst_log(&app, "info", format!("Preset: {}", preset_file), None, None);
-    emit_prefabdst_log(&app, "info", format!("Zones: {} (hp={})", zones, hp_zone), None, None);
-    emit_prefabdst_log(&app, "info", format!("Debris mass: {}", format_mass(debris_mass)), None, None);
-
-    let mut out_paths: Vec<String> = Vec::new();
-    for (idx, xob_path) in model_files.iter().enumerate() {
-        let cur = idx + 1;
-        emit_prefabdst_log(&app, "info", format!("Reading meta for: {}", xob_path), Some(cur), Some(total));
-        let xob_abs = PathBuf::from(xob_path);
-        let (base_guid, base_res) = read_xob_object_field_from_meta(&xob_abs)?;
-
-        // v2 model (.xob.meta) next to base file
-        let mut v2_guid: Option<String> = None;
-        let mut v2_res: Option<String> = None;
-        if let Some(stem) = xob_abs.file_stem().and_then(|s| s.to_str()) {
-            if let Some(parent) = xob_abs.parent() {
-                let v2 = parent.join(format!("{}_V2_dst.xob", stem));
-                if v2.is_file() {
-                    if let Ok((g2, r2)) = read_xob_object_field_from_meta(&v2) {
-                        v2_guid = Some(g2);
-                        v2_res = Some(r2);
-                    }
-                }
-            }
-        }
-
-        // Branch by preset generator
-        let hdr = parse_preset_header(&preset_text);
-        let gen_key = hdr.generator.trim().to_lowercase();
-        emit_prefabdst_log(&app, "info", format!("Generator: {}", if gen_key.is_empty() { "(unknown)" } else { &gen_key }), Some(cur), Some(total));
-
-        // For zone_fractal presets, determine per-file scan/override for debris/colliders marker replacement.
-        // If UI provided overrides, prefer those instead of re-scanning.
-        let mut full_scan: Option<FullDstScanResult> = None;
-        let mut zones_for_this_file = zones;
-        if gen_key != "template" {
-            if let Some(map) = full_override.as_ref() {
-                if let Some(ov) = map.get(xob_path) {
-                    let inferred = ov.zones.len().clamp(1, 26);
-                    zones_for_this_file = inferred;
-                    full_scan = Some(ov.clone());
-                    emit_prefabdst_log(
-                        &app,
-                        "info",
-                        format!("Zones: {} (from UI override)", inferred),
-                        Some(cur),
-                        Some(total)
-                    );
-                }
-            }
-
-            if full_scan.is_none() {
-                // Fallback: auto-scan from base xob if no override was provided.
-                match prefabdst_scan_full_dst(xob_path.to_string()).await {
-                    Ok(scan) => {
-                        let inferred = scan.zones.len().clamp(1, 26);
-                        zones_for_this_file = inferred;
-                        full_scan = Some(scan);
-                        emit_prefabdst_log(
-                            &app,
-                            "info",
-                            format!("Auto zones: {} (from GeometryParam tags)", inferred),
-                            Some(cur),
-                            Some(total)
-                        );
-                    }
-                    Err(e) => {
-                        emit_prefabdst_log(
-                            &app,
-                            "warn",
-                            format!("Auto zones skipped (full dst scan failed): {}", e),
-                            Some(cur),
-                            Some(total)
-                        );
-                    }
-                }
-            }
-        }
+    dry_run: Option<bool>,
+) -> Result<JsonValue, String> {
+    let args = PrefabDstBuildArgs {
+        preset_file,
+        preset_text,
+        zones,
+        hp_zone,
+        debris_mass,
+        model_files,
+        save_folder,
+        scr_override,
+        full_override,
+        dry_run,
+    };
+    spawn_job(app, JobRequest::PrefabdstBuild(args)).wait().await
+}
 
-        emit_prefabdst_log(&app, "info", "Generating ET text...", Some(cur), Some(total));
-        let mut et_text = if gen_key == "template" {
-            // scr_destructible template rendering (auto-scan dst)
-            render_scr_destructible_template(&preset_text, &base_guid, &base_res, &xob_abs, scr_override.as_ref())
-        } else {
-            // default to zone_fractal
-            build_zone_fractal_from_preset(
-                &preset_text,
-                &base_guid,
-                &base_res,
-                v2_guid.as_deref(),
-                v2_res.as_deref(),
-                zones_for_this_file,
-                hp_zone,
-            )
-        };
+#[tauri::command]
+async fn prefabdst_read_meta(xob_path: String) -> Result<MetaEntry, String> {
+    prefabdst::read_meta(xob_path).await
+}
 
-        // For zone_fractal presets, fill debris/collider markers using a scan that matches the UI tree.
-        if gen_key != "template" {
-            if et_text.contains("{{DEBRIS_ID-") || et_text.contains("{{COLLIDERS_ID-") {
-                if let Some(scan) = full_scan.as_ref() {
-                    let offsets = collect_debris_offsets(&app, &xob_abs, scan);
-                    et_text = replace_full_dst_markers_with_offsets(&et_text, scan, debris_mass, &offsets);
-                } else {
-                    emit_prefabdst_log(
-                        &app,
-                        "warn",
-                        "Full DST scan unavailable (markers not replaced)".to_string(),
-                        Some(cur),
-                        Some(total)
-                    );
-                }
-            }
-        }
+#[tauri::command]
+async fn prefabdst_find_ruin_xob(xob_path: String) -> Result<Option<String>, String> {
+    prefabdst::find_ruin_xob(xob_path).await
+}
 
-        // Always normalize any remaining {gen vec3} marker to 0 0 0.
-        et_text = replace_gen_vec3_markers(&et_text);
-
-        let stem = xob_abs
-            .file_stem()
-            .and_then(|s| s.to_str())
-            .ok_or_else(|| "Invalid xob file name".to_string())?;
-        let out_path = out_dir.join(format!("{}_test_dst_prefab.et", stem));
-        emit_prefabdst_log(&app, "info", format!("Writing: {}", out_path.to_string_lossy()), Some(cur), Some(total));
-        fs::write(&out_path, et_text).map_err(|e| e.to_string())?;
-        out_paths.push(out_path.to_string_lossy().to_string());
-    }
+#[tauri::command]
+async fn prefabdst_scan_full_dst(xob_path: String) -> Result<FullDstScanResult, String> {
+    prefabdst::scan_full_dst(xob_path).await
+}
 
-    emit_prefabdst_log(&app, "info", format!("Done. Generated {} file(s).", out_paths.len()), None, None);
-    Ok(PrefabDstBuildResult { out_paths })
+#[tauri::command]
+async fn prefabdst_scan_dst(xob_path: String) -> Result<ScrDstScanResult, String> {
+    prefabdst::scan_scr_dst(xob_path).await
 }
+
 #[tauri::command]
 fn greet(name: &str) -> String {
     format!("Hello, {}! You've been greeted from Rust!", name)
@@ -2822,10 +911,97 @@ async fn updater_install_msi(app: tauri::AppHandle, version: String) -> Result<(
     Ok(())
 }
 
+/// Writes `contents` to a temporary file next to `path`, then renames it over `path`, so a
+/// crash mid-write leaves the previous file instead of a truncated one.
+fn write_atomic(path: &Path, contents: impl AsRef<[u8]>) -> Result<(), String> {
+    static TMP_CTR: AtomicU64 = AtomicU64::new(1);
+    let file_name = path.file_name().ok_or_else(|| format!("Not a file path: {}", path.to_string_lossy()))?;
+    let tmp = path.with_file_name(format!(
+        "{}.tmp-{}-{}",
+        file_name.to_string_lossy(),
+        std::process::id(),
+        TMP_CTR.fetch_add(1, Ordering::Relaxed)
+    ));
+    let written = fs::File::create(&tmp).and_then(|mut f| {
+        f.write_all(contents.as_ref())?;
+        f.sync_all()
+    });
+    if let Err(err) = written.and_then(|_| fs::rename(&tmp, path)) {
+        let _ = fs::remove_file(&tmp);
+        return Err(format!("Failed to write {}: {}", path.to_string_lossy(), err));
+    }
+    Ok(())
+}
+
+/// Writes every file with `write_atomic`. When one write fails, the files already written get
+/// their previous contents back (or are removed if they did not exist) and the error is
+/// returned.
+fn write_all_atomic(files: Vec<(PathBuf, String)>) -> Result<Vec<PathBuf>, String> {
+    let mut written: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+    for (path, contents) in files {
+        let before = fs::read(&path).ok();
+        if let Err(err) = write_atomic(&path, contents) {
+            for (p, before) in written.iter().rev() {
+                let _ = match before {
+                    Some(b) => write_atomic(p, b),
+                    None => fs::remove_file(p).map_err(|e| e.to_string()),
+                };
+            }
+            return Err(err);
+        }
+        written.push((path, before));
+    }
+    Ok(written.into_iter().map(|(p, _)| p).collect())
+}
+
+/// Moves an unreadable data file aside as `<name>.corrupt-<timestamp>` so it can be
+/// inspected later; returns the backup path.
+fn backup_corrupt_file(path: &Path) -> Option<PathBuf> {
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let backup = path.with_file_name(format!("{}.corrupt-{}", name, Utc::now().format("%Y%m%d-%H%M%S")));
+    match fs::rename(path, &backup) {
+        Ok(()) => {
+            eprintln!("{} is corrupt; moved it to {}", path.to_string_lossy(), backup.to_string_lossy());
+            Some(backup)
+        }
+        Err(err) => {
+            eprintln!("{} is corrupt and could not be moved aside: {}", path.to_string_lossy(), err);
+            None
+        }
+    }
+}
+
+/// Parses the JSON data file at `path`. A missing file gives the default; one that does not
+/// parse is backed up first, so the default written back later does not destroy it.
+fn load_json_or_backup<T: serde::de::DeserializeOwned + Default>(path: &Path) -> T {
+    let Ok(text) = fs::read_to_string(path) else { return T::default() };
+    match serde_json::from_str::<T>(&text) {
+        Ok(v) => v,
+        Err(err) => {
+            eprintln!("Failed to parse {}: {}", path.to_string_lossy(), err);
+            backup_corrupt_file(path);
+            T::default()
+        }
+    }
+}
+
+/// Legacy JSON prefab index; only read once to seed the SQLite index.
 fn prefab_index_path() -> PathBuf {
     ensure_data_dir().join("AutoSocket_PrefabIndex.json")
 }
 
+fn prefab_db_path() -> PathBuf {
+    ensure_data_dir().join("AutoSocket_PrefabIndex.db")
+}
+
+fn extract_cache_path() -> PathBuf {
+    ensure_data_dir().join("AutoSocket_ExtractCache.json")
+}
+
+fn job_history_path() -> PathBuf {
+    ensure_data_dir().join("OwlTools_JobHistory.json")
+}
+
 fn settings_path() -> PathBuf {
     ensure_data_dir().join("AutoSocket_Settings.json")
 }
@@ -2834,13 +1010,31 @@ fn presets_path() -> PathBuf {
     ensure_data_dir().join("AutoSocket_Presets.json")
 }
 
+fn batch_state_path() -> PathBuf {
+    ensure_data_dir().join("AutoSocket_BatchState.json")
+}
+
+fn match_rules_path() -> PathBuf {
+    ensure_data_dir().join("AutoSocket_MatchRules.json")
+}
+
 #[derive(Default, Serialize, Deserialize, Clone)]
 struct AutoSettings {
     svn_root: Option<String>,
+    /// Named roots (base data plus addons) indexed together; when unset, `svn_root` is the
+    /// only root.
+    #[serde(default)]
+    prefab_roots: Option<Vec<prefab_db::ProjectRoot>>,
     save_dir: Option<String>,
     extra_dirs: Option<Vec<String>>,
     blender_path: Option<String>,
     ebt_addons_dir: Option<String>,
+    /// Keep the prefab index updated from file changes in the roots; on unless false.
+    #[serde(default)]
+    watch_index: Option<bool>,
+    /// Socket matching rule file; AutoSocket_MatchRules.json in the data folder when unset.
+    #[serde(default)]
+    match_rules_file: Option<String>,
 }
 
 #[derive(Default, Serialize, Deserialize, Clone)]
@@ -2852,35 +1046,19 @@ struct AutoPreset {
 }
 
 fn load_presets() -> Vec<AutoPreset> {
-    let path = presets_path();
-    if let Ok(text) = fs::read_to_string(path) {
-        if let Ok(p) = serde_json::from_str::<Vec<AutoPreset>>(&text) {
-            return p;
-        }
-    }
-    Vec::new()
+    load_json_or_backup(&presets_path())
 }
 
 fn save_presets(presets: &Vec<AutoPreset>) -> Result<(), String> {
-    let path = presets_path();
-    fs::write(&path, serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?)
-        .map_err(|e| e.to_string())
+    write_atomic(&presets_path(), serde_json::to_string_pretty(presets).map_err(|e| e.to_string())?)
 }
 
 fn load_settings() -> AutoSettings {
-    let path = settings_path();
-    if let Ok(text) = fs::read_to_string(path) {
-        if let Ok(s) = serde_json::from_str::<AutoSettings>(&text) {
-            return s;
-        }
-    }
-    AutoSettings::default()
+    load_json_or_backup(&settings_path())
 }
 
 fn save_settings(settings: &AutoSettings) -> Result<(), String> {
-    let path = settings_path();
-    fs::write(&path, serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?)
-        .map_err(|e| e.to_string())
+    write_atomic(&settings_path(), serde_json::to_string_pretty(settings).map_err(|e| e.to_string())?)
 }
 
 #[tauri::command]
@@ -2974,9 +1152,8 @@ fn remember_ebt_addons_dir(path: Option<String>) -> Result<(), String> {
     save_settings(&settings)
 }
 
-#[tauri::command]
-async fn mqa_report_from_xob(
-    app: tauri::AppHandle,
+async fn mqa_report(
+    app: &dyn LogSink,
     xob_path: String,
     workbench_port: Option<u16>,
     asset_type: Option<String>,
@@ -2994,13 +1171,7 @@ async fn mqa_report_from_xob(
     }
 
     let settings = load_settings();
-    let blender = settings
-        .blender_path
-        .as_deref()
-        .map(PathBuf::from)
-        .filter(|p| p.is_file())
-        .or_else(resolve_blender_path)
-        .ok_or_else(|| "Blender not configured".to_string())?;
+    let launch = blender_worker::resolve_launch().ok_or_else(|| "Blender not configured".to_string())?;
 
     let addons_dir = settings
         .ebt_addons_dir
@@ -3020,396 +1191,68 @@ async fn mqa_report_from_xob(
         _ => "GENERIC".to_string(),
     };
 
-    let py = format!(
-        r#"import sys, pathlib, json, socket, os
-import bpy
-from bpy.props import StringProperty, EnumProperty
-
-try:
-    if not hasattr(bpy.types.Material, 'ebt_enfusion_shader_type'):
-        bpy.types.Material.ebt_enfusion_shader_type = StringProperty()
-    if not hasattr(bpy.types.Material, 'ebt_resource_name'):
-        bpy.types.Material.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the material, used for exporting to .xob')
-    if not hasattr(bpy.types.Image, 'ebt_resource_name'):
-        bpy.types.Image.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the texture, used for exporting to .xob')
-    if not hasattr(bpy.types.Material, 'Cull'):
-        bpy.types.Material.Cull = EnumProperty(items=[('ccw','ccw','',0), ('none','none','',1)], default='ccw')
-except Exception:
-    pass
-
-socket.setdefaulttimeout(3.0)
-
-def stage(msg):
-    try:
-        print('OWLTOOLS_STAGE=' + str(msg), flush=True)
-    except Exception:
-        pass
-
-debug = {{}}
-errors = []
-
-addons_dir = pathlib.Path(r'''{0}''')
-if not addons_dir.exists():
-    raise RuntimeError('EBT addons dir does not exist: ' + str(addons_dir))
-sys.path.insert(0, str(addons_dir))
-
-stage('Load EBT modules')
-
-try:
-    import EnfusionBlenderTools
-    debug['ebt_imported'] = True
-except Exception as e:
-    debug['ebt_imported'] = False
-    errors.append('import EnfusionBlenderTools failed: ' + str(e))
-
-stage('Register ModelQA')
-
-try:
-    import EnfusionBlenderTools.modelqa as modelqa
-    try:
-        modelqa.register()
-        debug['modelqa_registered'] = True
-    except Exception as e:
-        msg = str(e)
-        if 'already registered' in msg or 'already registered as a subclass' in msg:
-            debug['modelqa_registered'] = True
-            debug['modelqa_register_already'] = True
-        else:
-            debug['modelqa_registered'] = False
-            errors.append('modelqa.register failed: ' + msg)
-except Exception as e:
-    debug['modelqa_registered'] = False
-    errors.append('import modelqa failed: ' + str(e))
-
-try:
-    from EnfusionBlenderTools.workbench import Workbench
-    from EnfusionBlenderTools.core.fbx import fbx_io
-    debug['ebt_modules_ok'] = True
-except Exception as e:
-    debug['ebt_modules_ok'] = False
-    errors.append('import Workbench/fbx_io failed: ' + str(e))
-    raise
-
-stage('Register material schemas')
-try:
-    from EnfusionBlenderTools.core.materials.accessors import schemas as mat_schemas
-    mat_schemas.register()
-    debug['material_schemas_registered'] = True
-except Exception as e:
-    debug['material_schemas_registered'] = False
-    errors.append('material schemas register failed: ' + str(e))
-
-stage('Check Workbench status')
-try:
-    Workbench.init(client_id='OwlTools', port={1})
-    st = Workbench.get_status()
-    if not st:
-        errors.append('Workbench not ready: ' + str(st))
-        payload = dict(fbx=str(pathlib.Path(r'''{2}''')), count=0, items=[], debug=debug, errors=errors)
-        print('OWLTOOLS_MQA_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-        try:
-            bpy.ops.wm.quit_blender()
-        except Exception:
-            pass
-        try:
-            sys.stdout.flush()
-            sys.stderr.flush()
-        except Exception:
-            pass
-        os._exit(0)
-except Exception as e:
-    errors.append('Workbench.init/status failed: ' + str(e))
-    payload = dict(fbx=str(pathlib.Path(r'''{2}''')), count=0, items=[], debug=debug, errors=errors)
-    print('OWLTOOLS_MQA_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-    try:
-        bpy.ops.wm.quit_blender()
-    except Exception:
-        pass
-    try:
-        sys.stdout.flush()
-        sys.stderr.flush()
-    except Exception:
-        pass
-    os._exit(0)
-fbx = pathlib.Path(r'''{2}''')
-
-stage('Import FBX')
-fbx_io.import_fbx(fbx)
-
-op_exists = False
-try:
-    if hasattr(bpy.ops, 'ebt') and ('mqa_report_conventions' in dir(bpy.ops.ebt)):
-        op_exists = True
-except Exception:
-    op_exists = False
-debug['mqa_operator_exists'] = op_exists
-
-if op_exists:
-    try:
-        stage('Run MQA operator')
-        bpy.ops.ebt.mqa_report_conventions(asset_type=r'''{3}''')
-        debug['mqa_operator_ran'] = True
-    except Exception as e:
-        debug['mqa_operator_ran'] = False
-        errors.append('mqa_report_conventions failed: ' + str(e))
-else:
-    debug['mqa_operator_ran'] = False
-    errors.append('mqa_report_conventions operator not found')
-
-scene = bpy.context.scene
-items = []
-try:
-    if not hasattr(scene, 'ebt_report_messages'):
-        raise RuntimeError('scene.ebt_report_messages not found (modelqa not registered?)')
-    msgs = scene.ebt_report_messages
-    for m in msgs:
-        objs = []
-        try:
-            for o in m.objs:
-                objs.append(getattr(o, 'name', ''))
-        except Exception:
-            pass
-        items.append(dict(
-            category=getattr(m, 'category', ''),
-            message=getattr(m, 'message', ''),
-            count=len(objs),
-            objects=objs,
-        ))
-except Exception as e:
-    items = []
-    errors.append('collect messages failed: ' + str(e))
-
-payload = dict(fbx=str(fbx), count=len(items), items=items, debug=debug, errors=errors)
-print('OWLTOOLS_MQA_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-try:
-    bpy.ops.wm.quit_blender()
-except Exception:
-    pass
-try:
-    sys.stdout.flush()
-    sys.stderr.flush()
-except Exception:
-    pass
-os._exit(0)
-"#,
-        addons_dir.to_string_lossy(),
-        wb_port,
-        fbx_abs.to_string_lossy(),
-        asset_type_norm
-    );
-
-    fn write_temp_blender_script(prefix: &str, contents: &str) -> Result<PathBuf, String> {
-        let mut p = std::env::temp_dir();
-        let ts = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| e.to_string())?
-            .as_millis();
-        let pid = std::process::id();
-        p.push(format!("{}_{}_{}.py", prefix, pid, ts));
-        fs::write(&p, contents).map_err(|e| format!("Failed to write temp script: {}", e))?;
-        Ok(p)
+    let args = json!({
+        "addons_dir": addons_dir.to_string_lossy(),
+        "port": wb_port,
+        "asset_type": asset_type_norm,
+        "xobs": [xob_abs.to_string_lossy()],
+    });
+    let batch = mqa_worker_request(app, &launch, args, 60, 60 * 10).await?;
+
+    // Flatten the one-file batch into the single report shape the UI expects.
+    let report = batch
+        .get("reports")
+        .and_then(|r| r.as_array())
+        .and_then(|r| r.first())
+        .cloned()
+        .unwrap_or_else(|| json!({}));
+    let mut errors: Vec<JsonValue> = batch.get("errors").and_then(|e| e.as_array()).cloned().unwrap_or_default();
+    if let Some(file_errors) = report.get("errors").and_then(|e| e.as_array()) {
+        errors.extend(file_errors.iter().cloned());
     }
+    Ok(json!({
+        "fbx": fbx_abs.to_string_lossy(),
+        "count": report.get("count").cloned().unwrap_or(json!(0)),
+        "items": report.get("items").cloned().unwrap_or(json!([])),
+        "debug": batch.get("debug").cloned().unwrap_or(json!({})),
+        "errors": errors,
+    }))
+}
 
-    let script_path = write_temp_blender_script("owltools_mqa", &py)?;
-    let script_path_str = script_path.to_string_lossy().to_string();
-
-    let tail_lines = |s: &str, max_lines: usize| -> String {
-        let lines: Vec<&str> = s.lines().collect();
-        if lines.len() <= max_lines {
-            return s.trim().to_string();
-        }
-        lines[lines.len().saturating_sub(max_lines)..]
-            .join("\n")
-            .trim()
-            .to_string()
-    };
-
-    let find_json_line = |stdout: &str| -> Option<String> {
-        for line in stdout.lines().rev() {
-            let t = line.trim();
-            if let Some(rest) = t.strip_prefix("OWLTOOLS_MQA_JSON=") {
-                return Some(rest.to_string());
-            }
-        }
-        None
-    };
+#[tauri::command]
+async fn mqa_report_from_xob(
+    app: tauri::AppHandle,
+    xob_path: String,
+    workbench_port: Option<u16>,
+    asset_type: Option<String>,
+) -> Result<JsonValue, String> {
+    mqa_report(&app, xob_path, workbench_port, asset_type).await
+}
 
-    async fn run_blender(
-        app: &tauri::AppHandle,
-        blender: &Path,
-        args: &[&str],
-        idle_timeout_secs: u64,
-    ) -> Result<std::process::Output, String> {
-        let mut cmd = TokioCommand::new(blender);
-        cmd.args(args);
-        cmd.env("EBT_TEST", "1");
-        cmd.kill_on_drop(true);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let _ = app.emit("mqa_stage", "Launching Blender".to_string());
-        let mut child = cmd.spawn().map_err(|e| format!("Failed to run Blender: {}", e))?;
-        let _ = app.emit("mqa_stage", "Blender spawned".to_string());
-        let stdout = child.stdout.take().ok_or_else(|| "Failed to capture Blender stdout".to_string())?;
-        let stderr = child.stderr.take().ok_or_else(|| "Failed to capture Blender stderr".to_string())?;
-
-        let mut out_buf: Vec<u8> = Vec::new();
-        let mut err_buf: Vec<u8> = Vec::new();
-
-        let mut last_stage: String = String::new();
-        let mut idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-
-        let mut out_reader = BufReader::new(stdout).lines();
-        let mut err_reader = BufReader::new(stderr).lines();
-
-        loop {
-            tokio::select! {
-                line = out_reader.next_line() => {
-                    match line {
-                        Ok(Some(l)) => {
-                            idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-                            out_buf.extend_from_slice(l.as_bytes());
-                            out_buf.push(b'\n');
-                            if let Some(stage) = l.trim().strip_prefix("OWLTOOLS_STAGE=") {
-                                last_stage = stage.trim().to_string();
-                                let _ = app.emit("mqa_stage", last_stage.clone());
-                            }
-                        }
-                        Ok(None) => { /* stdout closed */ }
-                        Err(_) => { /* ignore */ }
-                    }
-                }
-                line = err_reader.next_line() => {
-                    match line {
-                        Ok(Some(l)) => {
-                            idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-                            err_buf.extend_from_slice(l.as_bytes());
-                            err_buf.push(b'\n');
-                        }
-                        Ok(None) => { /* stderr closed */ }
-                        Err(_) => { /* ignore */ }
-                    }
-                }
-                status = child.wait() => {
-                    let status = status.map_err(|e| format!("Failed to wait Blender: {}", e))?;
-                    return Ok(std::process::Output { status, stdout: out_buf, stderr: err_buf });
-                }
-                _ = tokio::time::sleep_until(idle_deadline) => {
-                    let _ = child.kill().await;
-                    let _ = child.wait().await;
-                    let stage_suffix = if last_stage.is_empty() {
-                        "".to_string()
-                    } else {
-                        format!(" (last stage: {})", last_stage)
-                    };
-                    return Err(format!("Blender stalled (no output for {}s){}", idle_timeout_secs, stage_suffix));
-                }
-            }
+/// Runs the `mqa` op on the Blender worker, retrying once without `--background` when
+/// Blender reports that the operator needs a GPU context.
+async fn mqa_worker_request(
+    app: &dyn LogSink,
+    launch: &blender_worker::WorkerLaunch,
+    args: JsonValue,
+    timeout_secs: u64,
+    idle_timeout_secs: u64,
+) -> Result<JsonValue, String> {
+    let on_stage = |stage: &str| emit_mqa_stage(app, stage);
+    let timeout = Duration::from_secs(timeout_secs);
+    let idle_timeout = Duration::from_secs(idle_timeout_secs);
+    match blender_worker::request(launch, "mqa", args.clone(), timeout, idle_timeout, &on_stage).await {
+        Err(_) if blender_worker::needs_gpu_retry(launch) => {
+            emit_mqa_stage(app, "Retrying without --background (GPU required)");
+            let launch = blender_worker::without_background(launch);
+            blender_worker::request(&launch, "mqa", args, timeout, idle_timeout, &on_stage).await
         }
+        res => res,
     }
-
-    async fn run_blender_with_timeout(
-        app: &tauri::AppHandle,
-        blender: &Path,
-        args: &[&str],
-        timeout_secs: u64,
-        idle_timeout_secs: u64,
-    ) -> Result<std::process::Output, String> {
-        tokio::time::timeout(
-            Duration::from_secs(timeout_secs),
-            run_blender(app, blender, args, idle_timeout_secs),
-        )
-            .await
-            .map_err(|_| format!("Blender timed out after {}s", timeout_secs))?
-    }
-
-    let total_timeout_secs: u64 = 60;
-    let idle_timeout_secs: u64 = 60 * 10;
-
-    let use_python = resolve_python_path().filter(|p| python_has_bpy(p));
-
-    let (out, stdout, stderr, json_line) = if let Some(python) = use_python {
-        let out = run_blender_with_timeout(
-            &app,
-            &python,
-            &["-u", &script_path_str],
-            total_timeout_secs,
-            idle_timeout_secs,
-        )
-        .await?;
-        let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&out.stderr).to_string();
-        let json_line = find_json_line(&stdout);
-        (out, stdout, stderr, json_line)
-    } else {
-        let mut out = run_blender_with_timeout(
-            &app,
-            &blender,
-            &["--background", "--factory-startup", "--python", &script_path_str],
-            total_timeout_secs,
-            idle_timeout_secs,
-        )
-        .await?;
-
-        let mut stdout = String::from_utf8_lossy(&out.stdout).to_string();
-        let mut stderr = String::from_utf8_lossy(&out.stderr).to_string();
-
-        let mut json_line = find_json_line(&stdout);
-        if json_line.is_none()
-            && (stderr.contains("GPU functions for drawing are not available in background mode")
-                || stdout.contains("GPU functions for drawing are not available in background mode"))
-        {
-            out = run_blender_with_timeout(
-                &app,
-                &blender,
-                &["--factory-startup", "--python", &script_path_str],
-                total_timeout_secs,
-                idle_timeout_secs,
-            )
-            .await?;
-            stdout = String::from_utf8_lossy(&out.stdout).to_string();
-            stderr = String::from_utf8_lossy(&out.stderr).to_string();
-            json_line = find_json_line(&stdout);
-        }
-        (out, stdout, stderr, json_line)
-    };
-
-    let json_line = if let Some(j) = json_line {
-        j
-    } else {
-        let status = out
-            .status
-            .code()
-            .map(|c| format!("exit code {}", c))
-            .unwrap_or_else(|| "terminated".to_string());
-        let stderr_tail = tail_lines(&stderr, 80);
-        let stdout_tail = tail_lines(&stdout, 80);
-        if stderr_tail.is_empty() && stdout_tail.is_empty() {
-            return Err(format!("Failed to capture MQA report from Blender ({})", status));
-        } else if stderr_tail.is_empty() {
-            return Err(format!(
-                "Failed to capture MQA report from Blender ({})\n--- stdout (tail) ---\n{}",
-                status, stdout_tail
-            ));
-        } else if stdout_tail.is_empty() {
-            return Err(format!(
-                "Failed to capture MQA report from Blender ({})\n--- stderr (tail) ---\n{}",
-                status, stderr_tail
-            ));
-        } else {
-            return Err(format!(
-                "Failed to capture MQA report from Blender ({})\n--- stderr (tail) ---\n{}\n--- stdout (tail) ---\n{}",
-                status, stderr_tail, stdout_tail
-            ));
-        }
-    };
-
-    serde_json::from_str::<JsonValue>(&json_line).map_err(|e| e.to_string())
 }
 
-#[tauri::command]
-async fn mqa_report_from_xobs_batch(
-    app: tauri::AppHandle,
+async fn mqa_report_batch(
+    app: &dyn LogSink,
     xob_paths: Vec<String>,
     workbench_port: Option<u16>,
     asset_type: Option<String>,
@@ -3435,13 +1278,7 @@ async fn mqa_report_from_xobs_batch(
     }
 
     let settings = load_settings();
-    let blender = settings
-        .blender_path
-        .as_deref()
-        .map(PathBuf::from)
-        .filter(|p| p.is_file())
-        .or_else(resolve_blender_path)
-        .ok_or_else(|| "Blender not configured".to_string())?;
+    let launch = blender_worker::resolve_launch().ok_or_else(|| "Blender not configured".to_string())?;
 
     let addons_dir = settings
         .ebt_addons_dir
@@ -3461,483 +1298,51 @@ async fn mqa_report_from_xobs_batch(
         _ => "GENERIC".to_string(),
     };
 
-    let xob_list_json = serde_json::to_string(
-        &abs_xobs
-            .iter()
-            .map(|p| p.to_string_lossy().to_string())
-            .collect::<Vec<String>>(),
-    )
-    .map_err(|e| e.to_string())?;
-
-    let py = format!(
-        r#"import sys, pathlib, json, socket, os
-import bpy
-from bpy.props import StringProperty, EnumProperty
-
-try:
-    if not hasattr(bpy.types.Material, 'ebt_enfusion_shader_type'):
-        bpy.types.Material.ebt_enfusion_shader_type = StringProperty()
-    if not hasattr(bpy.types.Material, 'ebt_resource_name'):
-        bpy.types.Material.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the material, used for exporting to .xob')
-    if not hasattr(bpy.types.Image, 'ebt_resource_name'):
-        bpy.types.Image.ebt_resource_name = StringProperty(name='Resource Name', default='', description='Resource name for the texture, used for exporting to .xob')
-    if not hasattr(bpy.types.Material, 'Cull'):
-        bpy.types.Material.Cull = EnumProperty(items=[('ccw','ccw','',0), ('none','none','',1)], default='ccw')
-except Exception:
-    pass
-
-socket.setdefaulttimeout(3.0)
-
-def stage(msg):
-    try:
-        print('OWLTOOLS_STAGE=' + str(msg), flush=True)
-    except Exception:
-        pass
-
-debug = {{}}
-errors = []
-
-addons_dir = pathlib.Path(r'''{0}''')
-if not addons_dir.exists():
-    raise RuntimeError('EBT addons dir does not exist: ' + str(addons_dir))
-sys.path.insert(0, str(addons_dir))
-
-stage('Load EBT modules')
-
-try:
-    import EnfusionBlenderTools
-    debug['ebt_imported'] = True
-except Exception as e:
-    debug['ebt_imported'] = False
-    errors.append('import EnfusionBlenderTools failed: ' + str(e))
-
-stage('Register ModelQA')
-try:
-    import EnfusionBlenderTools.modelqa as modelqa
-    try:
-        modelqa.register()
-        debug['modelqa_registered'] = True
-    except Exception as e:
-        msg = str(e)
-        if 'already registered' in msg or 'already registered as a subclass' in msg:
-            debug['modelqa_registered'] = True
-            debug['modelqa_register_already'] = True
-        else:
-            debug['modelqa_registered'] = False
-            errors.append('modelqa.register failed: ' + msg)
-except Exception as e:
-    debug['modelqa_registered'] = False
-    errors.append('import modelqa failed: ' + str(e))
-
-try:
-    from EnfusionBlenderTools.workbench import Workbench
-    from EnfusionBlenderTools.core.fbx import fbx_io
-except Exception as e:
-    errors.append('import Workbench/fbx_io failed: ' + str(e))
-    raise
-
-stage('Register material schemas')
-try:
-    from EnfusionBlenderTools.core.materials.accessors import schemas as mat_schemas
-    mat_schemas.register()
-    debug['material_schemas_registered'] = True
-except Exception as e:
-    debug['material_schemas_registered'] = False
-    errors.append('material schemas register failed: ' + str(e))
-
-stage('Check Workbench status')
-try:
-    Workbench.init(client_id='OwlTools', port={1})
-    st = Workbench.get_status()
-    if not st:
-        errors.append('Workbench not ready: ' + str(st))
-        payload = dict(reports=[], debug=debug, errors=errors)
-        print('OWLTOOLS_MQA_BATCH_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-        try:
-            bpy.ops.wm.quit_blender()
-        except Exception:
-            pass
-        try:
-            sys.stdout.flush()
-            sys.stderr.flush()
-        except Exception:
-            pass
-        os._exit(0)
-except Exception as e:
-    errors.append('Workbench.init/status failed: ' + str(e))
-    payload = dict(reports=[], debug=debug, errors=errors)
-    print('OWLTOOLS_MQA_BATCH_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-    try:
-        bpy.ops.wm.quit_blender()
-    except Exception:
-        pass
-    try:
-        sys.stdout.flush()
-        sys.stderr.flush()
-    except Exception:
-        pass
-    os._exit(0)
-xobs = json.loads(r'''{2}''')
-reports = []
-
-def _remove_orphans_from(collection):
-    # Remove datablocks with no users; repeat until stable.
-    changed = True
-    while changed:
-        changed = False
-        for datablock in list(collection):
-            try:
-                if getattr(datablock, 'users', 0) == 0:
-                    collection.remove(datablock)
-                    changed = True
-            except Exception:
-                pass
-
-def reset_scene(deep=False):
-    # Avoid bpy.ops.wm.read_factory_settings in headless/non-UI contexts (can crash).
-    try:
-        for obj in list(bpy.data.objects):
-            try:
-                bpy.data.objects.remove(obj, do_unlink=True)
-            except Exception:
-                pass
-        for col in list(bpy.data.collections):
-            try:
-                bpy.data.collections.remove(col)
-            except Exception:
-                pass
-
-        if deep:
-            try:
-                _remove_orphans_from(bpy.data.meshes)
-                _remove_orphans_from(bpy.data.materials)
-                _remove_orphans_from(bpy.data.images)
-                _remove_orphans_from(bpy.data.node_groups)
-                _remove_orphans_from(bpy.data.textures)
-                _remove_orphans_from(bpy.data.actions)
-                _remove_orphans_from(bpy.data.armatures)
-                _remove_orphans_from(bpy.data.curves)
-                _remove_orphans_from(bpy.data.lights)
-                _remove_orphans_from(bpy.data.cameras)
-            except Exception:
-                pass
-
-            # Blender 4.x has bpy.data.orphans_purge which doesn't require UI context.
-            try:
-                if hasattr(bpy.data, 'orphans_purge'):
-                    bpy.data.orphans_purge(do_recursive=True)
-            except Exception:
-                pass
-
-            try:
-                import gc
-                gc.collect()
-            except Exception:
-                pass
-    except Exception:
-        pass
-
-for idx, xobp in enumerate(xobs):
-    xob = pathlib.Path(xobp)
-    fbx = xob.with_suffix('.fbx')
-    name = xob.name
-    stage('File {{}}/{{}}: {{}} | Import FBX'.format(idx+1, len(xobs), name))
-    file_errors = []
-    try:
-        reset_scene(deep=((idx % 10) == 0))
-        fbx_io.import_fbx(fbx)
-    except Exception as e:
-        file_errors.append('import_fbx failed: ' + str(e))
-
-    stage('File {{}}/{{}}: {{}} | Run MQA'.format(idx+1, len(xobs), name))
-    if not file_errors:
-        try:
-            if hasattr(bpy.ops, 'ebt') and ('mqa_report_conventions' in dir(bpy.ops.ebt)):
-                bpy.ops.ebt.mqa_report_conventions(asset_type=r'''{3}''')
-            else:
-                file_errors.append('mqa_report_conventions operator not found')
-        except Exception as e:
-            file_errors.append('mqa_report_conventions failed: ' + str(e))
-
-    items = []
-    try:
-        scene = bpy.context.scene
-        if hasattr(scene, 'ebt_report_messages'):
-            msgs = scene.ebt_report_messages
-            for m in msgs:
-                objs = []
-                try:
-                    for o in m.objs:
-                        objs.append(getattr(o, 'name', ''))
-                except Exception:
-                    pass
-                items.append(dict(
-                    category=getattr(m, 'category', ''),
-                    message=getattr(m, 'message', ''),
-                    count=len(objs),
-                    objects=objs,
-                ))
-        else:
-            file_errors.append('scene.ebt_report_messages not found')
-    except Exception as e:
-        file_errors.append('collect messages failed: ' + str(e))
-
-    reports.append(dict(xob=str(xob), fbx=str(fbx), count=len(items), items=items, errors=file_errors))
-
-payload = dict(reports=reports, debug=debug, errors=errors)
-print('OWLTOOLS_MQA_BATCH_JSON=' + json.dumps(payload, ensure_ascii=False), flush=True)
-try:
-    bpy.ops.wm.quit_blender()
-except Exception:
-    pass
-try:
-    sys.stdout.flush()
-    sys.stderr.flush()
-except Exception:
-    pass
-os._exit(0)
-"#,
-        addons_dir.to_string_lossy(),
-        wb_port,
-        xob_list_json,
-        asset_type_norm
-    );
-
-    fn write_temp_blender_script(prefix: &str, contents: &str) -> Result<PathBuf, String> {
-        let mut p = std::env::temp_dir();
-        let ts = std::time::SystemTime::now()
-            .duration_since(std::time::UNIX_EPOCH)
-            .map_err(|e| e.to_string())?
-            .as_millis();
-        let pid = std::process::id();
-        p.push(format!("{}_{}_{}.py", prefix, pid, ts));
-        fs::write(&p, contents).map_err(|e| format!("Failed to write temp script: {}", e))?;
-        Ok(p)
-    }
-
-    let script_path = write_temp_blender_script("owltools_mqa_batch", &py)?;
-    let script_path_str = script_path.to_string_lossy().to_string();
-
-    let tail_lines = |s: &str, max_lines: usize| -> String {
-        let lines: Vec<&str> = s.lines().collect();
-        if lines.len() <= max_lines {
-            return s.trim().to_string();
-        }
-        lines[lines.len().saturating_sub(max_lines)..]
-            .join("\n")
-            .trim()
-            .to_string()
-    };
-
-    let find_batch_json_line = |stdout: &str| -> Option<String> {
-        for line in stdout.lines().rev() {
-            let t = line.trim();
-            if let Some(rest) = t.strip_prefix("OWLTOOLS_MQA_BATCH_JSON=") {
-                return Some(rest.to_string());
-            }
-        }
-        None
-    };
-
-    async fn run_blender(
-        app: &tauri::AppHandle,
-        blender: &Path,
-        args: &[&str],
-        idle_timeout_secs: u64,
-    ) -> Result<std::process::Output, String> {
-        let mut cmd = TokioCommand::new(blender);
-        cmd.args(args);
-        cmd.env("EBT_TEST", "1");
-        cmd.kill_on_drop(true);
-        cmd.stdout(Stdio::piped());
-        cmd.stderr(Stdio::piped());
-
-        let _ = app.emit("mqa_stage", "Launching Blender".to_string());
-        let mut child = cmd.spawn().map_err(|e| format!("Failed to run Blender: {}", e))?;
-        let _ = app.emit("mqa_stage", "Blender spawned".to_string());
-        let stdout = child.stdout.take().ok_or_else(|| "Failed to capture Blender stdout".to_string())?;
-        let stderr = child.stderr.take().ok_or_else(|| "Failed to capture Blender stderr".to_string())?;
-
-        let mut out_buf: Vec<u8> = Vec::new();
-        let mut err_buf: Vec<u8> = Vec::new();
-
-        let mut last_stage: String = String::new();
-        let mut idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-
-        let mut out_reader = BufReader::new(stdout).lines();
-        let mut err_reader = BufReader::new(stderr).lines();
-
-        loop {
-            tokio::select! {
-                line = out_reader.next_line() => {
-                    match line {
-                        Ok(Some(l)) => {
-                            idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-                            out_buf.extend_from_slice(l.as_bytes());
-                            out_buf.push(b'\n');
-                            if let Some(stage) = l.trim().strip_prefix("OWLTOOLS_STAGE=") {
-                                last_stage = stage.trim().to_string();
-                                let _ = app.emit("mqa_stage", last_stage.clone());
-                            }
-                        }
-                        Ok(None) => { /* stdout closed */ }
-                        Err(_) => { /* ignore */ }
-                    }
-                }
-                line = err_reader.next_line() => {
-                    match line {
-                        Ok(Some(l)) => {
-                            idle_deadline = tokio::time::Instant::now() + Duration::from_secs(idle_timeout_secs);
-                            err_buf.extend_from_slice(l.as_bytes());
-                            err_buf.push(b'\n');
-                        }
-                        Ok(None) => { /* stderr closed */ }
-                        Err(_) => { /* ignore */ }
-                    }
-                }
-                status = child.wait() => {
-                    let status = status.map_err(|e| format!("Failed to wait Blender: {}", e))?;
-                    return Ok(std::process::Output { status, stdout: out_buf, stderr: err_buf });
-                }
-                _ = tokio::time::sleep_until(idle_deadline) => {
-                    let _ = child.kill().await;
-                    let _ = child.wait().await;
-                    let stage_suffix = if last_stage.is_empty() {
-                        "".to_string()
-                    } else {
-                        format!(" (last stage: {})", last_stage)
-                    };
-                    return Err(format!("Blender stalled (no output for {}s){}", idle_timeout_secs, stage_suffix));
-                }
-            }
-        }
-    }
-
-    async fn run_blender_with_timeout(
-        app: &tauri::AppHandle,
-        blender: &Path,
-        args: &[&str],
-        timeout_secs: u64,
-        idle_timeout_secs: u64,
-    ) -> Result<std::process::Output, String> {
-        tokio::time::timeout(
-            Duration::from_secs(timeout_secs),
-            run_blender(app, blender, args, idle_timeout_secs),
-        )
-            .await
-            .map_err(|_| format!("Blender timed out after {}s", timeout_secs))?
-    }
-
-    // Batch can be long-running; scale timeout with number of files.
-    let total_timeout_secs: u64 = 60 * (10 + 5 * (abs_xobs.len() as u64));
-    // No output while importing can be normal; keep this relatively high to avoid false positives.
-    let idle_timeout_secs: u64 = 60 * 15;
-
-    let use_python = resolve_python_path().filter(|p| python_has_bpy(p));
-
-    let (out, stdout, stderr) = if let Some(python) = use_python {
-        let out = run_blender_with_timeout(
-            &app,
-            &python,
-            &["-u", &script_path_str],
-            total_timeout_secs,
-            idle_timeout_secs,
-        )
-        .await?;
-        let stdout = String::from_utf8_lossy(&out.stdout).to_string();
-        let stderr = String::from_utf8_lossy(&out.stderr).to_string();
-        (out, stdout, stderr)
-    } else {
-        let mut out = run_blender_with_timeout(
-            &app,
-            &blender,
-            &["--background", "--factory-startup", "--python", &script_path_str],
-            total_timeout_secs,
-            idle_timeout_secs,
-        )
-        .await?;
-
-        let mut stdout = String::from_utf8_lossy(&out.stdout).to_string();
-        let mut stderr = String::from_utf8_lossy(&out.stderr).to_string();
-
-        if stderr.contains("GPU functions for drawing are not available in background mode")
-            || stdout.contains("GPU functions for drawing are not available in background mode")
-        {
-            let _ = app.emit("mqa_stage", "Retrying without --background (GPU required)".to_string());
-            out = run_blender_with_timeout(
-                &app,
-                &blender,
-                &["--factory-startup", "--python", &script_path_str],
-                total_timeout_secs,
-                idle_timeout_secs,
-            )
-            .await?;
-            stdout = String::from_utf8_lossy(&out.stdout).to_string();
-            stderr = String::from_utf8_lossy(&out.stderr).to_string();
-        }
-        (out, stdout, stderr)
-    };
+    let args = json!({
+        "addons_dir": addons_dir.to_string_lossy(),
+        "port": wb_port,
+        "asset_type": asset_type_norm,
+        "xobs": abs_xobs.iter().map(|p| p.to_string_lossy().to_string()).collect::<Vec<String>>(),
+    });
 
-    let json_line = find_batch_json_line(&stdout).ok_or_else(|| {
-        let status = out
-            .status
-            .code()
-            .map(|c| format!("exit code {}", c))
-            .unwrap_or_else(|| "terminated".to_string());
-        let stderr_tail = tail_lines(&stderr, 80);
-        let stdout_tail = tail_lines(&stdout, 80);
-        if stderr_tail.is_empty() && stdout_tail.is_empty() {
-            format!("Failed to capture MQA batch report from Blender ({})", status)
-        } else if stderr_tail.is_empty() {
-            format!(
-                "Failed to capture MQA batch report from Blender ({})\n--- stdout (tail) ---\n{}",
-                status, stdout_tail
-            )
-        } else if stdout_tail.is_empty() {
-            format!(
-                "Failed to capture MQA batch report from Blender ({})\n--- stderr (tail) ---\n{}",
-                status, stderr_tail
-            )
-        } else {
-            format!(
-                "Failed to capture MQA batch report from Blender ({})\n--- stderr (tail) ---\n{}\n--- stdout (tail) ---\n{}",
-                status, stderr_tail, stdout_tail
-            )
-        }
-    })?;
+    // Batch can be long-running; scale timeout with number of files.
+    let total_timeout_secs: u64 = 60 * (10 + 5 * (abs_xobs.len() as u64));
+    // No output while importing can be normal; keep this relatively high to avoid false positives.
+    let idle_timeout_secs: u64 = 60 * 15;
 
-    serde_json::from_str::<JsonValue>(&json_line).map_err(|e| e.to_string())
+    mqa_worker_request(app, &launch, args, total_timeout_secs, idle_timeout_secs).await
 }
 
+#[tauri::command]
+async fn mqa_report_from_xobs_batch(
+    app: tauri::AppHandle,
+    xob_paths: Vec<String>,
+    workbench_port: Option<u16>,
+    asset_type: Option<String>,
+) -> Result<JsonValue, String> {
+    spawn_job(app, JobRequest::MqaBatch { xob_paths, workbench_port, asset_type }).wait().await
+}
 
 fn cached_prefab_status() -> PrefabCacheStatus {
-    let path = prefab_index_path();
-    if let Ok(text) = fs::read_to_string(&path) {
-        if let Ok(value) = serde_json::from_str::<JsonValue>(&text) {
-            let generated = value
-                .get("generated")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-            let svn_root = value
-                .get("svn_root")
-                .and_then(|v| v.as_str())
-                .map(|s| s.to_string());
-            let prefab_count = value
-                .get("prefabs")
-                .and_then(|v| v.as_array())
-                .map(|a| a.len())
-                .or_else(|| value.get("name_index").and_then(|v| v.as_object()).map(|o| o.len()))
-                .unwrap_or(0);
-            return PrefabCacheStatus {
-                has_cache: true,
-                cache_path: Some(path.to_string_lossy().to_string()),
-                svn_root,
-                generated,
-                prefab_count,
-            };
-        }
+    let path = prefab_db_path();
+    if !path.is_file() && !prefab_index_path().is_file() {
+        return PrefabCacheStatus::default();
+    }
+    let Ok(db) = prefab_db::open() else { return PrefabCacheStatus::default() };
+    let resource_count = db.count().unwrap_or(0);
+    let svn_root = db.meta("svn_root").ok().flatten();
+    if resource_count == 0 && svn_root.is_none() {
+        return PrefabCacheStatus::default();
+    }
+    PrefabCacheStatus {
+        has_cache: true,
+        cache_path: Some(path.to_string_lossy().to_string()),
+        svn_root,
+        generated: db.meta("generated").ok().flatten(),
+        prefab_count: db.count_ext("et").unwrap_or(0),
+        resource_count,
+        roots: db.roots().unwrap_or_default(),
     }
-    PrefabCacheStatus::default()
 }
 
 fn detect_svn_candidates() -> Vec<PathBuf> {
@@ -4025,16 +1430,21 @@ fn auto_detect_svn_blocking() -> Option<PathBuf> {
 }
 
 fn read_meta_name_field(meta_path: &Path) -> Option<String> {
+    read_meta_fields(meta_path).and_then(|(name, _)| name)
+}
+
+/// `Name` and resource class of a .meta file; the class is empty when the meta has no
+/// `Configurations` entry.
+fn read_meta_fields(meta_path: &Path) -> Option<(Option<String>, String)> {
     if !meta_path.is_file() {
         return None;
     }
-    fs::read_to_string(meta_path).ok().and_then(|text| {
-        let needle = "Name \"";
-        let idx = text.find(needle)?;
-        let rest = &text[idx + needle.len()..];
-        let end_idx = rest.find('"')?;
-        Some(rest[..end_idx].trim().to_string())
-    })
+    let text = fs::read_to_string(meta_path).ok()?;
+    let doc = enfusion_text::parse(&text).ok()?;
+    Some((
+        doc.meta_name().map(|n| n.trim().to_string()),
+        doc.meta_resource_class().unwrap_or_default().to_string(),
+    ))
 }
 
 fn extract_guid(name_value: &str) -> Option<String> {
@@ -4058,280 +1468,252 @@ fn meta_mtime_seconds(meta_path: &Path) -> Option<f64> {
 }
 
 fn update_prefab_cache_with_new_meta(et_path: &Path, meta_path: &Path, name_value: &str) -> Result<(), String> {
+    let mtime = meta_mtime_seconds(meta_path).unwrap_or(0.0);
+    let class = read_meta_fields(meta_path).map(|(_, class)| class).unwrap_or_default();
+    let mut db = prefab_db::open()?;
     let et_str = et_path.to_string_lossy().to_string();
-    let meta_str = meta_path.to_string_lossy().to_string();
-    let key = et_path
-        .file_name()
-        .map(|n| n.to_string_lossy().to_lowercase())
-        .unwrap_or_else(|| et_str.to_lowercase());
-
-    let (svn_root, mut name_index, mut guid_index, mut et_path_index, mut guid_path_index, mut meta_mtime) =
-        match load_existing_prefab_cache() {
-            Some((prev_root, n, g, etp, gpp, mt)) => (prev_root, n, g, etp, gpp, mt),
-            None => (String::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new()),
+    let row = prefab_db::Resource::new(
+        db.root_for_path(&et_str)?,
+        et_str.clone(),
+        meta_path.to_string_lossy().to_string(),
+        name_value.to_string(),
+        class,
+        mtime,
+    );
+    let refs = if ref_graph::scans_references(&row.ext) { ref_graph::read_references(et_path) } else { Vec::new() };
+    db.upsert(&[row])?;
+    db.set_references(&[(et_str, refs)])
+}
+
+/// Brings the index up to date with files a command wrote: `.meta` files are upserted and
+/// text resources get their references re-read.
+fn reindex_written_files(app: &dyn LogSink, written: &[PathBuf]) {
+    for p in written {
+        let p_s = p.to_string_lossy().to_string();
+        let res = if let Some(res_s) = p_s.strip_suffix(".meta") {
+            let Some(name_value) = read_meta_name_field(p) else { continue };
+            update_prefab_cache_with_new_meta(Path::new(res_s), p, &name_value)
+        } else if p.extension().is_some_and(|e| ref_graph::scans_references(&e.to_string_lossy().to_lowercase())) {
+            prefab_db::open().and_then(|mut db| db.set_references(&[(p_s.clone(), ref_graph::read_references(p))]))
+        } else {
+            continue;
         };
-
-    name_index.insert(key.clone(), name_value.to_string());
-    et_path_index.insert(key, et_str.clone());
-    if let Some(guid) = extract_guid(name_value) {
-        guid_index.insert(guid.clone(), name_value.to_string());
-        guid_path_index.insert(guid, et_str);
+        if let Err(err) = res {
+            emit_scan_log(app, "warn", format!("Failed to update prefab cache: {}", err), None, None);
+        }
     }
-    if let Some(m) = meta_mtime_seconds(meta_path) {
-        meta_mtime.insert(meta_str, m);
+}
+
+/// Roots to index: `prefab_roots` when set, otherwise `svn_root` as the base root.
+fn configured_prefab_roots(settings: &AutoSettings) -> Vec<prefab_db::ProjectRoot> {
+    match settings.prefab_roots.as_ref().filter(|r| !r.is_empty()) {
+        Some(roots) => roots.clone(),
+        None => settings
+            .svn_root
+            .iter()
+            .map(|p| prefab_db::ProjectRoot { name: prefab_db::BASE_ROOT.to_string(), path: p.clone(), priority: 0 })
+            .collect(),
     }
+}
 
-    let cache_path = prefab_index_path();
-    let mut payload = json!({
-        "version": 1,
-        "generated": Utc::now().to_rfc3339(),
-        "svn_root": svn_root,
-        "name_index": name_index,
-        "guid_index": guid_index,
-        "et_path_index": et_path_index,
-        "guid_path_index": guid_path_index,
-    });
-    if let Some(obj) = payload.as_object_mut() {
-        obj.insert("meta_mtime".to_string(), json!(meta_mtime));
+/// Queues a full scan when `prefab_db::open` had to replace a corrupt or unmigratable index
+/// with an empty one.
+fn rebuild_recovered_index(app: &tauri::AppHandle) {
+    let Ok(Some((backup, reason))) = prefab_db::open().and_then(|db| db.recovered()) else { return };
+    emit_scan_log(app, "warn", format!("Prefab index {} (kept as {}); rebuilding", reason, backup), None, None);
+    if !configured_prefab_roots(&load_settings()).is_empty() {
+        spawn_job(app.clone(), JobRequest::ScanPrefabRoots { verbose: None });
     }
-    fs::write(
-        &cache_path,
-        serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
-    )
-    .map_err(|e| e.to_string())?;
-    Ok(())
 }
 
-fn load_existing_prefab_cache() -> Option<(String, BTreeMap<String, String>, BTreeMap<String, String>, BTreeMap<String, String>, BTreeMap<String, String>, BTreeMap<String, f64>)> {
-    let fp = prefab_index_path();
-    let txt = fs::read_to_string(fp).ok()?;
-    let v: JsonValue = serde_json::from_str(&txt).ok()?;
-    let svn_root = v.get("svn_root").and_then(|x| x.as_str()).unwrap_or("").to_string();
-    let mut name_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut guid_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut et_path_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut guid_path_index: BTreeMap<String, String> = BTreeMap::new();
-    let mut meta_mtime: BTreeMap<String, f64> = BTreeMap::new();
-
-    if let Some(obj) = v.get("name_index").and_then(|x| x.as_object()) {
-        for (k, vv) in obj {
-            if let Some(s) = vv.as_str() {
-                name_index.insert(k.to_string(), s.to_string());
-            }
-        }
+/// Points the index watcher at the configured roots, or stops it when watching is turned off.
+fn restart_index_watcher(app: &dyn LogSink, settings: &AutoSettings) {
+    if settings.watch_index == Some(false) {
+        index_watcher::stop();
+        return;
     }
-    if let Some(obj) = v.get("guid_index").and_then(|x| x.as_object()) {
-        for (k, vv) in obj {
-            if let Some(s) = vv.as_str() {
-                guid_index.insert(k.to_string(), s.to_string());
-            }
-        }
+    if let Err(err) = index_watcher::restart(&configured_prefab_roots(settings)) {
+        emit_scan_log(app, "warn", err, None, None);
     }
-    if let Some(obj) = v.get("et_path_index").and_then(|x| x.as_object()) {
-        for (k, vv) in obj {
-            if let Some(s) = vv.as_str() {
-                et_path_index.insert(k.to_string(), s.to_string());
-            }
+}
+
+fn canonical_dir(path: &str) -> PathBuf {
+    let pb = PathBuf::from(path);
+    pb.canonicalize().unwrap_or(pb)
+}
+
+/// Folders never indexed (nor watched) inside a root.
+const INDEX_EXCLUDE_DIRS: [&str; 10] = [
+    ".svn", "node_modules", ".git", ".idea", ".vscode", "Library", "Temp", "Logs", "obj", "bin",
+];
+
+/// Metas checked per parallel batch; progress and cancellation are reported between batches.
+const SCAN_CHUNK: usize = 512;
+
+/// Every `.meta` file under `dir`, sorted. Subfolders are walked in parallel (one task each,
+/// picked up by whichever worker is idle); excluded folders and `skip_dirs` are not entered.
+fn collect_meta_files(dir: &Path, skip_dirs: &[PathBuf], is_cancelled: &(dyn Fn() -> bool + Sync)) -> Result<Vec<PathBuf>, String> {
+    fn walk(dir: &Path, skip_dirs: &[PathBuf], is_cancelled: &(dyn Fn() -> bool + Sync)) -> Vec<PathBuf> {
+        if is_cancelled() {
+            return Vec::new();
         }
-    }
-    if let Some(obj) = v.get("guid_path_index").and_then(|x| x.as_object()) {
-        for (k, vv) in obj {
-            if let Some(s) = vv.as_str() {
-                guid_path_index.insert(k.to_string(), s.to_string());
+        let Ok(entries) = fs::read_dir(dir) else { return Vec::new() };
+        let mut metas: Vec<PathBuf> = Vec::new();
+        let mut subdirs: Vec<PathBuf> = Vec::new();
+        for entry in entries.filter_map(Result::ok) {
+            let Ok(file_type) = entry.file_type() else { continue };
+            let name = entry.file_name().to_string_lossy().to_lowercase();
+            if file_type.is_dir() {
+                let path = entry.path();
+                if !INDEX_EXCLUDE_DIRS.iter().any(|d| name.eq_ignore_ascii_case(d)) && !skip_dirs.contains(&path) {
+                    subdirs.push(path);
+                }
+            } else if file_type.is_file() && name.ends_with(".meta") && name != ".meta" {
+                metas.push(entry.path());
             }
         }
+        let nested: Vec<Vec<PathBuf>> = subdirs.par_iter().map(|d| walk(d, skip_dirs, is_cancelled)).collect();
+        metas.extend(nested.into_iter().flatten());
+        metas
     }
-    if let Some(obj) = v.get("meta_mtime").and_then(|x| x.as_object()) {
-        for (k, vv) in obj {
-            if let Some(n) = vv.as_f64() {
-                meta_mtime.insert(k.to_string(), n);
-            }
-        }
+    let mut metas = walk(dir, skip_dirs, is_cancelled);
+    if is_cancelled() {
+        return Err("Cancelled".into());
+    }
+    metas.sort();
+    Ok(metas)
+}
+
+/// Index row for `meta_path`, or None when its mtime matches the previous scan. Text
+/// resources also come with their references, and count as changed when only their content
+/// changed (new references do not touch the .meta).
+fn index_meta_file(
+    root: &prefab_db::ProjectRoot,
+    canonical_root: &Path,
+    meta_path: &Path,
+    prev_mtimes: &HashMap<String, f64>,
+) -> Option<(prefab_db::Resource, Option<Vec<prefab_db::Reference>>)> {
+    let meta_str = meta_path.to_string_lossy().to_string();
+    let res_str = meta_str[..meta_str.len() - ".meta".len()].to_string();
+    let res_path = PathBuf::from(&res_str);
+    let scan_refs = res_path
+        .extension()
+        .is_some_and(|e| ref_graph::scans_references(&e.to_string_lossy().to_lowercase()));
+    let mut mtime = meta_mtime_seconds(meta_path).unwrap_or(0.0);
+    if scan_refs {
+        mtime = mtime.max(meta_mtime_seconds(&res_path).unwrap_or(0.0));
+    }
+    let prev_m = prev_mtimes.get(&meta_str).copied().unwrap_or(-1.0);
+    if (mtime - prev_m).abs() < f64::EPSILON {
+        return None;
     }
-    Some((svn_root, name_index, guid_index, et_path_index, guid_path_index, meta_mtime))
+
+    let rel_path = res_path
+        .strip_prefix(canonical_root)
+        .map(|p| p.to_string_lossy().replace('\\', "/"))
+        .unwrap_or_else(|_| res_str.clone());
+    let (name_value, class) = read_meta_fields(meta_path).unwrap_or_default();
+    let name_value = name_value.unwrap_or(rel_path);
+    let refs = scan_refs.then(|| ref_graph::read_references(&res_path));
+    Some((prefab_db::Resource::new(root.name.clone(), res_str, meta_str, name_value, class, mtime), refs))
 }
 
+/// Rescans one root into the index. Folders in `skip_dirs` (other roots nested inside this
+/// one) are left to their own root.
+///
+/// A first pass collects every .meta so progress has a real total; the list is then checked
+/// and parsed in parallel batches, keeping its sorted order, so the index is written the
+/// same way on every run.
 fn build_prefab_index(
-    svn_root: &Path,
+    root: &prefab_db::ProjectRoot,
+    skip_dirs: &[PathBuf],
     verbose: bool,
     mut on_log: impl FnMut(&str, String, Option<(usize, usize)>),
+    is_cancelled: impl Fn() -> bool + Sync,
 ) -> Result<(usize, PathBuf), String> {
-    if !svn_root.is_dir() {
-        return Err("SVN root is not a directory".into());
+    if !Path::new(&root.path).is_dir() {
+        return Err(format!("Root '{}' is not a directory: {}", root.name, root.path));
     }
-    let canonical_root = svn_root
-        .canonicalize()
-        .unwrap_or_else(|_| svn_root.to_path_buf());
+    let canonical_root = canonical_dir(&root.path);
+    let root_s = canonical_root.to_string_lossy().to_string();
+    let root = prefab_db::ProjectRoot { path: root_s.clone(), ..root.clone() };
 
     on_log(
         "info",
-        format!("SVN root: {}", canonical_root.to_string_lossy()),
+        format!("Root '{}' (priority {}): {}", root.name, root.priority, root_s),
         None,
     );
-    on_log("info", "Scanning for .et.meta files...".to_string(), None);
-
-    // Load previous cache for incremental update
-    let (mut name_index, mut guid_index, mut et_path_index, mut guid_path_index, mut meta_mtime) =
-        match load_existing_prefab_cache() {
-            Some((prev_root, n, g, etp, gpp, mt)) => {
-                if !prev_root.is_empty() && prev_root != canonical_root.to_string_lossy() {
-                    on_log("info", "Existing cache svn_root differs; rebuilding indices".to_string(), None);
-                    (BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new())
-                } else {
-                    on_log(
-                        "info",
-                        format!("Incremental scan: loaded cache (entries={})", n.len()),
-                        None,
-                    );
-                    (n, g, etp, gpp, mt)
-                }
-            }
-            None => (BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new(), BTreeMap::new()),
-        };
+    on_log("info", "Counting .meta files...".to_string(), None);
+    let metas = collect_meta_files(&canonical_root, skip_dirs, &is_cancelled)?;
+    let total_metas = metas.len();
+    on_log("info", format!("Found {} .meta files", total_metas), Some((0, total_metas)));
 
-    // Track current files to remove deletions
-    let mut present_keys: HashSet<String> = HashSet::new();
-    let mut present_et_paths: HashSet<String> = HashSet::new();
-    let mut touched = 0usize;
-    let mut updated = 0usize;
-
-    const EXCLUDE_DIRS: [&str; 10] = [
-        ".svn", "node_modules", ".git", ".idea", ".vscode", "Library", "Temp", "Logs", "obj", "bin",
-    ];
+    // Load previous index for incremental update
+    let mut db = prefab_db::open()?;
+    if db.set_root(&root)? {
+        on_log("info", format!("Root '{}' moved; rebuilding its entries", root.name), None);
+    }
+    let meta_mtime = db.meta_mtimes(&root.name)?;
+    if !meta_mtime.is_empty() {
+        on_log("info", format!("Incremental scan: loaded index (entries={})", meta_mtime.len()), None);
+    }
 
-    let walker = WalkDir::new(&canonical_root)
-        .follow_links(false)
-        .into_iter()
-        .filter_entry(|e| {
-            if !e.file_type().is_dir() {
-                return true;
-            }
-            let name = e.file_name().to_string_lossy();
-            if EXCLUDE_DIRS.iter().any(|d| name.eq_ignore_ascii_case(d)) {
-                return false;
+    // Track current files to remove deletions
+    let present_metas: HashSet<String> = metas.iter().map(|p| p.to_string_lossy().to_string()).collect();
+    let mut changed: Vec<prefab_db::Resource> = Vec::new();
+    let mut changed_refs: Vec<(String, Vec<prefab_db::Reference>)> = Vec::new();
+
+    let mut done = 0usize;
+    for chunk in metas.chunks(SCAN_CHUNK) {
+        if is_cancelled() {
+            return Err("Cancelled".into());
+        }
+        let rows: Vec<_> = chunk
+            .par_iter()
+            .map(|meta_path| index_meta_file(&root, &canonical_root, meta_path, &meta_mtime))
+            .collect();
+        for (row, refs) in rows.into_iter().flatten() {
+            if verbose {
+                on_log("debug", format!("Updated {} -> {}", row.file_name, row.name_value), None);
             }
-            true
-        });
-
-    for entry in walker.filter_map(Result::ok) {
-        if !entry.file_type().is_file() {
-            continue;
-        }
-        let name = entry.file_name().to_string_lossy();
-        if !name.to_lowercase().ends_with(".et.meta") {
-            continue;
-        }
-        touched += 1;
-
-        // Convert meta path -> et path
-        let meta_path = entry.into_path();
-        let meta_str = meta_path.to_string_lossy().to_string();
-        let et_str = meta_str.trim_end_matches(".meta").to_string();
-        let et_path = PathBuf::from(&et_str);
-
-        let key = et_path
-            .file_name()
-            .map(|n| n.to_string_lossy().to_lowercase())
-            .unwrap_or_else(|| et_str.to_lowercase());
-
-        present_keys.insert(key.clone());
-        present_et_paths.insert(et_str.clone());
-
-        let mtime = meta_mtime_seconds(&meta_path).unwrap_or(0.0);
-        let prev_m = meta_mtime.get(&meta_str).copied().unwrap_or(-1.0);
-        if (mtime - prev_m).abs() < f64::EPSILON {
-            // unchanged
-            if !et_path_index.contains_key(&key) {
-                et_path_index.insert(key.clone(), et_str.clone());
+            if let Some(refs) = refs {
+                changed_refs.push((row.abs_path.clone(), refs));
             }
-            continue;
-        }
-
-        // Changed/new: parse and update
-        let rel_path = et_path
-            .strip_prefix(&canonical_root)
-            .unwrap_or(&et_path)
-            .to_string_lossy()
-            .replace('\\', "/");
-        let name_value = read_meta_name_field(&meta_path).unwrap_or_else(|| rel_path.clone());
-        name_index.insert(key.clone(), name_value.clone());
-        et_path_index.insert(key.clone(), et_str.clone());
-        if let Some(guid) = extract_guid(&name_value) {
-            guid_index.insert(guid.clone(), name_value.clone());
-            guid_path_index.insert(guid.clone(), et_str.clone());
-        }
-        meta_mtime.insert(meta_str.clone(), mtime);
-        updated += 1;
-
-        if verbose {
-            on_log("debug", format!("Updated {} -> {}", key, name_value), None);
-        } else if updated == 1 || updated % 200 == 0 {
-            on_log("info", format!("Indexing changes... {}", updated), None);
-        }
-
-        // Keep progress indeterminate (faster: no pre-count)
-        if touched == 1 || touched % 1000 == 0 {
-            on_log("info", format!("Scanning... {} meta", touched), Some((touched, touched + 1)));
+            changed.push(row);
         }
+        done += chunk.len();
+        on_log(
+            "info",
+            format!("Scanning... {}/{} meta ({} changed)", done, total_metas, changed.len()),
+            Some((done, total_metas)),
+        );
     }
 
-    // Remove deleted keys
-    let before = name_index.len();
-    name_index.retain(|k, _| present_keys.contains(k));
-    et_path_index.retain(|k, _| present_keys.contains(k));
-    let removed_keys = before.saturating_sub(name_index.len());
-
-    // Remove guid entries pointing to deleted paths
-    guid_path_index.retain(|_, p| present_et_paths.contains(p));
-    guid_index.retain(|g, _| guid_path_index.contains_key(g));
-
-    // Remove stale meta_mtime entries
-    meta_mtime.retain(|mp, _| {
-        // only keep if meta still exists
-        PathBuf::from(mp).is_file()
-    });
-
-    on_log(
-        "info",
-        format!("Scan summary: meta_seen={}, updated={}, removed_keys={}", touched, updated, removed_keys),
-        None,
-    );
-
-    let cache_path = prefab_index_path();
+    let cache_path = prefab_db_path();
     on_log(
         "info",
-        format!("Writing cache: {}", cache_path.to_string_lossy()),
+        format!("Writing index: {}", cache_path.to_string_lossy()),
         None,
     );
-    let mut payload = json!({
-        "version": 1,
-        "generated": Utc::now().to_rfc3339(),
-        "svn_root": canonical_root.to_string_lossy(),
-        "name_index": name_index,
-        "guid_index": guid_index,
-        "et_path_index": et_path_index,
-        "guid_path_index": guid_path_index,
-    });
-    if !meta_mtime.is_empty() {
-        if let Some(obj) = payload.as_object_mut() {
-            obj.insert("meta_mtime".to_string(), json!(meta_mtime));
-        }
+    db.upsert(&changed)?;
+    db.set_references(&changed_refs)?;
+    let removed = db.remove_missing(&root.name, &present_metas)?;
+    let generated = Utc::now().to_rfc3339();
+    db.mark_root_scanned(&root.name, &generated)?;
+    if root.name == prefab_db::BASE_ROOT {
+        db.set_meta("svn_root", &root_s)?;
     }
-    fs::write(
-        &cache_path,
-        serde_json::to_string_pretty(&payload).map_err(|e| e.to_string())?,
-    )
-    .map_err(|e| e.to_string())?;
+    db.set_meta("generated", &generated)?;
+
     on_log(
         "info",
-        format!("Scan complete. Indexed {} prefabs", payload
-            .get("name_index")
-            .and_then(|v| v.as_object())
-            .map(|o| o.len())
-            .unwrap_or(0)),
+        format!("Scan summary: meta_seen={}, updated={}, removed={}", total_metas, changed.len(), removed),
         None,
     );
-    Ok((name_index.len(), cache_path))
+    let total = db.count()?;
+    let prefabs = db.count_ext("et")?;
+    on_log("info", format!("Scan complete. Indexed {} resources ({} prefabs)", total, prefabs), None);
+    Ok((total, cache_path))
 }
 
 #[derive(Serialize, Deserialize, Default, Clone)]
@@ -4341,12 +1723,40 @@ struct PrefabCacheStatus {
     svn_root: Option<String>,
     generated: Option<String>,
     prefab_count: usize,
+    /// Every indexed resource, prefabs included.
+    resource_count: usize,
+    roots: Vec<prefab_db::RootInfo>,
 }
 
 #[derive(Serialize)]
 struct PrefabScanResult {
     total_entries: usize,
     cache_path: String,
+    guid_collisions: usize,
+    duplicate_names: usize,
+}
+
+#[derive(Serialize)]
+struct PrefabRootsScanResult {
+    total_entries: usize,
+    cache_path: String,
+    roots: Vec<prefab_db::RootInfo>,
+    guid_collisions: usize,
+    duplicate_names: usize,
+}
+
+/// Logs GUID collisions and ambiguous prefab names after a scan; returns their counts.
+fn report_index_conflicts(app: &dyn LogSink) -> (usize, usize) {
+    match prefab_db::open().and_then(|db| guid_conflicts::find(&db)) {
+        Ok(c) => {
+            guid_conflicts::log_conflicts(&c, |level, msg| emit_scan_log(app, level, msg, None, None));
+            (c.guid_collisions.len(), c.duplicate_names.len())
+        }
+        Err(err) => {
+            emit_scan_log(app, "warn", format!("Conflict check failed: {}", err), None, None);
+            (0, 0)
+        }
+    }
 }
 
 #[tauri::command]
@@ -4356,6 +1766,130 @@ fn remember_svn_root(path: Option<String>) -> Result<(), String> {
     save_settings(&settings)
 }
 
+#[tauri::command]
+fn get_extract_cache_status() -> Result<extract_cache::ExtractCacheStatus, String> {
+    Ok(extract_cache::status())
+}
+
+/// Clears the FBX extraction cache, or only the entry for `fbx_path` when given.
+#[tauri::command]
+fn clear_extract_cache(fbx_path: Option<String>) -> Result<usize, String> {
+    extract_cache::clear(fbx_path.as_deref().map(Path::new))
+}
+
+/// Fuzzy search over the resource index by file name, path or GUID, best match first.
+/// `types` limits hits to extensions (`et`, `xob`) or resource classes; default is all.
+#[tauri::command]
+async fn search_prefabs(
+    query: String,
+    limit: Option<usize>,
+    types: Option<Vec<String>>,
+) -> Result<Vec<prefab_db::SearchHit>, String> {
+    let limit = limit.unwrap_or(50).min(1000);
+    let types = types.unwrap_or_default();
+    tauri::async_runtime::spawn_blocking(move || prefab_db::open()?.search(&query, limit, &types))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Resources that reference `guid`, following references back up to `depth` levels
+/// (default 1, at most ref_graph::MAX_DEPTH).
+#[tauri::command]
+async fn find_references(guid: String, depth: Option<usize>) -> Result<Vec<ref_graph::GraphNode>, String> {
+    let guid = guid.trim().trim_start_matches('{').trim_end_matches('}').to_string();
+    tauri::async_runtime::spawn_blocking(move || ref_graph::references(&prefab_db::open()?, &guid, depth.unwrap_or(1)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Resources that the resource with `guid` references, up to `depth` levels deep.
+#[tauri::command]
+async fn find_dependencies(guid: String, depth: Option<usize>) -> Result<Vec<ref_graph::GraphNode>, String> {
+    let guid = guid.trim().trim_start_matches('{').trim_end_matches('}').to_string();
+    tauri::async_runtime::spawn_blocking(move || ref_graph::dependencies(&prefab_db::open()?, &guid, depth.unwrap_or(1)))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Indexed resource (of any type) whose meta carries `guid`, e.g. the .xob a prefab points to.
+#[tauri::command]
+fn resolve_guid(guid: String) -> Result<Option<prefab_db::Resource>, String> {
+    let guid = guid.trim().trim_start_matches('{').trim_end_matches('}').to_uppercase();
+    Ok(prefab_db::open()?.by_guid(&guid))
+}
+
+/// One page of index entries matching `query` (prefabs unless `types` says otherwise), one
+/// per project path, ordered by path.
+#[tauri::command]
+async fn query_prefab_index(query: prefab_db::IndexQuery) -> Result<prefab_db::IndexPage, String> {
+    tauri::async_runtime::spawn_blocking(move || prefab_db::open()?.query(&query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Subfolders of `query.folder` that hold matching entries, with their counts.
+#[tauri::command]
+async fn list_prefab_folders(query: prefab_db::IndexQuery) -> Result<Vec<prefab_db::IndexFolder>, String> {
+    tauri::async_runtime::spawn_blocking(move || prefab_db::open()?.folders(&query))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+#[derive(Serialize)]
+struct PrefabEntryDetails {
+    #[serde(flatten)]
+    resource: prefab_db::Resource,
+    /// `mtime` as RFC 3339.
+    modified: Option<String>,
+    /// Whether the resource and its .meta are still on disk.
+    exists: bool,
+    meta_exists: bool,
+    /// Priority of the entry's root.
+    priority: i32,
+    /// Entries at the same project path in lower-priority roots.
+    overrides: Vec<prefab_db::Resource>,
+    /// Number of indexed files that reference the entry's GUID.
+    referenced_by: usize,
+}
+
+/// Details of the entry for `key`: a GUID (braced or not), an absolute resource or .meta
+/// path, or a project path.
+fn prefab_entry_details(db: &prefab_db::PrefabDb, key: &str) -> Result<Option<PrefabEntryDetails>, String> {
+    let key = key.trim();
+    let bare = key.trim_start_matches('{').trim_end_matches('}');
+    let resource = match extract_guid(&format!("{{{}}}", bare)) {
+        Some(guid) if bare.len() == 16 => db.by_guid(&guid),
+        _ => db
+            .by_abs_path(key.strip_suffix(".meta").unwrap_or(key))
+            .or_else(|| db.by_rel_path(key)),
+    };
+    let Some(resource) = resource else { return Ok(None) };
+    let mut same_path = db.all_at_rel_path(&resource.rel_path)?;
+    same_path.retain(|r| r.abs_path != resource.abs_path);
+    let priority = db.roots()?.into_iter().find(|r| r.root.name == resource.root).map_or(0, |r| r.root.priority);
+    let referenced_by = match &resource.guid {
+        Some(guid) => db.referencing(guid)?.len(),
+        None => 0,
+    };
+    Ok(Some(PrefabEntryDetails {
+        modified: chrono::DateTime::from_timestamp(resource.mtime as i64, 0).map(|t| t.to_rfc3339()),
+        exists: Path::new(&resource.abs_path).is_file(),
+        meta_exists: Path::new(&resource.meta_path).is_file(),
+        priority,
+        overrides: same_path,
+        referenced_by,
+        resource,
+    }))
+}
+
+/// Full details of one index entry, looked up by GUID, absolute path or project path.
+#[tauri::command]
+async fn get_prefab_entry(key: String) -> Result<Option<PrefabEntryDetails>, String> {
+    tauri::async_runtime::spawn_blocking(move || prefab_entry_details(&prefab_db::open()?, &key))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
 #[tauri::command]
 fn get_prefab_cache_status() -> Result<PrefabCacheStatus, String> {
     Ok(cached_prefab_status())
@@ -4376,40 +1910,324 @@ async fn auto_detect_svn_root() -> Result<Option<String>, String> {
     }
 }
 
-#[tauri::command]
-async fn scan_prefab_index(app: tauri::AppHandle, svn_root: String, verbose: Option<bool>) -> Result<PrefabScanResult, String> {
+/// Scans `svn_root` alone. It updates the configured root with that path, or becomes the
+/// base root if no root matches; other roots keep their entries.
+async fn run_prefab_scan(app: Arc<dyn LogSink>, svn_root: String, verbose: Option<bool>) -> Result<PrefabScanResult, String> {
     let path = PathBuf::from(&svn_root);
     if !path.is_dir() {
         let msg = format!("SVN root does not exist or is not a directory: {}", svn_root);
-        emit_scan_log(&app, "error", msg.clone(), None, None);
+        emit_scan_log(app.as_ref(), "error", msg.clone(), None, None);
         return Err(msg);
     }
-    let path_for_scan = path.clone();
-    let app_for_scan = app.clone();
-    let verbose = verbose.unwrap_or(false);
-    emit_scan_log(&app, "info", "Starting prefab scan...", Some(0), None);
-    let (total, cache_path) =
-        tauri::async_runtime::spawn_blocking(move || {
-            let mut last_progress: Option<(usize, usize)> = None;
-            build_prefab_index(&path_for_scan, verbose, |level, msg, prog| {
-                if let Some((c, t)) = prog {
-                    last_progress = Some((c, t));
-                }
-                let (cur, tot) = match last_progress {
-                    Some((c, t)) => (Some(c), Some(t)),
-                    None => (None, None),
-                };
-                emit_scan_log(&app_for_scan, level, msg, cur, tot);
-            })
-        })
-            .await
-            .map_err(|e| e.to_string())??;
-    remember_svn_root(Some(path.to_string_lossy().to_string())).ok();
-    emit_scan_log(&app, "info", "Prefab scan finished", None, None);
+    let canonical = canonical_dir(&svn_root);
+    let roots = configured_prefab_roots(&load_settings());
+    let root = roots
+        .iter()
+        .find(|r| canonical_dir(&r.path) == canonical)
+        .cloned()
+        .unwrap_or_else(|| prefab_db::ProjectRoot {
+            name: prefab_db::BASE_ROOT.to_string(),
+            path: canonical.to_string_lossy().to_string(),
+            priority: roots.iter().find(|r| r.name == prefab_db::BASE_ROOT).map_or(0, |r| r.priority),
+        });
+    let skip_dirs: Vec<PathBuf> = roots.iter().filter(|r| r.name != root.name).map(|r| canonical_dir(&r.path)).collect();
+    emit_scan_log(app.as_ref(), "info", "Starting prefab scan...", Some(0), None);
+    let (total, cache_path) = run_root_scan(app.clone(), root.clone(), skip_dirs, verbose.unwrap_or(false)).await?;
+    if root.name == prefab_db::BASE_ROOT {
+        remember_svn_root(Some(path.to_string_lossy().to_string())).ok();
+    }
+    if roots.len() <= 1 {
+        prefab_db::open()?.clear_recovered()?;
+    }
+    let (guid_collisions, duplicate_names) = report_index_conflicts(app.as_ref());
+    restart_index_watcher(app.as_ref(), &load_settings());
+    emit_scan_log(app.as_ref(), "info", "Prefab scan finished", None, None);
     Ok(PrefabScanResult {
         total_entries: total,
         cache_path: cache_path.to_string_lossy().to_string(),
+        guid_collisions,
+        duplicate_names,
+    })
+}
+
+/// Scans every configured root, highest priority first, and drops indexed roots that are no
+/// longer configured.
+async fn run_prefab_roots_scan(app: Arc<dyn LogSink>, verbose: Option<bool>) -> Result<PrefabRootsScanResult, String> {
+    let roots = configured_prefab_roots(&load_settings());
+    if roots.is_empty() {
+        let msg = "No project roots configured".to_string();
+        emit_scan_log(app.as_ref(), "error", msg.clone(), None, None);
+        return Err(msg);
+    }
+    let db = prefab_db::open()?;
+    for stale in db.roots()?.into_iter().filter(|s| !roots.iter().any(|r| r.name == s.root.name)) {
+        let removed = db.remove_root(&stale.root.name)?;
+        emit_scan_log(app.as_ref(), "info", format!("Removed root '{}' ({} entries)", stale.root.name, removed), None, None);
+    }
+    drop(db);
+
+    let mut ordered = roots.clone();
+    ordered.sort_by_key(|r| std::cmp::Reverse(r.priority));
+    emit_scan_log(app.as_ref(), "info", format!("Starting prefab scan of {} root(s)...", ordered.len()), Some(0), None);
+    let mut total = 0;
+    let mut cache_path = prefab_db_path();
+    for root in ordered {
+        if app.is_cancelled() {
+            return Err("Cancelled".into());
+        }
+        if !Path::new(&root.path).is_dir() {
+            emit_scan_log(app.as_ref(), "warn", format!("Skipping root '{}': not a directory: {}", root.name, root.path), None, None);
+            continue;
+        }
+        let skip_dirs: Vec<PathBuf> = roots.iter().filter(|r| r.name != root.name).map(|r| canonical_dir(&r.path)).collect();
+        (total, cache_path) = run_root_scan(app.clone(), root, skip_dirs, verbose.unwrap_or(false)).await?;
+    }
+    prefab_db::open()?.clear_recovered()?;
+    let (guid_collisions, duplicate_names) = report_index_conflicts(app.as_ref());
+    restart_index_watcher(app.as_ref(), &load_settings());
+    emit_scan_log(app.as_ref(), "info", "Prefab scan finished", None, None);
+    Ok(PrefabRootsScanResult {
+        total_entries: total,
+        cache_path: cache_path.to_string_lossy().to_string(),
+        roots: prefab_db::open()?.roots()?,
+        guid_collisions,
+        duplicate_names,
+    })
+}
+
+async fn run_root_scan(
+    app: Arc<dyn LogSink>,
+    root: prefab_db::ProjectRoot,
+    skip_dirs: Vec<PathBuf>,
+    verbose: bool,
+) -> Result<(usize, PathBuf), String> {
+    tauri::async_runtime::spawn_blocking(move || {
+        let mut last_progress: Option<(usize, usize)> = None;
+        build_prefab_index(&root, &skip_dirs, verbose, |level, msg, prog| {
+            if let Some((c, t)) = prog {
+                last_progress = Some((c, t));
+            }
+            let (cur, tot) = match last_progress {
+                Some((c, t)) => (Some(c), Some(t)),
+                None => (None, None),
+            };
+            emit_scan_log(app.as_ref(), level, msg, cur, tot);
+        }, || app.is_cancelled())
     })
+    .await
+    .map_err(|e| e.to_string())?
+}
+
+/// Runs the prefab scan as a job and waits for it, so it queues behind other jobs and can be
+/// cancelled with `cancel_job`.
+#[tauri::command]
+async fn scan_prefab_index(app: tauri::AppHandle, svn_root: String, verbose: Option<bool>) -> Result<JsonValue, String> {
+    spawn_job(app, JobRequest::ScanPrefabIndex { svn_root, verbose }).wait().await
+}
+
+/// Runs the scan of every configured project root as a job and waits for it.
+#[tauri::command]
+async fn scan_prefab_roots(app: tauri::AppHandle, verbose: Option<bool>) -> Result<JsonValue, String> {
+    spawn_job(app, JobRequest::ScanPrefabRoots { verbose }).wait().await
+}
+
+/// Checks every `{GUID}path` reference in the indexed text resources and reports unknown
+/// GUIDs, stale paths and missing targets. Runs as a job.
+#[tauri::command]
+async fn validate_references(app: tauri::AppHandle) -> Result<JsonValue, String> {
+    spawn_job(app, JobRequest::ValidateReferences).wait().await
+}
+
+/// GUIDs shared by several resources and prefab file names used more than once.
+#[tauri::command]
+async fn find_index_conflicts() -> Result<guid_conflicts::IndexConflicts, String> {
+    tauri::async_runtime::spawn_blocking(|| guid_conflicts::find(&prefab_db::open()?))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Gives a copied resource a new GUID and fixes the references to it in its folder (or
+/// `args.subtree`). With `dry_run` the changes come back as a write plan.
+#[tauri::command]
+async fn regenerate_guid(
+    app: tauri::AppHandle,
+    args: guid_conflicts::RegenerateGuidArgs,
+) -> Result<guid_conflicts::RegenerateGuidResult, String> {
+    tauri::async_runtime::spawn_blocking(move || guid_conflicts::regenerate_guid(&app, args))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Moves or renames a resource with its .meta (and .fbx/.txo for a .xob) and fixes the
+/// references to it. With `dry_run` only the moves and diffs are returned.
+#[tauri::command]
+async fn move_resource(
+    app: tauri::AppHandle,
+    args: resource_move::MoveResourceArgs,
+) -> Result<resource_move::MoveResourceResult, String> {
+    tauri::async_runtime::spawn_blocking(move || resource_move::move_resource(&app, args))
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+/// Configured project roots, highest priority first, with their index counts.
+#[tauri::command]
+fn get_prefab_roots() -> Result<Vec<prefab_db::RootInfo>, String> {
+    let stored = prefab_db::open()?.roots()?;
+    let mut roots: Vec<prefab_db::RootInfo> = configured_prefab_roots(&load_settings())
+        .into_iter()
+        .map(|root| {
+            let s = stored.iter().find(|s| s.root.name == root.name && canonical_dir(&s.root.path) == canonical_dir(&root.path));
+            prefab_db::RootInfo {
+                generated: s.and_then(|s| s.generated.clone()),
+                count: s.map_or(0, |s| s.count),
+                root,
+            }
+        })
+        .collect();
+    roots.sort_by_key(|r| std::cmp::Reverse(r.root.priority));
+    Ok(roots)
+}
+
+/// Replaces the configured project roots. Roots that were removed are dropped from the index
+/// right away; new or moved roots are indexed by the next `scan_prefab_roots`.
+#[tauri::command]
+fn set_prefab_roots(app: tauri::AppHandle, roots: Vec<prefab_db::ProjectRoot>) -> Result<Vec<prefab_db::RootInfo>, String> {
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut cleaned: Vec<prefab_db::ProjectRoot> = Vec::new();
+    for r in roots {
+        let name = r.name.trim().to_string();
+        if name.is_empty() || r.path.trim().is_empty() {
+            return Err("Every root needs a name and a path".into());
+        }
+        if !seen.insert(name.to_lowercase()) {
+            return Err(format!("Duplicate root name: {}", name));
+        }
+        cleaned.push(prefab_db::ProjectRoot { name, path: r.path.trim().to_string(), priority: r.priority });
+    }
+    let mut settings = load_settings();
+    settings.prefab_roots = if cleaned.is_empty() { None } else { Some(cleaned) };
+    save_settings(&settings)?;
+
+    let configured = configured_prefab_roots(&settings);
+    let db = prefab_db::open()?;
+    for stored in db.roots()? {
+        match configured.iter().find(|r| r.name == stored.root.name) {
+            Some(r) => {
+                let path = canonical_dir(&r.path).to_string_lossy().to_string();
+                db.set_root(&prefab_db::ProjectRoot { path, ..r.clone() })?;
+            }
+            None => {
+                db.remove_root(&stored.root.name)?;
+            }
+        }
+    }
+    drop(db);
+    restart_index_watcher(&app, &settings);
+    get_prefab_roots()
+}
+
+#[tauri::command]
+fn get_index_watcher_status() -> Result<index_watcher::WatcherStatus, String> {
+    Ok(index_watcher::status())
+}
+
+/// Turns the index watcher on or off and remembers the choice in the settings.
+#[tauri::command]
+fn set_index_watcher(app: tauri::AppHandle, enabled: bool) -> Result<index_watcher::WatcherStatus, String> {
+    let mut settings = load_settings();
+    settings.watch_index = Some(enabled);
+    save_settings(&settings)?;
+    restart_index_watcher(&app, &settings);
+    Ok(index_watcher::status())
+}
+
+/// Long operations that can run as background jobs; `kind` selects the operation.
+#[derive(Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+enum JobRequest {
+    ScanPrefabIndex { svn_root: String, verbose: Option<bool> },
+    ScanPrefabRoots { verbose: Option<bool> },
+    ValidateReferences,
+    MqaBatch { xob_paths: Vec<String>, workbench_port: Option<u16>, asset_type: Option<String> },
+    PrefabdstBuild(PrefabDstBuildArgs),
+    AutosocketBatch(autosocket_batch::BatchArgs),
+}
+
+fn spawn_job(app: tauri::AppHandle, request: JobRequest) -> jobs::JobTicket {
+    let forward: Arc<dyn LogSink> = Arc::new(app);
+    match request {
+        JobRequest::ScanPrefabIndex { svn_root, verbose } => {
+            let label = format!("Prefab scan: {}", svn_root);
+            jobs::spawn("scan_prefab_index", label, false, forward, move |sink| async move {
+                let res = run_prefab_scan(sink, svn_root, verbose).await?;
+                serde_json::to_value(res).map_err(|e| e.to_string())
+            })
+        }
+        JobRequest::ScanPrefabRoots { verbose } => {
+            jobs::spawn("scan_prefab_roots", "Prefab scan: all roots".to_string(), false, forward, move |sink| async move {
+                let res = run_prefab_roots_scan(sink, verbose).await?;
+                serde_json::to_value(res).map_err(|e| e.to_string())
+            })
+        }
+        JobRequest::ValidateReferences => {
+            jobs::spawn("validate_references", "Validate references".to_string(), false, forward, move |sink| async move {
+                let res = tauri::async_runtime::spawn_blocking(move || {
+                    ref_validate::validate(&prefab_db::open()?, sink.as_ref())
+                })
+                .await
+                .map_err(|e| e.to_string())??;
+                serde_json::to_value(res).map_err(|e| e.to_string())
+            })
+        }
+        JobRequest::MqaBatch { xob_paths, workbench_port, asset_type } => {
+            let label = format!("MQA: {} file(s)", xob_paths.len());
+            jobs::spawn("mqa_batch", label, true, forward, move |sink| async move {
+                mqa_report_batch(sink.as_ref(), xob_paths, workbench_port, asset_type).await
+            })
+        }
+        JobRequest::PrefabdstBuild(args) => {
+            let label = format!("PrefabDST: {} model(s)", args.model_files.len());
+            jobs::spawn("prefabdst_build", label, true, forward, move |sink| async move {
+                let res = prefabdst::build_dst_prefabs(sink.as_ref(), args).await?;
+                serde_json::to_value(res).map_err(|e| e.to_string())
+            })
+        }
+        JobRequest::AutosocketBatch(args) => {
+            let label = format!("AutoSocket batch: {} input(s)", args.inputs.len());
+            jobs::spawn("autosocket_batch", label, true, forward, move |sink| async move {
+                let res = autosocket_batch::run_batch(sink, args).await?;
+                serde_json::to_value(res).map_err(|e| e.to_string())
+            })
+        }
+    }
+}
+
+/// Queues a long operation and returns its job ID right away; follow it through
+/// `job_update` events, `get_job` and `list_jobs`.
+#[tauri::command]
+fn start_job(app: tauri::AppHandle, request: JobRequest) -> Result<String, String> {
+    Ok(spawn_job(app, request).id)
+}
+
+#[tauri::command]
+async fn cancel_job(job_id: String) -> Result<bool, String> {
+    Ok(jobs::cancel(&job_id).await)
+}
+
+#[tauri::command]
+fn list_jobs() -> Result<Vec<jobs::Job>, String> {
+    Ok(jobs::list())
+}
+
+#[tauri::command]
+fn get_job(job_id: String) -> Result<Option<jobs::Job>, String> {
+    Ok(jobs::get(&job_id))
+}
+
+#[tauri::command]
+fn clear_job_history() -> Result<usize, String> {
+    Ok(jobs::clear_history())
 }
 
 #[derive(Clone, Serialize, Deserialize, Debug)]
@@ -4871,6 +2689,23 @@ pub fn run() {
             }
             // start remote control server
             let app_handle = app.handle();
+            let job_events = app_handle.clone();
+            jobs::set_notify(Arc::new(move |job| {
+                let _ = job_events.emit("job_update", job);
+            }));
+            // keep the prefab index in sync with the roots; overflows queue a full rescan job
+            let rescan_app = app_handle.clone();
+            index_watcher::init(
+                Arc::new(app_handle.clone()),
+                Arc::new(move || {
+                    spawn_job(rescan_app.clone(), JobRequest::ScanPrefabRoots { verbose: None });
+                }),
+            );
+            let watcher_app = app_handle.clone();
+            std::thread::spawn(move || {
+                rebuild_recovered_index(&watcher_app);
+                restart_index_watcher(&watcher_app, &load_settings());
+            });
             let (tx, _rx) = broadcast::channel(32);
             let remote = RemoteState { app: app_handle.clone(), inner: Arc::new(Mutex::new(RemoteData::default())), tx };
             app.manage(remote.clone());
@@ -4954,6 +2789,7 @@ pub fn run() {
             read_text_file,
             write_text_file,
             get_backend_status,
+            stop_blender_worker,
             wb_call,
             updater_download_msi,
             updater_install_msi,
@@ -4963,8 +2799,31 @@ pub fn run() {
             start_quick_tunnel_unique,
             stop_quick_tunnel,
             get_prefab_cache_status,
+            search_prefabs,
+            scan_prefab_roots,
+            get_prefab_roots,
+            set_prefab_roots,
+            resolve_guid,
+            find_references,
+            find_dependencies,
+            validate_references,
+            find_index_conflicts,
+            regenerate_guid,
+            move_resource,
+            query_prefab_index,
+            list_prefab_folders,
+            get_prefab_entry,
+            get_index_watcher_status,
+            set_index_watcher,
+            get_extract_cache_status,
+            clear_extract_cache,
             auto_detect_svn_root,
             scan_prefab_index,
+            start_job,
+            cancel_job,
+            list_jobs,
+            get_job,
+            clear_job_history,
             remember_svn_root,
             get_autosocket_settings,
             get_autosocket_presets,
@@ -4977,7 +2836,14 @@ pub fn run() {
             mqa_report_from_xobs_batch,
             create_new_et_from_xob,
             suggest_prefab_folders_from_xob,
+            get_match_rules,
+            save_match_rules,
+            explain_socket_matches,
+            discover_xob_sockets,
+            create_ets_batch,
             create_new_et_with_meta_from_xob,
+            apply_write_plan,
+            discard_write_plan,
             prefabdst_build,
             prefabdst_scan_dst,
             prefabdst_scan_full_dst,
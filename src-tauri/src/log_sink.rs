@@ -0,0 +1,133 @@
+// Destination for backend log and progress messages.
+//
+// The GUI forwards them to the webview as Tauri events, the CLI prints them to stderr and
+// `MemorySink` keeps them for callers (and tests) that inspect the log afterwards.
+// Backend functions take `&dyn LogSink` so they run the same way everywhere.
+
+use serde::Serialize;
+use std::sync::Mutex;
+use tauri::Emitter;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum LogChannel {
+    /// AutoSocket / prefab index messages (`prefab_scan_log` event).
+    Scan,
+    /// PrefabDST builder messages (`prefabdst_log` event).
+    PrefabDst,
+    /// Blender/MQA stage updates (`mqa_stage` event, plain string payload).
+    MqaStage,
+}
+
+impl LogChannel {
+    pub fn event_name(self) -> &'static str {
+        match self {
+            LogChannel::Scan => "prefab_scan_log",
+            LogChannel::PrefabDst => "prefabdst_log",
+            LogChannel::MqaStage => "mqa_stage",
+        }
+    }
+}
+
+#[derive(Serialize, Clone)]
+pub struct LogPayload {
+    pub level: String,
+    pub message: String,
+    pub current: Option<usize>,
+    pub total: Option<usize>,
+}
+
+pub trait LogSink: Send + Sync {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, current: Option<usize>, total: Option<usize>);
+
+    /// Polled by long blocking loops; only job sinks ever report true.
+    fn is_cancelled(&self) -> bool {
+        false
+    }
+}
+
+impl LogSink for tauri::AppHandle {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, current: Option<usize>, total: Option<usize>) {
+        if channel == LogChannel::MqaStage {
+            let _ = self.emit(channel.event_name(), message.to_string());
+            return;
+        }
+        let payload = LogPayload {
+            level: level.to_string(),
+            message: message.to_string(),
+            current,
+            total,
+        };
+        let _ = self.emit(channel.event_name(), payload);
+    }
+}
+
+/// Prints `[level] message` lines to stderr. With `quiet`, only warnings and errors.
+pub struct StderrSink {
+    pub quiet: bool,
+}
+
+impl LogSink for StderrSink {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, current: Option<usize>, total: Option<usize>) {
+        if self.quiet && level != "warn" && level != "error" {
+            return;
+        }
+        let level = if channel == LogChannel::MqaStage { "stage" } else { level };
+        match (current, total) {
+            (Some(c), Some(t)) => eprintln!("[{}] ({}/{}) {}", level, c, t, message),
+            _ => eprintln!("[{}] {}", level, message),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LogRecord {
+    pub channel: LogChannel,
+    pub level: String,
+    pub message: String,
+    pub current: Option<usize>,
+    pub total: Option<usize>,
+}
+
+/// Collects every message in memory.
+#[derive(Default)]
+pub struct MemorySink {
+    records: Mutex<Vec<LogRecord>>,
+}
+
+impl MemorySink {
+    pub fn new() -> MemorySink {
+        MemorySink::default()
+    }
+
+    pub fn records(&self) -> Vec<LogRecord> {
+        self.records.lock().unwrap().clone()
+    }
+
+    /// Messages logged on `channel`, in order.
+    pub fn messages(&self, channel: LogChannel) -> Vec<String> {
+        self.records
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|r| r.channel == channel)
+            .map(|r| r.message.clone())
+            .collect()
+    }
+
+    /// Returns the collected records and clears the buffer.
+    pub fn take(&self) -> Vec<LogRecord> {
+        std::mem::take(&mut *self.records.lock().unwrap())
+    }
+}
+
+impl LogSink for MemorySink {
+    fn log(&self, channel: LogChannel, level: &str, message: &str, current: Option<usize>, total: Option<usize>) {
+        self.records.lock().unwrap().push(LogRecord {
+            channel,
+            level: level.to_string(),
+            message: message.to_string(),
+            current,
+            total,
+        });
+    }
+}
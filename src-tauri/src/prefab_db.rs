@@ -0,0 +1,1129 @@
+// SQLite resource index (AutoSocket_PrefabIndex.db), replacing AutoSocket_PrefabIndex.json.
+//
+// One row per `*.meta` under each project root (prefabs, models, materials, textures,
+// configs, layers, ...) with its GUID, paths, lowercase file name, meta mtime, extension and
+// the resource class from the meta `Configurations` block. Roots are named (base game data,
+// addons) and each row remembers the root it was found in, so one root can be rescanned or
+// dropped without touching the others. When several roots carry the same GUID, file name or
+// project path, the root with the highest priority wins, the way a later addon overrides the
+// data below it. Lookups by GUID or file name hit indexes instead of loading the whole index,
+// and an FTS5 table over file name and relative path backs `search`, which re-ranks the
+// candidates with a fuzzy score so typos and partial names still find the resource.
+// The `refs` table holds the `{GUID}path` references found in text resources (see ref_graph).
+
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const SCHEMA_VERSION: i64 = 4;
+
+/// Root used for the single `svn_root` setting and for indexes built before roots existed.
+pub const BASE_ROOT: &str = "base";
+
+const META_SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS meta (
+    key TEXT PRIMARY KEY,
+    value TEXT NOT NULL
+);
+";
+
+/// In-place upgrades: entry `i` takes schema version `i + 1` to `i + 2`. Each step only adds
+/// what `SCHEMA` cannot create on its own (new columns, changed triggers) and resets the mtime
+/// of rows the next scan has to reread.
+const MIGRATIONS: [&str; 3] = [
+    // 1 -> 2: every .meta is indexed, not only prefabs.
+    "ALTER TABLE resources ADD COLUMN ext TEXT NOT NULL DEFAULT 'et';",
+    // 2 -> 3: named roots; existing rows belong to the svn root.
+    "
+    ALTER TABLE resources ADD COLUMN root TEXT NOT NULL DEFAULT 'base';
+    CREATE TABLE IF NOT EXISTS roots (
+        name TEXT PRIMARY KEY,
+        path TEXT NOT NULL,
+        priority INTEGER NOT NULL,
+        generated TEXT
+    );
+    INSERT OR IGNORE INTO roots(name, path, priority, generated)
+        SELECT 'base', value, 0, (SELECT value FROM meta WHERE key = 'generated') FROM meta WHERE key = 'svn_root';
+    ",
+    // 3 -> 4: reference table, filled when text resources are reread.
+    "
+    DROP TRIGGER IF EXISTS resources_ad;
+    UPDATE resources SET mtime = 0 WHERE ext IN ('et', 'conf', 'layer', 'ent');
+    ",
+];
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS roots (
+    name TEXT PRIMARY KEY,
+    path TEXT NOT NULL,
+    priority INTEGER NOT NULL,
+    generated TEXT
+);
+CREATE TABLE IF NOT EXISTS resources (
+    id INTEGER PRIMARY KEY,
+    root TEXT NOT NULL,
+    abs_path TEXT NOT NULL UNIQUE,
+    meta_path TEXT NOT NULL,
+    file_name TEXT NOT NULL,
+    guid TEXT,
+    rel_path TEXT NOT NULL,
+    name_value TEXT NOT NULL,
+    ext TEXT NOT NULL,
+    class TEXT NOT NULL,
+    mtime REAL NOT NULL
+);
+CREATE INDEX IF NOT EXISTS idx_resources_root ON resources(root);
+CREATE INDEX IF NOT EXISTS idx_resources_guid ON resources(guid);
+CREATE INDEX IF NOT EXISTS idx_resources_ext ON resources(ext);
+CREATE INDEX IF NOT EXISTS idx_resources_file_name ON resources(file_name);
+CREATE INDEX IF NOT EXISTS idx_resources_meta_path ON resources(meta_path);
+CREATE TABLE IF NOT EXISTS refs (
+    src_path TEXT NOT NULL,
+    guid TEXT NOT NULL,
+    path TEXT NOT NULL,
+    PRIMARY KEY (src_path, guid, path)
+) WITHOUT ROWID;
+CREATE INDEX IF NOT EXISTS idx_refs_guid ON refs(guid);
+CREATE VIRTUAL TABLE IF NOT EXISTS resources_fts USING fts5(
+    file_name, rel_path, content='resources', content_rowid='id'
+);
+CREATE TRIGGER IF NOT EXISTS resources_ai AFTER INSERT ON resources BEGIN
+    INSERT INTO resources_fts(rowid, file_name, rel_path) VALUES (new.id, new.file_name, new.rel_path);
+END;
+CREATE TRIGGER IF NOT EXISTS resources_ad AFTER DELETE ON resources BEGIN
+    INSERT INTO resources_fts(resources_fts, rowid, file_name, rel_path) VALUES ('delete', old.id, old.file_name, old.rel_path);
+    DELETE FROM refs WHERE src_path = old.abs_path;
+END;
+CREATE TRIGGER IF NOT EXISTS resources_au AFTER UPDATE ON resources BEGIN
+    INSERT INTO resources_fts(resources_fts, rowid, file_name, rel_path) VALUES ('delete', old.id, old.file_name, old.rel_path);
+    INSERT INTO resources_fts(rowid, file_name, rel_path) VALUES (new.id, new.file_name, new.rel_path);
+END;
+";
+
+/// Rows matching an `IndexQuery`, one per project path (the highest-priority root's).
+/// Parameters: ?1 type list, ?2 folder LIKE pattern, ?3 1-based start of the part below the
+/// folder (0 to include subfolders), ?4 file name pattern, ?5 GUID pattern, ?6 path prefix
+/// pattern, ?7 root.
+const FILTERED: &str = "
+SELECT * FROM (
+    SELECT r.*, ROW_NUMBER() OVER (
+        PARTITION BY lower(r.rel_path) ORDER BY COALESCE(o.priority, 0) DESC, r.id DESC
+    ) AS path_rank
+    FROM resources r LEFT JOIN roots o ON o.name = r.root
+    WHERE (?1 = '' OR instr(?1, ',' || r.ext || ',') > 0 OR instr(?1, ',' || lower(r.class) || ',') > 0)
+      AND (?2 = '' OR lower(r.rel_path) LIKE ?2 ESCAPE '\\')
+      AND (?3 = 0 OR instr(substr(r.rel_path, ?3), '/') = 0)
+      AND (?4 = '' OR r.file_name LIKE ?4 ESCAPE '\\')
+      AND (?5 = '' OR r.guid LIKE ?5 ESCAPE '\\')
+      AND (?6 = '' OR lower(r.rel_path) LIKE ?6 ESCAPE '\\')
+      AND (?7 = '' OR r.root = ?7)
+) WHERE path_rank = 1";
+
+/// The typo-tolerant fallback of `search` scores at most this many rows, and only for queries
+/// of `FUZZY_MIN_QUERY` characters or more.
+const FUZZY_SCAN_LIMIT: i64 = 20_000;
+const FUZZY_MIN_QUERY: usize = 3;
+
+pub const DEFAULT_PAGE_SIZE: usize = 100;
+pub const MAX_PAGE_SIZE: usize = 1000;
+
+/// Lookups that may hit several roots take the row from the highest-priority root.
+const BY_PRIORITY: &str = "SELECT r.* FROM resources r LEFT JOIN roots o ON o.name = r.root";
+const PRIORITY_ORDER: &str = "ORDER BY COALESCE(o.priority, 0) DESC, r.id DESC LIMIT 1";
+
+/// A named folder scanned into the index. Higher `priority` overrides lower ones.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+pub struct ProjectRoot {
+    pub name: String,
+    pub path: String,
+    #[serde(default)]
+    pub priority: i32,
+}
+
+/// A root as stored in the index, with its entry count and last scan time.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RootInfo {
+    #[serde(flatten)]
+    pub root: ProjectRoot,
+    pub generated: Option<String>,
+    pub count: usize,
+}
+
+/// A `{GUID}path` reference found in a resource file.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Reference {
+    pub guid: String,
+    pub path: String,
+}
+
+/// One indexed resource.
+#[derive(Serialize, Clone, Debug)]
+pub struct Resource {
+    /// Name of the root the resource was found in.
+    pub root: String,
+    /// Path of the resource itself (the .et/.xob/..., not the .meta).
+    pub abs_path: String,
+    pub meta_path: String,
+    /// Lowercase file name, e.g. `barrel_01.et`.
+    pub file_name: String,
+    pub guid: Option<String>,
+    /// Project-relative path from the meta `Name` (`Prefabs/...`).
+    pub rel_path: String,
+    /// The meta `Name` value, `{GUID}Prefabs/...`.
+    pub name_value: String,
+    /// Lowercase extension: `et`, `xob`, `emat`, `edds`, `conf`, `layer`, `ent`, ...
+    pub ext: String,
+    /// Resource class of the meta `Configurations` entries (`EntityTemplateResourceClass`,
+    /// `XOBResourceClass`, ...); empty if the meta has none.
+    pub class: String,
+    /// Meta mtime, or the resource's own when newer for files scanned for references.
+    pub mtime: f64,
+}
+
+impl Resource {
+    /// Row for the resource described by `meta_path`. `name_value` and `class` come from the
+    /// meta's `Name` field and `Configurations` block.
+    pub fn new(
+        root: String,
+        abs_path: String,
+        meta_path: String,
+        name_value: String,
+        class: String,
+        mtime: f64,
+    ) -> Resource {
+        let p = PathBuf::from(&abs_path);
+        let file_name = p
+            .file_name()
+            .map(|n| n.to_string_lossy().to_lowercase())
+            .unwrap_or_else(|| abs_path.to_lowercase());
+        let ext = p
+            .extension()
+            .map(|e| e.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let rel_path = match name_value.find('}') {
+            Some(close) if name_value.trim_start().starts_with('{') => name_value[(close + 1)..].to_string(),
+            _ => name_value.clone(),
+        };
+        Resource {
+            guid: crate::extract_guid(&name_value),
+            root,
+            abs_path,
+            meta_path,
+            file_name,
+            rel_path,
+            name_value,
+            ext,
+            class,
+            mtime,
+        }
+    }
+
+    fn from_row(row: &rusqlite::Row) -> rusqlite::Result<Resource> {
+        Ok(Resource {
+            root: row.get("root")?,
+            abs_path: row.get("abs_path")?,
+            meta_path: row.get("meta_path")?,
+            file_name: row.get("file_name")?,
+            guid: row.get("guid")?,
+            rel_path: row.get("rel_path")?,
+            name_value: row.get("name_value")?,
+            ext: row.get("ext")?,
+            class: row.get("class")?,
+            mtime: row.get("mtime")?,
+        })
+    }
+}
+
+/// Filters for browsing the index; unset fields match everything.
+#[derive(Deserialize, Default, Clone, Debug)]
+pub struct IndexQuery {
+    /// Project folder (`Prefabs/Props`); matches everything below it.
+    pub folder: Option<String>,
+    /// With `folder`: only resources directly in it, not in its subfolders.
+    pub direct_only: Option<bool>,
+    /// Part of the file name.
+    pub name: Option<String>,
+    /// GUID or the start of one.
+    pub guid: Option<String>,
+    /// Start of the project path.
+    pub path_prefix: Option<String>,
+    pub root: Option<String>,
+    /// Extensions or resource classes; defaults to prefabs (`et`), empty for every type.
+    pub types: Option<Vec<String>>,
+    pub offset: Option<usize>,
+    /// Page size, `DEFAULT_PAGE_SIZE` by default and at most `MAX_PAGE_SIZE`.
+    pub limit: Option<usize>,
+}
+
+/// One page of `PrefabDb::query`, ordered by project path.
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexPage {
+    /// Matches across all pages.
+    pub total: usize,
+    pub offset: usize,
+    pub items: Vec<Resource>,
+}
+
+/// A subfolder of the browsed folder with the number of matching resources below it.
+#[derive(Serialize, Clone, Debug)]
+pub struct IndexFolder {
+    pub name: String,
+    /// Project path of the folder, in the casing of its first resource.
+    pub path: String,
+    pub count: usize,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SearchHit {
+    #[serde(flatten)]
+    pub resource: Resource,
+    pub score: f64,
+    /// Lower-priority roots that have a resource at the same project path.
+    pub overrides: Vec<String>,
+}
+
+pub struct PrefabDb {
+    conn: Connection,
+}
+
+fn sql_err(e: rusqlite::Error) -> String {
+    format!("Prefab index: {}", e)
+}
+
+fn is_corrupt(e: &rusqlite::Error) -> bool {
+    matches!(
+        e.sqlite_error_code(),
+        Some(rusqlite::ErrorCode::DatabaseCorrupt | rusqlite::ErrorCode::NotADatabase)
+    )
+}
+
+/// Connects and reads the stored schema version.
+fn connect(path: &Path) -> rusqlite::Result<(Connection, Option<i64>)> {
+    let conn = Connection::open(path)?;
+    // The index watcher and scan jobs write from different threads.
+    conn.busy_timeout(std::time::Duration::from_secs(10))?;
+    conn.execute_batch("PRAGMA journal_mode=WAL; PRAGMA synchronous=NORMAL;")?;
+    conn.execute_batch(META_SCHEMA)?;
+    let version: Option<String> = conn
+        .query_row("SELECT value FROM meta WHERE key = 'schema_version'", [], |r| r.get(0))
+        .optional()?;
+    Ok((conn, version.and_then(|v| v.parse().ok())))
+}
+
+/// Opens (creating if needed) the prefab index database. An older schema is migrated in
+/// place. A file SQLite reports as corrupt, or one whose schema cannot be migrated (failed
+/// migration, unknown or newer version), is moved aside and replaced by an empty index; the
+/// backup path and the reason stay in the `recovered_from` and `recovered_reason` meta keys
+/// until a rescan clears them.
+pub fn open() -> Result<PrefabDb, String> {
+    open_at(&crate::prefab_db_path(), Some(&crate::prefab_index_path()))
+}
+
+/// `open` for the database at `path`; a new one is seeded from the JSON index at `legacy`.
+pub(crate) fn open_at(path: &Path, legacy: Option<&Path>) -> Result<PrefabDb, String> {
+    let mut recovered: Option<(PathBuf, String)> = None;
+    let (conn, version) = match connect(path) {
+        Err(e) if is_corrupt(&e) => {
+            let backup = crate::backup_corrupt_file(path);
+            remove_wal(path);
+            recovered = backup.map(|b| (b, "was corrupt".to_string()));
+            connect(path).map_err(sql_err)?
+        }
+        res => res.map_err(sql_err)?,
+    };
+    let mut db = PrefabDb { conn };
+    let unusable = match version {
+        Some(v) if (1..SCHEMA_VERSION).contains(&v) => db.migrate(v).err(),
+        Some(v) if v != SCHEMA_VERSION => Some(format!("unknown schema version {}", v)),
+        _ => None,
+    };
+    let (mut db, version) = match unusable {
+        None => (db, version),
+        Some(err) => {
+            // The failed migration was rolled back; keep that index as it is rather than
+            // dropping its tables, and start over with an empty one.
+            drop(db);
+            let backup = set_aside(path)
+                .map_err(|e| format!("Prefab index could not be migrated ({}) or moved aside: {}", err, e))?;
+            recovered = Some((backup, format!("could not be migrated ({})", err)));
+            let (conn, version) = connect(path).map_err(sql_err)?;
+            (PrefabDb { conn }, version)
+        }
+    };
+    db.conn.execute_batch(SCHEMA).map_err(sql_err)?;
+    if version != Some(SCHEMA_VERSION) {
+        db.set_meta("schema_version", &SCHEMA_VERSION.to_string())?;
+        if let Some(legacy) = legacy.filter(|_| version.is_none() && recovered.is_none()) {
+            db.import_legacy_json(legacy)?;
+        }
+    }
+    if let Some((backup, reason)) = recovered {
+        db.set_meta("recovered_from", &backup.to_string_lossy())?;
+        db.set_meta("recovered_reason", &reason)?;
+    }
+    Ok(db)
+}
+
+/// Moves an index that could not be migrated to `<name>.old-<timestamp>`. The connection is
+/// closed, so SQLite has already folded the WAL back into the file.
+fn set_aside(path: &Path) -> Result<PathBuf, String> {
+    let name = path.file_name().map(|n| n.to_string_lossy().to_string()).unwrap_or_default();
+    let backup = path.with_file_name(format!("{}.old-{}", name, chrono::Utc::now().format("%Y%m%d-%H%M%S")));
+    fs::rename(path, &backup).map_err(|e| e.to_string())?;
+    remove_wal(path);
+    Ok(backup)
+}
+
+fn remove_wal(path: &Path) {
+    for suffix in ["-wal", "-shm"] {
+        let _ = fs::remove_file(format!("{}{}", path.to_string_lossy(), suffix));
+    }
+}
+
+impl PrefabDb {
+    /// Applies the migrations from schema `from` up to `SCHEMA_VERSION` in one transaction.
+    fn migrate(&mut self, from: i64) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        for (i, sql) in MIGRATIONS.iter().enumerate().skip((from - 1) as usize) {
+            tx.execute_batch(sql).map_err(|e| format!("migration to schema {} failed: {}", i + 2, e))?;
+        }
+        tx.execute(
+            "UPDATE meta SET value = ?1 WHERE key = 'schema_version'",
+            [SCHEMA_VERSION.to_string()],
+        )
+        .map_err(sql_err)?;
+        tx.commit().map_err(sql_err)
+    }
+
+    pub fn meta(&self, key: &str) -> Result<Option<String>, String> {
+        self.conn
+            .query_row("SELECT value FROM meta WHERE key = ?1", [key], |r| r.get(0))
+            .optional()
+            .map_err(sql_err)
+    }
+
+    pub fn set_meta(&self, key: &str, value: &str) -> Result<(), String> {
+        self.conn
+            .execute(
+                "INSERT INTO meta(key, value) VALUES (?1, ?2) ON CONFLICT(key) DO UPDATE SET value = excluded.value",
+                params![key, value],
+            )
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+
+    pub fn remove_meta(&self, key: &str) -> Result<(), String> {
+        self.conn.execute("DELETE FROM meta WHERE key = ?1", [key]).map(|_| ()).map_err(sql_err)
+    }
+
+    /// Backup path and reason left by `open` when it replaced an unusable index.
+    pub fn recovered(&self) -> Result<Option<(String, String)>, String> {
+        let Some(backup) = self.meta("recovered_from")? else { return Ok(None) };
+        let reason = self.meta("recovered_reason")?.unwrap_or_else(|| "was corrupt".to_string());
+        Ok(Some((backup, reason)))
+    }
+
+    /// Forgets the recovery note once a rescan rebuilt the index.
+    pub fn clear_recovered(&self) -> Result<(), String> {
+        self.remove_meta("recovered_from")?;
+        self.remove_meta("recovered_reason")
+    }
+
+    pub fn count(&self) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM resources", [], |r| r.get::<_, i64>(0))
+            .map(|n| n as usize)
+            .map_err(sql_err)
+    }
+
+    /// Number of resources with extension `ext` (`et` for prefabs).
+    pub fn count_ext(&self, ext: &str) -> Result<usize, String> {
+        self.conn
+            .query_row("SELECT COUNT(*) FROM resources WHERE ext = ?1", [ext], |r| r.get::<_, i64>(0))
+            .map(|n| n as usize)
+            .map_err(sql_err)
+    }
+
+    /// Runs `BY_PRIORITY` with `filter` and returns the winning row.
+    fn query_one(&self, filter: &str, arg: &str) -> Option<Resource> {
+        let sql = format!("{} WHERE {} {}", BY_PRIORITY, filter, PRIORITY_ORDER);
+        self.conn.query_row(&sql, [arg], Resource::from_row).optional().ok().flatten()
+    }
+
+    /// Resource whose meta carries `guid`, whatever its type.
+    pub fn by_guid(&self, guid: &str) -> Option<Resource> {
+        self.query_one("r.guid = ?1", &guid.to_uppercase())
+    }
+
+    pub fn by_abs_path(&self, abs_path: &str) -> Option<Resource> {
+        self.query_one("r.abs_path = ?1", abs_path)
+    }
+
+    /// Prefab (.et) whose meta carries `guid`; socket GUIDs only ever point at prefabs.
+    pub fn prefab_by_guid(&self, guid: &str) -> Option<Resource> {
+        self.query_one("r.guid = ?1 AND r.ext = 'et'", &guid.to_uppercase())
+    }
+
+    /// Resource by lowercase file name (`name.et`); the highest-priority root wins, then the
+    /// most recently indexed row.
+    pub fn by_file_name(&self, file_name: &str) -> Option<Resource> {
+        self.query_one("r.file_name = ?1", &file_name.to_lowercase())
+    }
+
+    /// Resource by project path (`Prefabs/...`, case-insensitive).
+    pub fn by_rel_path(&self, rel_path: &str) -> Option<Resource> {
+        self.query_one("lower(r.rel_path) = ?1", &rel_path.replace('\\', "/").to_lowercase())
+    }
+
+    /// Every resource with one of the extensions in `exts`, ordered by path.
+    pub fn with_exts(&self, exts: &[&str]) -> Result<Vec<Resource>, String> {
+        let list = format!(",{},", exts.join(","));
+        let mut stmt = self
+            .conn
+            .prepare("SELECT * FROM resources WHERE instr(?1, ',' || ext || ',') > 0 ORDER BY abs_path")
+            .map_err(sql_err)?;
+        let rows = stmt.query_map([list], Resource::from_row).map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Rows whose GUID is carried by more than one row, ordered by GUID.
+    pub fn shared_guids(&self) -> Result<Vec<Resource>, String> {
+        self.rows_sharing(
+            "SELECT * FROM resources WHERE guid IN
+                 (SELECT guid FROM resources WHERE guid IS NOT NULL GROUP BY guid HAVING COUNT(*) > 1)
+             ORDER BY guid, abs_path",
+        )
+    }
+
+    /// Prefab rows whose lowercase file name is used by more than one prefab, ordered by name.
+    pub fn shared_prefab_names(&self) -> Result<Vec<Resource>, String> {
+        self.rows_sharing(
+            "SELECT * FROM resources WHERE ext = 'et' AND file_name IN
+                 (SELECT file_name FROM resources WHERE ext = 'et' GROUP BY file_name HAVING COUNT(*) > 1)
+             ORDER BY file_name, abs_path",
+        )
+    }
+
+    fn rows_sharing(&self, sql: &str) -> Result<Vec<Resource>, String> {
+        let mut stmt = self.conn.prepare(sql).map_err(sql_err)?;
+        let rows = stmt.query_map([], Resource::from_row).map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Stored roots, highest priority first.
+    pub fn roots(&self) -> Result<Vec<RootInfo>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT o.name, o.path, o.priority, o.generated,
+                        (SELECT COUNT(*) FROM resources r WHERE r.root = o.name)
+                 FROM roots o ORDER BY o.priority DESC, o.name",
+            )
+            .map_err(sql_err)?;
+        let rows = stmt
+            .query_map([], |r| {
+                Ok(RootInfo {
+                    root: ProjectRoot { name: r.get(0)?, path: r.get(1)?, priority: r.get(2)? },
+                    generated: r.get(3)?,
+                    count: r.get::<_, i64>(4)? as usize,
+                })
+            })
+            .map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Stores `root`. If it was indexed from a different path, its entries are dropped first;
+    /// returns true in that case.
+    pub fn set_root(&self, root: &ProjectRoot) -> Result<bool, String> {
+        let prev: Option<String> = self
+            .conn
+            .query_row("SELECT path FROM roots WHERE name = ?1", [&root.name], |r| r.get(0))
+            .optional()
+            .map_err(sql_err)?;
+        let moved = prev.is_some_and(|p| p != root.path);
+        if moved {
+            self.conn.execute("DELETE FROM resources WHERE root = ?1", [&root.name]).map_err(sql_err)?;
+        }
+        self.conn
+            .execute(
+                "INSERT INTO roots(name, path, priority) VALUES (?1, ?2, ?3)
+                 ON CONFLICT(name) DO UPDATE SET path = excluded.path, priority = excluded.priority",
+                params![root.name, root.path, root.priority],
+            )
+            .map_err(sql_err)?;
+        Ok(moved)
+    }
+
+    pub fn mark_root_scanned(&self, name: &str, generated: &str) -> Result<(), String> {
+        self.conn
+            .execute("UPDATE roots SET generated = ?2 WHERE name = ?1", params![name, generated])
+            .map(|_| ())
+            .map_err(sql_err)
+    }
+
+    /// Drops a root and its entries. Returns how many entries were removed.
+    pub fn remove_root(&self, name: &str) -> Result<usize, String> {
+        let n = self.conn.execute("DELETE FROM resources WHERE root = ?1", [name]).map_err(sql_err)?;
+        self.conn.execute("DELETE FROM roots WHERE name = ?1", [name]).map_err(sql_err)?;
+        Ok(n)
+    }
+
+    /// Name of the stored root containing `abs_path` (the deepest one if roots are nested),
+    /// or BASE_ROOT when none does.
+    pub fn root_for_path(&self, abs_path: &str) -> Result<String, String> {
+        let norm = |s: &str| s.replace('\\', "/").trim_end_matches('/').to_lowercase();
+        let p = norm(abs_path);
+        Ok(self
+            .roots()?
+            .into_iter()
+            .filter(|r| {
+                let root = norm(&r.root.path);
+                p.strip_prefix(&root).is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|r| r.root.path.len())
+            .map(|r| r.root.name)
+            .unwrap_or_else(|| BASE_ROOT.to_string()))
+    }
+
+    /// Project path (`Prefabs/...`) of `abs_path` under the deepest stored root containing it.
+    pub fn project_path(&self, abs_path: &str) -> Result<Option<String>, String> {
+        let norm = |s: &str| s.replace('\\', "/").trim_end_matches('/').to_string();
+        let p = norm(abs_path);
+        let mut roots = self.roots()?;
+        roots.sort_by_key(|r| std::cmp::Reverse(r.root.path.len()));
+        Ok(roots.iter().find_map(|r| {
+            let root = norm(&r.root.path);
+            let head = p.get(..root.len())?;
+            let rest = p[root.len()..].strip_prefix('/')?;
+            head.eq_ignore_ascii_case(&root).then(|| rest.to_string())
+        }))
+    }
+
+    /// meta path -> mtime for every row of `root`, used to skip unchanged files when rescanning.
+    pub fn meta_mtimes(&self, root: &str) -> Result<HashMap<String, f64>, String> {
+        let mut stmt = self.conn.prepare("SELECT meta_path, mtime FROM resources WHERE root = ?1").map_err(sql_err)?;
+        let rows = stmt
+            .query_map([root], |r| Ok((r.get::<_, String>(0)?, r.get::<_, f64>(1)?)))
+            .map_err(sql_err)?;
+        rows.collect::<Result<HashMap<_, _>, _>>().map_err(sql_err)
+    }
+
+    pub fn upsert(&mut self, rows: &[Resource]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        {
+            let mut stmt = tx
+                .prepare(
+                    "INSERT INTO resources(root, abs_path, meta_path, file_name, guid, rel_path, name_value, ext, class, mtime)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT(abs_path) DO UPDATE SET
+                        root = excluded.root, meta_path = excluded.meta_path, file_name = excluded.file_name, guid = excluded.guid,
+                        rel_path = excluded.rel_path, name_value = excluded.name_value, ext = excluded.ext,
+                        class = excluded.class, mtime = excluded.mtime",
+                )
+                .map_err(sql_err)?;
+            for r in rows {
+                stmt.execute(params![
+                    r.root, r.abs_path, r.meta_path, r.file_name, r.guid, r.rel_path, r.name_value, r.ext, r.class,
+                    r.mtime
+                ])
+                .map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    /// Replaces the outgoing references of each resource (by `abs_path`).
+    pub fn set_references(&mut self, refs: &[(String, Vec<Reference>)]) -> Result<(), String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        {
+            let mut del = tx.prepare("DELETE FROM refs WHERE src_path = ?1").map_err(sql_err)?;
+            let mut ins = tx
+                .prepare("INSERT OR IGNORE INTO refs(src_path, guid, path) VALUES (?1, ?2, ?3)")
+                .map_err(sql_err)?;
+            for (src, list) in refs {
+                del.execute([src]).map_err(sql_err)?;
+                for r in list {
+                    ins.execute(params![src, r.guid, r.path]).map_err(sql_err)?;
+                }
+            }
+        }
+        tx.commit().map_err(sql_err)
+    }
+
+    /// References made by the resource at `abs_path`.
+    pub fn references_from(&self, abs_path: &str) -> Result<Vec<Reference>, String> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT guid, path FROM refs WHERE src_path = ?1 ORDER BY path")
+            .map_err(sql_err)?;
+        let rows = stmt
+            .query_map([abs_path], |r| Ok(Reference { guid: r.get(0)?, path: r.get(1)? }))
+            .map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Indexed resources whose files reference `guid`.
+    pub fn referencing(&self, guid: &str) -> Result<Vec<Resource>, String> {
+        let mut stmt = self
+            .conn
+            .prepare(
+                "SELECT r.* FROM refs f JOIN resources r ON r.abs_path = f.src_path
+                 WHERE f.guid = ?1 GROUP BY r.id ORDER BY r.rel_path",
+            )
+            .map_err(sql_err)?;
+        let rows = stmt.query_map([guid.to_uppercase()], Resource::from_row).map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Deletes the rows of the given resource paths. Returns how many were removed.
+    pub fn remove_paths(&mut self, abs_paths: &[String]) -> Result<usize, String> {
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        let mut n = 0;
+        {
+            let mut stmt = tx.prepare("DELETE FROM resources WHERE abs_path = ?1").map_err(sql_err)?;
+            for p in abs_paths {
+                n += stmt.execute([p]).map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)?;
+        Ok(n)
+    }
+
+    /// Deletes rows of `root` whose meta file is not in `present`. Returns how many were removed.
+    pub fn remove_missing(&mut self, root: &str, present: &HashSet<String>) -> Result<usize, String> {
+        let stale: Vec<String> = self.meta_mtimes(root)?.into_keys().filter(|k| !present.contains(k)).collect();
+        let tx = self.conn.transaction().map_err(sql_err)?;
+        {
+            let mut stmt = tx.prepare("DELETE FROM resources WHERE meta_path = ?1").map_err(sql_err)?;
+            for k in &stale {
+                stmt.execute([k]).map_err(sql_err)?;
+            }
+        }
+        tx.commit().map_err(sql_err)?;
+        Ok(stale.len())
+    }
+
+    /// Bind parameters of `FILTERED` for `q`, in placeholder order.
+    fn query_params(q: &IndexQuery) -> Vec<rusqlite::types::Value> {
+        let text = |v: &Option<String>| v.as_deref().map(str::trim).unwrap_or_default().to_string();
+        let folder = folder_key(&text(&q.folder));
+        let types = q.types.clone().unwrap_or_else(|| vec!["et".to_string()]);
+        let direct_start = if q.direct_only.unwrap_or(false) { folder.len() as i64 + if folder.is_empty() { 1 } else { 2 } } else { 0 };
+        let pattern = |v: String, before: &str, after: &str| if v.is_empty() { v } else { format!("{}{}{}", before, like_escape(&v), after) };
+        vec![
+            type_list(&types).into(),
+            pattern(folder, "", "/%").into(),
+            direct_start.into(),
+            pattern(text(&q.name).to_lowercase(), "%", "%").into(),
+            pattern(text(&q.guid).trim_start_matches('{').trim_end_matches('}').to_uppercase(), "", "%").into(),
+            pattern(text(&q.path_prefix).replace('\\', "/").trim_start_matches('/').to_lowercase(), "", "%").into(),
+            text(&q.root).into(),
+        ]
+    }
+
+    /// Resources matching `q`, one per project path (the highest-priority root's), a page at
+    /// a time.
+    pub fn query(&self, q: &IndexQuery) -> Result<IndexPage, String> {
+        let params = Self::query_params(q);
+        let total: i64 = self
+            .conn
+            .query_row(&format!("SELECT COUNT(*) FROM ({})", FILTERED), rusqlite::params_from_iter(&params), |r| r.get(0))
+            .map_err(sql_err)?;
+        let offset = q.offset.unwrap_or(0);
+        let limit = q.limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+        let mut page_params = params;
+        page_params.push((limit as i64).into());
+        page_params.push((offset as i64).into());
+        let mut stmt = self
+            .conn
+            .prepare(&format!("{} ORDER BY lower(rel_path) LIMIT ?8 OFFSET ?9", FILTERED))
+            .map_err(sql_err)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(&page_params), Resource::from_row).map_err(sql_err)?;
+        Ok(IndexPage {
+            total: total as usize,
+            offset,
+            items: rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)?,
+        })
+    }
+
+    /// Every resource matching `q`, by path; paging fields are ignored.
+    pub fn matching(&self, q: &IndexQuery) -> Result<Vec<Resource>, String> {
+        let mut stmt = self.conn.prepare(&format!("{} ORDER BY lower(rel_path)", FILTERED)).map_err(sql_err)?;
+        let rows = stmt.query_map(rusqlite::params_from_iter(Self::query_params(q)), Resource::from_row).map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Subfolders directly below `q.folder` (the project top level when unset) holding
+    /// resources that match the other filters of `q`, by name. Paging fields are ignored.
+    pub fn folders(&self, q: &IndexQuery) -> Result<Vec<IndexFolder>, String> {
+        let q = IndexQuery { direct_only: None, ..q.clone() };
+        let parent_len = folder_key(q.folder.as_deref().unwrap_or_default()).len();
+        let skip = if parent_len == 0 { 0 } else { parent_len + 1 };
+        let mut stmt = self.conn.prepare(&format!("SELECT rel_path FROM ({})", FILTERED)).map_err(sql_err)?;
+        let rows = stmt
+            .query_map(rusqlite::params_from_iter(Self::query_params(&q)), |r| r.get::<_, String>(0))
+            .map_err(sql_err)?;
+        let mut folders: BTreeMap<String, IndexFolder> = BTreeMap::new();
+        for rel in rows.flatten() {
+            let rel = rel.replace('\\', "/");
+            let Some(below) = rel.get(skip..) else { continue };
+            let Some((name, _)) = below.split_once('/') else { continue };
+            folders
+                .entry(name.to_lowercase())
+                .or_insert_with(|| IndexFolder { name: name.to_string(), path: rel[..skip + name.len()].to_string(), count: 0 })
+                .count += 1;
+        }
+        Ok(folders.into_values().collect())
+    }
+
+    fn query_all(&self, filter: &str, arg: &str) -> Result<Vec<Resource>, String> {
+        let sql = format!("{} WHERE {} ORDER BY COALESCE(o.priority, 0) DESC, r.id DESC", BY_PRIORITY, filter);
+        let mut stmt = self.conn.prepare(&sql).map_err(sql_err)?;
+        let rows = stmt.query_map([arg], Resource::from_row).map_err(sql_err)?;
+        rows.collect::<Result<Vec<_>, _>>().map_err(sql_err)
+    }
+
+    /// Every row at the project path `rel_path`, highest-priority root first.
+    pub fn all_at_rel_path(&self, rel_path: &str) -> Result<Vec<Resource>, String> {
+        self.query_all("lower(r.rel_path) = ?1", &rel_path.replace('\\', "/").to_lowercase())
+    }
+
+    /// Every row with the file name `file_name`, highest-priority root first.
+    pub fn all_by_file_name(&self, file_name: &str) -> Result<Vec<Resource>, String> {
+        self.query_all("r.file_name = ?1", &file_name.to_lowercase())
+    }
+
+    /// Full-text candidates re-ranked by fuzzy similarity to `query`. A 16-digit hex query
+    /// also matches the GUID exactly. `types` limits hits to those extensions or resource
+    /// classes (case-insensitive); empty means every type. Resources at the same project path
+    /// in several roots are reported once, from the highest-priority root.
+    pub fn search(&self, query: &str, limit: usize, types: &[String]) -> Result<Vec<SearchHit>, String> {
+        let q = query.trim().to_lowercase();
+        if q.is_empty() || limit == 0 {
+            return Ok(Vec::new());
+        }
+        let type_list = type_list(types);
+        let type_ok = |r: &Resource| {
+            type_list.is_empty()
+                || type_list.contains(&format!(",{},", r.ext))
+                || type_list.contains(&format!(",{},", r.class.to_lowercase()))
+        };
+        let tokens: Vec<String> = q
+            .split(|c: char| !c.is_alphanumeric())
+            .filter(|t| !t.is_empty())
+            .map(|t| t.to_string())
+            .collect();
+
+        let mut candidates: HashMap<String, Resource> = HashMap::new();
+        if q.len() == 16 && q.chars().all(|c| c.is_ascii_hexdigit()) {
+            let mut stmt = self.conn.prepare("SELECT * FROM resources WHERE guid = ?1").map_err(sql_err)?;
+            let rows = stmt.query_map([q.to_uppercase()], Resource::from_row).map_err(sql_err)?;
+            for r in rows.flatten().filter(|r| type_ok(r)) {
+                candidates.insert(r.abs_path.clone(), r);
+            }
+        }
+        if !tokens.is_empty() {
+            let fts_query = tokens.iter().map(|t| format!("\"{}\"*", t)).collect::<Vec<_>>().join(" OR ");
+            let mut stmt = self
+                .conn
+                .prepare(
+                    "SELECT r.* FROM resources_fts f JOIN resources r ON r.id = f.rowid
+                     WHERE resources_fts MATCH ?1
+                       AND (?3 = '' OR instr(?3, ',' || r.ext || ',') > 0 OR instr(?3, ',' || lower(r.class) || ',') > 0)
+                     ORDER BY bm25(resources_fts) LIMIT ?2",
+                )
+                .map_err(sql_err)?;
+            let rows = stmt
+                .query_map(params![fts_query, (limit * 20).max(200) as i64, type_list], Resource::from_row)
+                .map_err(sql_err)?;
+            for r in rows.flatten() {
+                candidates.insert(r.abs_path.clone(), r);
+            }
+        }
+        // Typos defeat prefix matching; fall back to scoring file names. Only the columns the
+        // score needs are read, and full rows are loaded for the best ones alone.
+        if candidates.len() < limit && q.chars().count() >= FUZZY_MIN_QUERY {
+            let mut stmt = self
+                .conn
+                .prepare(
+                    "SELECT id, abs_path, file_name, rel_path FROM resources
+                     WHERE ?1 = '' OR instr(?1, ',' || ext || ',') > 0 OR instr(?1, ',' || lower(class) || ',') > 0
+                     LIMIT ?2",
+                )
+                .map_err(sql_err)?;
+            let rows = stmt
+                .query_map(params![type_list, FUZZY_SCAN_LIMIT], |row| {
+                    Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?, row.get::<_, String>(2)?, row.get::<_, String>(3)?))
+                })
+                .map_err(sql_err)?;
+            let mut scored: Vec<(f64, i64)> = rows
+                .flatten()
+                .filter(|(_, abs_path, _, _)| !candidates.contains_key(abs_path))
+                .map(|(id, _, file_name, rel_path)| (fuzzy_score(&q, &tokens, &file_name, &rel_path), id))
+                .filter(|(s, _)| *s >= 0.75)
+                .collect();
+            scored.sort_by(|a, b| b.0.total_cmp(&a.0));
+            let mut by_id = self.conn.prepare("SELECT * FROM resources WHERE id = ?1").map_err(sql_err)?;
+            for (_, id) in scored.into_iter().take(limit) {
+                if let Some(r) = by_id.query_row([id], Resource::from_row).optional().map_err(sql_err)? {
+                    candidates.insert(r.abs_path.clone(), r);
+                }
+            }
+        }
+
+        let priority: HashMap<String, i32> =
+            self.roots()?.into_iter().map(|r| (r.root.name, r.root.priority)).collect();
+        let prio = |r: &Resource| priority.get(&r.root).copied().unwrap_or(0);
+        let mut by_rel: HashMap<String, Vec<Resource>> = HashMap::new();
+        for r in candidates.into_values() {
+            by_rel.entry(r.rel_path.to_lowercase()).or_default().push(r);
+        }
+        let mut hits: Vec<SearchHit> = by_rel
+            .into_values()
+            .map(|mut group| {
+                group.sort_by_key(|r| std::cmp::Reverse(prio(r)));
+                let r = group.remove(0);
+                let mut score = fuzzy_score(&q, &tokens, &r.file_name, &r.rel_path);
+                if r.guid.as_deref().is_some_and(|g| g.eq_ignore_ascii_case(&q)) {
+                    score += 2.0;
+                }
+                SearchHit { resource: r, score, overrides: group.into_iter().map(|o| o.root).collect() }
+            })
+            .collect();
+        hits.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.resource.file_name.cmp(&b.resource.file_name)));
+        hits.truncate(limit);
+        Ok(hits)
+    }
+
+    /// One-time import of the JSON index written by older versions.
+    fn import_legacy_json(&mut self, path: &Path) -> Result<(), String> {
+        let Ok(text) = fs::read_to_string(path) else { return Ok(()) };
+        let Ok(v) = serde_json::from_str::<serde_json::Value>(&text) else { return Ok(()) };
+        // Version 1 is the only JSON layout ever written.
+        if v.get("version").and_then(|x| x.as_i64()).is_some_and(|n| n != 1) {
+            return Ok(());
+        }
+        let map = |key: &str| v.get(key).and_then(|x| x.as_object()).cloned().unwrap_or_default();
+        let (names, paths) = (map("name_index"), map("et_path_index"));
+        let mut rows: Vec<Resource> = Vec::new();
+        for (key, name_value) in &names {
+            let (Some(name_value), Some(abs)) = (name_value.as_str(), paths.get(key).and_then(|p| p.as_str())) else {
+                continue;
+            };
+            // mtime 0 so the next scan rereads these metas and fills in their class.
+            let meta_path = format!("{}.meta", abs);
+            rows.push(Resource::new(
+                BASE_ROOT.to_string(),
+                abs.to_string(),
+                meta_path,
+                name_value.to_string(),
+                String::new(),
+                0.0,
+            ));
+        }
+        if rows.is_empty() {
+            return Ok(());
+        }
+        if let Some(svn_root) = v.get("svn_root").and_then(|x| x.as_str()) {
+            self.set_root(&ProjectRoot { name: BASE_ROOT.to_string(), path: svn_root.to_string(), priority: 0 })?;
+            self.set_meta("svn_root", svn_root)?;
+        }
+        self.upsert(&rows)?;
+        if let Some(generated) = v.get("generated").and_then(|x| x.as_str()) {
+            self.mark_root_scanned(BASE_ROOT, generated)?;
+            self.set_meta("generated", generated)?;
+        }
+        Ok(())
+    }
+}
+
+/// ",et,xob," form of `types`, matched with instr() so one parameter covers any number of
+/// types; empty when `types` is.
+fn type_list(types: &[String]) -> String {
+    if types.is_empty() {
+        return String::new();
+    }
+    format!(",{},", types.iter().map(|t| t.trim().trim_start_matches('.').to_lowercase()).collect::<Vec<_>>().join(","))
+}
+
+fn like_escape(s: &str) -> String {
+    s.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Project folder in `Prefabs/Props` form: forward slashes, no leading or trailing slash.
+fn folder_key(folder: &str) -> String {
+    folder.replace('\\', "/").trim_matches('/').to_lowercase()
+}
+
+/// 0..~2 similarity of a resource to the query: Jaro-Winkler on the file stem plus bonuses
+/// for exact, prefix and substring matches and for each query token found in the path.
+fn fuzzy_score(q: &str, tokens: &[String], file_name: &str, rel_path: &str) -> f64 {
+    let stem = file_name.rsplit_once('.').map(|(s, _)| s).unwrap_or(file_name);
+    let mut score = strsim::jaro_winkler(q, stem);
+    if stem == q {
+        score += 1.0;
+    } else if stem.starts_with(q) {
+        score += 0.5;
+    } else if stem.contains(q) {
+        score += 0.3;
+    }
+    if !tokens.is_empty() {
+        let rel = rel_path.to_lowercase();
+        let found = tokens.iter().filter(|t| rel.contains(t.as_str())).count();
+        score += 0.2 * found as f64 / tokens.len() as f64;
+    }
+    score
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_db(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("owltools_prefab_db_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir.join("prefab_index.sqlite")
+    }
+
+    /// Builds an index as schema `version` left it, with `rows` prefabs under the svn root.
+    fn legacy_db(path: &Path, version: i64, rows: usize) {
+        let conn = Connection::open(path).unwrap();
+        let ext_col = if version >= 2 { "ext TEXT NOT NULL," } else { "" };
+        let root_col = if version >= 3 { "root TEXT NOT NULL," } else { "" };
+        conn.execute_batch(&format!(
+            "CREATE TABLE meta (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+             CREATE TABLE resources (
+                 id INTEGER PRIMARY KEY, {root_col} abs_path TEXT NOT NULL UNIQUE, meta_path TEXT NOT NULL,
+                 file_name TEXT NOT NULL, guid TEXT, rel_path TEXT NOT NULL, name_value TEXT NOT NULL,
+                 {ext_col} class TEXT NOT NULL, mtime REAL NOT NULL
+             );
+             CREATE VIRTUAL TABLE resources_fts USING fts5(
+                 file_name, rel_path, content='resources', content_rowid='id'
+             );
+             CREATE TRIGGER resources_ai AFTER INSERT ON resources BEGIN
+                 INSERT INTO resources_fts(rowid, file_name, rel_path) VALUES (new.id, new.file_name, new.rel_path);
+             END;
+             CREATE TRIGGER resources_ad AFTER DELETE ON resources BEGIN
+                 INSERT INTO resources_fts(resources_fts, rowid, file_name, rel_path)
+                     VALUES ('delete', old.id, old.file_name, old.rel_path);
+             END;"
+        ))
+        .unwrap();
+        if version >= 3 {
+            conn.execute_batch(
+                "CREATE TABLE roots (name TEXT PRIMARY KEY, path TEXT NOT NULL, priority INTEGER NOT NULL, generated TEXT);
+                 INSERT INTO roots VALUES ('base', 'C:/svn', 0, '2024-01-01');",
+            )
+            .unwrap();
+        }
+        conn.execute("INSERT INTO meta VALUES ('schema_version', ?1)", [version.to_string()]).unwrap();
+        conn.execute("INSERT INTO meta VALUES ('svn_root', 'C:/svn')", []).unwrap();
+        for i in 0..rows {
+            let mut cols = vec!["abs_path", "meta_path", "file_name", "guid", "rel_path", "name_value", "class", "mtime"];
+            let mut vals: Vec<String> = vec![
+                format!("C:/svn/Prefabs/Crate_{:02}.et", i),
+                format!("C:/svn/Prefabs/Crate_{:02}.et.meta", i),
+                format!("crate_{:02}.et", i),
+                format!("{:016X}", 0xABC0 + i),
+                format!("Prefabs/Crate_{:02}.et", i),
+                format!("{{{:016X}}}Prefabs/Crate_{:02}.et", 0xABC0 + i, i),
+                "EntityTemplate".into(),
+                "12.5".into(),
+            ];
+            if version >= 2 {
+                cols.push("ext");
+                vals.push("et".into());
+            }
+            if version >= 3 {
+                cols.push("root");
+                vals.push("base".into());
+            }
+            let marks: Vec<String> = (1..=cols.len()).map(|n| format!("?{}", n)).collect();
+            let sql = format!("INSERT INTO resources({}) VALUES ({})", cols.join(", "), marks.join(", "));
+            conn.execute(&sql, rusqlite::params_from_iter(&vals)).unwrap();
+        }
+    }
+
+    fn assert_migrated(version: i64) {
+        let path = temp_db(&format!("v{}", version));
+        legacy_db(&path, version, 5);
+
+        let db = open_at(&path, None).unwrap();
+        assert_eq!(db.meta("schema_version").unwrap().as_deref(), Some("4"));
+        assert_eq!(db.recovered().unwrap(), None);
+        assert_eq!(db.count().unwrap(), 5);
+        assert_eq!(db.count_ext("et").unwrap(), 5);
+
+        let hit = db.by_guid(&format!("{:016X}", 0xABC3)).unwrap();
+        assert_eq!(hit.rel_path, "Prefabs/Crate_03.et");
+        assert_eq!(hit.root, BASE_ROOT);
+        let roots = db.roots().unwrap();
+        assert_eq!(roots.len(), 1);
+        assert_eq!(roots[0].root.name, BASE_ROOT);
+        assert_eq!(roots[0].count, 5);
+        let hits = db.search("crate_02", 10, &[]).unwrap();
+        assert_eq!(hits.first().map(|h| h.resource.file_name.as_str()), Some("crate_02.et"));
+
+        // Reopening a migrated index leaves it alone.
+        drop(db);
+        assert_eq!(open_at(&path, None).unwrap().count().unwrap(), 5);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn migrates_from_schema_1() {
+        assert_migrated(1);
+    }
+
+    #[test]
+    fn migrates_from_schema_2() {
+        assert_migrated(2);
+    }
+
+    #[test]
+    fn migrates_from_schema_3() {
+        assert_migrated(3);
+    }
+
+    #[test]
+    fn failed_migration_keeps_the_old_index() {
+        let path = temp_db("failed");
+        // A schema 2 index that already has the column migration 2 -> 3 adds.
+        legacy_db(&path, 3, 4);
+        Connection::open(&path).unwrap().execute("UPDATE meta SET value = '2' WHERE key = 'schema_version'", []).unwrap();
+
+        let db = open_at(&path, None).unwrap();
+        assert_eq!(db.count().unwrap(), 0);
+        assert_eq!(db.meta("schema_version").unwrap().as_deref(), Some("4"));
+        let (backup, reason) = db.recovered().unwrap().unwrap();
+        assert!(reason.starts_with("could not be migrated (migration to schema 3 failed"), "{}", reason);
+
+        let old = Connection::open(&backup).unwrap();
+        let rows: i64 = old.query_row("SELECT COUNT(*) FROM resources", [], |r| r.get(0)).unwrap();
+        assert_eq!(rows, 4);
+        let version: String =
+            old.query_row("SELECT value FROM meta WHERE key = 'schema_version'", [], |r| r.get(0)).unwrap();
+        assert_eq!(version, "2");
+
+        db.clear_recovered().unwrap();
+        assert_eq!(db.recovered().unwrap(), None);
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+
+    #[test]
+    fn newer_schema_is_set_aside() {
+        let path = temp_db("newer");
+        legacy_db(&path, 3, 2);
+        Connection::open(&path).unwrap().execute("UPDATE meta SET value = '99' WHERE key = 'schema_version'", []).unwrap();
+
+        let db = open_at(&path, None).unwrap();
+        assert_eq!(db.count().unwrap(), 0);
+        let (backup, reason) = db.recovered().unwrap().unwrap();
+        assert_eq!(reason, "could not be migrated (unknown schema version 99)");
+        assert!(Path::new(&backup).exists());
+        let _ = fs::remove_dir_all(path.parent().unwrap());
+    }
+}
@@ -0,0 +1,1361 @@
+// PrefabDST: scans the _dst folder next to a building .xob (phases, debris, collider
+// GeometryParams) and renders destructible prefabs from a preset template.
+//
+// Nothing in here depends on Tauri; progress goes through a `LogSink`.
+
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::time::Duration;
+use walkdir::WalkDir;
+
+use crate::log_sink::LogSink;
+use crate::{blender_worker, emit_prefabdst_log, enfusion_text, extract_cache, fbx, write_plan};
+use crate::{detect_newline, gen_guid16, read_xob_object_field_from_meta, rel_from_known_roots};
+
+/// Offsets by key for every FBX Blender managed to import; `None` when it has no UCX offset.
+/// Files Blender could not open, or a failed run, leave no entry.
+async fn extract_ucx_offsets_bulk_with_blender(
+    app: &dyn LogSink,
+    pairs: &Vec<(String, PathBuf)>,
+) -> std::collections::HashMap<String, Option<(f32, f32, f32)>> {
+    let mut out: std::collections::HashMap<String, Option<(f32, f32, f32)>> = std::collections::HashMap::new();
+    if pairs.is_empty() { return out; }
+
+    let launch = match blender_worker::resolve_launch() {
+        Some(l) => l,
+        None => {
+            emit_prefabdst_log(app, "info", "Blender debris offset: blender.exe not configured — skip".to_string(), None, None);
+            return out;
+        }
+    };
+    emit_prefabdst_log(app, "info", format!("Debris offset: bulk running {}", launch.exe.to_string_lossy()), None, None);
+
+    let mut list: Vec<(String, String)> = Vec::new();
+    for (key, pb) in pairs {
+        list.push((key.clone(), pb.to_string_lossy().to_string()));
+    }
+    let res = blender_worker::request(
+        &launch,
+        "ucx_offsets",
+        json!({ "files": list }),
+        Duration::from_secs(60 * (5 + pairs.len() as u64)),
+        Duration::from_secs(60 * 5),
+        &|_| {},
+    )
+    .await;
+    match res {
+        Ok(v) => {
+            if let Some(obj) = v.as_object() {
+                for (k, val) in obj.iter() {
+                    if val.is_null() {
+                        out.insert(k.to_lowercase(), None);
+                    } else if let (Some(x), Some(y), Some(z)) = (
+                        val.get("x").and_then(|n| n.as_f64()),
+                        val.get("y").and_then(|n| n.as_f64()),
+                        val.get("z").and_then(|n| n.as_f64()),
+                    ) {
+                        out.insert(k.to_lowercase(), Some((x as f32, y as f32, z as f32)));
+                    }
+                }
+            }
+        }
+        Err(e) => {
+            emit_prefabdst_log(app, "warn", format!("Blender debris offset bulk failed: {}", e), None, None);
+        }
+    }
+    out
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct MetaEntry { pub guid: String, pub path: String }
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FullDstZoneInfo {
+    pub part_id: String,
+    pub debris: Vec<MetaEntry>,
+    pub colliders: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct FullDstScanResult {
+    pub base_guid: String,
+    pub base_path: String,
+    pub v2_guid: String,
+    pub v2_path: String,
+    pub zones: Vec<FullDstZoneInfo>,
+}
+
+pub async fn read_meta(xob_path: String) -> Result<MetaEntry, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    match read_xob_object_field_from_meta(&xob_abs) {
+        Ok((g, p)) => Ok(MetaEntry { guid: g, path: p }),
+        Err(_) => Ok(MetaEntry { guid: String::new(), path: xob_path }),
+    }
+}
+
+pub async fn find_ruin_xob(xob_path: String) -> Result<Option<String>, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() {
+        return Err("Invalid xob path".into());
+    }
+    let parent = xob_abs
+        .parent()
+        .ok_or_else(|| "Invalid xob directory".to_string())?
+        .to_path_buf();
+    let parent_parent = parent.parent().map(|p| p.to_path_buf());
+    let stem0 = xob_abs
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| "Invalid xob file name".to_string())?
+        .to_string();
+    let stem = Regex::new(r"(?i)_(ruin|ruined)$")
+        .unwrap()
+        .replace(&stem0, "")
+        .to_string();
+
+    let rx = Regex::new(&format!(r"(?i)^{}_(ruin|ruined)\.xob$", regex::escape(&stem)))
+        .map_err(|e| format!("Regex error: {}", e))?;
+
+    fn scan_root(root: &Path, rx: &Regex) -> Option<String> {
+        let mut best_ruin: Option<String> = None;
+        let mut best_ruined: Option<String> = None;
+        for ent in WalkDir::new(root)
+            .follow_links(false)
+            .max_depth(6)
+            .into_iter()
+            .filter_map(|e| e.ok())
+        {
+            let p = ent.path();
+            if !p.is_file() {
+                continue;
+            }
+            let name = match p.file_name().and_then(|s| s.to_str()) {
+                Some(s) => s,
+                None => continue,
+            };
+            if !rx.is_match(name) {
+                continue;
+            }
+            let lower = name.to_lowercase();
+            let abs = p.to_string_lossy().to_string();
+            if lower.ends_with("_ruin.xob") {
+                best_ruin = Some(abs);
+                break;
+            }
+            if lower.ends_with("_ruined.xob") {
+                best_ruined = Some(abs);
+            }
+        }
+        best_ruin.or(best_ruined)
+    }
+
+    if let Some(found) = scan_root(&parent, &rx) {
+        return Ok(Some(found));
+    }
+    if let Some(pp) = parent_parent {
+        if let Some(found) = scan_root(&pp, &rx) {
+            return Ok(Some(found));
+        }
+    }
+    Ok(None)
+}
+
+fn parse_geometry_param_names(v2_meta_text: &str) -> Vec<String> {
+    // Extract names from lines like:
+    //  GeometryParam UTM_Base_Ruin_base {
+    //  GeometryParam "UTM_House_..." {
+    let re = Regex::new(r#"(?m)^\s*GeometryParam\s+(?:\"([^\"]+)\"|([^\"\s\{]+))\s*\{"#).unwrap();
+    let mut out: Vec<String> = Vec::new();
+    for cap in re.captures_iter(v2_meta_text) {
+        let name = cap.get(1).or_else(|| cap.get(2)).map(|m| m.as_str()).unwrap_or("");
+        let n = name.trim();
+        if !n.is_empty() {
+            out.push(n.to_string());
+        }
+    }
+    // Preserve order but remove duplicates
+    let mut seen: HashSet<String> = HashSet::new();
+    let mut dedup: Vec<String> = Vec::new();
+    for n in out {
+        let key = n.to_lowercase();
+        if seen.insert(key) {
+            dedup.push(n);
+        }
+    }
+    dedup
+}
+
+fn geometry_param_belongs_to_part(name: &str, part_id: &str) -> bool {
+    let n = name.to_lowercase();
+    let p = part_id.to_lowercase();
+    if p.is_empty() { return false; }
+
+    // Include exact part tags commonly used by Arma prefabs
+    if n.contains(&format!("id-{}", p)) { return true; }
+    if n.contains(&format!("fdst_id-{}", p)) { return true; }
+    // VIS tags like: ..._VIS-A^B, ..._VIS-!A, ..._VIS-!A^!B
+    if n.contains(&format!("vis-{}", p)) { return true; }
+    if n.contains(&format!("vis-!{}", p)) { return true; }
+    false
+}
+
+pub async fn scan_full_dst(xob_path: String) -> Result<FullDstScanResult, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() { return Err("Invalid xob path".into()); }
+
+    let (base_guid, base_path) = read_xob_object_field_from_meta(&xob_abs)?;
+
+    let stem = xob_abs
+        .file_stem()
+        .and_then(|s| s.to_str())
+        .ok_or_else(|| "Invalid xob file name".to_string())?;
+    let parent = xob_abs.parent().ok_or_else(|| "Invalid xob directory".to_string())?;
+
+    // sibling v2 model
+    let v2_xob = parent.join(format!("{}_V2_dst.xob", stem));
+    if !v2_xob.is_file() {
+        return Err("V2 dst file not found next to base xob".into());
+    }
+    let (v2_guid, v2_path) = read_xob_object_field_from_meta(&v2_xob)?;
+
+    // Parse GeometryParams from v2 meta
+    let v2_meta_path = PathBuf::from(format!("{}.meta", v2_xob.to_string_lossy()));
+    let v2_meta_text = fs::read_to_string(&v2_meta_path)
+        .map_err(|e| format!("Failed to read v2 .xob.meta: {}", e))?;
+    let mut geom_params = parse_geometry_param_names(&v2_meta_text);
+
+    let base_meta_path = PathBuf::from(format!("{}.meta", xob_abs.to_string_lossy()));
+    if let Ok(base_meta_text) = fs::read_to_string(&base_meta_path) {
+        let base_params = parse_geometry_param_names(&base_meta_text)
+            .into_iter()
+            .filter(|n| n.to_lowercase().contains("fdst_"));
+        let mut seen: HashSet<String> = geom_params.iter().map(|s| s.to_lowercase()).collect();
+        for p in base_params {
+            let key = p.to_lowercase();
+            if seen.insert(key) {
+                geom_params.push(p);
+            }
+        }
+    }
+
+    // Scan debris from Dst folder
+    let mut debris_by_part: BTreeMap<String, Vec<(i32, MetaEntry)>> = BTreeMap::new();
+    if let Some(dst_dir) = find_dst_directory(&xob_abs) {
+        let rx = Regex::new(&format!(
+            r"(?i)^(?:{}_V2_dst|{}_V2_dst_FDST|{}_v2_FDST_dst|{}_FDST)_ID-(?P<p>[A-Z])(?:_VIS-[^_]+)?_dbr_(?P<d>\d+)\.xob$",
+            regex::escape(stem),
+            regex::escape(stem),
+            regex::escape(stem),
+            regex::escape(stem)
+        )).unwrap();
+        if let Ok(rd) = fs::read_dir(&dst_dir) {
+            for ent in rd.flatten() {
+                let p = ent.path();
+                if !p.is_file() { continue; }
+                let name = match p.file_name().and_then(|s| s.to_str()) { Some(s) => s, None => continue };
+                if let Some(c) = rx.captures(name) {
+                    let pid = c.name("p").map(|m| m.as_str()).unwrap_or("").to_string();
+                    let dn = c.name("d").map(|m| m.as_str()).unwrap_or("0").parse::<i32>().unwrap_or(0);
+                    let entry = match read_xob_object_field_from_meta(&p) {
+                        Ok((g, r)) => MetaEntry { guid: g, path: r },
+                        Err(_) => MetaEntry { guid: String::new(), path: rel_from_known_roots(&p) },
+                    };
+                    debris_by_part.entry(pid).or_default().push((dn, entry));
+                }
+            }
+        }
+    }
+
+    // Determine which part IDs exist.
+    // Previously we built zones only from debris. That breaks collider replacement when a model has
+    // no dbr files. Include parts inferred from GeometryParam names as well.
+    let mut part_ids: BTreeMap<String, ()> = BTreeMap::new();
+    for pid in debris_by_part.keys() {
+        part_ids.insert(pid.clone(), ());
+    }
+    {
+        let re_part = Regex::new(r"(?i)(?:id-|fdst_id-|vis-|vis-!)(?P<p>[a-z])").unwrap();
+        for n in &geom_params {
+            if let Some(c) = re_part.captures(n) {
+                let pid = c.name("p").map(|m| m.as_str()).unwrap_or("").to_uppercase();
+                if pid.len() == 1 {
+                    part_ids.insert(pid, ());
+                }
+            }
+        }
+    }
+
+    // Sort debris by dbr number and build zones list.
+    let mut zones: Vec<FullDstZoneInfo> = Vec::new();
+    for (pid, _) in part_ids {
+        let mut items = debris_by_part.remove(&pid).unwrap_or_default();
+        items.sort_by_key(|(n, _)| *n);
+        let debris: Vec<MetaEntry> = items.into_iter().map(|(_, e)| e).collect();
+        let colliders: Vec<String> = geom_params
+            .iter()
+            .filter(|n| geometry_param_belongs_to_part(n, &pid))
+            .cloned()
+            .collect();
+        zones.push(FullDstZoneInfo { part_id: pid, debris, colliders });
+    }
+
+    Ok(FullDstScanResult { base_guid, base_path, v2_guid, v2_path, zones })
+}
+
+fn extract_template_body(all_text: &str) -> String {
+    // Prefer content between '--- TEMPLATE ---' and '--- END TEMPLATE ---' (case-insensitive)
+    let nl = detect_newline(all_text);
+    let mut start: Option<usize> = None;
+    let mut end: Option<usize> = None;
+    let mut offset = 0usize;
+    for ln in all_text.lines() {
+        let low = ln.trim().to_lowercase();
+        if start.is_none() && low.contains("--- template ---") {
+            start = Some(offset + ln.len() + nl.len());
+        } else if start.is_some() && low.contains("--- end template ---") {
+            end = Some(offset);
+            break;
+        }
+        offset += ln.len() + nl.len();
+    }
+    if let (Some(s), Some(e)) = (start, end) {
+        return all_text[s..e].to_string();
+    }
+    // Fallback: strip header only
+    strip_preset_header(all_text)
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScrDebrisItem { pub guid: String, pub path: String }
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScrPhaseItem { pub pid: String, pub model_guid: String, pub model_path: String, pub debris: Vec<ScrDebrisItem> }
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct ScrDstScanResult { pub base_guid: String, pub base_path: String, pub phases: Vec<ScrPhaseItem> }
+
+pub async fn scan_scr_dst(xob_path: String) -> Result<ScrDstScanResult, String> {
+    let xob_abs = PathBuf::from(&xob_path);
+    if !xob_abs.is_file() { return Err("Invalid xob path".into()); }
+    let (base_guid, base_path) = read_xob_object_field_from_meta(&xob_abs)?;
+    let (phases_vec, debris_map) = scan_dst_for_phases_debris(&xob_abs);
+    let mut phases: Vec<ScrPhaseItem> = Vec::new();
+    for (pid, (g, p)) in phases_vec {
+        let debris_items: Vec<ScrDebrisItem> = debris_map
+            .get(&pid)
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(dg, dp)| ScrDebrisItem { guid: dg, path: dp })
+            .collect();
+        phases.push(ScrPhaseItem { pid, model_guid: g, model_path: p, debris: debris_items });
+    }
+    Ok(ScrDstScanResult { base_guid, base_path, phases })
+}
+
+#[derive(Default)]
+struct PresetHeader {
+    id: String,
+    title: String,
+    project: String,
+    generator: String,
+    description: String,
+}
+
+fn parse_preset_header(all_text: &str) -> PresetHeader {
+    let mut h = PresetHeader::default();
+    for ln in all_text.lines() {
+        let line = ln.trim();
+        if line.is_empty() { break; }
+        if let Some(col) = line.find(':') {
+            let k = line[..col].trim().to_lowercase();
+            let v = line[col+1..].trim();
+            match k.as_str() {
+                "id" => h.id = v.to_string(),
+                "title" => h.title = v.to_string(),
+                "project" => h.project = v.to_string(),
+                "generator" => h.generator = v.to_string(),
+                "description" => h.description = v.to_string(),
+                _ => {}
+            }
+        }
+    }
+    h
+}
+
+fn find_dst_directory(xob_abs: &Path) -> Option<PathBuf> {
+    let parent = xob_abs.parent()?;
+    let exact = parent.join("dst");
+    if exact.is_dir() { return Some(exact); }
+    // case-insensitive search among siblings
+    if let Ok(rd) = fs::read_dir(parent) {
+        for ent in rd.flatten() {
+            let p = ent.path();
+            if p.is_dir() {
+                if let Some(name) = p.file_name().and_then(|s| s.to_str()) {
+                    if name.eq_ignore_ascii_case("dst") {
+                        return Some(p);
+                    }
+                }
+            }
+        }
+    }
+    None
+}
+
+fn scan_dst_for_phases_debris(base_xob_abs: &Path) -> (Vec<(String, (String, String))>, std::collections::HashMap<String, Vec<(String, String)>>) {
+    let mut phases: Vec<(String, (String, String))> = Vec::new();
+    let mut debris: std::collections::HashMap<String, Vec<(String, String)>> = std::collections::HashMap::new();
+    let Some(dst_dir) = find_dst_directory(base_xob_abs) else { return (phases, debris); };
+
+    let stem = base_xob_abs.file_stem().and_then(|s| s.to_str()).unwrap_or("");
+    let base_cf = stem;
+    let rx_phase = Regex::new(&format!(r"(?i){}{}_dst_(?P<p>\d+)\.xob$", regex::escape(""), regex::escape(&base_cf))).unwrap();
+    let rx_debris = Regex::new(&format!(r"(?i){}{}_dst_(?P<p>\d+)_dbr_(?P<d>\d+)\.xob$", regex::escape(""), regex::escape(&base_cf))).unwrap();
+
+    if let Ok(rd) = fs::read_dir(&dst_dir) {
+        for ent in rd.flatten() {
+            let p = ent.path();
+            if !p.is_file() { continue; }
+            let name = match p.file_name().and_then(|s| s.to_str()) { Some(s)=>s, None=>continue };
+            if let Some(c) = rx_phase.captures(name) {
+                let pid_raw = c.name("p").map(|m| m.as_str()).unwrap_or("");
+                let pid = format!("{:0>2}", pid_raw);
+                match read_xob_object_field_from_meta(&p) {
+                    Ok((g, r)) => phases.push((pid, (g, r))),
+                    Err(_) => phases.push((pid, (String::new(), String::new()))),
+                }
+                continue;
+            }
+            if let Some(c) = rx_debris.captures(name) {
+                let pid_raw = c.name("p").map(|m| m.as_str()).unwrap_or("");
+                let pid = format!("{:0>2}", pid_raw);
+                match read_xob_object_field_from_meta(&p) {
+                    Ok((g, r)) => debris.entry(pid).or_default().push((g, r)),
+                    Err(_) => debris.entry(pid).or_default().push((String::new(), String::new())),
+                }
+            }
+        }
+    }
+    // Ensure every PID that has debris also has a phase entry (even if empty)
+    {
+        use std::collections::HashSet;
+        let have: HashSet<String> = phases.iter().map(|(p, _)| p.clone()).collect();
+        for pid in debris.keys() {
+            if !have.contains(pid) {
+                phases.push((pid.clone(), (String::new(), String::new())));
+            }
+        }
+    }
+    // sort phases by pid numeric
+    phases.sort_by_key(|(pid, _)| pid.parse::<i32>().unwrap_or(9999));
+    // sort debris by trailing _dbr_YY number inferred from path, fallback by name
+    for (_pid, arr) in debris.iter_mut() {
+        arr.sort_by_key(|(_g, path)| {
+            let name = Path::new(path).file_name().and_then(|s| s.to_str()).unwrap_or("");
+            let re = Regex::new(r"(?i)_dbr_(\d+)\.xob$").unwrap();
+            if let Some(c) = re.captures(name) { c[1].parse::<i32>().unwrap_or(9999) } else { 9999 }
+        });
+    }
+    (phases, debris)
+}
+
+fn render_scr_destructible_template(
+    preset_text: &str,
+    base_guid: &str,
+    base_res: &str,
+    base_xob_abs: &Path,
+    override_data: Option<&ScrDstScanResult>,
+) -> String {
+    let nl = detect_newline(preset_text);
+    let mut body = extract_template_body(preset_text);
+
+    // Generate IDs
+    let entity_id = gen_guid16();
+    let mesh_id = gen_guid16();
+    let id1 = gen_guid16();
+    let id2 = gen_guid16();
+    let id3 = gen_guid16();
+    let id4 = gen_guid16();
+    let id5 = gen_guid16();
+    let id6 = gen_guid16();
+
+    // Base object line (prefer override base if provided)
+    let (use_guid, use_res) = if let Some(ov) = override_data {
+        if !ov.base_guid.is_empty() || !ov.base_path.is_empty() {
+            (ov.base_guid.clone(), ov.base_path.clone())
+        } else { (base_guid.to_string(), base_res.to_string()) }
+    } else {
+        (base_guid.to_string(), base_res.to_string())
+    };
+    let base_object_line = if !use_guid.is_empty() && !use_res.is_empty() {
+        format!("   Object \"{{{}}}{}\"", use_guid, use_res)
+    } else if !base_guid.is_empty() && !base_res.is_empty() {
+        format!("   Object \"{{{}}}{}\"", base_guid, base_res)
+    } else {
+        "   Object \"\"".to_string()
+    };
+
+    // Scan dst for phases and debris, unless override provided from UI
+    let (phases, debris_map) = if let Some(ov) = override_data {
+        let mut phases: Vec<(String, (String, String))> = Vec::new();
+        let mut debris: std::collections::HashMap<String, Vec<(String, String)>> = std::collections::HashMap::new();
+        for ph in &ov.phases {
+            phases.push((ph.pid.clone(), (ph.model_guid.clone(), ph.model_path.clone())));
+            let mut arr: Vec<(String, String)> = Vec::new();
+            for d in &ph.debris { arr.push((d.guid.clone(), d.path.clone())); }
+            debris.insert(ph.pid.clone(), arr);
+        }
+        (phases, debris)
+    } else {
+        scan_dst_for_phases_debris(base_xob_abs)
+    };
+    let first_phase = phases.first().map(|(_pid,(g,r))| (g.clone(), r.clone()));
+    let last_phase = phases.last().map(|(_pid,(g,r))| (g.clone(), r.clone()));
+    let first_pid = phases.first().map(|(pid, _)| pid.clone());
+    let last_pid = phases.last().map(|(pid, _)| pid.clone());
+
+    let first_phase_model = first_phase.map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).unwrap_or_else(|| "\"\"".to_string());
+    let last_phase_model = last_phase.map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).unwrap_or_else(|| "\"\"".to_string());
+
+    let join_debris = |pid_opt: Option<String>| -> String {
+        if let Some(pid) = pid_opt {
+            if let Some(arr) = debris_map.get(&pid) {
+                let parts: Vec<String> = arr.iter().map(|(g,r)| format!("\"{{{}}}{}\"", g, r)).collect();
+                return parts.join(" ");
+            }
+        }
+        String::new()
+    };
+    let first_phase_debris = join_debris(first_pid);
+    let last_phase_debris = join_debris(last_pid);
+
+    // Replace placeholders
+    let replaces = [
+        ("{{ENTITY_ID}}", &entity_id),
+        ("{{MESH_ID_BRACED}}", &format!("{{{}}}", mesh_id)),
+        ("{{ID1}}", &id1),
+        ("{{ID2}}", &id2),
+        ("{{ID3}}", &id3),
+        ("{{ID4}}", &id4),
+        ("{{ID5}}", &id5),
+        ("{{ID6}}", &id6),
+        ("{{BASE_OBJECT_LINE}}", &base_object_line),
+        ("{{FIRST_PHASE_MODEL}}", &first_phase_model),
+        ("{{LAST_PHASE_MODEL}}", &last_phase_model),
+        ("{{FIRST_PHASE_DEBRIS}}", &first_phase_debris),
+        ("{{LAST_PHASE_DEBRIS}}", &last_phase_debris),
+    ];
+    for (k, v) in replaces {
+        body = body.replace(k, v);
+    }
+    // Ensure newline style remains consistent
+    if nl == "\r\n" { body = body.replace("\n", "\r\n"); }
+    body
+}
+
+fn strip_preset_header(body: &str) -> String {
+    // Remove leading "KEY: value" lines until first blank line.
+    let nl = detect_newline(body);
+    let mut out: Vec<&str> = Vec::new();
+    let mut skipping = true;
+    let re_kv = Regex::new(r"^\s*[A-Za-z_][A-Za-z0-9_\-]*\s*:\s*.*$").unwrap();
+    for ln in body.lines() {
+        if skipping {
+            if ln.trim().is_empty() {
+                skipping = false;
+                continue;
+            }
+            if re_kv.is_match(ln) {
+                continue;
+            }
+            // Unexpected line: treat as start of body
+            skipping = false;
+        }
+        out.push(ln);
+    }
+    out.join(nl)
+}
+
+fn replace_object_marker(text: &str, guid: &str, res: &str) -> String {
+    // Replace annotated marker line like: Object "{guid from xob input}path from xob input"
+    let rep = format!("Object \"{{{}}}{}\"", guid, res);
+    let re = Regex::new(r#"Object\s+"[^"\n]*guid\s+from\s+xob\s+input[^"\n]*""#).unwrap();
+    re.replace_all(text, rep).to_string()
+}
+
+fn replace_v2_model_marker(text: &str, guid: &str, res: &str) -> String {
+    let rep = format!("Model \"{{{}}}{}\"", guid, res);
+    let re = Regex::new(r#"Model\s+"[^"\n]*same\s+name_v2_dst[^"\n]*""#).unwrap();
+    re.replace_all(text, rep).to_string()
+}
+
+fn replace_gen_guid_markers(text: &str) -> String {
+    let re_braced = Regex::new(r"\{\s*gen\s+guid\s*\}").unwrap();
+    let re_quoted = Regex::new(r#""\s*gen\s+guid\s*""#).unwrap();
+    let s = re_braced
+        .replace_all(text, |_caps: &regex::Captures| format!("{{{}}}", gen_guid16()))
+        .to_string();
+    re_quoted
+        .replace_all(&s, |_caps: &regex::Captures| format!("\"{}\"", gen_guid16()))
+        .to_string()
+}
+
+fn replace_gen_vec3_markers(text: &str) -> String {
+    // The UI/build pipeline should not generate random vec3 values.
+    // Default to 0 0 0 for any {gen vec3} marker.
+    let re = Regex::new(r"\{\s*gen\s+vec3\s*\}").unwrap();
+    re.replace_all(text, "0 0 0").to_string()
+}
+
+fn find_fractalparts_bounds(text: &str) -> Option<(usize, usize, usize)> {
+    // Returns (open_brace_idx, close_brace_idx, keyword_idx)
+    let (kw, open, close) = enfusion_text::find_block(text, "FractalParts")?;
+    Some((open, close, kw))
+}
+
+fn extract_fractalpart_blocks(inner: &str) -> (Vec<(char, String)>, Vec<String>) {
+    // Parse by lines with brace counting, matching the Python approach.
+    let lines: Vec<&str> = inner.lines().collect();
+    let mut blocks: Vec<(char, String)> = Vec::new();
+    let mut others: Vec<String> = Vec::new();
+    let mut i = 0usize;
+    while i < lines.len() {
+        let ln = lines[i];
+        if ln.trim_start().starts_with("FractalPartData") {
+            let mut depth: i32 = 0;
+            let mut j = i;
+            let mut started = false;
+            while j < lines.len() {
+                let l2 = lines[j];
+                if l2.contains('{') {
+                    started = true;
+                    depth += l2.matches('{').count() as i32;
+                }
+                if l2.contains('}') {
+                    depth -= l2.matches('}').count() as i32;
+                }
+                j += 1;
+                if started && depth <= 0 {
+                    break;
+                }
+            }
+            let nl = detect_newline(inner);
+            let blk = lines[i..j].join(nl);
+            let letter = Regex::new(r##"\bPartId\s+\"([A-Z])\""##)
+                .unwrap()
+                .captures(&blk)
+                .and_then(|c| c.get(1))
+                .and_then(|m| m.as_str().chars().next())
+                .unwrap_or('A');
+            blocks.push((letter, blk));
+            i = j;
+        } else {
+            others.push(ln.to_string());
+            i += 1;
+        }
+    }
+    blocks.sort_by_key(|(c, _)| *c as u32);
+    (blocks, others)
+}
+
+fn clone_fractalpart_block(block: &str, src_letter: char, new_letter: char) -> String {
+    let mut out = block.to_string();
+    let re_partid = Regex::new(&format!(r##"\bPartId\s+\"{}\""##, regex::escape(&src_letter.to_string()))).unwrap();
+    out = re_partid.replace_all(&out, format!("PartId \"{}\"", new_letter)).to_string();
+
+    let re_q_letter = Regex::new(&format!(r##"\"{}\""##, regex::escape(&src_letter.to_string()))).unwrap();
+    out = re_q_letter.replace_all(&out, format!("\"{}\"", new_letter)).to_string();
+
+    let re_q_letter_num = Regex::new(&format!(r##"\"{}(\d+)\""##, regex::escape(&src_letter.to_string()))).unwrap();
+    out = re_q_letter_num
+        .replace_all(&out, |caps: &regex::Captures| format!("\"{}{}\"", new_letter, &caps[1]))
+        .to_string();
+
+    let re_guid = Regex::new(r##"\"\{[0-9A-Fa-f]{16}\}\""##).unwrap();
+    out = re_guid
+        .replace_all(&out, |_caps: &regex::Captures| format!("\"{{{}}}\"", gen_guid16()))
+        .to_string();
+
+    // Also update template markers that encode the part letter.
+    // Example: {{DEBRIS_ID-A}} / {{COLLIDERS_ID-A}}
+    out = out.replace(
+        &format!("{{{{DEBRIS_ID-{}}}}}", src_letter),
+        &format!("{{{{DEBRIS_ID-{}}}}}", new_letter)
+    );
+    out = out.replace(
+        &format!("{{{{COLLIDERS_ID-{}}}}}", src_letter),
+        &format!("{{{{COLLIDERS_ID-{}}}}}", new_letter)
+    );
+    out
+}
+
+fn format_mass(mass: f32) -> String {
+    if !mass.is_finite() {
+        return "0".to_string();
+    }
+    let m = mass.max(0.0);
+    if (m.fract()).abs() < 0.00001 {
+        format!("{:.0}", m)
+    } else {
+        let s = format!("{}", m);
+        s
+    }
+}
+
+fn build_debris_infos_block(items: &[MetaEntry], indent: &str, debris_mass: f32) -> String {
+    // Build a list of SCR_DebrisInfo blocks.
+    // We don't currently have offsets/mass; use neutral defaults.
+    if items.is_empty() {
+        return format!("{}", "");
+    }
+    let mass_s = format_mass(debris_mass);
+    let mut lines: Vec<String> = Vec::new();
+    for e in items {
+        let info_id = gen_guid16();
+        let ap_id = gen_guid16();
+        lines.push(format!("{}SCR_DebrisInfo \"{{{}}}\" {{", indent, info_id));
+        lines.push(format!("{} ModelPrefab \"{{{}}}{}\"", indent, e.guid, e.path));
+        lines.push(format!("{} LocalTransform AttachPoint \"{{{}}}\" {{", indent, ap_id));
+        lines.push(format!("{}  Offset 0 0 0", indent));
+        lines.push(format!("{}  Angles 0.00001 0 0", indent));
+        lines.push(format!("{} }}", indent));
+        lines.push(format!("{} m_fMass {}", indent, mass_s));
+        lines.push(format!("{}}}", indent));
+    }
+    lines.join("\n")
+}
+
+fn build_debris_infos_block_with_offsets(
+    items: &[MetaEntry],
+    indent: &str,
+    debris_mass: f32,
+    offsets_by_file: Option<&std::collections::HashMap<String, (f32, f32, f32)>>,
+) -> String {
+    if items.is_empty() {
+        return String::new();
+    }
+    let mass_s = format_mass(debris_mass);
+    let mut lines: Vec<String> = Vec::new();
+    for e in items {
+        let info_id = gen_guid16();
+        let ap_id = gen_guid16();
+        lines.push(format!("{}SCR_DebrisInfo \"{{{}}}\" {{", indent, info_id));
+        lines.push(format!("{} ModelPrefab \"{{{}}}{}\"", indent, e.guid, e.path));
+
+        // Default offset
+        let mut off_line = format!("{}  Offset 0 0 0", indent);
+        if let Some(map) = offsets_by_file {
+            let key = std::path::Path::new(&e.path)
+                .file_name()
+                .and_then(|s| s.to_str())
+                .unwrap_or("")
+                .to_lowercase();
+            if let Some((ox, oy, oz)) = map.get(&key) {
+                off_line = format!("{}  Offset {} {} {}", indent, ox, oy, oz);
+            }
+        }
+
+        lines.push(format!("{} LocalTransform AttachPoint \"{{{}}}\" {{", indent, ap_id));
+        lines.push(off_line);
+        lines.push(format!("{}  Angles 0.00001 0 0", indent));
+        lines.push(format!("{} }}", indent));
+        lines.push(format!("{} m_fMass {}", indent, mass_s));
+        lines.push(format!("{}}}", indent));
+    }
+    lines.join("\n")
+}
+
+fn build_colliders_line(tags: &[String]) -> String {
+    if tags.is_empty() {
+        return "\"\"".to_string();
+    }
+    tags.iter()
+        .map(|t| format!("\"{}\"", t))
+        .collect::<Vec<String>>()
+        .join(" ")
+}
+
+fn replace_full_dst_markers(text: &str, scan: &FullDstScanResult, debris_mass: f32) -> String {
+    // Replace {{DEBRIS_ID-X}} and {{COLLIDERS_ID-X}} markers.
+    let mut by_part: HashMap<String, (Vec<MetaEntry>, Vec<String>)> = HashMap::new();
+    for z in &scan.zones {
+        by_part.insert(z.part_id.clone(), (z.debris.clone(), z.colliders.clone()));
+    }
+
+    let re_debris = Regex::new(r"(?m)^(?P<indent>\s*)\{\{DEBRIS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
+    let re_cols = Regex::new(r"(?m)^(?P<indent>\s*)\{\{COLLIDERS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
+
+    let s = re_debris
+        .replace_all(text, |caps: &regex::Captures| {
+            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
+            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
+            let (debris, _cols) = by_part.get(pid).cloned().unwrap_or_default();
+            // Indent children one extra space relative to marker line.
+            let child_indent = format!("{} ", indent);
+            build_debris_infos_block(&debris, &child_indent, debris_mass)
+        })
+        .to_string();
+
+    re_cols
+        .replace_all(&s, |caps: &regex::Captures| {
+            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
+            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
+            let (_debris, cols) = by_part.get(pid).cloned().unwrap_or_default();
+            format!("{}{}", indent, build_colliders_line(&cols))
+        })
+        .to_string()
+}
+
+fn ensure_fractalparts_zone_count(text: &str, desired_count: usize) -> String {
+    let desired = desired_count.clamp(1, 26);
+    let nl = detect_newline(text);
+    let Some((open, close, _kw)) = find_fractalparts_bounds(text) else {
+        return text.to_string();
+    };
+    let inner = &text[(open + 1)..close];
+    let (blocks, other_lines) = extract_fractalpart_blocks(inner);
+
+    let mut existing: HashMap<char, String> = HashMap::new();
+    for (c, b) in blocks.iter() {
+        existing.insert(*c, b.clone());
+    }
+
+    let mut template_letter: Option<char> = None;
+    let mut template_block: Option<String> = None;
+    for (c, b) in blocks.iter().rev() {
+        if Regex::new(r##"\bPartId\s+\"([A-Z])\""##).unwrap().is_match(b) {
+            template_letter = Some(*c);
+            template_block = Some(b.clone());
+            break;
+        }
+    }
+    if template_block.is_none() {
+        if let Some((c, b)) = blocks.last() {
+            template_letter = Some(*c);
+            template_block = Some(b.clone());
+        }
+    }
+
+    let mut kept: Vec<String> = Vec::new();
+    for i in 0..desired {
+        let lt = (b'A' + (i as u8)) as char;
+        if let Some(b) = existing.get(&lt) {
+            kept.push(b.clone());
+        } else if let (Some(tb), Some(tl)) = (template_block.as_ref(), template_letter) {
+            kept.push(clone_fractalpart_block(tb, tl, lt));
+        }
+    }
+
+    let mut inner_new_lines: Vec<String> = Vec::new();
+    for blk in kept {
+        inner_new_lines.extend(blk.lines().map(|s| s.to_string()));
+    }
+    if other_lines.iter().any(|s| !s.trim().is_empty()) {
+        inner_new_lines.extend(other_lines);
+    }
+    let new_inner = inner_new_lines.join(nl);
+    format!("{}{}{}{}{}", &text[..(open + 1)], nl, new_inner, nl, &text[close..])
+}
+
+fn apply_zone_hp(text: &str, hp: i32) -> String {
+    let nl = detect_newline(text);
+    let Some((open, close, _kw)) = find_fractalparts_bounds(text) else {
+        return text.to_string();
+    };
+    let inner = &text[(open + 1)..close];
+    let (blocks, other_lines) = extract_fractalpart_blocks(inner);
+    let re_hp = Regex::new(r"(?m)^(\s*MaxHealth)\s+[0-9]+(?:\.[0-9]+)?").unwrap();
+    let mut out_blocks: Vec<String> = Vec::new();
+    for (_c, b) in blocks {
+        out_blocks.push(re_hp.replace_all(&b, |caps: &regex::Captures| format!("{} {}", &caps[1], hp)).to_string());
+    }
+    let mut inner_new_lines: Vec<String> = Vec::new();
+    for blk in out_blocks {
+        inner_new_lines.extend(blk.lines().map(|s| s.to_string()));
+    }
+    if other_lines.iter().any(|s| !s.trim().is_empty()) {
+        inner_new_lines.extend(other_lines);
+    }
+    let new_inner = inner_new_lines.join(nl);
+    format!("{}{}{}{}{}", &text[..(open + 1)], nl, new_inner, nl, &text[close..])
+}
+
+fn build_zone_fractal_from_preset(
+    preset_text: &str,
+    base_guid: &str,
+    base_res: &str,
+    v2_guid: Option<&str>,
+    v2_res: Option<&str>,
+    zone_count: usize,
+    hp_zone: i32,
+) -> String {
+    let mut s = strip_preset_header(preset_text);
+    s = replace_object_marker(&s, base_guid, base_res);
+    if let (Some(g), Some(r)) = (v2_guid, v2_res) {
+        s = replace_v2_model_marker(&s, g, r);
+    }
+    s = replace_gen_guid_markers(&s);
+    s = ensure_fractalparts_zone_count(&s, zone_count);
+    s = apply_zone_hp(&s, hp_zone);
+    s
+}
+
+fn replace_full_dst_markers_with_offsets(
+    text: &str,
+    scan: &FullDstScanResult,
+    debris_mass: f32,
+    offsets_by_file: &std::collections::HashMap<String, (f32, f32, f32)>,
+) -> String {
+    let mut by_part: HashMap<String, (Vec<MetaEntry>, Vec<String>)> = HashMap::new();
+    for z in &scan.zones {
+        by_part.insert(z.part_id.clone(), (z.debris.clone(), z.colliders.clone()));
+    }
+
+    let re_debris = Regex::new(r"(?m)^(?P<indent>\s*)\{\{DEBRIS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
+    let re_cols = Regex::new(r"(?m)^(?P<indent>\s*)\{\{COLLIDERS_ID-(?P<p>[A-Z])\}\}\s*$").unwrap();
+
+    let s = re_debris
+        .replace_all(text, |caps: &regex::Captures| {
+            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
+            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
+            let (debris, _cols) = by_part.get(pid).cloned().unwrap_or_default();
+            let child_indent = format!("{} ", indent);
+            build_debris_infos_block_with_offsets(&debris, &child_indent, debris_mass, Some(offsets_by_file))
+        })
+        .to_string();
+
+    re_cols
+        .replace_all(&s, |caps: &regex::Captures| {
+            let indent = caps.name("indent").map(|m| m.as_str()).unwrap_or("");
+            let pid = caps.name("p").map(|m| m.as_str()).unwrap_or("A");
+            let (_debris, cols) = by_part.get(pid).cloned().unwrap_or_default();
+            format!("{}{}", indent, build_colliders_line(&cols))
+        })
+        .to_string()
+}
+
+async fn extract_ucx_offset_with_blender(app: &dyn LogSink, fbx_abs: &Path) -> Option<(f32, f32, f32)> {
+    if !fbx_abs.is_file() {
+        return None;
+    }
+    let key = fbx_abs.to_string_lossy().to_lowercase();
+    let pairs = vec![(key.clone(), fbx_abs.to_path_buf())];
+    extract_ucx_offsets_bulk_with_blender(app, &pairs).await.remove(&key).flatten()
+}
+
+pub async fn collect_debris_offsets(
+    app: &dyn LogSink,
+    base_xob_abs: &Path,
+    scan: &FullDstScanResult,
+) -> std::collections::HashMap<String, (f32, f32, f32)> {
+    let Some(dst_dir) = find_dst_directory(base_xob_abs) else { return std::collections::HashMap::new(); };
+    let mut pairs: Vec<(String, PathBuf)> = Vec::new();
+    for z in &scan.zones {
+        for e in &z.debris {
+            if let Some(fname) = Path::new(&e.path).file_name().and_then(|s| s.to_str()) {
+                let fbx_abs = dst_dir.join(fname).with_extension("fbx");
+                if fbx_abs.is_file() {
+                    pairs.push((fname.to_lowercase(), fbx_abs));
+                }
+            }
+        }
+    }
+    if pairs.is_empty() { return std::collections::HashMap::new(); }
+
+    // Native FBX reader first; only files it cannot parse go to Blender.
+    let mut map: std::collections::HashMap<String, (f32, f32, f32)> = std::collections::HashMap::new();
+    let mut fallback: Vec<(String, PathBuf)> = Vec::new();
+    let mut cached = 0usize;
+    for (key, fbx_abs) in &pairs {
+        if let Some(hit) = extract_cache::get(fbx_abs).filter(|e| e.ucx_extracted) {
+            if let Some([x, y, z]) = hit.ucx_offset {
+                map.insert(key.clone(), (x, y, z));
+            }
+            cached += 1;
+            continue;
+        }
+        match fbx::read_models(fbx_abs) {
+            Ok(models) => {
+                let data = extract_cache::FbxExtract::from_models(&models);
+                if let Some([x, y, z]) = data.ucx_offset {
+                    map.insert(key.clone(), (x, y, z));
+                }
+                let extracted = data.ucx_extracted;
+                if let Err(e) = extract_cache::update(fbx_abs, |d| d.merge(data)) {
+                    emit_prefabdst_log(app, "warn", format!("Failed to update extraction cache: {}", e), None, None);
+                }
+                if !extracted {
+                    let msg = format!("Debris offset: {} has no user properties (falling back to Blender)", key);
+                    emit_prefabdst_log(app, "info", msg, None, None);
+                    fallback.push((key.clone(), fbx_abs.clone()));
+                }
+            }
+            Err(e) => {
+                emit_prefabdst_log(app, "warn", format!("Debris offset: {} (falling back to Blender)", e), None, None);
+                fallback.push((key.clone(), fbx_abs.clone()));
+            }
+        }
+    }
+    if cached > 0 {
+        emit_prefabdst_log(app, "info", format!("Debris offsets: {} FBX from cache", cached), None, None);
+    }
+    if !fallback.is_empty() {
+        let found = extract_ucx_offsets_bulk_with_blender(app, &fallback).await;
+        for (key, fbx_abs) in &fallback {
+            // Misses are cached too, so an unchanged FBX without an offset skips Blender next time.
+            let Some(&offset) = found.get(key) else { continue; };
+            let _ = extract_cache::update(fbx_abs, |d| {
+                d.ucx_extracted = true;
+                d.ucx_offset = offset.map(|(x, y, z)| [x, y, z]);
+                d.source = "blender".to_string();
+            });
+            if let Some(o) = offset {
+                map.insert(key.clone(), o);
+            }
+        }
+    }
+    emit_prefabdst_log(
+        app,
+        "info",
+        format!("Debris offsets: {} found out of {} FBX", map.len(), pairs.len()),
+        None,
+        None,
+    );
+    map
+}
+
+#[derive(Serialize)]
+pub struct PrefabDstBuildResult {
+    pub out_paths: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub plan: Option<write_plan::WritePlan>,
+}
+
+/// Arguments of `prefabdst_build`.
+#[derive(Deserialize, Clone)]
+pub struct PrefabDstBuildArgs {
+    pub preset_file: String,
+    pub preset_text: String,
+    pub zones: usize,
+    pub hp_zone: i32,
+    pub debris_mass: f32,
+    pub model_files: Vec<String>,
+    pub save_folder: String,
+    pub scr_override: Option<ScrDstScanResult>,
+    pub full_override: Option<HashMap<String, FullDstScanResult>>,
+    pub dry_run: Option<bool>,
+}
+
+pub async fn build_dst_prefabs(app: &dyn LogSink, args: PrefabDstBuildArgs) -> Result<PrefabDstBuildResult, String> {
+    let PrefabDstBuildArgs {
+        preset_file,
+        preset_text,
+        zones,
+        hp_zone,
+        debris_mass,
+        model_files,
+        save_folder,
+        scr_override,
+        full_override,
+        dry_run,
+    } = args;
+    let dry_run = dry_run.unwrap_or(false);
+    let zones = zones.clamp(1, 26);
+    let hp_zone = hp_zone.clamp(1, 9999);
+    if model_files.is_empty() {
+        return Err("No model files".into());
+    }
+    if save_folder.trim().is_empty() {
+        return Err("No save folder".into());
+    }
+    let out_dir = PathBuf::from(save_folder.trim());
+    if !dry_run {
+        fs::create_dir_all(&out_dir).map_err(|e| e.to_string())?;
+    }
+
+    let total = model_files.len();
+    emit_prefabdst_log(app, "info", format!("Preset: {}", preset_file), None, None);
+    emit_prefabdst_log(app, "info", format!("Zones: {} (hp={})", zones, hp_zone), None, None);
+    emit_prefabdst_log(app, "info", format!("Debris mass: {}", format_mass(debris_mass)), None, None);
+
+    let mut out_paths: Vec<String> = Vec::new();
+    let mut planned: Vec<write_plan::PlannedFile> = Vec::new();
+    for (idx, xob_path) in model_files.iter().enumerate() {
+        let cur = idx + 1;
+        emit_prefabdst_log(app, "info", format!("Reading meta for: {}", xob_path), Some(cur), Some(total));
+        let xob_abs = PathBuf::from(xob_path);
+        let (base_guid, base_res) = read_xob_object_field_from_meta(&xob_abs)?;
+
+        // v2 model (.xob.meta) next to base file
+        let mut v2_guid: Option<String> = None;
+        let mut v2_res: Option<String> = None;
+        if let Some(stem) = xob_abs.file_stem().and_then(|s| s.to_str()) {
+            if let Some(parent) = xob_abs.parent() {
+                let v2 = parent.join(format!("{}_V2_dst.xob", stem));
+                if v2.is_file() {
+                    if let Ok((g2, r2)) = read_xob_object_field_from_meta(&v2) {
+                        v2_guid = Some(g2);
+                        v2_res = Some(r2);
+                    }
+                }
+            }
+        }
+
+        // Branch by preset generator
+        let hdr = parse_preset_header(&preset_text);
+        let gen_key = hdr.generator.trim().to_lowercase();
+        emit_prefabdst_log(app, "info", format!("Generator: {}", if gen_key.is_empty() { "(unknown)" } else { &gen_key }), Some(cur), Some(total));
+
+        // For zone_fractal presets, determine per-file scan/override for debris/colliders marker replacement.
+        // If UI provided overrides, prefer those instead of re-scanning.
+        let mut full_scan: Option<FullDstScanResult> = None;
+        let mut zones_for_this_file = zones;
+        if gen_key != "template" {
+            if let Some(map) = full_override.as_ref() {
+                if let Some(ov) = map.get(xob_path) {
+                    let inferred = ov.zones.len().clamp(1, 26);
+                    zones_for_this_file = inferred;
+                    full_scan = Some(ov.clone());
+                    emit_prefabdst_log(
+                        app,
+                        "info",
+                        format!("Zones: {} (from UI override)", inferred),
+                        Some(cur),
+                        Some(total)
+                    );
+                }
+            }
+
+            if full_scan.is_none() {
+                // Fallback: auto-scan from base xob if no override was provided.
+                match scan_full_dst(xob_path.to_string()).await {
+                    Ok(scan) => {
+                        let inferred = scan.zones.len().clamp(1, 26);
+                        zones_for_this_file = inferred;
+                        full_scan = Some(scan);
+                        emit_prefabdst_log(
+                            app,
+                            "info",
+                            format!("Auto zones: {} (from GeometryParam tags)", inferred),
+                            Some(cur),
+                            Some(total)
+                        );
+                    }
+                    Err(e) => {
+                        emit_prefabdst_log(
+                            app,
+                            "warn",
+                            format!("Auto zones skipped (full dst scan failed): {}", e),
+                            Some(cur),
+                            Some(total)
+                        );
+                    }
+                }
+            }
+        }
+
+        emit_prefabdst_log(app, "info", "Generating ET text...", Some(cur), Some(total));
+        let mut et_text = if gen_key == "template" {
+            // scr_destructible template rendering (auto-scan dst)
+            render_scr_destructible_template(&preset_text, &base_guid, &base_res, &xob_abs, scr_override.as_ref())
+        } else {
+            // default to zone_fractal
+            build_zone_fractal_from_preset(
+                &preset_text,
+                &base_guid,
+                &base_res,
+                v2_guid.as_deref(),
+                v2_res.as_deref(),
+                zones_for_this_file,
+                hp_zone,
+            )
+        };
+
+        // For zone_fractal presets, fill debris/collider markers using a scan that matches the UI tree.
+        if gen_key != "template" {
+            if et_text.contains("{{DEBRIS_ID-") || et_text.contains("{{COLLIDERS_ID-") {
+                if let Some(scan) = full_scan.as_ref() {
+                    let offsets = collect_debris_offsets(app, &xob_abs, scan).await;
+                    et_text = replace_full_dst_markers_with_offsets(&et_text, scan, debris_mass, &offsets);
+                } else {
+                    emit_prefabdst_log(
+                        app,
+                        "warn",
+                        "Full DST scan unavailable (markers not replaced)".to_string(),
+                        Some(cur),
+                        Some(total)
+                    );
+                }
+            }
+        }
+
+        // Always normalize any remaining {gen vec3} marker to 0 0 0.
+        et_text = replace_gen_vec3_markers(&et_text);
+
+        let stem = xob_abs
+            .file_stem()
+            .and_then(|s| s.to_str())
+            .ok_or_else(|| "Invalid xob file name".to_string())?;
+        let out_path = out_dir.join(format!("{}_test_dst_prefab.et", stem));
+        if dry_run {
+            let f = write_plan::plan_file(&out_path, et_text);
+            emit_prefabdst_log(app, "info", format!("Dry run: {} ({})", f.path, f.status()), Some(cur), Some(total));
+            planned.push(f);
+        } else {
+            emit_prefabdst_log(app, "info", format!("Writing: {}", out_path.to_string_lossy()), Some(cur), Some(total));
+            fs::write(&out_path, et_text).map_err(|e| e.to_string())?;
+        }
+        out_paths.push(out_path.to_string_lossy().to_string());
+    }
+
+    if dry_run {
+        let plan = write_plan::store(planned);
+        emit_prefabdst_log(app, "info", format!("Dry run finished; nothing written (plan {})", plan.id), None, None);
+        return Ok(PrefabDstBuildResult { out_paths, plan: Some(plan) });
+    }
+    emit_prefabdst_log(app, "info", format!("Done. Generated {} file(s).", out_paths.len()), None, None);
+    Ok(PrefabDstBuildResult { out_paths, plan: None })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::log_sink::{LogChannel, MemorySink};
+
+    const PRESET: &str = "Id: test\nTitle: Test\nGenerator: zone_fractal\n\nGenericEntity {\n ID \"{gen guid}\"\n components {\n  MeshObject \"{gen guid}\" {\n   Object \"{guid from xob input}path from xob input\"\n  }\n  SCR_DestructionFractalComponent \"{gen guid}\" {\n   Model \"same name_v2_dst\"\n   FractalParts {\n    FractalPartData \"{5A3C000000000001}\" {\n     PartId \"A\"\n     MaxHealth 100\n     DebrisInfos {\n      {{DEBRIS_ID-A}}\n     }\n     Colliders {\n      {{COLLIDERS_ID-A}}\n     }\n    }\n   }\n  }\n }\n coords {gen vec3}\n}\n";
+
+    fn scan() -> FullDstScanResult {
+        FullDstScanResult {
+            base_guid: "AAAAAAAAAAAAAAAA".into(),
+            base_path: "Assets/b.xob".into(),
+            v2_guid: "BBBBBBBBBBBBBBBB".into(),
+            v2_path: "Assets/b_V2_dst.xob".into(),
+            zones: vec![FullDstZoneInfo {
+                part_id: "A".into(),
+                debris: vec![MetaEntry { guid: "DDDDDDDDDDDDDDDD".into(), path: "Assets/Dst/b_dbr_01.xob".into() }],
+                colliders: vec!["UCX_FDST_ID-A_01".into()],
+            }],
+        }
+    }
+
+    #[test]
+    fn zone_fractal_preset_fills_models_zones_and_hp() {
+        let text = build_zone_fractal_from_preset(
+            PRESET,
+            "AAAAAAAAAAAAAAAA",
+            "Assets/b.xob",
+            Some("BBBBBBBBBBBBBBBB"),
+            Some("Assets/b_V2_dst.xob"),
+            3,
+            250,
+        );
+        assert!(!text.contains("Generator:"));
+        assert!(text.contains("Object \"{AAAAAAAAAAAAAAAA}Assets/b.xob\""));
+        assert!(text.contains("Model \"{BBBBBBBBBBBBBBBB}Assets/b_V2_dst.xob\""));
+        assert!(!text.contains("gen guid"));
+        for part in ["A", "B", "C"] {
+            assert!(text.contains(&format!("PartId \"{}\"", part)));
+            assert!(text.contains(&format!("{{{{DEBRIS_ID-{}}}}}", part)));
+        }
+        assert_eq!(text.matches("FractalPartData").count(), 3);
+        assert_eq!(text.matches("MaxHealth 250").count(), 3);
+        // Cloned parts get fresh IDs.
+        assert_eq!(text.matches("{5A3C000000000001}").count(), 1);
+    }
+
+    #[test]
+    fn dst_markers_become_debris_infos_and_colliders() {
+        let offsets = HashMap::from([("b_dbr_01.xob".to_string(), (1.5f32, 2.0f32, -3.0f32))]);
+        let text = build_zone_fractal_from_preset(PRESET, "AAAAAAAAAAAAAAAA", "Assets/b.xob", None, None, 2, 100);
+        let text = replace_full_dst_markers_with_offsets(&text, &scan(), 12.5, &offsets);
+
+        assert!(!text.contains("{{"));
+        assert!(text.contains("       ModelPrefab \"{DDDDDDDDDDDDDDDD}Assets/Dst/b_dbr_01.xob\"\n"));
+        assert!(text.contains("        Offset 1.5 2 -3\n"));
+        assert!(text.contains("       m_fMass 12.5\n"));
+        assert!(text.contains("      \"UCX_FDST_ID-A_01\"\n"));
+        // Part B has no zone in the scan: no debris, empty collider list.
+        assert_eq!(text.matches("SCR_DebrisInfo").count(), 1);
+        assert!(text.contains("      \"\"\n"));
+        enfusion_text::parse(&text).unwrap();
+    }
+
+    #[test]
+    fn scr_template_uses_override_phases() {
+        let preset = "Id: scr\nGenerator: template\n\n--- TEMPLATE ---\nObj {{ENTITY_ID}}\n{{BASE_OBJECT_LINE}}\nFirst {{FIRST_PHASE_MODEL}} {{FIRST_PHASE_DEBRIS}}\nLast {{LAST_PHASE_MODEL}}\n--- END TEMPLATE ---\n";
+        let ov = ScrDstScanResult {
+            base_guid: String::new(),
+            base_path: String::new(),
+            phases: vec![
+                ScrPhaseItem {
+                    pid: "01".into(),
+                    model_guid: "1111111111111111".into(),
+                    model_path: "Assets/b_dst_01.xob".into(),
+                    debris: vec![ScrDebrisItem { guid: "2222222222222222".into(), path: "Assets/b_dst_01_dbr_01.xob".into() }],
+                },
+                ScrPhaseItem { pid: "02".into(), model_guid: "3333333333333333".into(), model_path: "Assets/b_dst_02.xob".into(), debris: vec![] },
+            ],
+        };
+        let text = render_scr_destructible_template(preset, "AAAAAAAAAAAAAAAA", "Assets/b.xob", Path::new("b.xob"), Some(&ov));
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[1], "   Object \"{AAAAAAAAAAAAAAAA}Assets/b.xob\"");
+        assert_eq!(lines[2], "First \"{1111111111111111}Assets/b_dst_01.xob\" \"{2222222222222222}Assets/b_dst_01_dbr_01.xob\"");
+        assert_eq!(lines[3], "Last \"{3333333333333333}Assets/b_dst_02.xob\"");
+        assert!(!lines[0].contains("{{"));
+    }
+
+    #[tokio::test]
+    async fn dry_run_build_plans_the_prefab_and_logs_progress() {
+        let dir = std::env::temp_dir().join(format!("owltools_prefabdst_test_{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let xob = dir.join("b.xob");
+        fs::write(&xob, b"").unwrap();
+        fs::write(dir.join("b.xob.meta"), "MetaFileClass {\n Name \"{AAAAAAAAAAAAAAAA}Assets/b.xob\"\n}\n").unwrap();
+        let xob_path = xob.to_string_lossy().to_string();
+        let out_dir = dir.join("out");
+
+        let sink = MemorySink::new();
+        let args = PrefabDstBuildArgs {
+            preset_file: "test.txt".into(),
+            preset_text: PRESET.into(),
+            zones: 1,
+            hp_zone: 100,
+            debris_mass: 10.0,
+            model_files: vec![xob_path.clone()],
+            save_folder: out_dir.to_string_lossy().to_string(),
+            scr_override: None,
+            full_override: Some(HashMap::from([(xob_path, scan())])),
+            dry_run: Some(true),
+        };
+        let res = build_dst_prefabs(&sink, args).await;
+        let out_exists = out_dir.exists();
+        fs::remove_dir_all(&dir).unwrap();
+        let res = res.unwrap();
+
+        assert!(!out_exists);
+        let plan = res.plan.unwrap();
+        assert_eq!(plan.files.len(), 1);
+        assert!(plan.files[0].is_new);
+        assert!(plan.files[0].path.ends_with("b_test_dst_prefab.et"));
+        let text = &plan.files[0].contents;
+        assert!(text.contains("Object \"{AAAAAAAAAAAAAAAA}Assets/b.xob\""));
+        assert!(text.contains("ModelPrefab \"{DDDDDDDDDDDDDDDD}Assets/Dst/b_dbr_01.xob\""));
+        assert!(text.contains(" coords 0 0 0\n"));
+
+        let records = sink.records();
+        assert!(records.iter().all(|r| r.channel == LogChannel::PrefabDst && r.level == "info"));
+        let messages = sink.messages(LogChannel::PrefabDst);
+        assert_eq!(messages[..3], ["Preset: test.txt", "Zones: 1 (hp=100)", "Debris mass: 10"]);
+        assert!(messages.contains(&"Zones: 1 (from UI override)".to_string()));
+        assert_eq!(messages.last().unwrap(), &format!("Dry run finished; nothing written (plan {})", plan.id));
+        let progress = records.iter().find(|r| r.message.starts_with("Dry run: ")).unwrap();
+        assert_eq!((progress.current, progress.total), (Some(1), Some(1)));
+        assert!(progress.message.ends_with("(new)"));
+    }
+}
@@ -0,0 +1,156 @@
+// Reference graph over the prefab index.
+//
+// Text resources (.et, .conf, .layer, .ent) point at other resources with `{GUID}path`
+// strings. `extract_references` collects them while the index is scanned, and the edges are
+// stored in the index next to the resources. `references` walks them backwards (who uses this
+// GUID) and `dependencies` forwards (what this resource uses), breadth first up to a depth
+// limit.
+
+use serde::Serialize;
+use std::collections::{HashSet, VecDeque};
+use std::fs;
+use std::path::Path;
+
+use crate::extract_guid;
+use crate::prefab_db::{PrefabDb, Reference, Resource};
+
+/// Extensions whose files are read for references.
+pub const REFERENCE_EXTS: [&str; 4] = ["et", "conf", "layer", "ent"];
+pub const MAX_DEPTH: usize = 10;
+
+pub fn scans_references(ext: &str) -> bool {
+    REFERENCE_EXTS.contains(&ext)
+}
+
+/// Every distinct `{GUID}path` in `text`, in order of appearance. Braced GUIDs without a path
+/// (component and entity IDs) are not references and are skipped.
+pub fn extract_references(text: &str) -> Vec<Reference> {
+    let mut seen: HashSet<(String, String)> = HashSet::new();
+    let mut out = Vec::new();
+    for (i, _) in text.match_indices('{') {
+        let Some(guid) = extract_guid(&text[i..]) else { continue };
+        // '{' + 16 hex digits + '}'
+        let path: String = text[i + 18..]
+            .chars()
+            .take_while(|c| *c != '"' && !c.is_whitespace())
+            .collect();
+        if path.is_empty() {
+            continue;
+        }
+        if seen.insert((guid.clone(), path.clone())) {
+            out.push(Reference { guid, path });
+        }
+    }
+    out
+}
+
+/// `extract_references` per line, with 1-based line numbers. Repeats on different lines are
+/// all reported.
+pub fn extract_references_by_line(text: &str) -> Vec<(usize, Reference)> {
+    text.lines()
+        .enumerate()
+        .flat_map(|(i, line)| extract_references(line).into_iter().map(move |r| (i + 1, r)))
+        .collect()
+}
+
+/// Replaces every `{guid}path` reference in `text`, whatever its path, with `new_ref`.
+/// Returns the new text and the number of replaced references.
+pub fn rewrite_references(text: &str, guid: &str, new_ref: &str) -> (String, usize) {
+    let mut out = String::with_capacity(text.len());
+    let mut last = 0;
+    let mut count = 0;
+    for (i, _) in text.match_indices('{') {
+        if i < last || extract_guid(&text[i..]).as_deref() != Some(guid) {
+            continue;
+        }
+        let path_len: usize = text[i + 18..]
+            .chars()
+            .take_while(|c| *c != '"' && !c.is_whitespace())
+            .map(char::len_utf8)
+            .sum();
+        if path_len == 0 {
+            continue;
+        }
+        out.push_str(&text[last..i]);
+        out.push_str(new_ref);
+        last = i + 18 + path_len;
+        count += 1;
+    }
+    out.push_str(&text[last..]);
+    (out, count)
+}
+
+/// References of the file at `path`; empty if it cannot be read as text.
+pub fn read_references(path: &Path) -> Vec<Reference> {
+    fs::read_to_string(path).map(|t| extract_references(&t)).unwrap_or_default()
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct GraphNode {
+    /// 1 for direct references/dependencies of the start GUID.
+    pub depth: usize,
+    pub guid: String,
+    /// Project path: the resource's own for references, the referenced path for dependencies.
+    pub path: String,
+    /// GUID of the node one level closer to the start.
+    pub parent: String,
+    /// The indexed resource; None for a dependency that is not in the index.
+    pub resource: Option<Resource>,
+}
+
+fn clamp_depth(depth: usize) -> usize {
+    depth.clamp(1, MAX_DEPTH)
+}
+
+/// Resources that reference `guid`, directly (depth 1) or through other resources.
+pub fn references(db: &PrefabDb, guid: &str, depth: usize) -> Result<Vec<GraphNode>, String> {
+    let depth = clamp_depth(depth);
+    let start = guid.to_uppercase();
+    let mut seen: HashSet<String> = HashSet::from([start.clone()]);
+    let mut queue: VecDeque<(String, usize)> = VecDeque::from([(start, 0)]);
+    let mut out = Vec::new();
+    while let Some((g, d)) = queue.pop_front() {
+        if d >= depth {
+            continue;
+        }
+        for r in db.referencing(&g)? {
+            if !seen.insert(r.abs_path.clone()) {
+                continue;
+            }
+            let node_guid = r.guid.clone().unwrap_or_default();
+            if !node_guid.is_empty() && seen.insert(node_guid.clone()) {
+                queue.push_back((node_guid.clone(), d + 1));
+            }
+            out.push(GraphNode { depth: d + 1, guid: node_guid, path: r.rel_path.clone(), parent: g.clone(), resource: Some(r) });
+        }
+    }
+    Ok(out)
+}
+
+/// Resources referenced by the resource with `guid`, directly (depth 1) or transitively.
+pub fn dependencies(db: &PrefabDb, guid: &str, depth: usize) -> Result<Vec<GraphNode>, String> {
+    let depth = clamp_depth(depth);
+    let start = db
+        .by_guid(guid)
+        .ok_or_else(|| format!("No indexed resource with GUID {}", guid.to_uppercase()))?;
+    let start_guid = start.guid.clone().unwrap_or_default();
+    let mut seen: HashSet<String> = HashSet::from([start_guid.clone()]);
+    let mut queue: VecDeque<(Resource, String, usize)> = VecDeque::from([(start, start_guid, 0)]);
+    let mut out = Vec::new();
+    while let Some((res, g, d)) = queue.pop_front() {
+        if d >= depth || !scans_references(&res.ext) {
+            continue;
+        }
+        for r in db.references_from(&res.abs_path)? {
+            if !seen.insert(r.guid.clone()) {
+                continue;
+            }
+            let target = db.by_guid(&r.guid);
+            if let Some(t) = target.clone() {
+                queue.push_back((t, r.guid.clone(), d + 1));
+            }
+            out.push(GraphNode { depth: d + 1, guid: r.guid, path: r.path, parent: g.clone(), resource: target });
+        }
+    }
+    Ok(out)
+}
@@ -0,0 +1,138 @@
+// Broken-reference validation over the indexed text resources.
+//
+// Every `{GUID}path` in the .et/.conf/.layer/.ent files of the index is checked against the
+// index: the GUID must be known, the path must match the indexed path for that GUID, and the
+// target file must still exist. Each problem is reported with file, line and a suggested
+// replacement taken from the index, so it can be fixed before Workbench fails to load it.
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use crate::emit_scan_log;
+use crate::log_sink::LogSink;
+use crate::prefab_db::{PrefabDb, Resource};
+use crate::ref_graph::{extract_references_by_line, REFERENCE_EXTS};
+
+#[derive(Serialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum RefProblem {
+    /// No indexed resource has the GUID.
+    UnknownGuid,
+    /// The GUID is indexed under a different path.
+    PathMismatch,
+    /// The GUID is indexed but its file is gone.
+    MissingFile,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct RefIssue {
+    pub file: String,
+    pub root: String,
+    /// 1-based.
+    pub line: usize,
+    /// The reference as written, `{GUID}path`.
+    pub reference: String,
+    pub problem: RefProblem,
+    pub message: String,
+    /// Replacement reference from the index, when one can be found.
+    pub suggestion: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct RefValidationReport {
+    pub files_checked: usize,
+    pub references_checked: usize,
+    /// Text resources that could not be read.
+    pub unreadable: Vec<String>,
+    pub issues: Vec<RefIssue>,
+}
+
+fn name_value(r: &Resource) -> String {
+    format!("{{{}}}{}", r.guid.as_deref().unwrap_or_default(), r.rel_path)
+}
+
+fn same_path(a: &str, b: &str) -> bool {
+    a.replace('\\', "/").eq_ignore_ascii_case(&b.replace('\\', "/"))
+}
+
+/// Checks every reference in the indexed text resources. Stops with "Cancelled" when the sink
+/// reports cancellation.
+pub fn validate(db: &PrefabDb, app: &dyn LogSink) -> Result<RefValidationReport, String> {
+    let files = db.with_exts(&REFERENCE_EXTS)?;
+    let total = files.len();
+    emit_scan_log(app, "info", format!("Validating references in {} file(s)...", total), Some(0), Some(total));
+
+    let mut report = RefValidationReport::default();
+    let mut by_guid: HashMap<String, Option<Resource>> = HashMap::new();
+    let mut by_path: HashMap<String, Option<Resource>> = HashMap::new();
+    let mut exists: HashMap<String, bool> = HashMap::new();
+
+    for (i, file) in files.iter().enumerate() {
+        if app.is_cancelled() {
+            return Err("Cancelled".into());
+        }
+        if (i + 1) % 200 == 0 {
+            emit_scan_log(app, "info", format!("Validating... {}/{}", i + 1, total), Some(i + 1), Some(total));
+        }
+        let Ok(text) = fs::read_to_string(&file.abs_path) else {
+            report.unreadable.push(file.abs_path.clone());
+            continue;
+        };
+        report.files_checked += 1;
+
+        for (line, r) in extract_references_by_line(&text) {
+            report.references_checked += 1;
+            let target = by_guid.entry(r.guid.clone()).or_insert_with(|| db.by_guid(&r.guid)).clone();
+            let (problem, message, suggestion) = match target {
+                None => {
+                    let same = by_path
+                        .entry(r.path.to_lowercase())
+                        .or_insert_with(|| db.by_rel_path(&r.path))
+                        .clone();
+                    let message = match &same {
+                        Some(s) => format!("GUID {} is not indexed; {} has GUID {}", r.guid, s.rel_path, s.guid.as_deref().unwrap_or("?")),
+                        None => format!("GUID {} is not indexed and no resource has path {}", r.guid, r.path),
+                    };
+                    (RefProblem::UnknownGuid, message, same.as_ref().map(name_value))
+                }
+                Some(t) if !same_path(&t.rel_path, &r.path) => (
+                    RefProblem::PathMismatch,
+                    format!("GUID {} is indexed as {}", r.guid, t.rel_path),
+                    Some(name_value(&t)),
+                ),
+                Some(t) if !*exists.entry(t.abs_path.clone()).or_insert_with(|| Path::new(&t.abs_path).is_file()) => (
+                    RefProblem::MissingFile,
+                    format!("{} no longer exists; rescan the index or restore it", t.abs_path),
+                    None,
+                ),
+                Some(_) => continue,
+            };
+            report.issues.push(RefIssue {
+                file: file.abs_path.clone(),
+                root: file.root.clone(),
+                line,
+                reference: format!("{{{}}}{}", r.guid, r.path),
+                problem,
+                message,
+                suggestion,
+            });
+        }
+    }
+
+    let level = if report.issues.is_empty() { "info" } else { "warn" };
+    emit_scan_log(
+        app,
+        level,
+        format!(
+            "Validation done: {} file(s), {} reference(s), {} issue(s)",
+            report.files_checked,
+            report.references_checked,
+            report.issues.len()
+        ),
+        Some(total),
+        Some(total),
+    );
+    Ok(report)
+}
@@ -0,0 +1,213 @@
+// Moving and renaming resources without breaking references.
+//
+// `move_resource` renames a resource together with its .meta (and, for a .xob, the .fbx and
+// .txo next to it), points the `Name` of each moved meta at the new project path, and
+// rewrites the path half of every `{GUID}path` reference to a moved GUID in the files the
+// index lists as referencing it. The index is updated in place afterwards. A dry run lists
+// the moves and the diff of every file that would change without touching the disk.
+
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::log_sink::LogSink;
+use crate::ref_graph::rewrite_references;
+use crate::{emit_scan_log, enfusion_text, extract_guid, prefab_db, reindex_written_files, write_all_atomic, write_plan};
+
+/// Files moved along with a .xob (same stem).
+const XOB_SIBLINGS: [&str; 2] = ["fbx", "txo"];
+
+#[derive(Deserialize, Default, Clone)]
+pub struct MoveResourceArgs {
+    /// The resource to move (or its .meta).
+    pub path: String,
+    /// New file path, or an existing folder to move the resource into.
+    pub destination: String,
+    pub dry_run: Option<bool>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct MovedFile {
+    pub from: String,
+    pub to: String,
+}
+
+#[derive(Serialize)]
+pub struct MoveResourceResult {
+    pub moves: Vec<MovedFile>,
+    /// (old, new) `Name` value of each moved .meta.
+    pub renamed: Vec<(String, String)>,
+    /// Referencing files and the number of references updated in each.
+    pub updated_files: Vec<(String, usize)>,
+    /// Every file whose contents change, with its diff: moved metas at their new path and
+    /// the referencing files.
+    pub changes: Vec<write_plan::PlannedFile>,
+    pub dry_run: bool,
+}
+
+fn meta_of(p: &Path) -> PathBuf {
+    PathBuf::from(format!("{}.meta", p.to_string_lossy()))
+}
+
+fn lossy(p: &Path) -> String {
+    p.to_string_lossy().to_string()
+}
+
+/// Renames `moves` in order; on failure the ones already done are moved back.
+fn rename_all(moves: &[(PathBuf, PathBuf)]) -> Result<(), String> {
+    for (i, (from, to)) in moves.iter().enumerate() {
+        if let Err(e) = fs::rename(from, to) {
+            move_back(&moves[..i]);
+            return Err(format!("Failed to move {} to {}: {}", lossy(from), lossy(to), e));
+        }
+    }
+    Ok(())
+}
+
+/// Undoes `moves`, last first.
+fn move_back(moves: &[(PathBuf, PathBuf)]) {
+    for (f, t) in moves.iter().rev() {
+        let _ = fs::rename(t, f);
+    }
+}
+
+pub fn move_resource(app: &dyn LogSink, args: MoveResourceArgs) -> Result<MoveResourceResult, String> {
+    let src = PathBuf::from(args.path.strip_suffix(".meta").unwrap_or(&args.path));
+    if !src.is_file() {
+        return Err(format!("Resource not found: {}", lossy(&src)));
+    }
+    if !meta_of(&src).is_file() {
+        return Err(format!("No .meta next to {}", lossy(&src)));
+    }
+    let file_name = src.file_name().ok_or("Resource has no file name")?;
+    let dest = match PathBuf::from(&args.destination) {
+        d if d.is_dir() => d.join(file_name),
+        d => d,
+    };
+    let ext = |p: &Path| p.extension().map(|e| e.to_string_lossy().to_lowercase()).unwrap_or_default();
+    if ext(&src) != ext(&dest) {
+        return Err(format!("Destination must keep the .{} extension", ext(&src)));
+    }
+    if dest == src {
+        return Err("Destination is the resource itself".into());
+    }
+
+    // Resource files to move; each moves with its .meta when it has one.
+    let mut resources: Vec<(PathBuf, PathBuf)> = vec![(src.clone(), dest.clone())];
+    if ext(&src) == "xob" {
+        for sib in XOB_SIBLINGS {
+            let from = src.with_extension(sib);
+            if from.is_file() {
+                resources.push((from, dest.with_extension(sib)));
+            }
+        }
+    }
+    let mut moves: Vec<(PathBuf, PathBuf)> = Vec::new();
+    for (from, to) in &resources {
+        moves.push((from.clone(), to.clone()));
+        if meta_of(from).is_file() {
+            moves.push((meta_of(from), meta_of(to)));
+        }
+    }
+    let taken: Vec<String> = moves.iter().filter(|(_, to)| to.exists()).map(|(_, to)| lossy(to)).collect();
+    if !taken.is_empty() {
+        return Err(format!("Destination already exists: {}", taken.join(", ")));
+    }
+
+    let db = prefab_db::open()?;
+    let mut renamed: Vec<(String, String)> = Vec::new();
+    let mut changes: Vec<write_plan::PlannedFile> = Vec::new();
+    let mut new_metas: Vec<(PathBuf, String)> = Vec::new();
+    // Referencing file -> rewritten text and number of updated references.
+    let mut edits: BTreeMap<PathBuf, (String, usize)> = BTreeMap::new();
+    for (from, to) in &resources {
+        let (from_meta, to_meta) = (meta_of(from), meta_of(to));
+        let Ok(meta_text) = fs::read_to_string(&from_meta) else { continue };
+        let doc = enfusion_text::parse(&meta_text).map_err(|e| format!("Failed to parse {}: {}", lossy(&from_meta), e))?;
+        let Some(old_name) = doc.meta_name().map(|n| n.trim().to_string()) else { continue };
+        let Some(guid) = extract_guid(&old_name) else { continue };
+        let new_rel = db
+            .project_path(&lossy(to))?
+            .ok_or_else(|| format!("{} is outside the indexed roots", lossy(to)))?;
+        let new_name = format!("{{{}}}{}", guid, new_rel);
+        let quoted = format!("\"{}\"", old_name);
+        if !meta_text.contains(&quoted) {
+            return Err(format!("Name value not found verbatim in {}", lossy(&from_meta)));
+        }
+        let new_meta = meta_text.replacen(&quoted, &format!("\"{}\"", new_name), 1);
+        changes.push(write_plan::plan_moved_file(&from_meta, &to_meta, new_meta.clone()));
+        new_metas.push((to_meta, new_meta));
+
+        for r in db.referencing(&guid)? {
+            let path = PathBuf::from(&r.abs_path);
+            if !edits.contains_key(&path) {
+                let Ok(text) = fs::read_to_string(&path) else { continue };
+                edits.insert(path.clone(), (text, 0));
+            }
+            let (text, count) = edits.get_mut(&path).unwrap();
+            let (new_text, n) = rewrite_references(text, &guid, &new_name);
+            *text = new_text;
+            *count += n;
+        }
+        renamed.push((old_name, new_name));
+    }
+    drop(db);
+
+    // A moved file that references another moved file is written at its new path.
+    let moved_to = |p: &Path| moves.iter().find(|(f, _)| f == p).map(|(_, t)| t.clone()).unwrap_or_else(|| p.to_path_buf());
+    let mut updated_files: Vec<(String, usize)> = Vec::new();
+    let mut ref_writes: Vec<(PathBuf, String)> = Vec::new();
+    for (path, (text, count)) in edits {
+        if fs::read_to_string(&path).ok().as_deref() == Some(text.as_str()) {
+            continue;
+        }
+        let target = moved_to(&path);
+        changes.push(if target == path {
+            write_plan::plan_file(&path, text.clone())
+        } else {
+            write_plan::plan_moved_file(&path, &target, text.clone())
+        });
+        updated_files.push((lossy(&target), count));
+        ref_writes.push((target, text));
+    }
+
+    let dry_run = args.dry_run.unwrap_or(false);
+    let result = MoveResourceResult {
+        moves: moves.iter().map(|(f, t)| MovedFile { from: lossy(f), to: lossy(t) }).collect(),
+        renamed,
+        updated_files,
+        changes,
+        dry_run,
+    };
+    for m in &result.moves {
+        emit_scan_log(app, "info", format!("{}Move {} -> {}", if dry_run { "Dry run: " } else { "" }, m.from, m.to), None, None);
+    }
+    for (path, n) in &result.updated_files {
+        emit_scan_log(app, "info", format!("{}Update {} reference(s) in {}", if dry_run { "Dry run: " } else { "" }, n, path), None, None);
+    }
+    if dry_run {
+        emit_scan_log(app, "info", "Dry run finished; nothing moved", None, None);
+        return Ok(result);
+    }
+
+    if let Some(parent) = dest.parent() {
+        fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", lossy(parent), e))?;
+    }
+    rename_all(&moves)?;
+    // A failed write restores the files already rewritten, then the moves are undone.
+    let written = match write_all_atomic(new_metas.into_iter().chain(ref_writes).collect()) {
+        Ok(written) => written,
+        Err(err) => {
+            move_back(&moves);
+            return Err(err);
+        }
+    };
+    let old_paths: Vec<String> = resources.iter().map(|(f, _)| lossy(f)).collect();
+    if let Err(err) = prefab_db::open().and_then(|mut db| db.remove_paths(&old_paths)) {
+        emit_scan_log(app, "warn", format!("Failed to update prefab cache: {}", err), None, None);
+    }
+    reindex_written_files(app, &written);
+    emit_scan_log(app, "info", format!("Moved {} file(s), updated {} referencing file(s)", result.moves.len(), result.updated_files.len()), None, None);
+    Ok(result)
+}
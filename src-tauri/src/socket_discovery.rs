@@ -0,0 +1,278 @@
+// Socket discovery for an .xob from every file that lists them.
+//
+// The exported .txo names the sockets of the model as the engine sees them. The .xob itself
+// carries the same names in its binary; its layout is not documented, so names are picked out
+// of the file's string data rather than parsed from the bone table. The source FBX has the
+// socket nodes with their local transforms, which neither export keeps in a readable form.
+//
+// The exported files decide which sockets exist. The .txo list is used as is; a name found only
+// in the .xob scan may be any string that starts with `socket_`, so it is used only when the FBX
+// has it too or when neither the .txo nor the FBX lists a socket, and reported otherwise. FBX
+// sockets missing from the exports are reported (the export is older than the FBX) but not used.
+// Only when neither export lists any socket does the FBX list stand in.
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::Path;
+
+use crate::extract_cache;
+use crate::fbx::{self, LocalTransform};
+
+const SOCKET_PREFIX: &[u8] = b"socket_";
+
+#[derive(Serialize, Clone, Debug)]
+pub struct DiscoveredSocket {
+    pub name: String,
+    pub in_txo: bool,
+    pub in_xob: bool,
+    pub in_fbx: bool,
+    /// Local transform from the FBX node; None when the FBX does not have the socket.
+    pub transform: Option<LocalTransform>,
+}
+
+#[derive(Serialize, Clone, Debug, Default)]
+pub struct SocketDiscovery {
+    /// Sockets to map, .txo order first.
+    pub sockets: Vec<DiscoveredSocket>,
+    /// Sockets listed by only some of the files ("in FBX but not in the exported .txo", ...).
+    pub discrepancies: Vec<String>,
+    /// Files that could not be read; discovery goes on with the others.
+    pub errors: Vec<String>,
+}
+
+impl SocketDiscovery {
+    pub fn names(&self) -> Vec<String> {
+        self.sockets.iter().map(|s| s.name.clone()).collect()
+    }
+}
+
+pub fn parse_txo_socket_names(txo_text: &str) -> Vec<String> {
+    let mut out = Vec::new();
+    let mut i = 0usize;
+    let s = txo_text.as_bytes();
+    while i < s.len() {
+        // naive scan for $node "socket_
+        if s[i..].starts_with(b"$node\"") || s[i..].starts_with(b"$node \"") {
+            // find first quote
+            let q1 = txo_text[i..].find('"').map(|v| i + v);
+            if let Some(q1) = q1 {
+                let q2 = txo_text[(q1 + 1)..].find('"').map(|v| q1 + 1 + v);
+                if let Some(q2) = q2 {
+                    let name = txo_text[(q1 + 1)..q2].trim();
+                    if name.to_lowercase().starts_with("socket_") {
+                        out.push(name.to_string());
+                    }
+                    i = q2 + 1;
+                    continue;
+                }
+            }
+        }
+        i += 1;
+    }
+    out
+}
+
+/// `socket_*` names in the string data of an .xob, in file order. Strings that are part of a
+/// file name or path (`socket_x.emat`, `Assets/socket_x`) are skipped.
+pub fn scan_xob_socket_names(data: &[u8]) -> Vec<String> {
+    let is_run_byte = |b: u8| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'.' | b'-' | b'/' | b'\\');
+    let mut out: Vec<String> = Vec::new();
+    let mut i = 0usize;
+    while i + SOCKET_PREFIX.len() <= data.len() {
+        let starts = data[i..i + SOCKET_PREFIX.len()].eq_ignore_ascii_case(SOCKET_PREFIX);
+        if !starts || (i > 0 && is_run_byte(data[i - 1])) {
+            i += 1;
+            continue;
+        }
+        let end = data[i..].iter().position(|b| !is_run_byte(*b)).map_or(data.len(), |n| i + n);
+        let run = &data[i..end];
+        let is_name = run.iter().all(|b| b.is_ascii_alphanumeric() || matches!(b, b'_' | b'-'));
+        let name = String::from_utf8_lossy(run).to_string();
+        if is_name && end > i + SOCKET_PREFIX.len() && !out.iter().any(|o| o.eq_ignore_ascii_case(&name)) {
+            out.push(name);
+        }
+        i = end;
+    }
+    out
+}
+
+/// Socket transforms of the FBX, from the extraction cache when it is current.
+fn fbx_sockets(fbx_abs: &Path) -> Result<BTreeMap<String, LocalTransform>, String> {
+    if let Some(t) = extract_cache::get(fbx_abs).and_then(|e| e.socket_transforms) {
+        return Ok(t);
+    }
+    let models = fbx::read_models(fbx_abs)?;
+    let data = extract_cache::FbxExtract::from_models(&models);
+    let transforms = data.socket_transforms.clone().unwrap_or_default();
+    // A failed cache write only costs a re-read next time.
+    let _ = extract_cache::update(fbx_abs, |d| d.merge(data));
+    Ok(transforms)
+}
+
+/// Sockets of the model at `xob_abs`, from the .txo, the .xob and the .fbx next to it.
+pub fn discover(xob_abs: &Path) -> SocketDiscovery {
+    let mut out = SocketDiscovery::default();
+    let txo_abs = xob_abs.with_extension("txo");
+    let fbx_abs = xob_abs.with_extension("fbx");
+
+    let txo: Option<Vec<String>> = match fs::read_to_string(&txo_abs) {
+        Ok(text) => Some(parse_txo_socket_names(&text)),
+        Err(_) if !txo_abs.is_file() => None,
+        Err(e) => {
+            out.errors.push(format!("Failed to read .txo: {}", e));
+            None
+        }
+    };
+    let xob: Option<Vec<String>> = match fs::read(xob_abs) {
+        Ok(data) => Some(scan_xob_socket_names(&data)),
+        Err(e) => {
+            out.errors.push(format!("Failed to read .xob: {}", e));
+            None
+        }
+    };
+    let fbx: Option<BTreeMap<String, LocalTransform>> = if fbx_abs.is_file() {
+        fbx_sockets(&fbx_abs).map_err(|e| out.errors.push(e)).ok()
+    } else {
+        None
+    };
+    merge_lists(&mut out, txo, xob, fbx);
+    out
+}
+
+/// Merges the socket lists of the three files into `out` (see the rules at the top).
+fn merge_lists(
+    out: &mut SocketDiscovery,
+    txo: Option<Vec<String>>,
+    xob: Option<Vec<String>>,
+    fbx: Option<BTreeMap<String, LocalTransform>>,
+) {
+    let find = |list: &[DiscoveredSocket], name: &str| list.iter().position(|s| s.name.eq_ignore_ascii_case(name));
+    let add = |list: &mut Vec<DiscoveredSocket>, name: &str| match find(list, name) {
+        Some(i) => i,
+        None => {
+            list.push(DiscoveredSocket { name: name.to_string(), in_txo: false, in_xob: false, in_fbx: false, transform: None });
+            list.len() - 1
+        }
+    };
+    let mut sockets: Vec<DiscoveredSocket> = Vec::new();
+    for name in txo.iter().flatten() {
+        let i = add(&mut sockets, name);
+        sockets[i].in_txo = true;
+    }
+    let in_fbx = |name: &str| fbx.iter().flatten().any(|(f, _)| f.eq_ignore_ascii_case(name));
+    let listed = !sockets.is_empty() || fbx.as_ref().is_some_and(|f| !f.is_empty());
+    for name in xob.iter().flatten() {
+        if find(&sockets, name).is_none() && listed && !in_fbx(name) {
+            out.discrepancies.push(format!("{}: only in the .xob (not used)", name));
+            continue;
+        }
+        let i = add(&mut sockets, name);
+        sockets[i].in_xob = true;
+    }
+    let exported = !sockets.is_empty();
+    for (name, t) in fbx.iter().flatten() {
+        // FBX-only sockets are kept aside unless the exports list none at all.
+        if find(&sockets, name).is_none() && exported {
+            out.discrepancies.push(format!("{}: in the FBX but not in the exported model (re-export?)", name));
+            continue;
+        }
+        let i = add(&mut sockets, name);
+        sockets[i].in_fbx = true;
+        sockets[i].transform = Some(t.clone());
+    }
+
+    for s in &sockets {
+        if txo.is_some() && !s.in_txo && s.in_xob {
+            out.discrepancies.push(format!("{}: in the .xob but not in the .txo", s.name));
+        }
+        if xob.as_ref().is_some_and(|x| !x.is_empty()) && !s.in_xob && s.in_txo {
+            out.discrepancies.push(format!("{}: in the .txo but not in the .xob", s.name));
+        }
+        if fbx.is_some() && !s.in_fbx && exported {
+            out.discrepancies.push(format!("{}: exported but not in the FBX", s.name));
+        }
+    }
+    out.sockets = sockets;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn names(list: &[&str]) -> Option<Vec<String>> {
+        Some(list.iter().map(|s| s.to_string()).collect())
+    }
+
+    fn fbx(list: &[&str]) -> Option<BTreeMap<String, LocalTransform>> {
+        let t = LocalTransform { parent: None, translation: [1.0, 2.0, 3.0], rotation: [0.0; 3], scaling: [1.0; 3] };
+        Some(list.iter().map(|s| (s.to_string(), t.clone())).collect())
+    }
+
+    fn merged(
+        txo: Option<Vec<String>>,
+        xob: Option<Vec<String>>,
+        fbx: Option<BTreeMap<String, LocalTransform>>,
+    ) -> SocketDiscovery {
+        let mut out = SocketDiscovery::default();
+        merge_lists(&mut out, txo, xob, fbx);
+        out
+    }
+
+    #[test]
+    fn txo_nodes_are_read_in_order() {
+        let txo = "$node \"root\" {\n}\n$node \"socket_lamp_01\" {\n $parent \"root\"\n}\n$node\"Socket_Barrel\" {\n}\n$node \"ucx_socket\" {\n}\n";
+        assert_eq!(parse_txo_socket_names(txo), ["socket_lamp_01", "Socket_Barrel"]);
+        assert!(parse_txo_socket_names("$node \"socket_unclosed").is_empty());
+    }
+
+    #[test]
+    fn xob_scan_skips_paths_and_file_names() {
+        let mut data = b"\0\x07socket_lamp_01\0socket_barrel-2\x01SOCKET_LAMP_01\0".to_vec();
+        data.extend_from_slice(b"\0socket_x.emat\0Assets/socket_y\0mysocket_z\0socket_\0socket_w");
+        assert_eq!(scan_xob_socket_names(&data), ["socket_lamp_01", "socket_barrel-2", "socket_w"]);
+    }
+
+    #[test]
+    fn xob_only_names_are_reported_when_txo_lists_sockets() {
+        let out = merged(names(&["socket_a"]), names(&["socket_a", "socket_stray"]), None);
+        assert_eq!(out.names(), ["socket_a"]);
+        assert!(out.sockets[0].in_txo && out.sockets[0].in_xob);
+        assert_eq!(out.discrepancies, ["socket_stray: only in the .xob (not used)"]);
+    }
+
+    #[test]
+    fn xob_names_need_the_fbx_when_there_is_no_txo() {
+        let out = merged(None, names(&["socket_a", "socket_stray"]), fbx(&["Socket_A"]));
+        assert_eq!(out.names(), ["socket_a"]);
+        assert!(out.sockets[0].in_fbx);
+        assert_eq!(out.sockets[0].transform.as_ref().map(|t| t.translation), Some([1.0, 2.0, 3.0]));
+        assert_eq!(out.discrepancies, ["socket_stray: only in the .xob (not used)"]);
+
+        // A name in the .xob and the FBX is used even when the .txo lacks it.
+        let out = merged(names(&["socket_a"]), names(&["socket_b"]), fbx(&["socket_a", "socket_b"]));
+        assert_eq!(out.names(), ["socket_a", "socket_b"]);
+        assert_eq!(out.discrepancies, ["socket_a: in the .txo but not in the .xob", "socket_b: in the .xob but not in the .txo"]);
+    }
+
+    #[test]
+    fn xob_list_is_used_when_nothing_else_lists_sockets() {
+        let out = merged(None, names(&["socket_a"]), None);
+        assert_eq!(out.names(), ["socket_a"]);
+        assert!(out.discrepancies.is_empty());
+    }
+
+    #[test]
+    fn fbx_only_sockets_are_reported_unless_the_exports_list_none() {
+        let out = merged(names(&["socket_a"]), names(&["socket_a"]), fbx(&["socket_a", "socket_new"]));
+        assert_eq!(out.names(), ["socket_a"]);
+        assert_eq!(out.discrepancies, ["socket_new: in the FBX but not in the exported model (re-export?)"]);
+
+        let out = merged(names(&[]), names(&[]), fbx(&["socket_a"]));
+        assert_eq!(out.names(), ["socket_a"]);
+        assert!(out.discrepancies.is_empty());
+
+        let out = merged(names(&["socket_a"]), names(&["socket_a"]), fbx(&[]));
+        assert_eq!(out.discrepancies, ["socket_a: exported but not in the FBX"]);
+    }
+}
@@ -0,0 +1,861 @@
+// Socket-to-prefab matching rules.
+//
+// A socket is matched by trying the rules of a rule file in order; the first rule that
+// resolves to an indexed prefab wins. A rule runs a regex over the socket name (or over the
+// Blender `ref_guid` of the socket) and expands a capture-group template into a GUID, a file
+// name, a project path or a key of the alias table. Rules can be limited to project folders.
+// Every attempt is recorded, so the UI can show why a socket matched what it did, or nothing.
+//
+// Besides the accepted match every socket gets a ranked list of candidates: the hits of all
+// rules, scored by how the rule matched, and for sockets no rule resolved, prefabs with a
+// similar name (edit distance), ranked up when they sit in a folder other sockets matched in.
+// The caller can accept the top candidate or pick another one per socket (`apply_choices`).
+//
+// The prefab folders chosen for the model (`extra_dirs`) form the search scope: a hit in an
+// earlier folder beats one in a later folder, and both beat hits elsewhere in the index, which
+// only match when no rule finds anything in scope (or never, for a strict scope). GUID hits
+// name one prefab and are taken wherever it is.
+//
+// The rule file is AutoSocket_MatchRules.json in the data folder, or the file set in
+// `match_rules_file`. Without one, the built-in rules reproduce the old cascade: Blender
+// ref_guid, GUID in the socket name, then the socket name with and without its trailing index.
+
+use regex::{Captures, Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::extract_guid;
+use crate::prefab_db::{IndexQuery, PrefabDb, Resource};
+
+pub const RULES_VERSION: u32 = 1;
+
+/// Candidates kept per socket.
+const MAX_CANDIDATES: usize = 8;
+/// Name similarity (0..1) a prefab needs to be a fuzzy candidate, and the lower bar for
+/// prefabs in a folder where other sockets of the model matched.
+const FUZZY_MIN: f32 = 0.6;
+const SAME_FOLDER_MIN: f32 = 0.45;
+/// Fuzzy scores are scaled below every exact method; same-folder hits get a bonus on top.
+const FUZZY_WEIGHT: f32 = 0.7;
+const SAME_FOLDER_BONUS: f32 = 0.1;
+/// Applied to candidates outside a non-empty search scope.
+const OUT_OF_SCOPE_WEIGHT: f32 = 0.9;
+
+/// How a candidate was found.
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMethod {
+    ExactGuid,
+    ExactPath,
+    Alias,
+    ExactName,
+    /// Name with the trailing index (`_01`) removed.
+    StrippedSuffix,
+    /// Similar name, by edit distance.
+    Fuzzy,
+    /// Similar name, in a folder other sockets of the model matched in.
+    SameFolder,
+    /// Picked by the user.
+    Manual,
+}
+
+impl MatchMethod {
+    /// Score of a rule using this method when the rule sets none.
+    fn default_score(self) -> f32 {
+        match self {
+            MatchMethod::ExactGuid | MatchMethod::Manual => 1.0,
+            MatchMethod::ExactPath | MatchMethod::Alias => 0.95,
+            MatchMethod::ExactName => 0.9,
+            MatchMethod::StrippedSuffix => 0.8,
+            MatchMethod::SameFolder => 0.75,
+            MatchMethod::Fuzzy => FUZZY_WEIGHT,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Debug, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum RuleInput {
+    /// The socket name as written in the .txo.
+    #[default]
+    Socket,
+    /// The `ref_guid` custom property of the socket in the FBX.
+    RefGuid,
+}
+
+/// One rule. Exactly one of `guid`, `file`, `path` and `alias` is set; each is a template
+/// where `$1` or `${name}` stands for a capture group of `pattern` and `$$` for `$`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+pub struct MatchRule {
+    pub name: String,
+    #[serde(default)]
+    pub input: RuleInput,
+    /// Case-insensitive regex over the input; the whole input (`$0`) when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub guid: Option<String>,
+    /// File name of the prefab, e.g. `${1}.et`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub file: Option<String>,
+    /// Project path of the prefab, e.g. `Prefabs/Props/${1}.et`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Key of the alias table.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub alias: Option<String>,
+    /// Turns `.` into `_`, collapses repeated `_` and trims outer `_` in every capture.
+    #[serde(default)]
+    pub clean_captures: bool,
+    /// Project folders the prefab must be in; the file's `folders` when empty.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub folders: Vec<String>,
+    /// Method reported for hits; follows from the target when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub method: Option<MatchMethod>,
+    /// Score (0..1) of hits; the method's default when unset.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub score: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct RuleFile {
+    #[serde(default = "default_version")]
+    pub version: u32,
+    /// Alias key (case-insensitive) -> GUID, `{GUID}path`, project path or file name.
+    #[serde(default)]
+    pub aliases: BTreeMap<String, String>,
+    /// Project folders every rule without its own `folders` is limited to.
+    #[serde(default)]
+    pub folders: Vec<String>,
+    pub rules: Vec<MatchRule>,
+}
+
+fn default_version() -> u32 {
+    RULES_VERSION
+}
+
+/// Rules equivalent to the matching done before rule files existed.
+pub fn builtin() -> RuleFile {
+    let rule = |name: &str, pattern: &str| MatchRule { name: name.to_string(), pattern: Some(pattern.to_string()), ..Default::default() };
+    RuleFile {
+        version: RULES_VERSION,
+        aliases: BTreeMap::new(),
+        folders: Vec::new(),
+        rules: vec![
+            MatchRule {
+                name: "Blender ref_guid".into(),
+                input: RuleInput::RefGuid,
+                guid: Some("$0".into()),
+                ..Default::default()
+            },
+            MatchRule { guid: Some("$1".into()), ..rule("GUID in socket name", "^socket_([0-9a-f]{16})") },
+            MatchRule { alias: Some("$1".into()), clean_captures: true, ..rule("Alias", r"^socket_(.+?)(?:\.et.*)?$") },
+            MatchRule { file: Some("${1}.et".into()), clean_captures: true, ..rule("Socket name", r"^socket_(.+?)(?:\.et.*)?$") },
+            MatchRule {
+                file: Some("${1}.et".into()),
+                clean_captures: true,
+                method: Some(MatchMethod::StrippedSuffix),
+                ..rule("Socket name without index", r"^socket_(.+?)[._]+\d+[._]*(?:\.et.*)?$")
+            },
+        ],
+    }
+}
+
+/// One rule tried on a socket.
+#[derive(Serialize, Clone, Debug)]
+pub struct RuleAttempt {
+    pub rule: String,
+    /// What the rule looked up, e.g. `file barrel_01.et`.
+    pub lookup: Option<String>,
+    pub outcome: String,
+}
+
+/// A prefab a socket may map to.
+#[derive(Serialize, Clone, Debug)]
+pub struct Candidate {
+    pub prefab: Resource,
+    /// 0..1, higher is more certain.
+    pub score: f32,
+    pub method: MatchMethod,
+    /// Rule that found it; None for fuzzy and manual candidates.
+    pub rule: Option<String>,
+}
+
+#[derive(Serialize, Clone, Debug)]
+pub struct SocketMatch {
+    pub socket: String,
+    /// The prefab the socket is mapped to.
+    pub prefab: Option<Resource>,
+    /// Score and method of `prefab`; 0 and None when unmatched.
+    pub score: f32,
+    pub method: Option<MatchMethod>,
+    /// Name of the rule that matched.
+    pub rule: Option<String>,
+    /// Ranked alternatives, best first; includes `prefab`.
+    pub candidates: Vec<Candidate>,
+    /// Every rule tried, in order.
+    pub trace: Vec<RuleAttempt>,
+}
+
+impl SocketMatch {
+    fn accept(&mut self, c: &Candidate) {
+        self.prefab = Some(c.prefab.clone());
+        self.score = c.score;
+        self.method = Some(c.method);
+        self.rule = c.rule.clone();
+    }
+
+    /// Adds `c` unless the same project path is listed already (with a better score, as
+    /// candidates arrive best first per source), then re-ranks.
+    fn add_candidate(&mut self, c: Candidate) {
+        let key = c.prefab.rel_path.to_lowercase();
+        match self.candidates.iter_mut().find(|o| o.prefab.rel_path.to_lowercase() == key) {
+            Some(o) if o.score >= c.score => return,
+            Some(o) => *o = c,
+            None => self.candidates.push(c),
+        }
+        self.candidates.sort_by(|a, b| b.score.total_cmp(&a.score));
+        self.candidates.truncate(MAX_CANDIDATES);
+    }
+}
+
+enum Target {
+    Guid(String),
+    File(String),
+    Path(String),
+    Alias(String),
+}
+
+struct CompiledRule {
+    name: String,
+    input: RuleInput,
+    regex: Regex,
+    target: Target,
+    clean_captures: bool,
+    /// Lowercase, `/`-terminated project folders.
+    folders: Vec<String>,
+    method: MatchMethod,
+    score: f32,
+}
+
+/// A compiled rule file.
+pub struct RuleSet {
+    /// File the rules came from; None for the built-in rules.
+    pub source: Option<PathBuf>,
+    aliases: BTreeMap<String, String>,
+    rules: Vec<CompiledRule>,
+}
+
+/// Folders searched first, in priority order; with `only`, the only ones searched.
+#[derive(Default, Clone)]
+pub struct SearchScope {
+    /// Lowercase, `/`-separated and `/`-terminated absolute folders.
+    dirs: Vec<String>,
+    only: bool,
+}
+
+fn scope_path(path: &str) -> String {
+    let p = path.replace('\\', "/").to_lowercase();
+    p.strip_prefix("//?/").map(str::to_string).unwrap_or(p)
+}
+
+impl SearchScope {
+    pub fn new(dirs: &[String], only: bool) -> SearchScope {
+        let dirs: Vec<String> = dirs
+            .iter()
+            .map(|d| scope_path(d.trim()).trim_end_matches('/').to_string())
+            .filter(|d| !d.is_empty())
+            .map(|d| format!("{}/", d))
+            .collect();
+        SearchScope { only: only && !dirs.is_empty(), dirs }
+    }
+
+    /// Priority of the first scope folder holding `r` (0 is best); None outside the scope.
+    /// Everything is in an empty scope.
+    fn rank(&self, r: &Resource) -> Option<usize> {
+        if self.dirs.is_empty() {
+            return Some(0);
+        }
+        let path = scope_path(&r.abs_path);
+        self.dirs.iter().position(|d| path.starts_with(d.as_str()))
+    }
+
+    /// Score of a candidate found with `score`, lowered outside the scope.
+    fn weigh(&self, r: &Resource, score: f32) -> f32 {
+        if self.rank(r).is_some() {
+            score
+        } else {
+            score * OUT_OF_SCOPE_WEIGHT
+        }
+    }
+}
+
+fn folder_prefix(folder: &str) -> String {
+    format!("{}/", folder.replace('\\', "/").trim_matches('/').to_lowercase())
+}
+
+fn clean_capture(s: &str) -> String {
+    let mut out = s.replace('.', "_");
+    while out.contains("__") {
+        out = out.replace("__", "_");
+    }
+    out.trim_matches('_').to_string()
+}
+
+/// Expands `$N`, `${N}`, `${name}` and `$$` in `template` from `caps`.
+fn expand(template: &str, caps: &Captures, clean: bool) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+    while let Some(i) = rest.find('$') {
+        out.push_str(&rest[..i]);
+        rest = &rest[i + 1..];
+        if let Some(r) = rest.strip_prefix('$') {
+            out.push('$');
+            rest = r;
+            continue;
+        }
+        let (group, after) = match rest.strip_prefix('{') {
+            Some(r) => match r.find('}') {
+                Some(end) => (&r[..end], &r[end + 1..]),
+                None => ("", rest),
+            },
+            None => {
+                let end = rest.find(|c: char| !c.is_ascii_digit()).unwrap_or(rest.len());
+                (&rest[..end], &rest[end..])
+            }
+        };
+        if group.is_empty() {
+            out.push('$');
+            continue;
+        }
+        let value = match group.parse::<usize>() {
+            Ok(n) => caps.get(n),
+            Err(_) => caps.name(group),
+        }
+        .map_or("", |m| m.as_str());
+        out.push_str(&if clean { clean_capture(value) } else { value.to_string() });
+        rest = after;
+    }
+    out.push_str(rest);
+    out
+}
+
+impl RuleSet {
+    pub fn compile(file: RuleFile, source: Option<PathBuf>) -> Result<RuleSet, String> {
+        if file.version > RULES_VERSION {
+            return Err(format!("Match rules version {} is newer than this build supports ({})", file.version, RULES_VERSION));
+        }
+        let mut rules = Vec::new();
+        for (i, r) in file.rules.into_iter().enumerate() {
+            let name = if r.name.trim().is_empty() { format!("rule {}", i + 1) } else { r.name.clone() };
+            let target = match (r.guid, r.file, r.path, r.alias) {
+                (Some(t), None, None, None) => Target::Guid(t),
+                (None, Some(t), None, None) => Target::File(t),
+                (None, None, Some(t), None) => Target::Path(t),
+                (None, None, None, Some(t)) => Target::Alias(t),
+                _ => return Err(format!("Match rule '{}' needs exactly one of guid, file, path or alias", name)),
+            };
+            let regex = RegexBuilder::new(r.pattern.as_deref().unwrap_or("^.*$"))
+                .case_insensitive(true)
+                .build()
+                .map_err(|e| format!("Match rule '{}': invalid pattern: {}", name, e))?;
+            let folders = if r.folders.is_empty() { &file.folders } else { &r.folders };
+            let method = r.method.unwrap_or(match target {
+                Target::Guid(_) => MatchMethod::ExactGuid,
+                Target::File(_) => MatchMethod::ExactName,
+                Target::Path(_) => MatchMethod::ExactPath,
+                Target::Alias(_) => MatchMethod::Alias,
+            });
+            let score = r.score.unwrap_or(method.default_score());
+            if !(0.0..=1.0).contains(&score) {
+                return Err(format!("Match rule '{}': score must be between 0 and 1", name));
+            }
+            rules.push(CompiledRule {
+                name,
+                input: r.input,
+                regex,
+                target,
+                clean_captures: r.clean_captures,
+                folders: folders.iter().map(|f| folder_prefix(f)).collect(),
+                method,
+                score,
+            });
+        }
+        let aliases = file.aliases.into_iter().map(|(k, v)| (k.trim().to_lowercase(), v.trim().to_string())).collect();
+        Ok(RuleSet { source, aliases, rules })
+    }
+
+    /// Rules from `path`, else from the default rule file if it exists, else the built-in ones.
+    pub fn load(path: Option<&Path>) -> Result<RuleSet, String> {
+        let default_path = crate::match_rules_path();
+        let path = match path {
+            Some(p) => p.to_path_buf(),
+            None if default_path.is_file() => default_path,
+            None => return RuleSet::compile(builtin(), None),
+        };
+        let text = fs::read_to_string(&path).map_err(|e| format!("Failed to read {}: {}", path.to_string_lossy(), e))?;
+        let file: RuleFile = serde_json::from_str(&text).map_err(|e| format!("Failed to parse {}: {}", path.to_string_lossy(), e))?;
+        RuleSet::compile(file, Some(path))
+    }
+
+    /// The rules selected in the settings (`match_rules_file`), else as in `load`.
+    pub fn configured() -> Result<RuleSet, String> {
+        let path = crate::load_settings().match_rules_file.filter(|p| !p.trim().is_empty()).map(PathBuf::from);
+        RuleSet::load(path.as_deref())
+    }
+
+    /// Tries every rule on `socket`; the first rule with a hit in `scope` decides the match
+    /// (the first hit outside it when there is none), the others add candidates. `ref_guid`
+    /// is the socket's Blender `ref_guid`, when the FBX has one.
+    pub fn match_socket(&self, db: &PrefabDb, socket: &str, ref_guid: Option<&str>, scope: &SearchScope) -> SocketMatch {
+        let mut m = SocketMatch {
+            socket: socket.to_string(),
+            prefab: None,
+            score: 0.0,
+            method: None,
+            rule: None,
+            candidates: Vec::new(),
+            trace: Vec::new(),
+        };
+        let trace = &mut m.trace;
+        let mut hits: Vec<Candidate> = Vec::new();
+        let mut accepted: Option<Candidate> = None;
+        let mut fallback: Option<Candidate> = None;
+        for rule in &self.rules {
+            let input = match rule.input {
+                RuleInput::Socket => socket.trim(),
+                RuleInput::RefGuid => match ref_guid {
+                    Some(g) => g.trim(),
+                    None => {
+                        trace.push(RuleAttempt { rule: rule.name.clone(), lookup: None, outcome: "no ref_guid on this socket".into() });
+                        continue;
+                    }
+                },
+            };
+            let Some(caps) = rule.regex.captures(input) else {
+                trace.push(RuleAttempt { rule: rule.name.clone(), lookup: None, outcome: format!("pattern did not match '{}'", input) });
+                continue;
+            };
+            let (lookup, found) = self.resolve(db, &rule.target, &caps, rule.clean_captures);
+            let attempt = |outcome: String| RuleAttempt { rule: rule.name.clone(), lookup: Some(lookup.clone()), outcome };
+            let candidates = match found {
+                Ok(c) => c,
+                Err(outcome) => {
+                    trace.push(attempt(outcome));
+                    continue;
+                }
+            };
+            let allowed = |r: &Resource| {
+                let rel = r.rel_path.replace('\\', "/").to_lowercase();
+                rule.folders.is_empty() || rule.folders.iter().any(|f| rel.starts_with(f.as_str()))
+            };
+            let in_folders: Vec<&Resource> = candidates.iter().filter(|r| allowed(r)).collect();
+            let is_guid = matches!(rule.target, Target::Guid(_));
+            let mut found: Vec<(Option<usize>, Candidate)> = in_folders
+                .iter()
+                .map(|r| {
+                    let rank = if is_guid { Some(0) } else { scope.rank(r) };
+                    let score = if rank.is_some() { rule.score } else { scope.weigh(r, rule.score) };
+                    (rank, Candidate { prefab: (*r).clone(), score, method: rule.method, rule: Some(rule.name.clone()) })
+                })
+                .filter(|(rank, _)| rank.is_some() || !scope.only)
+                .collect();
+            found.sort_by_key(|(rank, _)| rank.unwrap_or(usize::MAX));
+            match found.first() {
+                Some((Some(_), hit)) if accepted.is_none() => {
+                    trace.push(attempt(format!("matched {}", hit.prefab.rel_path)));
+                    accepted = Some(hit.clone());
+                }
+                Some((None, hit)) if accepted.is_none() && fallback.is_none() => {
+                    trace.push(attempt(format!("found {} outside the search folders", hit.prefab.rel_path)));
+                    fallback = Some(hit.clone());
+                }
+                Some((_, hit)) => trace.push(attempt(format!("candidate {}", hit.prefab.rel_path))),
+                None if candidates.is_empty() => trace.push(attempt("no indexed prefab".into())),
+                None if in_folders.is_empty() => {
+                    trace.push(attempt(format!("found {}, outside the allowed folders", candidates[0].rel_path)))
+                }
+                None => trace.push(attempt(format!("found {}, outside the search folders", in_folders[0].rel_path))),
+            }
+            hits.extend(found.into_iter().map(|(_, c)| c));
+        }
+        for c in hits {
+            m.add_candidate(c);
+        }
+        if accepted.is_none() {
+            if let Some(c) = fallback.as_ref() {
+                m.trace.push(RuleAttempt {
+                    rule: c.rule.clone().unwrap_or_default(),
+                    lookup: None,
+                    outcome: format!("nothing in the search folders; matched {}", c.prefab.rel_path),
+                });
+            }
+        }
+        if let Some(c) = accepted.or(fallback) {
+            m.accept(&c);
+        }
+        m
+    }
+
+    /// The lookup a rule makes (for the trace) and its prefab candidates, best first, or why
+    /// it could not look anything up.
+    fn resolve(&self, db: &PrefabDb, target: &Target, caps: &Captures, clean: bool) -> (String, Result<Vec<Resource>, String>) {
+        let prefabs = |rows: Result<Vec<Resource>, String>| rows.map(|rows| rows.into_iter().filter(|r| r.ext == "et").collect());
+        match target {
+            Target::Guid(t) => {
+                let v = expand(t, caps, clean);
+                let guid = extract_guid(&format!("{{{}}}", v.trim_start_matches('{').trim_end_matches('}')));
+                match guid {
+                    Some(g) => (format!("guid {}", g), Ok(db.prefab_by_guid(&g).into_iter().collect())),
+                    None => (format!("guid {}", v), Err(format!("'{}' is not a GUID", v))),
+                }
+            }
+            Target::File(t) => {
+                let v = expand(t, caps, clean).to_lowercase();
+                (format!("file {}", v), prefabs(db.all_by_file_name(&v)))
+            }
+            Target::Path(t) => {
+                let v = expand(t, caps, clean).replace('\\', "/");
+                (format!("path {}", v), prefabs(db.all_at_rel_path(&v)))
+            }
+            Target::Alias(t) => {
+                let key = expand(t, caps, clean).to_lowercase();
+                let lookup = format!("alias {}", key);
+                let Some(value) = self.aliases.get(&key) else {
+                    return (lookup, Err(format!("no alias '{}'", key)));
+                };
+                let rows = if let Some(g) = extract_guid(value).or_else(|| extract_guid(&format!("{{{}}}", value))) {
+                    Ok(db.prefab_by_guid(&g).into_iter().collect())
+                } else if value.contains('/') || value.contains('\\') {
+                    prefabs(db.all_at_rel_path(value))
+                } else if Path::new(value).extension().is_some() {
+                    prefabs(db.all_by_file_name(value))
+                } else {
+                    prefabs(db.all_by_file_name(&format!("{}.et", value)))
+                };
+                (format!("{} -> {}", lookup, value), rows)
+            }
+        }
+    }
+}
+
+/// Lowercase socket name without `socket_`, anything from `.et` on, and separators cleaned;
+/// the form prefab file stems are compared against.
+fn socket_stem(socket: &str) -> String {
+    let s = socket.trim().to_lowercase();
+    let s = s.strip_prefix("socket_").unwrap_or(&s);
+    clean_capture(&s[..s.find(".et").unwrap_or(s.len())])
+}
+
+/// 1 for equal names, falling to 0 as the edit distance approaches the longer length.
+fn similarity(a: &str, b: &str) -> f32 {
+    let (la, lb) = (a.chars().count(), b.chars().count());
+    let longest = la.max(lb);
+    if longest == 0 {
+        return 0.0;
+    }
+    // The distance is at least the length difference; skip names that cannot reach the bar.
+    if (la.abs_diff(lb) as f32) > longest as f32 * (1.0 - SAME_FOLDER_MIN) {
+        return 0.0;
+    }
+    1.0 - strsim::levenshtein(a, b) as f32 / longest as f32
+}
+
+fn parent_folder(rel_path: &str) -> String {
+    let rel = rel_path.replace('\\', "/").to_lowercase();
+    rel.rsplit_once('/').map(|(dir, _)| dir.to_string()).unwrap_or_default()
+}
+
+/// Prefabs `add_fuzzy_candidates` compares unmatched sockets against. Load them once per
+/// model and reuse the list for all of its sockets.
+pub fn fuzzy_pool(db: &PrefabDb) -> Result<Vec<Resource>, String> {
+    db.matching(&IndexQuery { types: Some(vec!["et".into()]), ..Default::default() })
+}
+
+/// Adds similar-name candidates from `prefabs` (see `fuzzy_pool`) to the sockets no rule
+/// matched. Prefabs in a folder where another socket of the same model matched rank higher and
+/// pass a lower bar; prefabs outside `scope` rank lower, or are skipped for a strict scope.
+pub fn add_fuzzy_candidates(prefabs: &[Resource], matches: &mut [SocketMatch], scope: &SearchScope) {
+    let folders: HashSet<String> = matches.iter().filter_map(|m| m.prefab.as_ref()).map(|r| parent_folder(&r.rel_path)).collect();
+    let stems: Vec<&str> = prefabs.iter().map(|r| r.file_name.strip_suffix(".et").unwrap_or(&r.file_name)).collect();
+    for m in matches.iter_mut().filter(|m| m.prefab.is_none()) {
+        let stem = socket_stem(&m.socket);
+        // Also compare without the trailing index: socket_barrel_03 vs barrel.et.
+        let trimmed = stem.trim_end_matches(|c: char| c.is_ascii_digit()).trim_end_matches('_');
+        let keys: Vec<&str> = [stem.as_str(), trimmed].into_iter().filter(|k| !k.is_empty()).collect();
+        for (r, name) in prefabs.iter().zip(&stems) {
+            if scope.only && scope.rank(r).is_none() {
+                continue;
+            }
+            let sim = keys.iter().map(|k| similarity(k, name)).fold(0.0, f32::max);
+            let same_folder = folders.contains(&parent_folder(&r.rel_path));
+            let (method, score) = if same_folder && sim >= SAME_FOLDER_MIN {
+                (MatchMethod::SameFolder, (sim * FUZZY_WEIGHT + SAME_FOLDER_BONUS).min(MatchMethod::SameFolder.default_score()))
+            } else if sim >= FUZZY_MIN {
+                (MatchMethod::Fuzzy, sim * FUZZY_WEIGHT)
+            } else {
+                continue;
+            };
+            m.add_candidate(Candidate { prefab: r.clone(), score: scope.weigh(r, score), method, rule: None });
+        }
+    }
+}
+
+/// Applies the caller's decisions: `choices` maps a socket name to the GUID, project path or
+/// file name of the prefab to use (empty to leave the socket unmatched); with `accept_top`,
+/// sockets that are still unmatched take their best candidate.
+pub fn apply_choices(
+    db: &PrefabDb,
+    matches: &mut [SocketMatch],
+    choices: &BTreeMap<String, String>,
+    accept_top: bool,
+) -> Result<(), String> {
+    for (socket, choice) in choices {
+        let m = matches
+            .iter_mut()
+            .find(|m| m.socket.eq_ignore_ascii_case(socket.trim()))
+            .ok_or_else(|| format!("No socket named {}", socket))?;
+        let choice = choice.trim();
+        if choice.is_empty() {
+            m.prefab = None;
+            m.score = 0.0;
+            m.method = None;
+            m.rule = None;
+            continue;
+        }
+        let key = choice.replace('\\', "/").to_lowercase();
+        let guid = extract_guid(choice).or_else(|| extract_guid(&format!("{{{}}}", choice)));
+        let listed = m.candidates.iter().find(|c| match guid.as_ref() {
+            Some(g) => c.prefab.guid.as_ref() == Some(g),
+            None => c.prefab.rel_path.to_lowercase() == key || c.prefab.file_name == key,
+        });
+        let chosen = match listed {
+            Some(c) => Candidate { method: MatchMethod::Manual, score: 1.0, rule: None, ..c.clone() },
+            None => {
+                let found = match guid.as_ref() {
+                    Some(g) => db.prefab_by_guid(g),
+                    None if key.contains('/') => db.all_at_rel_path(&key)?.into_iter().find(|r| r.ext == "et"),
+                    None => db.all_by_file_name(&key)?.into_iter().find(|r| r.ext == "et"),
+                };
+                let prefab = found.ok_or_else(|| format!("No indexed prefab '{}' for socket {}", choice, m.socket))?;
+                Candidate { prefab, score: 1.0, method: MatchMethod::Manual, rule: None }
+            }
+        };
+        m.accept(&chosen);
+        m.add_candidate(chosen);
+    }
+    if accept_top {
+        let declined = |m: &SocketMatch| choices.keys().any(|k| m.socket.eq_ignore_ascii_case(k.trim()));
+        for m in matches.iter_mut().filter(|m| m.prefab.is_none() && !declined(m)) {
+            if let Some(top) = m.candidates.first().cloned() {
+                m.accept(&top);
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BARREL_GUID: &str = "5A3C00000000BA01";
+
+    fn temp_db(name: &str) -> (PathBuf, PrefabDb) {
+        let dir = std::env::temp_dir().join(format!("owltools_socket_rules_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        let mut db = crate::prefab_db::open_at(&dir.join("prefab_index.sqlite"), None).unwrap();
+        let prefab = |rel: &str, guid: &str| {
+            let file_name = rel.rsplit('/').next().unwrap().to_lowercase();
+            Resource {
+                root: crate::prefab_db::BASE_ROOT.into(),
+                abs_path: format!("C:/svn/{}", rel),
+                meta_path: format!("C:/svn/{}.meta", rel),
+                file_name,
+                guid: Some(guid.into()),
+                rel_path: rel.into(),
+                name_value: format!("{{{}}}{}", guid, rel),
+                ext: "et".into(),
+                class: "EntityTemplateResourceClass".into(),
+                mtime: 1.0,
+            }
+        };
+        db.upsert(&[
+            prefab("Prefabs/Props/barrel.et", BARREL_GUID),
+            prefab("Prefabs/Props/lamp_post.et", "5A3C00000000BA02"),
+            prefab("Prefabs/Weapons/crate.et", "5A3C00000000BA03"),
+        ])
+        .unwrap();
+        (dir, db)
+    }
+
+    fn outcomes(m: &SocketMatch) -> Vec<String> {
+        m.trace.iter().map(|a| format!("{}: {}", a.rule, a.outcome)).collect()
+    }
+
+    fn rule_file(rules: Vec<MatchRule>) -> RuleFile {
+        RuleFile { version: RULES_VERSION, aliases: BTreeMap::new(), folders: Vec::new(), rules }
+    }
+
+    fn file_rule(name: &str) -> MatchRule {
+        MatchRule { name: name.into(), pattern: Some("^socket_(.+)$".into()), file: Some("${1}.et".into()), ..Default::default() }
+    }
+
+    #[test]
+    fn expand_fills_groups() {
+        let re = Regex::new(r"^socket_(?P<name>[a-z.]+)_(\d+)$").unwrap();
+        let caps = re.captures("socket_lamp.post_02").unwrap();
+        assert_eq!(expand("$1_$2.et", &caps, false), "lamp.post_02.et");
+        assert_eq!(expand("${name}/${2}x", &caps, false), "lamp.post/02x");
+        assert_eq!(expand("${name}.et", &caps, true), "lamp_post.et");
+        assert_eq!(expand("$$1 costs $", &caps, false), "$1 costs $");
+        assert_eq!(expand("${name", &caps, false), "${name");
+        assert_eq!(expand("$x${missing}$9", &caps, false), "$x");
+    }
+
+    #[test]
+    fn clean_capture_normalises_separators() {
+        assert_eq!(clean_capture("..lamp.post__v2_"), "lamp_post_v2");
+        assert_eq!(clean_capture("barrel"), "barrel");
+        assert_eq!(clean_capture("._."), "");
+    }
+
+    #[test]
+    fn compile_rejects_invalid_rules() {
+        let err = |rules: Vec<MatchRule>| RuleSet::compile(rule_file(rules), None).err().unwrap();
+        assert_eq!(
+            err(vec![MatchRule { name: "empty".into(), ..Default::default() }]),
+            "Match rule 'empty' needs exactly one of guid, file, path or alias"
+        );
+        assert_eq!(
+            err(vec![MatchRule { guid: Some("$1".into()), ..file_rule("") }]),
+            "Match rule 'rule 1' needs exactly one of guid, file, path or alias"
+        );
+        assert!(err(vec![MatchRule { pattern: Some("socket_(".into()), ..file_rule("bad") }])
+            .starts_with("Match rule 'bad': invalid pattern:"));
+        assert_eq!(
+            err(vec![MatchRule { score: Some(1.5), ..file_rule("high") }]),
+            "Match rule 'high': score must be between 0 and 1"
+        );
+        let newer = RuleFile { version: RULES_VERSION + 1, ..rule_file(vec![file_rule("ok")]) };
+        assert_eq!(
+            RuleSet::compile(newer, None).err().unwrap(),
+            format!("Match rules version {} is newer than this build supports ({})", RULES_VERSION + 1, RULES_VERSION)
+        );
+        assert!(RuleSet::compile(builtin(), None).is_ok());
+    }
+
+    #[test]
+    fn builtin_rules_reproduce_the_old_cascade() {
+        let (dir, db) = temp_db("builtin");
+        let rules = RuleSet::compile(builtin(), None).unwrap();
+        let scope = SearchScope::default();
+        let matched = |socket: &str, ref_guid: Option<&str>| {
+            let m = rules.match_socket(&db, socket, ref_guid, &scope);
+            (m.prefab.as_ref().map(|r| r.rel_path.clone()), m.method)
+        };
+        let barrel = Some("Prefabs/Props/barrel.et".to_string());
+
+        assert_eq!(matched("socket_whatever", Some(BARREL_GUID)), (barrel.clone(), Some(MatchMethod::ExactGuid)));
+        assert_eq!(matched("socket_5a3c00000000ba01", None), (barrel.clone(), Some(MatchMethod::ExactGuid)));
+        assert_eq!(matched("socket_barrel", None), (barrel.clone(), Some(MatchMethod::ExactName)));
+        assert_eq!(matched("socket_barrel.et", None), (barrel.clone(), Some(MatchMethod::ExactName)));
+        assert_eq!(matched("socket_barrel_01", None), (barrel, Some(MatchMethod::StrippedSuffix)));
+        assert_eq!(
+            matched("socket_lamp.post_02", None),
+            (Some("Prefabs/Props/lamp_post.et".to_string()), Some(MatchMethod::StrippedSuffix))
+        );
+
+        let m = rules.match_socket(&db, "socket_barrel_01", None, &scope);
+        assert_eq!(
+            outcomes(&m),
+            [
+                "Blender ref_guid: no ref_guid on this socket",
+                "GUID in socket name: pattern did not match 'socket_barrel_01'",
+                "Alias: no alias 'barrel_01'",
+                "Socket name: no indexed prefab",
+                "Socket name without index: matched Prefabs/Props/barrel.et",
+            ]
+        );
+        assert_eq!(m.trace[4].lookup.as_deref(), Some("file barrel.et"));
+        assert_eq!(m.rule.as_deref(), Some("Socket name without index"));
+        assert_eq!(m.score, MatchMethod::StrippedSuffix.default_score());
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn aliases_resolve_by_guid_path_and_name() {
+        let (dir, db) = temp_db("alias");
+        let file = RuleFile {
+            aliases: BTreeMap::from([
+                ("Keg".to_string(), BARREL_GUID.to_string()),
+                ("light".to_string(), "Prefabs/Props/lamp_post.et".to_string()),
+                ("box".to_string(), "crate".to_string()),
+            ]),
+            ..rule_file(vec![MatchRule { name: "Alias".into(), pattern: Some("^socket_(.+)$".into()), alias: Some("$1".into()), ..Default::default() }])
+        };
+        let rules = RuleSet::compile(file, None).unwrap();
+        let scope = SearchScope::default();
+        let matched = |socket: &str| rules.match_socket(&db, socket, None, &scope).prefab.map(|r| r.file_name);
+        assert_eq!(matched("socket_keg").as_deref(), Some("barrel.et"));
+        assert_eq!(matched("socket_LIGHT").as_deref(), Some("lamp_post.et"));
+        assert_eq!(matched("socket_box").as_deref(), Some("crate.et"));
+        let m = rules.match_socket(&db, "socket_nothing", None, &scope);
+        assert_eq!(outcomes(&m), ["Alias: no alias 'nothing'"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn folders_and_scope_limit_matches() {
+        let (dir, db) = temp_db("folders");
+        let weapons_only = RuleFile { folders: vec!["Prefabs\\Weapons\\".into()], ..rule_file(vec![file_rule("Name")]) };
+        let rules = RuleSet::compile(weapons_only, None).unwrap();
+        let m = rules.match_socket(&db, "socket_barrel", None, &SearchScope::default());
+        assert!(m.prefab.is_none());
+        assert_eq!(outcomes(&m), ["Name: found Prefabs/Props/barrel.et, outside the allowed folders"]);
+        assert!(rules.match_socket(&db, "socket_crate", None, &SearchScope::default()).prefab.is_some());
+
+        // Outside the search scope: used only when nothing in scope matches, never when strict.
+        let rules = RuleSet::compile(rule_file(vec![file_rule("Name")]), None).unwrap();
+        let scope = SearchScope::new(&["C:\\svn\\Prefabs\\Weapons\\".to_string()], false);
+        let m = rules.match_socket(&db, "socket_barrel", None, &scope);
+        assert_eq!(m.prefab.as_ref().map(|r| r.file_name.as_str()), Some("barrel.et"));
+        assert_eq!(
+            outcomes(&m),
+            [
+                "Name: found Prefabs/Props/barrel.et outside the search folders",
+                "Name: nothing in the search folders; matched Prefabs/Props/barrel.et",
+            ]
+        );
+        assert!(m.score < 0.9);
+
+        let strict = SearchScope::new(&["C:/svn/Prefabs/Weapons".to_string()], true);
+        let m = rules.match_socket(&db, "socket_barrel", None, &strict);
+        assert!(m.prefab.is_none());
+        assert_eq!(outcomes(&m), ["Name: found Prefabs/Props/barrel.et, outside the search folders"]);
+        let _ = fs::remove_dir_all(dir);
+    }
+
+    #[test]
+    fn unmatched_sockets_get_fuzzy_candidates() {
+        let (dir, db) = temp_db("fuzzy");
+        let rules = RuleSet::compile(builtin(), None).unwrap();
+        let scope = SearchScope::default();
+        let mut matches = vec![
+            rules.match_socket(&db, "socket_lamp_post", None, &scope),
+            rules.match_socket(&db, "socket_barel_03", None, &scope),
+        ];
+        add_fuzzy_candidates(&fuzzy_pool(&db).unwrap(), &mut matches, &scope);
+
+        assert!(matches[1].prefab.is_none());
+        let top = &matches[1].candidates[0];
+        assert_eq!(top.prefab.file_name, "barrel.et");
+        // barrel.et sits in the folder socket_lamp_post matched in.
+        assert_eq!(top.method, MatchMethod::SameFolder);
+        let _ = fs::remove_dir_all(dir);
+    }
+}
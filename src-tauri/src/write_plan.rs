@@ -0,0 +1,285 @@
+// Dry-run support for commands that generate files.
+//
+// A command running in dry-run mode collects its outputs into a `WritePlan` instead of
+// writing them. The plan carries the would-be contents and a unified diff against what
+// is on disk, and is kept in memory until `apply` commits it (or `discard` drops it).
+// Applying refuses to overwrite a file that changed after the preview was taken.
+
+use once_cell::sync::OnceCell;
+use rand::Rng;
+use serde::Serialize;
+use similar::TextDiff;
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// Plans older than the newest `MAX_PLANS` are dropped when a new one is stored.
+const MAX_PLANS: usize = 32;
+
+#[derive(Clone, Debug, Serialize)]
+pub struct PlannedFile {
+    pub path: String,
+    /// Full contents that `apply` will write.
+    pub contents: String,
+    /// Unified diff against the file on disk (`/dev/null` for new files).
+    pub diff: String,
+    pub is_new: bool,
+    pub changed: bool,
+    /// Contents on disk when the plan was made; used to detect edits before apply.
+    #[serde(skip)]
+    original: Option<String>,
+}
+
+impl PlannedFile {
+    pub fn status(&self) -> &'static str {
+        if self.is_new {
+            "new"
+        } else if self.changed {
+            "modified"
+        } else {
+            "unchanged"
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct WritePlan {
+    pub id: String,
+    pub created: String,
+    pub files: Vec<PlannedFile>,
+}
+
+static PLANS: OnceCell<Mutex<HashMap<String, WritePlan>>> = OnceCell::new();
+
+fn plans() -> &'static Mutex<HashMap<String, WritePlan>> {
+    PLANS.get_or_init(|| Mutex::new(HashMap::new()))
+}
+
+pub fn unified_diff(old: &str, new: &str, old_label: &str, new_label: &str) -> String {
+    TextDiff::from_lines(old, new)
+        .unified_diff()
+        .context_radius(3)
+        .header(old_label, new_label)
+        .to_string()
+}
+
+/// Describes writing `contents` to `path`, diffed against the current file.
+pub fn plan_file(path: &Path, contents: String) -> PlannedFile {
+    let path_s = path.to_string_lossy().to_string();
+    let original = fs::read_to_string(path).ok();
+    let (diff, changed) = match original.as_deref() {
+        Some(old) => (
+            unified_diff(old, &contents, &format!("a/{}", path_s), &format!("b/{}", path_s)),
+            old != contents,
+        ),
+        None => (unified_diff("", &contents, "/dev/null", &format!("b/{}", path_s)), true),
+    };
+    PlannedFile {
+        path: path_s,
+        is_new: original.is_none(),
+        changed,
+        diff,
+        contents,
+        original,
+    }
+}
+
+/// Describes writing `contents` to `to` as the moved copy of `from`, diffed against `from`.
+pub fn plan_moved_file(from: &Path, to: &Path, contents: String) -> PlannedFile {
+    let old = fs::read_to_string(from).unwrap_or_default();
+    let to_s = to.to_string_lossy().to_string();
+    PlannedFile {
+        diff: unified_diff(&old, &contents, &format!("a/{}", from.to_string_lossy()), &format!("b/{}", to_s)),
+        path: to_s,
+        is_new: true,
+        changed: true,
+        contents,
+        original: None,
+    }
+}
+
+/// Registers a plan so a later `apply` call can commit it.
+pub fn store(files: Vec<PlannedFile>) -> WritePlan {
+    let id = format!("{:016X}", rand::thread_rng().gen::<u64>());
+    let plan = WritePlan {
+        id: id.clone(),
+        created: chrono::Utc::now().to_rfc3339(),
+        files,
+    };
+    let mut map = plans().lock().unwrap();
+    while map.len() >= MAX_PLANS {
+        let oldest = map
+            .values()
+            .min_by(|a, b| a.created.cmp(&b.created))
+            .map(|p| p.id.clone());
+        match oldest {
+            Some(k) => map.remove(&k),
+            None => break,
+        };
+    }
+    map.insert(id, plan.clone());
+    plan
+}
+
+pub fn discard(id: &str) -> bool {
+    plans().lock().unwrap().remove(id).is_some()
+}
+
+/// Writes every file of a stored plan and forgets it. Nothing is written if any target
+/// was created, modified or deleted since the preview.
+pub fn apply(id: &str) -> Result<Vec<PathBuf>, String> {
+    let plan = plans()
+        .lock()
+        .unwrap()
+        .remove(id)
+        .ok_or_else(|| format!("Unknown or already applied write plan: {}", id))?;
+
+    let mut stale: Vec<String> = Vec::new();
+    for f in &plan.files {
+        if fs::read_to_string(&f.path).ok() != f.original {
+            stale.push(f.path.clone());
+        }
+    }
+    if !stale.is_empty() {
+        return Err(format!(
+            "Files changed on disk since preview, run the preview again: {}",
+            stale.join(", ")
+        ));
+    }
+
+    let mut written = Vec::new();
+    for f in &plan.files {
+        let path = PathBuf::from(&f.path);
+        if !f.changed {
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).map_err(|e| e.to_string())?;
+        }
+        fs::write(&path, &f.contents).map_err(|e| format!("Failed to write {}: {}", f.path, e))?;
+        written.push(path);
+    }
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `PLANS` is shared by every test here; the eviction test would otherwise drop plans
+    /// the other tests are about to apply.
+    static PLANS_LOCK: Mutex<()> = Mutex::new(());
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("owltools_write_plan_test_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn plan_file_describes_new_unchanged_and_modified_files() {
+        let dir = temp_dir("plan");
+        let new_path = dir.join("new.et");
+        let same_path = dir.join("same.et");
+        let edit_path = dir.join("edit.et");
+        fs::write(&same_path, "a\nb\n").unwrap();
+        fs::write(&edit_path, "a\nb\n").unwrap();
+
+        let new = plan_file(&new_path, "a\n".into());
+        assert!(new.is_new && new.changed);
+        assert_eq!(new.status(), "new");
+        assert!(new.diff.starts_with("--- /dev/null\n"));
+        assert!(new.diff.contains(&format!("+++ b/{}\n", new_path.to_string_lossy())));
+        assert!(new.diff.contains("+a\n"));
+
+        let same = plan_file(&same_path, "a\nb\n".into());
+        assert!(!same.is_new && !same.changed);
+        assert_eq!(same.status(), "unchanged");
+        assert!(same.diff.is_empty());
+
+        let edit = plan_file(&edit_path, "a\nc\n".into());
+        assert!(!edit.is_new && edit.changed);
+        assert_eq!(edit.status(), "modified");
+        assert!(edit.diff.starts_with(&format!("--- a/{}\n", edit_path.to_string_lossy())));
+        assert!(edit.diff.contains(&format!("+++ b/{}\n", edit_path.to_string_lossy())));
+        assert!(edit.diff.contains("-b\n") && edit.diff.contains("+c\n"));
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn apply_writes_changed_files_and_skips_unchanged_ones() {
+        let _guard = PLANS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = temp_dir("apply");
+        let same_path = dir.join("same.et");
+        let edit_path = dir.join("edit.et");
+        let new_path = dir.join("sub").join("new.et");
+        fs::write(&same_path, "same\n").unwrap();
+        fs::write(&edit_path, "old\n").unwrap();
+
+        let plan = store(vec![
+            plan_file(&same_path, "same\n".into()),
+            plan_file(&edit_path, "new\n".into()),
+            plan_file(&new_path, "created\n".into()),
+        ]);
+        let written = apply(&plan.id).unwrap();
+        assert_eq!(written, vec![edit_path.clone(), new_path.clone()]);
+        assert_eq!(fs::read_to_string(&edit_path).unwrap(), "new\n");
+        assert_eq!(fs::read_to_string(&new_path).unwrap(), "created\n");
+        assert_eq!(fs::read_to_string(&same_path).unwrap(), "same\n");
+        // A plan is applied once.
+        assert!(apply(&plan.id).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    /// Plans `path` next to an untouched new file, runs `change` on `path` and checks that
+    /// `apply` refuses the plan without writing either file.
+    fn assert_refused_after(dir: &Path, path: &Path, change: impl Fn(&Path)) {
+        let untouched = dir.join("untouched.et");
+        let plan = store(vec![
+            plan_file(&untouched, "planned\n".into()),
+            plan_file(path, "planned\n".into()),
+        ]);
+        change(path);
+        let err = apply(&plan.id).unwrap_err();
+        assert!(err.contains("changed on disk since preview"), "{}", err);
+        assert!(err.contains(&*path.to_string_lossy()), "{}", err);
+        assert!(!untouched.exists(), "nothing is written when one target is stale");
+        assert_ne!(fs::read_to_string(path).ok().as_deref(), Some("planned\n"));
+    }
+
+    #[test]
+    fn apply_refuses_files_changed_after_the_preview() {
+        let _guard = PLANS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let dir = temp_dir("stale");
+        let created = dir.join("created.et");
+        let edited = dir.join("edited.et");
+        let deleted = dir.join("deleted.et");
+        fs::write(&edited, "before\n").unwrap();
+        fs::write(&deleted, "before\n").unwrap();
+
+        assert_refused_after(&dir, &created, |p| fs::write(p, "someone else\n").unwrap());
+        assert_refused_after(&dir, &edited, |p| fs::write(p, "edited\n").unwrap());
+        assert_refused_after(&dir, &deleted, |p| fs::remove_file(p).unwrap());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn store_evicts_the_oldest_plans_beyond_the_limit() {
+        let _guard = PLANS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+        let first = store(Vec::new());
+        // Keep `created` strictly ordered so the first plan is the oldest.
+        std::thread::sleep(std::time::Duration::from_millis(5));
+        let later: Vec<WritePlan> = (0..MAX_PLANS).map(|_| store(Vec::new())).collect();
+
+        assert!(plans().lock().unwrap().len() <= MAX_PLANS);
+        assert!(!discard(&first.id), "the oldest plan is evicted");
+        for plan in &later {
+            assert!(discard(&plan.id));
+        }
+    }
+}